@@ -0,0 +1,37 @@
+use crate::{prelude::*, Hasher};
+use sha2::{digest::FixedOutput, Digest, Sha256};
+
+/// Double SHA-256 implementation of the [`Hasher`] trait, `sha256(sha256(data))`, which Bitcoin
+/// uses for txids and the nodes of the transaction merkle tree.
+///
+/// # Examples
+///
+/// ```
+/// # use rs_merkle::{algorithms::{DoubleSha256, Sha256}, Hasher};
+/// let hash = DoubleSha256::hash("a".as_bytes());
+///
+/// assert_eq!(hash, Sha256::hash(&Sha256::hash("a".as_bytes())));
+/// ```
+///
+/// [`Hasher`]: crate::Hasher
+#[derive(Clone)]
+pub struct DoubleSha256Algorithm {}
+
+impl DoubleSha256Algorithm {
+    pub fn hash(data: &[u8]) -> [u8; 32] {
+        let first = Sha256::digest(data);
+        <[u8; 32]>::from(Sha256::new().chain_update(first).finalize_fixed())
+    }
+}
+
+impl Hasher for DoubleSha256Algorithm {
+    type Hash = [u8; 32];
+
+    fn hash(data: &[u8]) -> [u8; 32] {
+        DoubleSha256Algorithm::hash(data)
+    }
+
+    fn hasher_id() -> u32 {
+        3
+    }
+}
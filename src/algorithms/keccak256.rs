@@ -12,7 +12,8 @@ use sha3::{digest::FixedOutput, Digest, Keccak256};
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
 ///
 ///  let tree_properties = TreeProperties {
-///    sorted_pair_enabled: true,
+///      sorted_pair_enabled: true,
+///      ..Default::default()
 ///  };
 ///  let tree = MerkleTree::<Keccak256>::new(tree_properties);
 ///  let other_tree: MerkleTree<Keccak256> = MerkleTree::new(tree_properties);
@@ -50,4 +51,8 @@ impl Hasher for Keccak256Algorithm {
     fn hash(data: &[u8]) -> [u8; 32] {
         Keccak256Algorithm::hash(data)
     }
+
+    fn hasher_id() -> u32 {
+        2
+    }
 }
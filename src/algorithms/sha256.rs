@@ -10,9 +10,7 @@ use sha2::{digest::FixedOutput, Digest, Sha256};
 /// # use std::convert::TryFrom;
 /// #
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-///  let tree_properties = TreeProperties {
-///    sorted_pair_enabled: false,
-///  };
+///  let tree_properties = TreeProperties::default();
 ///  let tree = MerkleTree::<Sha256>::new(tree_properties);
 ///  let other_tree: MerkleTree<Sha256> = MerkleTree::new(tree_properties);
 ///
@@ -49,4 +47,8 @@ impl Hasher for Sha256Algorithm {
     fn hash(data: &[u8]) -> [u8; 32] {
         Sha256Algorithm::hash(data)
     }
+
+    fn hasher_id() -> u32 {
+        1
+    }
 }
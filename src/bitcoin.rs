@@ -0,0 +1,587 @@
+//! Bitcoin transaction merkle trees and BIP37 merkle blocks.
+//!
+//! Bitcoin hashes txids and tree nodes with [`DoubleSha256`], and a node without a right
+//! sibling is concatenated with itself, see [`TreeProperties::BITCOIN`]. Hashes are handled in
+//! the internal byte order, the one they are serialized in, while block explorers and RPCs
+//! display them reversed. Use [`hash_from_hex`] and [`hash_to_hex`] to convert between the two.
+//!
+//! [`PartialMerkleTree`] is the `CPartialMerkleTree` of Bitcoin Core, a depth-first encoding
+//! of the part of the tree needed to prove that some transactions are included in a block.
+//! [`MerkleBlock`] pairs it with the block header, the way the `merkleblock` P2P message and
+//! the `gettxoutproof` RPC serialize it, so SPV proofs from a real node can be verified.
+//!
+//! ## Examples
+//!
+//! ```
+//! # use rs_merkle::bitcoin::{self, PartialMerkleTree};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let txids: Vec<[u8; 32]> = (0u8..5).map(|i| [i; 32]).collect();
+//! let root = bitcoin::merkle_root(&txids).ok_or("couldn't get the merkle root")?;
+//!
+//! let partial_merkle_tree = PartialMerkleTree::from_txids(&txids, &[3])?;
+//! let bytes = partial_merkle_tree.to_bytes();
+//!
+//! let (partial_tree, matches) = PartialMerkleTree::from_bytes(&bytes)?.to_partial_tree()?;
+//! assert_eq!(partial_tree.root(), Some(root));
+//! assert_eq!(matches, vec![(3, txids[3])]);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`TreeProperties::BITCOIN`]: crate::utils::properties::TreeProperties::BITCOIN
+use crate::prelude::*;
+use crate::{
+    error::Error, node_stores::MemoryNodeStore, utils, utils::properties::TreeProperties,
+    NodeStore, PartialTree,
+};
+
+pub use crate::algorithms::DoubleSha256;
+
+/// Size of a serialized block header in bytes
+pub const BLOCK_HEADER_SIZE: usize = 80;
+
+/// The largest number of transactions a block can have, `MAX_BLOCK_WEIGHT / MIN_TRANSACTION_WEIGHT`
+/// in Bitcoin Core. Partial merkle trees of bigger blocks are rejected.
+pub const MAX_BLOCK_TRANSACTIONS: u32 = 4_000_000 / 240;
+
+/// A transaction matched by a partial merkle tree: its index in the block and its txid
+pub type MatchedTransaction = (usize, [u8; 32]);
+
+/// Position of the merkle root in a serialized block header
+const HEADER_MERKLE_ROOT: core::ops::Range<usize> = 36..68;
+
+/// Parses a hash from its displayed hex form, like a txid or a block hash from a block
+/// explorer, into the internal byte order. Returns `None` if it isn't 32 bytes of hex.
+pub fn hash_from_hex(hex: &str) -> Option<[u8; 32]> {
+    let mut hash: [u8; 32] = utils::collections::from_hex_string(hex)?.try_into().ok()?;
+    hash.reverse();
+    Some(hash)
+}
+
+/// Formats a hash in the internal byte order the way Bitcoin displays it, reversed and
+/// without the `0x` prefix
+pub fn hash_to_hex(hash: &[u8; 32]) -> String {
+    let mut reversed = *hash;
+    reversed.reverse();
+    utils::collections::to_hex_string(&reversed)
+}
+
+/// Calculates the merkle root of the block with the given txids, the way the `hashMerkleRoot`
+/// of its header is calculated. Returns `None` if there are no txids.
+///
+/// The root doesn't commit to the number of transactions, so a list with the trailing
+/// transactions duplicated has the same root. Check such lists with [`is_mutated`].
+pub fn merkle_root(txids: &[[u8; 32]]) -> Option<[u8; 32]> {
+    if txids.is_empty() {
+        return None;
+    }
+    PartialTree::<DoubleSha256>::from_leaves(txids, TreeProperties::BITCOIN)
+        .ok()?
+        .root()
+}
+
+/// Returns `true` if any node of the tree is hashed with an equal sibling, which is how
+/// Bitcoin Core detects the CVE-2012-2459 mutation: duplicating the trailing transactions of
+/// a block doesn't change its merkle root, so such a block must be rejected as invalid rather
+/// than marked as permanently invalid.
+pub fn is_mutated(txids: &[[u8; 32]]) -> bool {
+    let tree = match PartialTree::<DoubleSha256>::from_leaves(txids, TreeProperties::BITCOIN) {
+        Ok(tree) => tree,
+        Err(_) => return false,
+    };
+    tree.layers()
+        .iter()
+        .any(|layer| layer.chunks_exact(2).any(|pair| pair[0].1 == pair[1].1))
+}
+
+/// Returns the number of nodes of the tree with the given number of leaves at the height
+fn tree_width(transactions_count: u32, height: usize) -> usize {
+    ((transactions_count as usize) + (1 << height) - 1) >> height
+}
+
+/// Returns the height of the root of the tree with the given number of leaves
+fn tree_height(transactions_count: u32) -> usize {
+    let mut height = 0;
+    while tree_width(transactions_count, height) > 1 {
+        height += 1;
+    }
+    height
+}
+
+/// [`PartialMerkleTree`] is the part of the transaction merkle tree of a block that proves
+/// the inclusion of the matched transactions, encoded as in BIP37.
+///
+/// The tree is traversed depth-first, and a flag is stored for every visited node: whether it
+/// is an ancestor of a matched transaction or a matched transaction itself. The hashes of
+/// nodes with the `false` flag and of the leaves are stored, and the children of the other
+/// nodes are visited instead.
+///
+/// ## Examples
+///
+/// ```
+/// # use rs_merkle::bitcoin::{self, PartialMerkleTree};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let txids: Vec<[u8; 32]> = (0u8..3).map(|i| [i; 32]).collect();
+/// let partial_merkle_tree = PartialMerkleTree::from_txids(&txids, &[2])?;
+///
+/// // The root and its right child are ancestors of the match, the left child is not
+/// assert_eq!(partial_merkle_tree.flags(), &[true, false, true, true]);
+/// assert_eq!(partial_merkle_tree.hashes().len(), 2);
+///
+/// let (partial_tree, _) = partial_merkle_tree.to_partial_tree()?;
+/// assert_eq!(partial_tree.root(), bitcoin::merkle_root(&txids));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartialMerkleTree {
+    transactions_count: u32,
+    hashes: Vec<[u8; 32]>,
+    flags: Vec<bool>,
+}
+
+impl PartialMerkleTree {
+    pub fn new(transactions_count: u32, hashes: Vec<[u8; 32]>, flags: Vec<bool>) -> Self {
+        Self {
+            transactions_count,
+            hashes,
+            flags,
+        }
+    }
+
+    /// Builds a partial merkle tree that proves the inclusion of the transactions with the
+    /// given indices in the block with the given txids.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if there are no txids, more than [`MAX_BLOCK_TRANSACTIONS`] of them, or
+    /// an index is out of range.
+    pub fn from_txids(txids: &[[u8; 32]], matched_indices: &[usize]) -> Result<Self, Error> {
+        if txids.is_empty() || txids.len() > MAX_BLOCK_TRANSACTIONS as usize {
+            return Err(Error::invalid_merkle_block(
+                "the number of transactions is out of range",
+            ));
+        }
+        let tree = PartialTree::<DoubleSha256>::from_leaves(txids, TreeProperties::BITCOIN)?;
+        Self::from_partial_tree(&tree, txids.len() as u32, matched_indices)
+    }
+
+    /// Builds a partial merkle tree from the nodes of a tree with the given number of
+    /// transactions. The tree needs to contain the nodes of the proof only, for example the
+    /// tree extracted from another partial merkle tree with the same matches.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if there are no transactions or more than [`MAX_BLOCK_TRANSACTIONS`] of
+    /// them, if an index is out of range or if the tree doesn't contain a node of the proof.
+    pub fn from_partial_tree<S: NodeStore<DoubleSha256>>(
+        tree: &PartialTree<DoubleSha256, S>,
+        transactions_count: u32,
+        matched_indices: &[usize],
+    ) -> Result<Self, Error> {
+        if transactions_count == 0 || transactions_count > MAX_BLOCK_TRANSACTIONS {
+            return Err(Error::invalid_merkle_block(
+                "the number of transactions is out of range",
+            ));
+        }
+        let mut matches = vec![false; transactions_count as usize];
+        for index in matched_indices {
+            *matches.get_mut(*index).ok_or_else(|| {
+                Error::leaf_index_out_of_range(*index, transactions_count as usize)
+            })? = true;
+        }
+
+        let mut partial_merkle_tree = Self::new(transactions_count, Vec::new(), Vec::new());
+        partial_merkle_tree.traverse_and_build(
+            tree,
+            &matches,
+            tree_height(transactions_count),
+            0,
+        )?;
+        Ok(partial_merkle_tree)
+    }
+
+    /// Returns the number of transactions in the block
+    pub fn transactions_count(&self) -> u32 {
+        self.transactions_count
+    }
+
+    /// Returns the hashes in the depth-first order
+    pub fn hashes(&self) -> &[[u8; 32]] {
+        &self.hashes
+    }
+
+    /// Returns the flag bits in the depth-first order. Deserialized trees are padded with
+    /// `false` to whole bytes.
+    pub fn flags(&self) -> &[bool] {
+        &self.flags
+    }
+
+    /// Calculates the nodes of the tree the same way Bitcoin Core's `ExtractMatches` does.
+    /// Returns a [`PartialTree`] with the nodes of the proof and the calculated ancestors,
+    /// whose root is the merkle root of the block, and the matched transactions with their
+    /// indices in the block.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the tree is malformed: the number of transactions is out of range,
+    /// some hashes or flags are missing or unused, or two children of a node are equal, which
+    /// is the CVE-2012-2459 mutation.
+    pub fn to_partial_tree(
+        &self,
+    ) -> Result<(PartialTree<DoubleSha256>, Vec<MatchedTransaction>), Error> {
+        if self.transactions_count == 0 || self.transactions_count > MAX_BLOCK_TRANSACTIONS {
+            return Err(Error::invalid_merkle_block(
+                "the number of transactions is out of range",
+            ));
+        }
+        if self.hashes.len() > self.transactions_count as usize {
+            return Err(Error::invalid_merkle_block(
+                "there are more hashes than transactions",
+            ));
+        }
+        if self.flags.len() < self.hashes.len() {
+            return Err(Error::invalid_merkle_block(
+                "there are fewer flags than hashes",
+            ));
+        }
+
+        let height = tree_height(self.transactions_count);
+        let mut extraction = Extraction {
+            layers: vec![Vec::new(); height + 1],
+            matches: Vec::new(),
+            flags_used: 0,
+            hashes_used: 0,
+        };
+        self.traverse_and_extract(&mut extraction, height, 0)?;
+
+        // Flags are serialized in whole bytes, so only the padding of the last one can be unused
+        if extraction.flags_used.div_ceil(8) != self.flags.len().div_ceil(8) {
+            return Err(Error::invalid_merkle_block("not all flags are used"));
+        }
+        if extraction.hashes_used != self.hashes.len() {
+            return Err(Error::invalid_merkle_block("not all hashes are used"));
+        }
+
+        // A single transaction is also the root, like in `PartialTree::from_leaves`
+        if height == 0 {
+            let root_layer = extraction.layers[0].clone();
+            extraction.layers.push(root_layer);
+        }
+
+        let tree = PartialTree::with_store(MemoryNodeStore::from_layers(extraction.layers));
+        Ok((tree, extraction.matches))
+    }
+
+    /// Parses the partial merkle tree serialized as in the `merkleblock` message
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the bytes are truncated, have trailing data or a non-canonical
+    /// length prefix.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader { bytes };
+        let partial_merkle_tree = Self::read(&mut reader)?;
+        reader.finish()?;
+        Ok(partial_merkle_tree)
+    }
+
+    /// Serializes the partial merkle tree as in the `merkleblock` message: the number of
+    /// transactions, the hashes and the flag bits packed into bytes, least significant first
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&self.transactions_count.to_le_bytes());
+
+        write_compact_size(&mut bytes, self.hashes.len());
+        for hash in &self.hashes {
+            bytes.extend_from_slice(hash);
+        }
+
+        let mut flag_bytes = vec![0u8; self.flags.len().div_ceil(8)];
+        for (i, flag) in self.flags.iter().enumerate() {
+            flag_bytes[i / 8] |= u8::from(*flag) << (i % 8);
+        }
+        write_compact_size(&mut bytes, flag_bytes.len());
+        bytes.extend_from_slice(&flag_bytes);
+
+        bytes
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let transactions_count = reader.read_u32()?;
+
+        let hashes_len = reader.read_compact_size()?;
+        let mut hashes = Vec::with_capacity(hashes_len);
+        for _ in 0..hashes_len {
+            hashes.push(reader.read_hash()?);
+        }
+
+        let flag_bytes_len = reader.read_compact_size()?;
+        let flags = reader
+            .take(flag_bytes_len)?
+            .iter()
+            .flat_map(|byte| (0..8).map(move |bit| (byte >> bit) & 1 == 1))
+            .collect();
+
+        Ok(Self::new(transactions_count, hashes, flags))
+    }
+
+    /// Bitcoin Core's `TraverseAndBuild`
+    fn traverse_and_build<S: NodeStore<DoubleSha256>>(
+        &mut self,
+        tree: &PartialTree<DoubleSha256, S>,
+        matches: &[bool],
+        height: usize,
+        position: usize,
+    ) -> Result<(), Error> {
+        let first_leaf = position << height;
+        let last_leaf = ((position + 1) << height).min(matches.len());
+        let parent_of_match = matches[first_leaf..last_leaf]
+            .iter()
+            .any(|matched| *matched);
+        self.flags.push(parent_of_match);
+
+        if height == 0 || !parent_of_match {
+            let hash = tree
+                .try_get(height, position)
+                .map_err(Error::node_store)?
+                .ok_or_else(Error::not_enough_helper_nodes)?;
+            self.hashes.push(hash);
+        } else {
+            self.traverse_and_build(tree, matches, height - 1, position * 2)?;
+            if position * 2 + 1 < tree_width(self.transactions_count, height - 1) {
+                self.traverse_and_build(tree, matches, height - 1, position * 2 + 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Bitcoin Core's `TraverseAndExtract`
+    fn traverse_and_extract(
+        &self,
+        extraction: &mut Extraction,
+        height: usize,
+        position: usize,
+    ) -> Result<[u8; 32], Error> {
+        let parent_of_match = *self
+            .flags
+            .get(extraction.flags_used)
+            .ok_or_else(|| Error::invalid_merkle_block("there are not enough flags"))?;
+        extraction.flags_used += 1;
+
+        let hash = if height == 0 || !parent_of_match {
+            let hash = *self
+                .hashes
+                .get(extraction.hashes_used)
+                .ok_or_else(|| Error::invalid_merkle_block("there are not enough hashes"))?;
+            extraction.hashes_used += 1;
+            if height == 0 && parent_of_match {
+                extraction.matches.push((position, hash));
+            }
+            hash
+        } else {
+            let left = self.traverse_and_extract(extraction, height - 1, position * 2)?;
+            let right = if position * 2 + 1 < tree_width(self.transactions_count, height - 1) {
+                let right = self.traverse_and_extract(extraction, height - 1, position * 2 + 1)?;
+                if right == left {
+                    return Err(Error::invalid_merkle_block(
+                        "two children of a node are equal, the tree is mutated",
+                    ));
+                }
+                Some(right)
+            } else {
+                None
+            };
+            TreeProperties::BITCOIN
+                .concat_and_hash::<DoubleSha256>(&left, Some(right.as_ref().unwrap_or(&left)))
+        };
+
+        extraction.layers[height].push((position, hash));
+        Ok(hash)
+    }
+}
+
+/// State of [`PartialMerkleTree::to_partial_tree`]. Nodes are visited left to right, so every
+/// layer stays sorted by index.
+struct Extraction {
+    layers: Vec<Vec<MatchedTransaction>>,
+    matches: Vec<MatchedTransaction>,
+    flags_used: usize,
+    hashes_used: usize,
+}
+
+/// [`MerkleBlock`] is a block header with a [`PartialMerkleTree`] of its transactions, as
+/// sent in the BIP37 `merkleblock` message and returned by the `gettxoutproof` RPC.
+///
+/// ## Examples
+///
+/// ```
+/// # use rs_merkle::bitcoin::{self, MerkleBlock, PartialMerkleTree};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let txids: Vec<[u8; 32]> = (0u8..7).map(|i| [i; 32]).collect();
+/// let root = bitcoin::merkle_root(&txids).ok_or("couldn't get the merkle root")?;
+///
+/// let mut header = [0u8; 80];
+/// header[36..68].copy_from_slice(&root);
+/// let partial_merkle_tree = PartialMerkleTree::from_txids(&txids, &[1, 5])?;
+/// let merkle_block = MerkleBlock::new(header, partial_merkle_tree);
+///
+/// let merkle_block = MerkleBlock::from_bytes(&merkle_block.to_bytes())?;
+/// assert_eq!(merkle_block.verify()?, vec![(1, txids[1]), (5, txids[5])]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleBlock {
+    header: [u8; BLOCK_HEADER_SIZE],
+    partial_merkle_tree: PartialMerkleTree,
+}
+
+impl MerkleBlock {
+    pub fn new(header: [u8; BLOCK_HEADER_SIZE], partial_merkle_tree: PartialMerkleTree) -> Self {
+        Self {
+            header,
+            partial_merkle_tree,
+        }
+    }
+
+    /// Returns the serialized block header
+    pub fn header(&self) -> &[u8; BLOCK_HEADER_SIZE] {
+        &self.header
+    }
+
+    pub fn partial_merkle_tree(&self) -> &PartialMerkleTree {
+        &self.partial_merkle_tree
+    }
+
+    /// Returns the hash of the block in the internal byte order
+    pub fn block_hash(&self) -> [u8; 32] {
+        DoubleSha256::hash(&self.header)
+    }
+
+    /// Returns the merkle root committed to by the block header
+    pub fn merkle_root(&self) -> [u8; 32] {
+        let mut merkle_root = [0u8; 32];
+        merkle_root.copy_from_slice(&self.header[HEADER_MERKLE_ROOT]);
+        merkle_root
+    }
+
+    /// Verifies the partial merkle tree against the merkle root of the header and returns the
+    /// matched transactions with their indices in the block. The header itself, including its
+    /// proof of work, isn't checked.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the partial merkle tree is malformed or mutated, see
+    /// [`PartialMerkleTree::to_partial_tree`], or its root doesn't match the header.
+    pub fn verify(&self) -> Result<Vec<MatchedTransaction>, Error> {
+        let (tree, matches) = self.partial_merkle_tree.to_partial_tree()?;
+        if tree.root() != Some(self.merkle_root()) {
+            return Err(Error::invalid_merkle_block(
+                "the merkle root doesn't match the block header",
+            ));
+        }
+        Ok(matches)
+    }
+
+    /// Parses a serialized `merkleblock` message
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the bytes are truncated, have trailing data or a non-canonical
+    /// length prefix.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader { bytes };
+        let mut header = [0u8; BLOCK_HEADER_SIZE];
+        header.copy_from_slice(reader.take(BLOCK_HEADER_SIZE)?);
+        let partial_merkle_tree = PartialMerkleTree::read(&mut reader)?;
+        reader.finish()?;
+        Ok(Self::new(header, partial_merkle_tree))
+    }
+
+    /// Serializes the merkle block as in the `merkleblock` message
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header.to_vec();
+        bytes.extend(self.partial_merkle_tree.to_bytes());
+        bytes
+    }
+}
+
+fn write_compact_size(bytes: &mut Vec<u8>, size: usize) {
+    match size {
+        0..=0xfc => bytes.push(size as u8),
+        0xfd..=0xffff => {
+            bytes.push(0xfd);
+            bytes.extend_from_slice(&(size as u16).to_le_bytes());
+        }
+        0x10000..=0xffff_ffff => {
+            bytes.push(0xfe);
+            bytes.extend_from_slice(&(size as u32).to_le_bytes());
+        }
+        _ => {
+            bytes.push(0xff);
+            bytes.extend_from_slice(&(size as u64).to_le_bytes());
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if len > self.bytes.len() {
+            return Err(Error::invalid_merkle_block("unexpected end of data"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn read_hash(&mut self) -> Result<[u8; 32], Error> {
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(self.take(32)?);
+        Ok(hash)
+    }
+
+    /// Reads a length prefix, rejecting encodings that aren't the shortest possible
+    fn read_compact_size(&mut self) -> Result<usize, Error> {
+        let (size, min_size) = match self.take(1)?[0] {
+            0xfd => {
+                let mut bytes = [0u8; 2];
+                bytes.copy_from_slice(self.take(2)?);
+                (u64::from(u16::from_le_bytes(bytes)), 0xfd)
+            }
+            0xfe => (u64::from(self.read_u32()?), 0x10000),
+            0xff => {
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(self.take(8)?);
+                (u64::from_le_bytes(bytes), 0x1_0000_0000)
+            }
+            size => return Ok(usize::from(size)),
+        };
+        if size < min_size {
+            return Err(Error::invalid_merkle_block("non-canonical length prefix"));
+        }
+        // Every item takes at least one byte, so a longer size can't be valid
+        if size > self.bytes.len() as u64 {
+            return Err(Error::invalid_merkle_block("unexpected end of data"));
+        }
+        Ok(size as usize)
+    }
+
+    fn finish(&self) -> Result<(), Error> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::invalid_merkle_block("trailing bytes after the data"))
+        }
+    }
+}
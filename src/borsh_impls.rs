@@ -0,0 +1,106 @@
+//! `BorshSerialize` and `BorshDeserialize` implementations for the types that are generic over a
+//! [`Hasher`]. A list of hashes is encoded the same way Borsh encodes `Vec<[u8; N]>`: a `u32`
+//! length followed by the raw hashes. Indices and the number of leaves are encoded as `u64`.
+
+use crate::prelude::*;
+use crate::{utils::properties::TreeProperties, Hasher, IndexedMerkleProof, MerkleProof};
+use borsh::{
+    io::{Error, ErrorKind, Read, Result, Write},
+    BorshDeserialize, BorshSerialize,
+};
+use core::convert::TryFrom;
+
+// Lengths are read from untrusted input, so they are not used to preallocate more than this
+const MAX_PREALLOCATED_LEN: usize = 1024;
+
+fn write_len<W: Write>(len: usize, writer: &mut W) -> Result<()> {
+    u32::try_from(len)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "length doesn't fit into u32"))?
+        .serialize(writer)
+}
+
+fn read_len<R: Read>(reader: &mut R) -> Result<usize> {
+    usize::try_from(u32::deserialize_reader(reader)?)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "length doesn't fit into usize"))
+}
+
+fn read_usize<R: Read>(reader: &mut R) -> Result<usize> {
+    usize::try_from(u64::deserialize_reader(reader)?)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "value doesn't fit into usize"))
+}
+
+fn write_hashes<T: Hasher, W: Write>(hashes: &[T::Hash], writer: &mut W) -> Result<()> {
+    write_len(hashes.len(), writer)?;
+    for hash in hashes {
+        let bytes: Vec<u8> = (*hash).into();
+        writer.write_all(&bytes)?;
+    }
+    Ok(())
+}
+
+fn read_hashes<T: Hasher, R: Read>(reader: &mut R) -> Result<Vec<T::Hash>> {
+    let len = read_len(reader)?;
+    let mut hashes = Vec::with_capacity(len.min(MAX_PREALLOCATED_LEN));
+    for _ in 0..len {
+        let mut bytes = vec![0u8; T::hash_size()];
+        reader.read_exact(&mut bytes)?;
+        let hash = T::Hash::try_from(bytes).map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                "couldn't convert bytes into Hasher::Hash",
+            )
+        })?;
+        hashes.push(hash);
+    }
+    Ok(hashes)
+}
+
+/// Serialized as a list of proof hashes
+impl<T: Hasher> BorshSerialize for MerkleProof<T> {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_hashes::<T, W>(self.proof_hashes(), writer)
+    }
+}
+
+impl<T: Hasher> BorshDeserialize for MerkleProof<T> {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        Ok(MerkleProof::new(read_hashes::<T, R>(reader)?))
+    }
+}
+
+/// Serialized as the proof, followed by the leaf indices, the number of leaves and the tree
+/// properties
+impl<T: Hasher> BorshSerialize for IndexedMerkleProof<T> {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        BorshSerialize::serialize(self.proof(), writer)?;
+        write_len(self.leaf_indices().len(), writer)?;
+        for leaf_index in self.leaf_indices() {
+            (*leaf_index as u64).serialize(writer)?;
+        }
+        (self.total_leaves_count() as u64).serialize(writer)?;
+        self.tree_properties().serialize(writer)
+    }
+}
+
+impl<T: Hasher> BorshDeserialize for IndexedMerkleProof<T> {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let proof = MerkleProof::<T>::deserialize_reader(reader)?;
+        let leaf_indices_len = read_len(reader)?;
+        let mut leaf_indices = Vec::with_capacity(leaf_indices_len.min(MAX_PREALLOCATED_LEN));
+        for _ in 0..leaf_indices_len {
+            leaf_indices.push(read_usize(reader)?);
+        }
+        let total_leaves_count = read_usize(reader)?;
+        let tree_properties = TreeProperties::deserialize_reader(reader)?;
+
+        let indexed_proof =
+            IndexedMerkleProof::new(proof, leaf_indices, total_leaves_count, tree_properties);
+        indexed_proof.validate().map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                "proof doesn't match its leaf indices",
+            )
+        })?;
+        Ok(indexed_proof)
+    }
+}
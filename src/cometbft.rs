@@ -0,0 +1,243 @@
+//! CometBFT (formerly Tendermint) simple merkle trees, the ones `crypto/merkle` builds for
+//! block headers, transactions, validator sets and evidence.
+//!
+//! `HashFromByteSlices` hashes every item with the `0x00` prefix, see [`leaf_hash`], and
+//! splits the items at the largest power of two smaller than their number, see
+//! [`split_point`]. Both halves are hashed recursively and joined with the `0x01` prefix, see
+//! [`inner_hash`]. The left half of every split is a complete subtree, so this is the tree
+//! rs_merkle builds layer by layer when a node without a right sibling is promoted, the
+//! RFC 6962 layout of [`TreeProperties::COMETBFT`]. The only difference is that CometBFT items
+//! are arbitrary bytes, while rs_merkle leaves are hashes, so the functions of this module
+//! take the items themselves.
+//!
+//! When the items are hashes, like the transaction hashes that `Data.Hash` commits to, a
+//! [`MerkleTree`] with [`TreeProperties::COMETBFT`] has the same root and its proofs contain
+//! the same hashes as the [`Proof::aunts`].
+//!
+//! ## Examples
+//!
+//! ```
+//! # use rs_merkle::{MerkleTree, algorithms::Sha256, cometbft, utils::properties::TreeProperties, Hasher};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let txs: Vec<&[u8]> = vec![b"tx1", b"tx2", b"tx3"];
+//! let tx_hashes: Vec<[u8; 32]> = txs.iter().map(|tx| Sha256::hash(tx)).collect();
+//!
+//! let (data_hash, proofs) = cometbft::proofs_from_byte_slices(&tx_hashes);
+//! assert!(proofs[2].verify(&data_hash, &tx_hashes[2]));
+//!
+//! let merkle_tree = MerkleTree::<Sha256>::from_leaves(&tx_hashes, TreeProperties::COMETBFT);
+//! assert_eq!(merkle_tree.root(), Some(data_hash));
+//! assert_eq!(merkle_tree.proof(&[2]).proof_hashes(), proofs[2].aunts());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`MerkleTree`]: crate::MerkleTree
+//! [`TreeProperties::COMETBFT`]: crate::utils::properties::TreeProperties::COMETBFT
+use crate::prelude::*;
+use crate::{
+    algorithms::Sha256, utils, utils::properties::TreeProperties, IndexedMerkleProof, MerkleProof,
+    PartialTree,
+};
+
+/// The largest number of aunts a [`Proof`] can have, `MaxAunts` in CometBFT
+pub const MAX_AUNTS: usize = 100;
+
+/// Returns the root of a tree without items, the hash of an empty input
+pub fn empty_hash() -> [u8; 32] {
+    Sha256::hash(&[])
+}
+
+/// Hashes an item into a leaf, `sha256(0x00 ++ item)`
+pub fn leaf_hash(item: &[u8]) -> [u8; 32] {
+    Sha256::hash(&[&[0x00], item].concat())
+}
+
+/// Hashes two nodes into their parent, `sha256(0x01 ++ left ++ right)`
+pub fn inner_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    TreeProperties::COMETBFT.concat_and_hash::<Sha256>(left, Some(right))
+}
+
+/// Returns the number of items in the left subtree of a tree with the given number of items,
+/// the largest power of two smaller than it, or `0` if there is only one item. Computed from the
+/// bit length the way CometBFT's `getSplitPoint` does, so it can't overflow for any count.
+pub fn split_point(items_count: usize) -> usize {
+    if items_count <= 1 {
+        return 0;
+    }
+    let largest_power_of_two = 1 << (usize::BITS - items_count.leading_zeros() - 1);
+    if largest_power_of_two == items_count {
+        largest_power_of_two / 2
+    } else {
+        largest_power_of_two
+    }
+}
+
+/// Calculates the root of the items the way CometBFT's `HashFromByteSlices` does
+pub fn hash_from_byte_slices<I: AsRef<[u8]>>(items: &[I]) -> [u8; 32] {
+    match tree(items) {
+        Some(tree) => tree.root().unwrap_or_else(empty_hash),
+        None => empty_hash(),
+    }
+}
+
+/// Calculates the root of the items and a proof for every item, the way CometBFT's
+/// `ProofsFromByteSlices` does
+pub fn proofs_from_byte_slices<I: AsRef<[u8]>>(items: &[I]) -> ([u8; 32], Vec<Proof>) {
+    let tree = match tree(items) {
+        Some(tree) => tree,
+        None => return (empty_hash(), Vec::new()),
+    };
+
+    let proofs = items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let mut aunts = Vec::new();
+            let mut node_index = index;
+            for layer_index in 0..tree.depth() {
+                let sibling_index = utils::indices::get_sibling_index(node_index);
+                if let Some(sibling) = tree.get(layer_index, sibling_index) {
+                    aunts.push(sibling);
+                }
+                node_index /= 2;
+            }
+            Proof::new(items.len(), index, leaf_hash(item.as_ref()), aunts)
+        })
+        .collect();
+    let root = tree.root().unwrap_or_else(empty_hash);
+    (root, proofs)
+}
+
+/// Builds the tree over the leaf hashes of the items, `None` if there are no items
+fn tree<I: AsRef<[u8]>>(items: &[I]) -> Option<PartialTree<Sha256>> {
+    if items.is_empty() {
+        return None;
+    }
+    let leaves = items
+        .iter()
+        .map(|item| leaf_hash(item.as_ref()))
+        .enumerate()
+        .collect();
+    PartialTree::<Sha256>::build(
+        vec![leaves],
+        utils::indices::tree_depth(items.len()),
+        TreeProperties::COMETBFT,
+    )
+    .ok()
+}
+
+/// [`Proof`] is CometBFT's `merkle.Proof`: the proof that the item with the given index is one
+/// of the items of a tree. The aunts are the siblings of the nodes on the way from the leaf
+/// to the root, starting from the sibling of the leaf.
+///
+/// ## Examples
+///
+/// ```
+/// # use rs_merkle::cometbft::{self, Proof};
+/// let items: Vec<&[u8]> = vec![b"a", b"b", b"c", b"d", b"e"];
+/// let (root, proofs) = cometbft::proofs_from_byte_slices(&items);
+///
+/// // The last item is the right half of the first split
+/// assert_eq!(proofs[4].aunts(), &[cometbft::hash_from_byte_slices(&items[..4])]);
+/// assert!(proofs[4].verify(&root, b"e"));
+/// assert!(!proofs[4].verify(&root, b"d"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Proof {
+    total: usize,
+    index: usize,
+    leaf_hash: [u8; 32],
+    aunts: Vec<[u8; 32]>,
+}
+
+impl Proof {
+    pub fn new(total: usize, index: usize, leaf_hash: [u8; 32], aunts: Vec<[u8; 32]>) -> Self {
+        Self {
+            total,
+            index,
+            leaf_hash,
+            aunts,
+        }
+    }
+
+    /// Returns the number of items in the tree
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Returns the index of the proved item
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn leaf_hash(&self) -> [u8; 32] {
+        self.leaf_hash
+    }
+
+    pub fn aunts(&self) -> &[[u8; 32]] {
+        &self.aunts
+    }
+
+    /// Calculates the root from the leaf hash and the aunts, descending the tree by its split
+    /// points like CometBFT's `ComputeRootHash`. Returns `None` if the index is out of range or
+    /// the number of aunts doesn't match the position of the leaf.
+    pub fn compute_root_hash(&self) -> Option<[u8; 32]> {
+        compute_hash_from_aunts(self.index, self.total, self.leaf_hash, &self.aunts)
+    }
+
+    /// Verifies that the item is the one with the proved index in the tree with the given
+    /// root, like CometBFT's `Proof.Verify`. Proofs with more than [`MAX_AUNTS`] aunts are
+    /// rejected.
+    pub fn verify(&self, root: &[u8; 32], item: &[u8]) -> bool {
+        if self.aunts.len() > MAX_AUNTS || self.leaf_hash != leaf_hash(item) {
+            return false;
+        }
+        self.compute_root_hash().as_ref() == Some(root)
+    }
+
+    /// Converts the proof to an [`IndexedMerkleProof`] of a tree with
+    /// [`TreeProperties::COMETBFT`]. It verifies the 32-byte item that the leaf hash was
+    /// calculated from, like a transaction hash.
+    ///
+    /// [`TreeProperties::COMETBFT`]: crate::utils::properties::TreeProperties::COMETBFT
+    pub fn to_indexed_proof(&self) -> IndexedMerkleProof<Sha256> {
+        IndexedMerkleProof::new(
+            MerkleProof::new(self.aunts.clone()),
+            vec![self.index],
+            self.total,
+            TreeProperties::COMETBFT,
+        )
+    }
+}
+
+/// The aunts are consumed from the end, since the last one is the sibling of the topmost
+/// subtree
+fn compute_hash_from_aunts(
+    index: usize,
+    total: usize,
+    leaf_hash: [u8; 32],
+    aunts: &[[u8; 32]],
+) -> Option<[u8; 32]> {
+    if index >= total {
+        return None;
+    }
+    if total == 1 {
+        return if aunts.is_empty() {
+            Some(leaf_hash)
+        } else {
+            None
+        };
+    }
+
+    let (last_aunt, aunts) = aunts.split_last()?;
+    let left_count = split_point(total);
+    if index < left_count {
+        let left = compute_hash_from_aunts(index, left_count, leaf_hash, aunts)?;
+        Some(inner_hash(&left, last_aunt))
+    } else {
+        let right =
+            compute_hash_from_aunts(index - left_count, total - left_count, leaf_hash, aunts)?;
+        Some(inner_hash(last_aunt, &right))
+    }
+}
@@ -0,0 +1,165 @@
+use crate::prelude::*;
+use crate::{
+    error::Error,
+    utils::properties::{OddNodeStrategy, TreeProperties},
+    Hasher,
+};
+
+/// Pairs are hashed like OpenZeppelin's `commutativeKeccak256`, with the smaller node first
+pub(crate) const SORTED_PAIRS: TreeProperties = TreeProperties {
+    sorted_pair_enabled: true,
+    domain_separation: None,
+    odd_node_strategy: OddNodeStrategy::Promote,
+};
+
+/// [`CompactMultiProof`] is a multiproof in the `proof` and `proofFlags` layout of
+/// OpenZeppelin's `MerkleProof.multiProofVerify`, so it can be verified by Solidity contracts.
+///
+/// The verifier keeps a queue of known nodes that starts with the leaves. For every flag it
+/// takes the next node from the queue and hashes it with either the next node from the queue,
+/// if the flag is `true`, or the next proof hash, if the flag is `false`. The result is appended
+/// to the queue and the last computed hash is the root. Pairs are hashed in the sorted order,
+/// the same way [`MerkleTree`] hashes them with [`TreeProperties::sorted_pair_enabled`].
+///
+/// Proofs are generated with [`MerkleTree::compact_multiproof`]. Use [`Keccak256`] as the
+/// hasher to get proofs that OpenZeppelin's verifier accepts.
+///
+/// ## Examples
+///
+/// ```
+/// # use rs_merkle::{MerkleTree, algorithms::Keccak256, Hasher, utils::properties::TreeProperties};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let tree_properties = TreeProperties {
+///     sorted_pair_enabled: true,
+///     ..Default::default()
+/// };
+/// let leaves: Vec<[u8; 32]> = ["a", "b", "c", "d", "e", "f"]
+///     .iter()
+///     .map(|x| Keccak256::hash(x.as_bytes()))
+///     .collect();
+/// let merkle_tree = MerkleTree::<Keccak256>::from_leaves(&leaves, tree_properties);
+/// let root = merkle_tree.root().ok_or("couldn't get the merkle root")?;
+///
+/// let proof = merkle_tree.compact_multiproof(&[4, 1])?;
+///
+/// // Leaves are passed in the ascending order of their indices
+/// assert!(proof.verify(root, &[leaves[1], leaves[4]]));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`MerkleTree`]: crate::MerkleTree
+/// [`MerkleTree::compact_multiproof`]: crate::MerkleTree::compact_multiproof
+/// [`TreeProperties::sorted_pair_enabled`]: crate::utils::properties::TreeProperties::sorted_pair_enabled
+/// [`Keccak256`]: crate::algorithms::Keccak256
+#[derive(Clone)]
+pub struct CompactMultiProof<T: Hasher> {
+    proof_hashes: Vec<T::Hash>,
+    proof_flags: Vec<bool>,
+}
+
+impl<T: Hasher> CompactMultiProof<T> {
+    pub fn new(proof_hashes: Vec<T::Hash>, proof_flags: Vec<bool>) -> Self {
+        Self {
+            proof_hashes,
+            proof_flags,
+        }
+    }
+
+    /// Returns the `proof` array of OpenZeppelin's `multiProofVerify`
+    pub fn proof_hashes(&self) -> &[T::Hash] {
+        &self.proof_hashes
+    }
+
+    /// Returns the `proofFlags` array of OpenZeppelin's `multiProofVerify`
+    pub fn proof_flags(&self) -> &[bool] {
+        &self.proof_flags
+    }
+
+    /// Verifies that the leaves, given in the ascending order of their indices, are included in
+    /// the tree with the given root
+    pub fn verify(&self, root: T::Hash, leaf_hashes: &[T::Hash]) -> bool {
+        match self.root(leaf_hashes) {
+            Ok(extracted_root) => extracted_root == root,
+            Err(_) => false,
+        }
+    }
+
+    /// Calculates the root from the leaves, given in the ascending order of their indices, the
+    /// same way OpenZeppelin's `processMultiProof` does.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the number of leaves and proof hashes doesn't match the number of
+    /// flags.
+    pub fn root(&self, leaf_hashes: &[T::Hash]) -> Result<T::Hash, Error> {
+        if leaf_hashes.len() + self.proof_hashes.len() != self.proof_flags.len() + 1 {
+            return Err(Error::not_enough_hashes_to_calculate_root());
+        }
+
+        let mut hashes: Vec<T::Hash> = Vec::with_capacity(self.proof_flags.len());
+        let mut leaf_position = 0;
+        let mut hash_position = 0;
+        let mut proof_position = 0;
+
+        let mut next_known = |hashes: &Vec<T::Hash>| -> Result<T::Hash, Error> {
+            let node = if leaf_position < leaf_hashes.len() {
+                leaf_position += 1;
+                leaf_hashes[leaf_position - 1]
+            } else {
+                hash_position += 1;
+                *hashes
+                    .get(hash_position - 1)
+                    .ok_or_else(Error::not_enough_hashes_to_calculate_root)?
+            };
+            Ok(node)
+        };
+
+        for flag in &self.proof_flags {
+            let left = next_known(&hashes)?;
+            let right = if *flag {
+                next_known(&hashes)?
+            } else {
+                proof_position += 1;
+                *self
+                    .proof_hashes
+                    .get(proof_position - 1)
+                    .ok_or_else(Error::not_enough_hashes_to_calculate_root)?
+            };
+            hashes.push(SORTED_PAIRS.concat_and_hash::<T>(&left, Some(&right)));
+        }
+
+        match (
+            hashes.last(),
+            leaf_hashes.first(),
+            self.proof_hashes.first(),
+        ) {
+            (Some(root), _, _) => {
+                if proof_position != self.proof_hashes.len() {
+                    return Err(Error::too_many_proof_hashes());
+                }
+                Ok(*root)
+            }
+            (None, Some(leaf), _) => Ok(*leaf),
+            (None, None, Some(root)) => Ok(*root),
+            (None, None, None) => Err(Error::not_enough_hashes_to_calculate_root()),
+        }
+    }
+}
+
+impl<T: Hasher> PartialEq for CompactMultiProof<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.proof_hashes == other.proof_hashes && self.proof_flags == other.proof_flags
+    }
+}
+
+impl<T: Hasher> Eq for CompactMultiProof<T> where T::Hash: Eq {}
+
+impl<T: Hasher> core::fmt::Debug for CompactMultiProof<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CompactMultiProof")
+            .field("proof_hashes", &self.proof_hashes)
+            .field("proof_flags", &self.proof_flags)
+            .finish()
+    }
+}
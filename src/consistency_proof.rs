@@ -0,0 +1,122 @@
+use crate::prelude::*;
+use crate::{error::Error, utils::properties::TreeProperties, Hasher, MerkleProof};
+
+/// [`ConsistencyProof`] proves that a tree of one size is a prefix of a tree of a bigger size,
+/// i.e. that the bigger tree was obtained from the smaller one by only appending leaves.
+///
+/// Proofs are generated with [`MerkleTree::consistency_proof`] and follow
+/// [RFC 6962](https://datatracker.ietf.org/doc/html/rfc6962#section-2.1.2). The tree built by
+/// [`MerkleTree`] has the same shape as the RFC 6962 tree, so proofs and the verification
+/// algorithm are the same as in transparency logs, with the node hashing defined by the
+/// [`Hasher`] and [`TreeProperties`].
+///
+/// ## Examples
+///
+/// ```
+/// # use rs_merkle::{MerkleTree, ConsistencyProof, algorithms::Sha256, Hasher, utils::properties::TreeProperties};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let tree_properties = TreeProperties::default();
+/// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+///
+/// merkle_tree.append(&mut vec![Sha256::hash(b"a"), Sha256::hash(b"b"), Sha256::hash(b"c")]);
+/// merkle_tree.commit();
+/// let old_root = merkle_tree.root().ok_or("couldn't get the merkle root")?;
+///
+/// merkle_tree.append(&mut vec![Sha256::hash(b"d"), Sha256::hash(b"e")]);
+/// merkle_tree.commit();
+/// let new_root = merkle_tree.root().ok_or("couldn't get the merkle root")?;
+///
+/// let proof = merkle_tree.consistency_proof(3)?;
+/// assert!(proof.verify(old_root, new_root, 3, 5, tree_properties));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`MerkleTree`]: crate::MerkleTree
+/// [`MerkleTree::consistency_proof`]: crate::MerkleTree::consistency_proof
+#[derive(Clone)]
+pub struct ConsistencyProof<T: Hasher> {
+    proof_hashes: Vec<T::Hash>,
+}
+
+impl<T: Hasher> ConsistencyProof<T> {
+    pub fn new(proof_hashes: Vec<T::Hash>) -> Self {
+        Self { proof_hashes }
+    }
+
+    /// Returns proof hashes in the order defined by RFC 6962
+    pub fn proof_hashes(&self) -> &[T::Hash] {
+        &self.proof_hashes
+    }
+
+    /// Serializes proof hashes to a flat vector of bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        MerkleProof::<T>::new(self.proof_hashes.clone()).to_bytes()
+    }
+
+    /// Parses proof hashes serialized with [`ConsistencyProof::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let proof = MerkleProof::<T>::from_bytes(bytes)?;
+        Ok(Self::new(proof.proof_hashes().to_vec()))
+    }
+
+    /// Verifies that the tree of `old_size` leaves with `old_root` is a prefix of the tree
+    /// of `new_size` leaves with `new_root`.
+    pub fn verify(
+        &self,
+        old_root: T::Hash,
+        new_root: T::Hash,
+        old_size: usize,
+        new_size: usize,
+        tree_properties: TreeProperties,
+    ) -> bool {
+        if old_size == 0 || old_size > new_size {
+            return false;
+        }
+        if old_size == new_size {
+            return self.proof_hashes.is_empty() && old_root == new_root;
+        }
+
+        let mut proof_hashes = self.proof_hashes.clone();
+        // If the old tree is a complete subtree of the new one, its root is the first node
+        if old_size.is_power_of_two() {
+            proof_hashes.insert(0, old_root);
+        }
+
+        let mut old_node = old_size - 1;
+        let mut new_node = new_size - 1;
+        while !old_node.is_multiple_of(2) {
+            old_node >>= 1;
+            new_node >>= 1;
+        }
+
+        let (first, rest) = match proof_hashes.split_first() {
+            Some(split) => split,
+            None => return false,
+        };
+        let mut old_hash = *first;
+        let mut new_hash = *first;
+
+        for hash in rest {
+            if new_node == 0 {
+                return false;
+            }
+
+            if !old_node.is_multiple_of(2) || old_node == new_node {
+                old_hash = tree_properties.concat_and_hash::<T>(hash, Some(&old_hash));
+                new_hash = tree_properties.concat_and_hash::<T>(hash, Some(&new_hash));
+                while old_node.is_multiple_of(2) && old_node != 0 {
+                    old_node >>= 1;
+                    new_node >>= 1;
+                }
+            } else {
+                new_hash = tree_properties.concat_and_hash::<T>(&new_hash, Some(hash));
+            }
+
+            old_node >>= 1;
+            new_node >>= 1;
+        }
+
+        old_hash == old_root && new_hash == new_root && new_node == 0
+    }
+}
@@ -1,4 +1,5 @@
 use crate::prelude::*;
+use crate::CommitRef;
 use core::fmt::{Debug, Display, Formatter};
 
 /// A list specifying general categories of tree traversals/parsing errors.
@@ -21,6 +22,55 @@ pub enum ErrorKind {
     HashConversionError,
     NotEnoughHashesToCalculateRoot,
     LeavesIndicesCountMismatch,
+    /// Proof contains more hashes than needed to calculate the root
+    TooManyProofHashes,
+    /// Leaf index is greater than or equal to the number of leaves in the tree
+    LeafIndexOutOfRange,
+    /// Requested tree size is zero or exceeds the number of leaves in the tree
+    InvalidTreeSize,
+    /// Operation is not supported for the tree built with the given [`TreeProperties`]
+    ///
+    /// [`TreeProperties`]: crate::utils::properties::TreeProperties
+    UnsupportedTreeProperties,
+    /// Commit with the given id or label is not found in the [`MerkleTree`] history
+    ///
+    /// [`MerkleTree`]: crate::MerkleTree
+    CommitNotFound,
+    /// Snapshot bytes can't be parsed into a [`MerkleTree`]
+    ///
+    /// [`MerkleTree`]: crate::MerkleTree
+    InvalidSnapshot,
+    /// Bytes can't be parsed into a proof envelope, or the envelope was produced with a
+    /// different [`Hasher`]
+    ///
+    /// [`Hasher`]: crate::Hasher
+    InvalidProofEnvelope,
+    /// ABI types or values don't match the leaf encoding of a [`StandardMerkleTree`], or its
+    /// dump doesn't describe a valid tree
+    ///
+    /// [`StandardMerkleTree`]: crate::StandardMerkleTree
+    InvalidStandardTree,
+    /// Bytes can't be parsed into a Bitcoin merkle block, or its partial merkle tree is
+    /// malformed, mutated or doesn't match the merkle root of the block header
+    InvalidMerkleBlock,
+    /// Generalized index is zero, doesn't point to a node of the SSZ tree, or overlaps another
+    /// index of the same multiproof
+    InvalidGeneralizedIndex,
+    /// ICS23 proof or proof spec can't be decoded, uses an operation that isn't supported, or
+    /// doesn't match the tree it is converted from or to
+    InvalidIcs23Proof,
+    /// The [`NodeStore`] of a tree has failed to read or write the nodes, for example because
+    /// of an I/O error
+    ///
+    /// [`NodeStore`]: crate::NodeStore
+    NodeStore,
+    /// Number of leaves is too large for the positions of the nodes of a
+    /// [`MerkleMountainRange`] to fit in `usize`
+    ///
+    /// [`MerkleMountainRange`]: crate::MerkleMountainRange
+    TooManyLeaves,
+    /// The same leaf index is given several times with different hashes
+    ConflictingLeaves,
 }
 
 /// The error type for tree traversals/parsing errors of the [`MerkleProof`] and [`PartialTree`].
@@ -81,6 +131,113 @@ impl Error {
         )
     }
 
+    pub fn too_many_proof_hashes() -> Self {
+        Self::new(
+            ErrorKind::TooManyProofHashes,
+            "proof contains more hashes than needed to calculate the root".to_string(),
+        )
+    }
+
+    pub fn leaf_index_out_of_range(index: usize, leaves_len: usize) -> Self {
+        Self::new(
+            ErrorKind::LeafIndexOutOfRange,
+            format!(
+                "leaf index {} is out of range for a tree of {} leaves",
+                index, leaves_len
+            ),
+        )
+    }
+
+    pub fn invalid_tree_size(size: usize, leaves_len: usize) -> Self {
+        Self::new(
+            ErrorKind::InvalidTreeSize,
+            format!(
+                "tree size {} is invalid for a tree of {} leaves",
+                size, leaves_len
+            ),
+        )
+    }
+
+    pub fn unsupported_tree_properties(reason: &str) -> Self {
+        Self::new(
+            ErrorKind::UnsupportedTreeProperties,
+            format!(
+                "operation is not supported by the tree properties: {}",
+                reason
+            ),
+        )
+    }
+
+    pub fn commit_not_found(commit: CommitRef) -> Self {
+        Self::new(
+            ErrorKind::CommitNotFound,
+            format!("commit {} is not found in the history", commit),
+        )
+    }
+
+    pub fn invalid_snapshot(reason: &str) -> Self {
+        Self::new(
+            ErrorKind::InvalidSnapshot,
+            format!("snapshot can not be parsed: {}", reason),
+        )
+    }
+
+    pub fn invalid_proof_envelope(reason: &str) -> Self {
+        Self::new(
+            ErrorKind::InvalidProofEnvelope,
+            format!("proof envelope can not be parsed: {}", reason),
+        )
+    }
+
+    pub fn invalid_standard_tree(reason: &str) -> Self {
+        Self::new(
+            ErrorKind::InvalidStandardTree,
+            format!("standard merkle tree is invalid: {}", reason),
+        )
+    }
+
+    pub fn invalid_merkle_block(reason: &str) -> Self {
+        Self::new(
+            ErrorKind::InvalidMerkleBlock,
+            format!("merkle block is invalid: {}", reason),
+        )
+    }
+
+    pub fn invalid_generalized_index(gindex: u64) -> Self {
+        Self::new(
+            ErrorKind::InvalidGeneralizedIndex,
+            format!("generalized index {} is invalid", gindex),
+        )
+    }
+
+    pub fn invalid_ics23_proof(reason: &str) -> Self {
+        Self::new(
+            ErrorKind::InvalidIcs23Proof,
+            format!("ICS23 proof is invalid: {}", reason),
+        )
+    }
+
+    pub fn node_store(error: impl Display) -> Self {
+        Self::new(
+            ErrorKind::NodeStore,
+            format!("node store has failed: {}", error),
+        )
+    }
+
+    pub fn too_many_leaves(leaves_len: usize) -> Self {
+        Self::new(
+            ErrorKind::TooManyLeaves,
+            format!("{} leaves are too many to address the nodes", leaves_len),
+        )
+    }
+
+    pub fn conflicting_leaves(index: usize) -> Self {
+        Self::new(
+            ErrorKind::ConflictingLeaves,
+            format!("leaf index {} is given with different hashes", index),
+        )
+    }
+
     pub fn kind(&self) -> ErrorKind {
         self.kind
     }
@@ -54,13 +54,19 @@ pub trait Hasher: Clone {
     /// The provided default implementation propagates the left node if it doesn't
     /// have a sibling. The left node should always be present. The right node is optional.
     ///
-    /// For the tree to be compatible with different types of proofs this function
-    /// needs to be overridden. For example, in Bitcoin implementation,
+    /// For the tree to be compatible with different types of proofs the handling of a node
+    /// without a sibling may need to be changed. For example, in Bitcoin implementation,
     /// if the left node doesn't have a sibling it is concatenated to itself and
-    /// then hashed instead of just being propagated to the next level.
+    /// then hashed instead of just being propagated to the next level. Prefer selecting
+    /// [`OddNodeStrategy`] in [`TreeProperties`] to overriding this function, since the
+    /// strategy is also applied when verifying proofs. Bitcoin trees are supported by the
+    /// [`bitcoin`] module.
     ///
+    /// [`bitcoin`]: crate::bitcoin
     /// [`MerkleTree`]: crate::MerkleTree
     /// [`PartialTree`]: crate::PartialTree
+    /// [`OddNodeStrategy`]: crate::utils::properties::OddNodeStrategy
+    /// [`TreeProperties`]: crate::utils::properties::TreeProperties
     fn concat_and_hash(left: &Self::Hash, right: Option<&Self::Hash>) -> Self::Hash {
         let mut concatenated: Vec<u8> = (*left).into();
 
@@ -80,4 +86,14 @@ pub trait Hasher: Clone {
     fn hash_size() -> usize {
         mem::size_of::<Self::Hash>()
     }
+
+    /// Identifies the hashing algorithm in self-describing formats, such as
+    /// [`ProofEnvelope`], so a proof can't be verified with a different hasher by mistake.
+    /// The built-in hashers use small ids starting from 1, `0` means the hasher is not
+    /// identified.
+    ///
+    /// [`ProofEnvelope`]: crate::proof_serializers::ProofEnvelope
+    fn hasher_id() -> u32 {
+        0
+    }
 }
@@ -0,0 +1,77 @@
+use crate::prelude::*;
+use core::fmt::{Display, Formatter};
+
+/// Describes a commit stored in the [`MerkleTree`] history. Returned by
+/// [`MerkleTree::history`].
+///
+/// [`MerkleTree`]: crate::MerkleTree
+/// [`MerkleTree::history`]: crate::MerkleTree::history
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitInfo<H> {
+    /// Commit id. Ids are assigned in ascending order and are never reused, even if the commit
+    /// is rolled back
+    pub id: u64,
+    /// The root of the tree after the commit, `None` if the commit removed all leaves
+    pub root: Option<H>,
+    /// The number of leaves in the tree after the commit
+    pub leaves_len: usize,
+    /// The label set with [`MerkleTree::checkpoint`]
+    ///
+    /// [`MerkleTree::checkpoint`]: crate::MerkleTree::checkpoint
+    pub label: Option<String>,
+}
+
+/// Refers to a commit in the [`MerkleTree`] history either by its id or by its checkpoint label.
+/// Can be created from a `u64` id or a `&str` label.
+///
+/// [`MerkleTree`]: crate::MerkleTree
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommitRef<'a> {
+    Id(u64),
+    Label(&'a str),
+}
+
+impl From<u64> for CommitRef<'_> {
+    fn from(id: u64) -> Self {
+        CommitRef::Id(id)
+    }
+}
+
+impl<'a> From<&'a str> for CommitRef<'a> {
+    fn from(label: &'a str) -> Self {
+        CommitRef::Label(label)
+    }
+}
+
+impl Display for CommitRef<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CommitRef::Id(id) => write!(f, "{}", id),
+            CommitRef::Label(label) => write!(f, "\"{}\"", label),
+        }
+    }
+}
+
+/// Defines which commits are kept in the [`MerkleTree`] history. Older commits are squashed into
+/// a base snapshot: the oldest kept commit, which is listed in the history and can be used with
+/// [`MerkleTree::rollback_to`] and [`MerkleTree::proof_at`], but can't be rolled back itself.
+///
+/// Since the history stores only the nodes overwritten by each commit, squashing commits drops
+/// their data without copying the tree.
+///
+/// [`MerkleTree`]: crate::MerkleTree
+/// [`MerkleTree::rollback_to`]: crate::MerkleTree::rollback_to
+/// [`MerkleTree::proof_at`]: crate::MerkleTree::proof_at
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RetentionPolicy {
+    /// All commits are kept, so the tree can be rolled back to its initial empty state
+    #[default]
+    KeepAll,
+    /// Only the given number of most recent commits is kept. The most recent commit is always
+    /// kept, even if the number is zero
+    KeepLast(usize),
+    /// The commit with the given id is kept as the base snapshot along with all commits made
+    /// after it. If there's no such commit, the oldest commit made after it becomes the base
+    /// snapshot, or the most recent one if all commits are older
+    KeepAfter(u64),
+}
@@ -0,0 +1,166 @@
+//! Conversion of inclusion proofs to and from [ICS23] commitment proofs, the format IBC light
+//! clients use to verify the state of a counterparty chain.
+//!
+//! ICS23 proves a key/value pair: the leaf is calculated from the key and the value with a
+//! [`LeafOp`], and every step towards the root is an [`InnerOp`] that hashes the child between
+//! a prefix and a suffix holding its siblings. The counterparty checks the proof against a
+//! [`ProofSpec`] describing the tree, which [`proof_spec`] returns for the given
+//! [`TreeProperties`].
+//!
+//! An rs_merkle leaf is the result of [`leaf_op`] applied to the key and the value, see
+//! [`leaf_hash`]. The first inner operation of the path hashes it with the leaf prefix, like
+//! [`TreeProperties::hash_leaf`] does, so the tree has to be built with domain separation, and
+//! neither prefix can be [`KEY_VALUE_PREFIX`]. ICS23 doesn't allow inner operations to look
+//! like leaf operations, which is what makes the proofs safe against second-preimage attacks.
+//!
+//! Proofs are serialized with the protobuf encoding of `cosmos.ics23.v1`, so
+//! [`CommitmentProof::to_bytes`] can be passed to IBC as is.
+//!
+//! ## Examples
+//!
+//! ```
+//! # use rs_merkle::{MerkleTree, algorithms::Sha256, ics23::{self, CommitmentProof, ExistenceProof}, utils::properties::{DomainSeparation, TreeProperties}};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let tree_properties = TreeProperties {
+//!     domain_separation: Some(DomainSeparation::RFC_6962),
+//!     ..Default::default()
+//! };
+//! let entries = [("a", "1"), ("b", "2"), ("c", "3")];
+//! let leaves = entries
+//!     .iter()
+//!     .map(|(key, value)| ics23::leaf_hash::<Sha256>(key.as_bytes(), value.as_bytes()))
+//!     .collect::<Result<Vec<_>, _>>()?;
+//! let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+//! let root = merkle_tree.root().ok_or("couldn't get the merkle root")?;
+//!
+//! let proof = merkle_tree.indexed_proof(&[1]);
+//! let existence_proof = ExistenceProof::from_indexed_proof(&proof, b"b", b"2")?;
+//! let commitment_proof = CommitmentProof::from(existence_proof);
+//!
+//! let spec = ics23::proof_spec::<Sha256>(tree_properties)?;
+//! let bytes = commitment_proof.to_bytes();
+//! let commitment_proof = CommitmentProof::from_bytes(&bytes)?;
+//! assert!(ics23::verify_membership(&spec, &root, &commitment_proof, b"b", b"2"));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [ICS23]: https://github.com/cosmos/ics23
+//! [`TreeProperties::hash_leaf`]: crate::utils::properties::TreeProperties::hash_leaf
+use crate::prelude::*;
+use crate::{
+    error::Error,
+    utils::properties::{DomainSeparation, TreeProperties},
+    Hasher,
+};
+
+mod ops;
+mod proof;
+mod protobuf;
+
+pub use ops::{HashOp, InnerOp, LeafOp, LengthOp};
+pub use proof::{CommitmentProof, ExistenceProof, InnerSpec, ProofSpec};
+
+/// Prefix of the [`LeafOp`] calculating rs_merkle leaves from keys and values
+pub const KEY_VALUE_PREFIX: u8 = 0x02;
+
+/// Returns the ICS23 hash operation of the [`Hasher`], identified by [`Hasher::hasher_id`].
+///
+/// ## Errors
+///
+/// Returns an error if ICS23 doesn't have a matching operation, like for
+/// [`DoubleSha256`].
+///
+/// [`DoubleSha256`]: crate::algorithms::DoubleSha256
+pub fn hash_op<T: Hasher>() -> Result<HashOp, Error> {
+    match T::hasher_id() {
+        1 => Ok(HashOp::Sha256),
+        2 => Ok(HashOp::Keccak256),
+        _ => Err(Error::unsupported_tree_properties(
+            "ICS23 doesn't have a hash operation for the hasher",
+        )),
+    }
+}
+
+/// Returns the [`LeafOp`] calculating rs_merkle leaves from keys and values:
+/// `hash(0x02 ++ varint(len(key)) ++ key ++ varint(32) ++ hash(value))`, the same layout as
+/// the leaves of Tendermint's IAVL trees, with a different prefix.
+pub fn leaf_op<T: Hasher>() -> Result<LeafOp, Error> {
+    let hash = hash_op::<T>()?;
+    Ok(LeafOp {
+        hash,
+        prehash_key: HashOp::NoHash,
+        prehash_value: hash,
+        length: LengthOp::VarProto,
+        prefix: vec![KEY_VALUE_PREFIX],
+    })
+}
+
+/// Calculates the rs_merkle leaf of a key/value pair with [`leaf_op`]. Build the tree from
+/// these leaves to convert its proofs to ICS23.
+///
+/// ## Errors
+///
+/// Returns an error if the key or the value is empty, which ICS23 doesn't allow, or the
+/// [`Hasher`] isn't supported.
+pub fn leaf_hash<T: Hasher>(key: &[u8], value: &[u8]) -> Result<T::Hash, Error> {
+    let leaf = leaf_op::<T>()?.apply(key, value)?;
+    T::Hash::try_from(leaf).map_err(|_| Error::vec_to_hash_conversion_error())
+}
+
+/// Returns the [`ProofSpec`] of a tree with the given properties, which the counterparty
+/// verifies the proofs against. The spec doesn't limit the depth of the tree.
+///
+/// ## Errors
+///
+/// Returns an error if the tree doesn't use domain separation, its prefixes are equal or one
+/// of them is [`KEY_VALUE_PREFIX`], or the [`Hasher`] isn't supported.
+pub fn proof_spec<T: Hasher>(tree_properties: TreeProperties) -> Result<ProofSpec, Error> {
+    domain_separation(tree_properties)?;
+
+    Ok(ProofSpec {
+        leaf_spec: Some(leaf_op::<T>()?),
+        inner_spec: Some(InnerSpec {
+            child_order: vec![0, 1],
+            child_size: T::hash_size() as i32,
+            min_prefix_length: 1,
+            max_prefix_length: 1,
+            empty_child: Vec::new(),
+            hash: hash_op::<T>()?,
+        }),
+        max_depth: 0,
+        min_depth: 0,
+        prehash_key_before_comparison: false,
+    })
+}
+
+/// Returns the prefixes of a tree that ICS23 proofs can be converted from
+fn domain_separation(tree_properties: TreeProperties) -> Result<DomainSeparation, Error> {
+    let domain_separation = tree_properties
+        .domain_separation
+        .ok_or_else(|| Error::unsupported_tree_properties("ICS23 proofs need domain separation"))?;
+    if domain_separation.leaf_prefix == domain_separation.node_prefix
+        || domain_separation.leaf_prefix == KEY_VALUE_PREFIX
+        || domain_separation.node_prefix == KEY_VALUE_PREFIX
+    {
+        return Err(Error::unsupported_tree_properties(
+            "ICS23 proofs need distinct prefixes for leaves, nodes and key/value pairs",
+        ));
+    }
+    Ok(domain_separation)
+}
+
+/// Verifies that the proof commits the key/value pair to the root under the spec, like
+/// `VerifyMembership` of ICS23. Proof types other than [`CommitmentProof::Exist`] are not
+/// supported.
+pub fn verify_membership(
+    spec: &ProofSpec,
+    root: &[u8],
+    proof: &CommitmentProof,
+    key: &[u8],
+    value: &[u8],
+) -> bool {
+    match proof {
+        CommitmentProof::Exist(existence_proof) => existence_proof.verify(spec, root, key, value),
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{error::Error, prelude::*};
+use sha2::{Digest, Sha256, Sha512, Sha512_256};
+use sha3::Keccak256;
+
+/// Hash function of an operation, `HashOp` in ICS23's `proofs.proto`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HashOp {
+    /// The data is passed through unchanged
+    #[default]
+    NoHash = 0,
+    Sha256 = 1,
+    Sha512 = 2,
+    Keccak256 = 3,
+    Ripemd160 = 4,
+    /// `ripemd160(sha256(data))`
+    Bitcoin = 5,
+    Sha512256 = 6,
+    Blake2b512 = 7,
+    Blake2s256 = 8,
+    Blake3 = 9,
+}
+
+impl HashOp {
+    pub(crate) fn from_i32(value: i32) -> Option<Self> {
+        let hash_op = match value {
+            0 => HashOp::NoHash,
+            1 => HashOp::Sha256,
+            2 => HashOp::Sha512,
+            3 => HashOp::Keccak256,
+            4 => HashOp::Ripemd160,
+            5 => HashOp::Bitcoin,
+            6 => HashOp::Sha512256,
+            7 => HashOp::Blake2b512,
+            8 => HashOp::Blake2s256,
+            9 => HashOp::Blake3,
+            _ => return None,
+        };
+        Some(hash_op)
+    }
+
+    /// Hashes the data.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error for the hash functions this crate doesn't implement: RIPEMD-160,
+    /// Bitcoin's hash and the BLAKE family.
+    pub fn hash(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        let hash = match self {
+            HashOp::NoHash => data.to_vec(),
+            HashOp::Sha256 => Sha256::digest(data).to_vec(),
+            HashOp::Sha512 => Sha512::digest(data).to_vec(),
+            HashOp::Keccak256 => Keccak256::digest(data).to_vec(),
+            HashOp::Sha512256 => Sha512_256::digest(data).to_vec(),
+            HashOp::Ripemd160
+            | HashOp::Bitcoin
+            | HashOp::Blake2b512
+            | HashOp::Blake2s256
+            | HashOp::Blake3 => {
+                return Err(Error::invalid_ics23_proof(
+                    "the hash operation isn't supported",
+                ))
+            }
+        };
+        Ok(hash)
+    }
+}
+
+/// Length prefix of the key and the value of a leaf, `LengthOp` in ICS23's `proofs.proto`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LengthOp {
+    #[default]
+    NoPrefix = 0,
+    /// Protobuf varint
+    VarProto = 1,
+    VarRlp = 2,
+    Fixed32Big = 3,
+    Fixed32Little = 4,
+    Fixed64Big = 5,
+    Fixed64Little = 6,
+    /// No prefix, the data must be exactly 32 bytes
+    Require32Bytes = 7,
+    /// No prefix, the data must be exactly 64 bytes
+    Require64Bytes = 8,
+}
+
+impl LengthOp {
+    pub(crate) fn from_i32(value: i32) -> Option<Self> {
+        let length_op = match value {
+            0 => LengthOp::NoPrefix,
+            1 => LengthOp::VarProto,
+            2 => LengthOp::VarRlp,
+            3 => LengthOp::Fixed32Big,
+            4 => LengthOp::Fixed32Little,
+            5 => LengthOp::Fixed64Big,
+            6 => LengthOp::Fixed64Little,
+            7 => LengthOp::Require32Bytes,
+            8 => LengthOp::Require64Bytes,
+            _ => return None,
+        };
+        Some(length_op)
+    }
+
+    /// Prefixes the data with its length.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the data doesn't have the required length, or for RLP prefixes,
+    /// which this crate doesn't implement.
+    pub fn apply(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut prefixed = Vec::with_capacity(data.len() + 10);
+        match self {
+            LengthOp::NoPrefix => {}
+            LengthOp::VarProto => super::protobuf::write_varint(&mut prefixed, data.len() as u64),
+            LengthOp::VarRlp => {
+                return Err(Error::invalid_ics23_proof(
+                    "the length operation isn't supported",
+                ))
+            }
+            LengthOp::Fixed32Big => prefixed.extend_from_slice(&(data.len() as u32).to_be_bytes()),
+            LengthOp::Fixed32Little => {
+                prefixed.extend_from_slice(&(data.len() as u32).to_le_bytes())
+            }
+            LengthOp::Fixed64Big => prefixed.extend_from_slice(&(data.len() as u64).to_be_bytes()),
+            LengthOp::Fixed64Little => {
+                prefixed.extend_from_slice(&(data.len() as u64).to_le_bytes())
+            }
+            LengthOp::Require32Bytes | LengthOp::Require64Bytes => {
+                let required = if *self == LengthOp::Require32Bytes {
+                    32
+                } else {
+                    64
+                };
+                if data.len() != required {
+                    return Err(Error::invalid_ics23_proof(
+                        "the data doesn't have the required length",
+                    ));
+                }
+            }
+        }
+        prefixed.extend_from_slice(data);
+        Ok(prefixed)
+    }
+}
+
+/// Calculates a leaf node from a key and a value, `LeafOp` in ICS23's `proofs.proto`:
+/// `hash(prefix ++ length(prehash_key(key)) ++ length(prehash_value(value)))`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LeafOp {
+    pub hash: HashOp,
+    pub prehash_key: HashOp,
+    pub prehash_value: HashOp,
+    pub length: LengthOp,
+    pub prefix: Vec<u8>,
+}
+
+impl LeafOp {
+    /// Calculates the leaf node.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the key or the value is empty, or an operation isn't supported.
+    pub fn apply(&self, key: &[u8], value: &[u8]) -> Result<Vec<u8>, Error> {
+        if key.is_empty() || value.is_empty() {
+            return Err(Error::invalid_ics23_proof(
+                "a leaf needs a non-empty key and value",
+            ));
+        }
+        let mut preimage = self.prefix.clone();
+        preimage.extend(self.length.apply(&self.prehash_key.hash(key)?)?);
+        preimage.extend(self.length.apply(&self.prehash_value.hash(value)?)?);
+        self.hash.hash(&preimage)
+    }
+}
+
+/// Calculates a parent node from a child, `InnerOp` in ICS23's `proofs.proto`:
+/// `hash(prefix ++ child ++ suffix)`, where the prefix and the suffix contain the siblings
+/// of the child
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InnerOp {
+    pub hash: HashOp,
+    pub prefix: Vec<u8>,
+    pub suffix: Vec<u8>,
+}
+
+impl InnerOp {
+    /// Calculates the parent node.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the child is empty or the hash operation isn't supported.
+    pub fn apply(&self, child: &[u8]) -> Result<Vec<u8>, Error> {
+        if child.is_empty() {
+            return Err(Error::invalid_ics23_proof("an inner node needs a child"));
+        }
+        let mut preimage = self.prefix.clone();
+        preimage.extend_from_slice(child);
+        preimage.extend_from_slice(&self.suffix);
+        self.hash.hash(&preimage)
+    }
+}
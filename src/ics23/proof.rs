@@ -0,0 +1,426 @@
+use super::{domain_separation, hash_op, leaf_op, HashOp, InnerOp, LeafOp};
+use crate::prelude::*;
+use crate::{
+    error::Error,
+    utils::{
+        self, indices,
+        properties::{OddNodeStrategy, TreeProperties},
+    },
+    Hasher, IndexedMerkleProof, MerkleProof,
+};
+
+/// Proof that a key/value pair is committed to a root, `ExistenceProof` in ICS23's
+/// `proofs.proto`. The root is calculated by applying the leaf operation to the key and the
+/// value, and then every operation of the path, starting from the leaf.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExistenceProof {
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+    pub leaf: Option<LeafOp>,
+    pub path: Vec<InnerOp>,
+}
+
+impl ExistenceProof {
+    /// Converts a proof of a single leaf of a tree built from [`leaf_hash`] leaves. The key and
+    /// the value are the ones the leaf was calculated from.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the proof doesn't prove exactly one leaf, doesn't have the right
+    /// number of hashes, or the tree can't be represented by a [`ProofSpec`], see
+    /// [`proof_spec`].
+    ///
+    /// [`leaf_hash`]: super::leaf_hash
+    /// [`proof_spec`]: super::proof_spec
+    pub fn from_indexed_proof<T: Hasher>(
+        proof: &IndexedMerkleProof<T>,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<Self, Error> {
+        let tree_properties = proof.tree_properties();
+        let domain_separation = domain_separation(tree_properties)?;
+        let leaf_index = match proof.leaf_indices() {
+            [leaf_index] => *leaf_index,
+            _ => {
+                return Err(Error::invalid_ics23_proof(
+                    "an existence proof proves a single leaf",
+                ))
+            }
+        };
+        let total_leaves_count = proof.total_leaves_count();
+        if leaf_index >= total_leaves_count {
+            return Err(Error::leaf_index_out_of_range(
+                leaf_index,
+                total_leaves_count,
+            ));
+        }
+
+        check_promotion::<T>(tree_properties)?;
+
+        let leaf = leaf_op::<T>()?;
+        let hash = hash_op::<T>()?;
+        let leaf_node = InnerOp {
+            hash,
+            prefix: vec![domain_separation.leaf_prefix],
+            suffix: Vec::new(),
+        };
+        let mut node = leaf_node.apply(&leaf.apply(key, value)?)?;
+        let mut path = vec![leaf_node];
+
+        let mut proof_hashes = proof.proof().proof_hashes().iter();
+        for step in LayerSteps::new(leaf_index, total_leaves_count, tree_properties) {
+            let (sibling, node_is_left): (Vec<u8>, bool) = match step {
+                LayerStep::Sibling { node_is_left } => {
+                    let sibling = proof_hashes
+                        .next()
+                        .ok_or_else(Error::not_enough_hashes_to_calculate_root)?;
+                    ((*sibling).into(), node_is_left)
+                }
+                LayerStep::LoneNode { layer_index } => {
+                    let lone_node = T::Hash::try_from(node.clone())
+                        .map_err(|_| Error::vec_to_hash_conversion_error())?;
+                    let sibling = tree_properties
+                        .lone_node_sibling::<T>(&lone_node, layer_index)
+                        .ok_or_else(|| {
+                            Error::unsupported_tree_properties(
+                                "the odd node strategy doesn't give a sibling",
+                            )
+                        })?;
+                    (sibling.into(), true)
+                }
+            };
+
+            let node_is_left = if tree_properties.sorted_pair_enabled {
+                node <= sibling
+            } else {
+                node_is_left
+            };
+            let inner = if node_is_left {
+                InnerOp {
+                    hash,
+                    prefix: vec![domain_separation.node_prefix],
+                    suffix: sibling,
+                }
+            } else {
+                let mut prefix = vec![domain_separation.node_prefix];
+                prefix.extend(sibling);
+                InnerOp {
+                    hash,
+                    prefix,
+                    suffix: Vec::new(),
+                }
+            };
+            node = inner.apply(&node)?;
+            path.push(inner);
+        }
+
+        if proof_hashes.next().is_some() {
+            return Err(Error::too_many_proof_hashes());
+        }
+
+        Ok(ExistenceProof {
+            key: key.to_vec(),
+            value: value.to_vec(),
+            leaf: Some(leaf),
+            path,
+        })
+    }
+
+    /// Converts the proof back to a proof of the leaf with the given index, in a tree with the
+    /// given number of leaves and properties. The proof has to be shaped like the ones
+    /// [`ExistenceProof::from_indexed_proof`] returns. The leaf to verify it with is
+    /// [`leaf_hash`] of the key and the value.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the operations don't match the tree or the path doesn't have one
+    /// operation per layer the leaf has a sibling on.
+    ///
+    /// [`leaf_hash`]: super::leaf_hash
+    pub fn to_indexed_proof<T: Hasher>(
+        &self,
+        leaf_index: usize,
+        total_leaves_count: usize,
+        tree_properties: TreeProperties,
+    ) -> Result<IndexedMerkleProof<T>, Error> {
+        let domain_separation = domain_separation(tree_properties)?;
+        if leaf_index >= total_leaves_count {
+            return Err(Error::leaf_index_out_of_range(
+                leaf_index,
+                total_leaves_count,
+            ));
+        }
+        if self.leaf.as_ref() != Some(&leaf_op::<T>()?) {
+            return Err(Error::invalid_ics23_proof(
+                "the leaf operation doesn't match the tree",
+            ));
+        }
+
+        let hash = hash_op::<T>()?;
+        let mut path = self.path.iter();
+        let leaf_node = InnerOp {
+            hash,
+            prefix: vec![domain_separation.leaf_prefix],
+            suffix: Vec::new(),
+        };
+        if path.next() != Some(&leaf_node) {
+            return Err(Error::invalid_ics23_proof(
+                "the first operation doesn't hash the leaf with the leaf prefix",
+            ));
+        }
+
+        let hash_size = T::hash_size();
+        let mut proof_hashes = Vec::new();
+        for step in LayerSteps::new(leaf_index, total_leaves_count, tree_properties) {
+            let inner = path.next().ok_or_else(|| {
+                Error::invalid_ics23_proof("the path is shorter than the tree is deep")
+            })?;
+            let (sibling, node_is_left) = match (inner.prefix.as_slice(), &inner.suffix) {
+                ([_], suffix) if suffix.len() == hash_size => (suffix.as_slice(), true),
+                ([_, sibling @ ..], suffix) if suffix.is_empty() && sibling.len() == hash_size => {
+                    (sibling, false)
+                }
+                _ => {
+                    return Err(Error::invalid_ics23_proof(
+                        "an inner operation doesn't hash a pair of nodes",
+                    ))
+                }
+            };
+            if inner.hash != hash || inner.prefix[0] != domain_separation.node_prefix {
+                return Err(Error::invalid_ics23_proof(
+                    "an inner operation doesn't match the tree",
+                ));
+            }
+
+            if let LayerStep::Sibling {
+                node_is_left: expected,
+            } = step
+            {
+                if !tree_properties.sorted_pair_enabled && node_is_left != expected {
+                    return Err(Error::invalid_ics23_proof(
+                        "the path doesn't match the leaf index",
+                    ));
+                }
+                let sibling = T::Hash::try_from(sibling.to_vec())
+                    .map_err(|_| Error::vec_to_hash_conversion_error())?;
+                proof_hashes.push(sibling);
+            }
+        }
+
+        if path.next().is_some() {
+            return Err(Error::invalid_ics23_proof(
+                "the path is longer than the tree is deep",
+            ));
+        }
+
+        Ok(IndexedMerkleProof::new(
+            MerkleProof::new(proof_hashes),
+            vec![leaf_index],
+            total_leaves_count,
+            tree_properties,
+        ))
+    }
+
+    /// Calculates the root the proof commits the key/value pair to.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the proof doesn't have a leaf operation, or an operation can't be
+    /// applied.
+    pub fn calculate_root(&self) -> Result<Vec<u8>, Error> {
+        let leaf = self
+            .leaf
+            .as_ref()
+            .ok_or_else(|| Error::invalid_ics23_proof("the proof doesn't have a leaf operation"))?;
+        let mut node = leaf.apply(&self.key, &self.value)?;
+        for inner in &self.path {
+            node = inner.apply(&node)?;
+        }
+        Ok(node)
+    }
+
+    /// Checks that the operations of the proof are allowed by the spec, like
+    /// `CheckAgainstSpec` of ICS23: the leaf operation must match the leaf spec, inner
+    /// operations must use the hash of the inner spec and can't start with the leaf prefix,
+    /// and their prefixes and suffixes must fit the sizes of the children.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error describing the first operation that isn't allowed.
+    pub fn check_against_spec(&self, spec: &ProofSpec) -> Result<(), Error> {
+        let (leaf_spec, inner_spec) = match (&spec.leaf_spec, &spec.inner_spec) {
+            (Some(leaf_spec), Some(inner_spec)) => (leaf_spec, inner_spec),
+            _ => {
+                return Err(Error::invalid_ics23_proof(
+                    "the spec doesn't have a leaf spec and an inner spec",
+                ))
+            }
+        };
+        let leaf = self
+            .leaf
+            .as_ref()
+            .ok_or_else(|| Error::invalid_ics23_proof("the proof doesn't have a leaf operation"))?;
+        if leaf.hash != leaf_spec.hash
+            || leaf.prehash_key != leaf_spec.prehash_key
+            || leaf.prehash_value != leaf_spec.prehash_value
+            || leaf.length != leaf_spec.length
+            || !leaf.prefix.starts_with(&leaf_spec.prefix)
+        {
+            return Err(Error::invalid_ics23_proof(
+                "the leaf operation doesn't match the spec",
+            ));
+        }
+
+        let depth = self.path.len() as i64;
+        if (spec.min_depth > 0 && depth < i64::from(spec.min_depth))
+            || (spec.max_depth > 0 && depth > i64::from(spec.max_depth))
+        {
+            return Err(Error::invalid_ics23_proof(
+                "the depth of the path isn't allowed by the spec",
+            ));
+        }
+
+        if inner_spec.child_size <= 0 {
+            return Err(Error::invalid_ics23_proof(
+                "the child size of the spec isn't positive",
+            ));
+        }
+        let child_size = inner_spec.child_size as i64;
+        let max_left_children_size = (inner_spec.child_order.len() as i64 - 1).max(0) * child_size;
+        for inner in &self.path {
+            let prefix_len = inner.prefix.len() as i64;
+            if inner.hash != inner_spec.hash
+                || inner.prefix.starts_with(&leaf_spec.prefix)
+                || prefix_len < i64::from(inner_spec.min_prefix_length)
+                || prefix_len > i64::from(inner_spec.max_prefix_length) + max_left_children_size
+                || inner.suffix.len() as i64 % child_size != 0
+            {
+                return Err(Error::invalid_ics23_proof(
+                    "an inner operation doesn't match the spec",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Verifies that the proof commits the key/value pair to the root and that its operations
+    /// are allowed by the spec, like `VerifyExistence` of ICS23.
+    pub fn verify(&self, spec: &ProofSpec, root: &[u8], key: &[u8], value: &[u8]) -> bool {
+        if self.key != key || self.value != value || self.check_against_spec(spec).is_err() {
+            return false;
+        }
+        matches!(self.calculate_root(), Ok(calculated_root) if calculated_root == root)
+    }
+}
+
+/// Proof of a commitment, `CommitmentProof` in ICS23's `proofs.proto`. Only existence proofs
+/// are supported.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CommitmentProof {
+    Exist(ExistenceProof),
+}
+
+impl From<ExistenceProof> for CommitmentProof {
+    fn from(existence_proof: ExistenceProof) -> Self {
+        CommitmentProof::Exist(existence_proof)
+    }
+}
+
+/// Shape of the inner nodes of a tree, `InnerSpec` in ICS23's `proofs.proto`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InnerSpec {
+    /// Order of the children in the preimage of a node, `[0, 1]` for binary trees that hash the
+    /// left child first
+    pub child_order: Vec<i32>,
+    pub child_size: i32,
+    pub min_prefix_length: i32,
+    pub max_prefix_length: i32,
+    /// Value of an empty child, used by non-existence proofs
+    pub empty_child: Vec<u8>,
+    pub hash: HashOp,
+}
+
+/// Description of a tree that proofs are checked against, `ProofSpec` in ICS23's
+/// `proofs.proto`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProofSpec {
+    pub leaf_spec: Option<LeafOp>,
+    pub inner_spec: Option<InnerSpec>,
+    /// The largest number of inner operations in a path, `0` for no limit
+    pub max_depth: i32,
+    /// The smallest number of inner operations in a path, `0` for no limit
+    pub min_depth: i32,
+    /// Whether keys are hashed before being compared by non-existence proofs
+    pub prehash_key_before_comparison: bool,
+}
+
+/// Checks that the hasher promotes a node without a right sibling unchanged, otherwise the
+/// step can't be expressed with an ICS23 operation
+fn check_promotion<T: Hasher>(tree_properties: TreeProperties) -> Result<(), Error> {
+    let node = utils::collections::zero_hash::<T>();
+    if tree_properties.odd_node_strategy == OddNodeStrategy::Promote
+        && T::concat_and_hash(&node, None) != node
+    {
+        return Err(Error::unsupported_tree_properties(
+            "ICS23 proofs need the hasher to promote a node without a right sibling",
+        ));
+    }
+    Ok(())
+}
+
+/// A layer of the tree between a leaf and the root that needs an inner operation
+#[derive(Clone, Copy)]
+enum LayerStep {
+    /// The node is hashed with its sibling from the proof
+    Sibling { node_is_left: bool },
+    /// The node doesn't have a sibling and is hashed with the one given by the odd node
+    /// strategy
+    LoneNode { layer_index: usize },
+}
+
+/// Walks the layers of a tree from a leaf to the root, skipping the layers where the node is
+/// promoted unchanged
+struct LayerSteps {
+    index: usize,
+    width: usize,
+    layer_index: usize,
+    depth: usize,
+    odd_node_strategy: OddNodeStrategy,
+}
+
+impl LayerSteps {
+    fn new(leaf_index: usize, total_leaves_count: usize, tree_properties: TreeProperties) -> Self {
+        Self {
+            index: leaf_index,
+            width: total_leaves_count,
+            layer_index: 0,
+            depth: indices::tree_depth(total_leaves_count),
+            odd_node_strategy: tree_properties.odd_node_strategy,
+        }
+    }
+}
+
+impl Iterator for LayerSteps {
+    type Item = LayerStep;
+
+    fn next(&mut self) -> Option<LayerStep> {
+        while self.layer_index < self.depth {
+            let (index, width, layer_index) = (self.index, self.width, self.layer_index);
+            self.index /= 2;
+            self.width = indices::div_ceil(width, 2);
+            self.layer_index += 1;
+
+            if index ^ 1 < width {
+                return Some(LayerStep::Sibling {
+                    node_is_left: indices::is_left_index(index),
+                });
+            }
+            // A lone node is promoted if it is the root or the strategy promotes it
+            if self.odd_node_strategy != OddNodeStrategy::Promote && index / 2 != 0 {
+                return Some(LayerStep::LoneNode { layer_index });
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,359 @@
+//! Protobuf encoding of the ICS23 messages, with the field numbers of `cosmos.ics23.v1`.
+//! Fields with default values are omitted, like protobuf encoders do, and unknown fields are
+//! skipped when decoding.
+use super::{
+    CommitmentProof, ExistenceProof, HashOp, InnerOp, InnerSpec, LeafOp, LengthOp, ProofSpec,
+};
+use crate::{error::Error, prelude::*};
+
+const WIRE_VARINT: u8 = 0;
+const WIRE_FIXED64: u8 = 1;
+const WIRE_LENGTH_DELIMITED: u8 = 2;
+const WIRE_FIXED32: u8 = 5;
+
+impl CommitmentProof {
+    /// Serializes the proof to protobuf, the format IBC passes proofs in
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match self {
+            CommitmentProof::Exist(existence_proof) => {
+                write_message(&mut bytes, 1, &existence_proof.to_bytes())
+            }
+        }
+        bytes
+    }
+
+    /// Parses a proof serialized with [`CommitmentProof::to_bytes`] or another ICS23
+    /// implementation.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the bytes aren't a valid protobuf message, or the proof isn't an
+    /// existence proof.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut proof = None;
+        let mut reader = Reader::new(bytes);
+        while let Some((field, value)) = reader.read_field()? {
+            match field {
+                1 => {
+                    proof = Some(CommitmentProof::Exist(ExistenceProof::from_bytes(
+                        value.bytes()?,
+                    )?))
+                }
+                2..=4 => {
+                    return Err(Error::invalid_ics23_proof(
+                        "only existence proofs are supported",
+                    ))
+                }
+                _ => {}
+            }
+        }
+        proof.ok_or_else(|| Error::invalid_ics23_proof("the commitment proof is empty"))
+    }
+}
+
+impl ExistenceProof {
+    /// Serializes the proof to protobuf
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_bytes(&mut bytes, 1, &self.key);
+        write_bytes(&mut bytes, 2, &self.value);
+        if let Some(leaf) = &self.leaf {
+            write_message(&mut bytes, 3, &encode_leaf_op(leaf));
+        }
+        for inner in &self.path {
+            write_message(&mut bytes, 4, &encode_inner_op(inner));
+        }
+        bytes
+    }
+
+    /// Parses a proof serialized with [`ExistenceProof::to_bytes`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the bytes aren't a valid protobuf message.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut proof = ExistenceProof::default();
+        let mut reader = Reader::new(bytes);
+        while let Some((field, value)) = reader.read_field()? {
+            match field {
+                1 => proof.key = value.bytes()?.to_vec(),
+                2 => proof.value = value.bytes()?.to_vec(),
+                3 => proof.leaf = Some(decode_leaf_op(value.bytes()?)?),
+                4 => proof.path.push(decode_inner_op(value.bytes()?)?),
+                _ => {}
+            }
+        }
+        Ok(proof)
+    }
+}
+
+impl ProofSpec {
+    /// Serializes the spec to protobuf
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        if let Some(leaf_spec) = &self.leaf_spec {
+            write_message(&mut bytes, 1, &encode_leaf_op(leaf_spec));
+        }
+        if let Some(inner_spec) = &self.inner_spec {
+            write_message(&mut bytes, 2, &encode_inner_spec(inner_spec));
+        }
+        write_int32(&mut bytes, 3, self.max_depth);
+        write_int32(&mut bytes, 4, self.min_depth);
+        write_varint_field(&mut bytes, 5, self.prehash_key_before_comparison as u64);
+        bytes
+    }
+
+    /// Parses a spec serialized with [`ProofSpec::to_bytes`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the bytes aren't a valid protobuf message.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut spec = ProofSpec::default();
+        let mut reader = Reader::new(bytes);
+        while let Some((field, value)) = reader.read_field()? {
+            match field {
+                1 => spec.leaf_spec = Some(decode_leaf_op(value.bytes()?)?),
+                2 => spec.inner_spec = Some(decode_inner_spec(value.bytes()?)?),
+                3 => spec.max_depth = value.int32()?,
+                4 => spec.min_depth = value.int32()?,
+                5 => spec.prehash_key_before_comparison = value.varint()? != 0,
+                _ => {}
+            }
+        }
+        Ok(spec)
+    }
+}
+
+fn encode_leaf_op(leaf: &LeafOp) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_int32(&mut bytes, 1, leaf.hash as i32);
+    write_int32(&mut bytes, 2, leaf.prehash_key as i32);
+    write_int32(&mut bytes, 3, leaf.prehash_value as i32);
+    write_int32(&mut bytes, 4, leaf.length as i32);
+    write_bytes(&mut bytes, 5, &leaf.prefix);
+    bytes
+}
+
+fn decode_leaf_op(bytes: &[u8]) -> Result<LeafOp, Error> {
+    let mut leaf = LeafOp::default();
+    let mut reader = Reader::new(bytes);
+    while let Some((field, value)) = reader.read_field()? {
+        match field {
+            1 => leaf.hash = value.hash_op()?,
+            2 => leaf.prehash_key = value.hash_op()?,
+            3 => leaf.prehash_value = value.hash_op()?,
+            4 => {
+                leaf.length = LengthOp::from_i32(value.int32()?)
+                    .ok_or_else(|| Error::invalid_ics23_proof("unknown length operation"))?
+            }
+            5 => leaf.prefix = value.bytes()?.to_vec(),
+            _ => {}
+        }
+    }
+    Ok(leaf)
+}
+
+fn encode_inner_op(inner: &InnerOp) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_int32(&mut bytes, 1, inner.hash as i32);
+    write_bytes(&mut bytes, 2, &inner.prefix);
+    write_bytes(&mut bytes, 3, &inner.suffix);
+    bytes
+}
+
+fn decode_inner_op(bytes: &[u8]) -> Result<InnerOp, Error> {
+    let mut inner = InnerOp::default();
+    let mut reader = Reader::new(bytes);
+    while let Some((field, value)) = reader.read_field()? {
+        match field {
+            1 => inner.hash = value.hash_op()?,
+            2 => inner.prefix = value.bytes()?.to_vec(),
+            3 => inner.suffix = value.bytes()?.to_vec(),
+            _ => {}
+        }
+    }
+    Ok(inner)
+}
+
+fn encode_inner_spec(inner_spec: &InnerSpec) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    if !inner_spec.child_order.is_empty() {
+        // Repeated scalars are packed in proto3
+        let mut packed = Vec::new();
+        for child in &inner_spec.child_order {
+            write_varint(&mut packed, *child as i64 as u64);
+        }
+        write_message(&mut bytes, 1, &packed);
+    }
+    write_int32(&mut bytes, 2, inner_spec.child_size);
+    write_int32(&mut bytes, 3, inner_spec.min_prefix_length);
+    write_int32(&mut bytes, 4, inner_spec.max_prefix_length);
+    write_bytes(&mut bytes, 5, &inner_spec.empty_child);
+    write_int32(&mut bytes, 6, inner_spec.hash as i32);
+    bytes
+}
+
+fn decode_inner_spec(bytes: &[u8]) -> Result<InnerSpec, Error> {
+    let mut inner_spec = InnerSpec::default();
+    let mut reader = Reader::new(bytes);
+    while let Some((field, value)) = reader.read_field()? {
+        match field {
+            1 => match value {
+                FieldValue::LengthDelimited(packed) => {
+                    let mut packed = Reader::new(packed);
+                    while !packed.is_empty() {
+                        inner_spec.child_order.push(packed.read_varint()? as i32);
+                    }
+                }
+                value => inner_spec.child_order.push(value.int32()?),
+            },
+            2 => inner_spec.child_size = value.int32()?,
+            3 => inner_spec.min_prefix_length = value.int32()?,
+            4 => inner_spec.max_prefix_length = value.int32()?,
+            5 => inner_spec.empty_child = value.bytes()?.to_vec(),
+            6 => inner_spec.hash = value.hash_op()?,
+            _ => {}
+        }
+    }
+    Ok(inner_spec)
+}
+
+pub(crate) fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn write_key(bytes: &mut Vec<u8>, field: u32, wire_type: u8) {
+    write_varint(bytes, (u64::from(field) << 3) | u64::from(wire_type));
+}
+
+fn write_varint_field(bytes: &mut Vec<u8>, field: u32, value: u64) {
+    if value != 0 {
+        write_key(bytes, field, WIRE_VARINT);
+        write_varint(bytes, value);
+    }
+}
+
+/// Negative values are sign-extended to 64 bits, so they always take 10 bytes
+fn write_int32(bytes: &mut Vec<u8>, field: u32, value: i32) {
+    write_varint_field(bytes, field, value as i64 as u64);
+}
+
+fn write_bytes(bytes: &mut Vec<u8>, field: u32, data: &[u8]) {
+    if !data.is_empty() {
+        write_message(bytes, field, data);
+    }
+}
+
+/// Unlike [`write_bytes`], writes the field even if it's empty, so that a present message
+/// with default values isn't decoded as a missing one
+fn write_message(bytes: &mut Vec<u8>, field: u32, message: &[u8]) {
+    write_key(bytes, field, WIRE_LENGTH_DELIMITED);
+    write_varint(bytes, message.len() as u64);
+    bytes.extend_from_slice(message);
+}
+
+fn malformed() -> Error {
+    Error::invalid_ics23_proof("the protobuf encoding is malformed")
+}
+
+enum FieldValue<'a> {
+    Varint(u64),
+    LengthDelimited(&'a [u8]),
+    Fixed,
+}
+
+impl<'a> FieldValue<'a> {
+    fn varint(&self) -> Result<u64, Error> {
+        match self {
+            FieldValue::Varint(value) => Ok(*value),
+            _ => Err(malformed()),
+        }
+    }
+
+    /// Protobuf truncates varints to the lower 32 bits for `int32` fields
+    fn int32(&self) -> Result<i32, Error> {
+        Ok(self.varint()? as i32)
+    }
+
+    fn hash_op(&self) -> Result<HashOp, Error> {
+        HashOp::from_i32(self.int32()?)
+            .ok_or_else(|| Error::invalid_ics23_proof("unknown hash operation"))
+    }
+
+    fn bytes(&self) -> Result<&'a [u8], Error> {
+        match self {
+            FieldValue::LengthDelimited(bytes) => Ok(bytes),
+            _ => Err(malformed()),
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.bytes.len() < len {
+            return Err(malformed());
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn read_varint(&mut self) -> Result<u64, Error> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_bytes(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(malformed())
+    }
+
+    /// Reads the next field number and value, or `None` at the end of the message
+    fn read_field(&mut self) -> Result<Option<(u64, FieldValue<'a>)>, Error> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        let key = self.read_varint()?;
+        let field = key >> 3;
+        if field == 0 {
+            return Err(malformed());
+        }
+        let value = match (key & 0x7) as u8 {
+            WIRE_VARINT => FieldValue::Varint(self.read_varint()?),
+            WIRE_FIXED64 => {
+                self.read_bytes(8)?;
+                FieldValue::Fixed
+            }
+            WIRE_LENGTH_DELIMITED => {
+                let len = usize::try_from(self.read_varint()?).map_err(|_| malformed())?;
+                FieldValue::LengthDelimited(self.read_bytes(len)?)
+            }
+            WIRE_FIXED32 => {
+                self.read_bytes(4)?;
+                FieldValue::Fixed
+            }
+            _ => return Err(malformed()),
+        };
+        Ok(Some((field, value)))
+    }
+}
@@ -0,0 +1,141 @@
+use crate::prelude::*;
+use crate::{
+    error::Error, utils::properties::TreeProperties, Hasher, IndexedProofSerializer, MerkleProof,
+};
+
+/// [`IndexedMerkleProof`] is a [`MerkleProof`] bundled with everything else that is needed to
+/// verify it: the indices of the proved leaves, the number of leaves in the tree and the
+/// [`TreeProperties`] the tree was built with. Only the root and the proved leaves have to be
+/// passed to the verifier.
+///
+/// ## Examples
+///
+/// ```
+/// # use rs_merkle::{MerkleTree, algorithms::Sha256, Hasher, utils::properties::TreeProperties};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let tree_properties = TreeProperties::default();
+/// let leaves: Vec<[u8; 32]> = ["a", "b", "c", "d", "e"]
+///     .iter()
+///     .map(|x| Sha256::hash(x.as_bytes()))
+///     .collect();
+///
+/// let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+/// let root = merkle_tree.root().ok_or("couldn't get the merkle root")?;
+///
+/// let proof = merkle_tree.indexed_proof(&[1, 3]);
+/// assert_eq!(proof.leaf_indices(), &[1, 3]);
+/// assert_eq!(proof.total_leaves_count(), 5);
+/// assert!(proof.verify(root, &[leaves[1], leaves[3]]));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct IndexedMerkleProof<T: Hasher> {
+    proof: MerkleProof<T>,
+    leaf_indices: Vec<usize>,
+    total_leaves_count: usize,
+    tree_properties: TreeProperties,
+}
+
+impl<T: Hasher> IndexedMerkleProof<T> {
+    pub fn new(
+        proof: MerkleProof<T>,
+        leaf_indices: Vec<usize>,
+        total_leaves_count: usize,
+        tree_properties: TreeProperties,
+    ) -> Self {
+        Self {
+            proof,
+            leaf_indices,
+            total_leaves_count,
+            tree_properties,
+        }
+    }
+
+    pub fn proof(&self) -> &MerkleProof<T> {
+        &self.proof
+    }
+
+    pub fn leaf_indices(&self) -> &[usize] {
+        &self.leaf_indices
+    }
+
+    pub fn total_leaves_count(&self) -> usize {
+        self.total_leaves_count
+    }
+
+    pub fn tree_properties(&self) -> TreeProperties {
+        self.tree_properties
+    }
+
+    /// Returns the inner [`MerkleProof`], discarding the metadata
+    pub fn into_proof(self) -> MerkleProof<T> {
+        self.proof
+    }
+
+    /// Serializes the proof together with the indices, the number of leaves and the tree
+    /// properties, see [`ProofEnvelope`] for a built-in format.
+    ///
+    /// [`ProofEnvelope`]: crate::proof_serializers::ProofEnvelope
+    pub fn serialize<S: IndexedProofSerializer>(&self) -> Vec<u8> {
+        S::serialize_indexed(self)
+    }
+
+    /// Parses a proof serialized with [`IndexedMerkleProof::serialize`]
+    pub fn deserialize<S: IndexedProofSerializer>(bytes: &[u8]) -> Result<Self, Error> {
+        S::deserialize_indexed(bytes)
+    }
+
+    /// Verifies that the leaves, given in the same order as [`IndexedMerkleProof::leaf_indices`],
+    /// are included in the tree with the given root. See [`MerkleProof::verify`].
+    pub fn verify(&self, root: T::Hash, leaf_hashes: &[T::Hash]) -> bool {
+        self.proof.verify(
+            root,
+            &self.leaf_indices,
+            leaf_hashes,
+            self.total_leaves_count,
+            self.tree_properties,
+        )
+    }
+
+    /// Calculates the root from the leaves, given in the same order as
+    /// [`IndexedMerkleProof::leaf_indices`]. See [`MerkleProof::root`].
+    pub fn root(&self, leaf_hashes: &[T::Hash]) -> Result<T::Hash, Error> {
+        self.proof.root(
+            &self.leaf_indices,
+            leaf_hashes,
+            self.total_leaves_count,
+            self.tree_properties,
+        )
+    }
+
+    /// Checks that the indices are in range of the tree and that the proof has exactly as many
+    /// hashes as the indices need, so decoders can reject malformed proofs
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        self.proof
+            .proof_indices_by_layers(&self.leaf_indices, self.total_leaves_count)
+            .map(|_| ())
+    }
+}
+
+impl<T: Hasher> PartialEq for IndexedMerkleProof<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.proof == other.proof
+            && self.leaf_indices == other.leaf_indices
+            && self.total_leaves_count == other.total_leaves_count
+            && self.tree_properties == other.tree_properties
+    }
+}
+
+impl<T: Hasher> Eq for IndexedMerkleProof<T> where T::Hash: Eq {}
+
+impl<T: Hasher> core::fmt::Debug for IndexedMerkleProof<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("IndexedMerkleProof")
+            .field("proof", &self.proof)
+            .field("leaf_indices", &self.leaf_indices)
+            .field("total_leaves_count", &self.total_leaves_count)
+            .field("tree_properties", &self.tree_properties)
+            .finish()
+    }
+}
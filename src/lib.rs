@@ -34,9 +34,7 @@
 //! # use std::convert::TryFrom;
 //! #
 //! # fn main() -> Result<(), Box<dyn std::error::Error>> {
-//! let tree_properties = TreeProperties {
-//!    sorted_pair_enabled: false,
-//! };
+//! let tree_properties = TreeProperties::default();
 //! let leaf_values = ["a", "b", "c", "d", "e", "f"];
 //! let leaves: Vec<[u8; 32]> = leaf_values
 //!     .iter()
@@ -65,9 +63,7 @@
 //! # use rs_merkle::{MerkleTree, algorithms::Sha256, Hasher, Error, utils::properties::TreeProperties};
 //! #
 //! # fn main() -> Result<(), Box<dyn std::error::Error>> {
-//! let tree_properties = TreeProperties {
-//!    sorted_pair_enabled: false,
-//! };
+//! let tree_properties = TreeProperties::default();
 //! let elements = ["a", "b", "c", "d", "e", "f"];
 //! let mut leaves: Vec<[u8; 32]> = elements
 //!     .iter()
@@ -139,22 +135,53 @@ extern crate alloc;
 #[cfg(feature = "std")]
 extern crate std;
 
+pub use compact_multiproof::CompactMultiProof;
+pub use consistency_proof::ConsistencyProof;
 pub use error::Error;
 pub use error::ErrorKind;
 pub use hasher::Hasher;
-pub use merkle_proof::MerkleProof;
+pub use history::{CommitInfo, CommitRef, RetentionPolicy};
+pub use indexed_merkle_proof::IndexedMerkleProof;
+#[cfg(feature = "std")]
+pub use merkle_log::MerkleLog;
+pub use merkle_mountain_range::{MerkleMountainRange, MerkleMountainRangeProof};
+pub use merkle_proof::{Leaf, MerkleProof};
 pub use merkle_tree::MerkleTree;
+pub use node_stores::NodeStore;
 pub use partial_tree::PartialTree;
-pub use proof_serializers::MerkleProofSerializer;
+pub use proof_serializers::{IndexedProofSerializer, MerkleProofSerializer};
+pub use sparse_merkle_tree::{SparseMerkleProof, SparseMerkleTree};
+pub use standard_merkle_tree::{AbiType, AbiValue, StandardMerkleTree};
 
+#[cfg(feature = "borsh")]
+mod borsh_impls;
+mod compact_multiproof;
+mod consistency_proof;
 mod error;
 mod hasher;
+mod history;
+mod indexed_merkle_proof;
+#[cfg(feature = "std")]
+mod merkle_log;
+mod merkle_mountain_range;
 mod merkle_proof;
 mod merkle_tree;
 mod partial_tree;
 mod prelude;
+#[cfg(feature = "parity-scale-codec")]
+mod scale_impls;
+#[cfg(feature = "serde")]
+mod serde_impls;
+mod snapshot;
+mod sparse_merkle_tree;
+mod standard_merkle_tree;
 #[doc(hidden)]
 pub mod utils;
 
 pub mod algorithms;
+pub mod bitcoin;
+pub mod cometbft;
+pub mod ics23;
+pub mod node_stores;
 pub mod proof_serializers;
+pub mod ssz;
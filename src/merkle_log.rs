@@ -0,0 +1,490 @@
+use crate::prelude::*;
+use crate::{
+    node_stores::{into_ok, MemoryNodeStore},
+    utils::properties::TreeProperties,
+    CommitInfo, Hasher, MerkleTree, NodeStore, RetentionPolicy,
+};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const CHECKPOINTS_FILE_NAME: &str = "checkpoints";
+const CHECKPOINTS_MAGIC: &[u8; 6] = b"RSMLOG";
+const FORMAT_VERSION: u8 = 1;
+const HEADER_SIZE: usize = 8;
+
+/// An append-only Merkle tree that persists every commit to a directory, so the tree survives
+/// process restarts. Suitable for transparency logs, where leaves are only ever appended and
+/// clients check consistency between published roots.
+///
+/// Each commit writes the leaves and interior nodes it has changed as a single record to the
+/// current segment file, and then writes the new root to the checkpoint file. Segment files are
+/// rotated once they reach the segment size, see [`MerkleLog::set_segment_size`]. A commit is
+/// durable only after its checkpoint is written, so when the log is opened after a crash, any
+/// torn record or checkpoint is discarded and the log recovers to the last consistent commit.
+///
+/// The tree itself is kept in memory and can be used for proofs via [`MerkleLog::tree`]. The log
+/// must be reopened with the same [`Hasher`] and [`TreeProperties`].
+///
+/// ## Examples
+///
+/// ```
+/// # use rs_merkle::{MerkleLog, algorithms::Sha256, Hasher, utils::properties::TreeProperties};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let tree_properties = TreeProperties::default();
+/// let directory = std::env::temp_dir().join("rs_merkle_merkle_log_doc");
+/// # let _ = std::fs::remove_dir_all(&directory);
+///
+/// let mut merkle_log = MerkleLog::<Sha256>::open(&directory, tree_properties)?;
+/// merkle_log.insert(Sha256::hash("a".as_bytes()));
+/// merkle_log.insert(Sha256::hash("b".as_bytes()));
+/// merkle_log.commit()?;
+/// let root = merkle_log.root();
+///
+/// // Uncommitted leaves are not persisted
+/// merkle_log.insert(Sha256::hash("c".as_bytes()));
+/// drop(merkle_log);
+///
+/// let merkle_log = MerkleLog::<Sha256>::open(&directory, tree_properties)?;
+/// assert_eq!(merkle_log.root(), root);
+/// assert_eq!(merkle_log.leaves_len(), 2);
+/// # std::fs::remove_dir_all(&directory)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct MerkleLog<T: Hasher> {
+    directory: PathBuf,
+    tree: MerkleTree<T>,
+    checkpoints: Vec<LogCheckpoint<T::Hash>>,
+    checkpoints_file: File,
+    segment_file: File,
+    segment_index: usize,
+    segment_len: u64,
+    segment_size: u64,
+}
+
+/// A committed root and the end of the commit record in the segment files
+struct LogCheckpoint<H> {
+    leaves_len: usize,
+    root: H,
+    segment_index: usize,
+    segment_end: u64,
+}
+
+impl<T: Hasher> MerkleLog<T> {
+    /// Segment size used unless [`MerkleLog::set_segment_size`] is called, 64 MiB
+    pub const DEFAULT_SEGMENT_SIZE: u64 = 64 * 1024 * 1024;
+
+    /// Opens the log in the given directory, creating the directory if it doesn't exist.
+    /// Data that was written after the last complete checkpoint, for example by a commit
+    /// interrupted by a crash, is discarded. Returns an error if the files can't be read,
+    /// were written with another hash size, or if a checkpointed record is corrupted.
+    pub fn open(directory: impl AsRef<Path>, tree_properties: TreeProperties) -> io::Result<Self> {
+        let directory = directory.as_ref().to_path_buf();
+        fs::create_dir_all(&directory)?;
+
+        let mut checkpoints_file = open_file(&directory.join(CHECKPOINTS_FILE_NAME))?;
+        let checkpoints = Self::recover_checkpoints(&mut checkpoints_file)?;
+
+        let (segment_index, segment_len) = checkpoints
+            .last()
+            .map(|checkpoint| (checkpoint.segment_index, checkpoint.segment_end))
+            .unwrap_or((0, 0));
+
+        // Segments that were created after the last checkpoint contain only torn commits
+        let mut stale_segment_index = segment_index + 1;
+        while segment_path(&directory, stale_segment_index).exists() {
+            fs::remove_file(segment_path(&directory, stale_segment_index))?;
+            stale_segment_index += 1;
+        }
+
+        let segment_file = open_file(&segment_path(&directory, segment_index))?;
+        if segment_file.metadata()?.len() < segment_len {
+            return Err(invalid_data("segment file is shorter than its checkpoint"));
+        }
+        segment_file.set_len(segment_len)?;
+        segment_file.sync_all()?;
+        // The checkpoints file and the segment could have just been created, and stale segments
+        // removed, so the directory entries have to be persisted before any checkpoint is written
+        sync_directory(&directory)?;
+
+        let tree = Self::replay(&directory, &checkpoints, tree_properties)?;
+
+        Ok(Self {
+            directory,
+            tree,
+            checkpoints,
+            checkpoints_file,
+            segment_file,
+            segment_index,
+            segment_len,
+            segment_size: Self::DEFAULT_SEGMENT_SIZE,
+        })
+    }
+
+    /// Returns the directory of the log
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Sets the size in bytes after which a new segment file is started. A segment can be larger
+    /// than that if a single commit record doesn't fit into it.
+    pub fn set_segment_size(&mut self, segment_size: u64) {
+        self.segment_size = segment_size;
+    }
+
+    /// Returns the size in bytes after which a new segment file is started
+    pub fn segment_size(&self) -> u64 {
+        self.segment_size
+    }
+
+    /// Returns the in-memory tree of the log. Can be used to create inclusion and consistency
+    /// proofs for the committed leaves.
+    pub fn tree(&self) -> &MerkleTree<T> {
+        &self.tree
+    }
+
+    /// Returns the root of the last commit
+    pub fn root(&self) -> Option<T::Hash> {
+        self.tree.root()
+    }
+
+    /// Returns the number of committed leaves
+    pub fn leaves_len(&self) -> usize {
+        self.tree.leaves_len()
+    }
+
+    /// Returns the persisted commits, starting from the first one. The id of a commit is its
+    /// position in the log.
+    pub fn checkpoints(&self) -> Vec<CommitInfo<T::Hash>> {
+        self.checkpoints
+            .iter()
+            .enumerate()
+            .map(|(id, checkpoint)| CommitInfo {
+                id: id as u64,
+                root: Some(checkpoint.root),
+                leaves_len: checkpoint.leaves_len,
+                label: None,
+            })
+            .collect()
+    }
+
+    /// Inserts a new leaf. The leaf is persisted only after [`MerkleLog::commit`] is called.
+    pub fn insert(&mut self, leaf: T::Hash) -> &mut Self {
+        self.tree.insert(leaf);
+        self
+    }
+
+    /// Appends leaves to the log. Leaves are persisted only after [`MerkleLog::commit`] is called.
+    pub fn append(&mut self, leaves: &mut Vec<T::Hash>) -> &mut Self {
+        self.tree.append(leaves);
+        self
+    }
+
+    /// Clears all uncommitted leaves
+    pub fn abort_uncommitted(&mut self) {
+        self.tree.abort_uncommitted();
+    }
+
+    /// Commits the inserted leaves and persists them. Returns after the commit record and its
+    /// checkpoint are flushed to the disk. Does nothing if there are no uncommitted leaves.
+    ///
+    /// If writing fails, the commit is undone and its leaves are dropped, so the log stays
+    /// consistent with the files.
+    pub fn commit(&mut self) -> io::Result<()> {
+        let previous_leaves_len = self.tree.leaves_len();
+        self.tree.commit();
+
+        if self.tree.leaves_len() == previous_leaves_len {
+            return Ok(());
+        }
+
+        let record = self.commit_record(previous_leaves_len);
+        let previous_segment = (self.segment_index, self.segment_len);
+        if let Err(error) = self.write_commit(&record) {
+            self.tree.rollback();
+            // Leftovers of the failed commit are discarded on the next open anyway
+            let _ = self.restore_files(previous_segment);
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    /// Encodes the nodes that were added or changed since the tree had `previous_leaves_len`
+    /// leaves. Since leaves are only appended, those are the nodes to the right of the previous
+    /// last leaf on each layer.
+    fn commit_record(&self, previous_leaves_len: usize) -> Vec<u8> {
+        let store = self.tree.store();
+        let mut nodes_count: u64 = 0;
+        let mut nodes_bytes = Vec::new();
+
+        for layer_index in 0..store.layers_len() {
+            let first_changed_index = previous_leaves_len
+                .checked_shr(layer_index as u32)
+                .unwrap_or(0);
+            for node_index in first_changed_index..store.layer_len(layer_index) {
+                if let Some(node) = into_ok(store.get(layer_index, node_index)) {
+                    let mut node_bytes: Vec<u8> = node.into();
+                    nodes_bytes.extend_from_slice(&(layer_index as u32).to_le_bytes());
+                    nodes_bytes.extend_from_slice(&(node_index as u64).to_le_bytes());
+                    nodes_bytes.append(&mut node_bytes);
+                    nodes_count += 1;
+                }
+            }
+        }
+
+        let mut record = Vec::with_capacity(16 + nodes_bytes.len() + T::hash_size());
+        record.extend_from_slice(&(self.tree.leaves_len() as u64).to_le_bytes());
+        record.extend_from_slice(&nodes_count.to_le_bytes());
+        record.append(&mut nodes_bytes);
+        with_checksum::<T>(record)
+    }
+
+    fn write_commit(&mut self, record: &[u8]) -> io::Result<()> {
+        if self.segment_len > 0 && self.segment_len + record.len() as u64 > self.segment_size {
+            let segment_file = open_file(&segment_path(&self.directory, self.segment_index + 1))?;
+            segment_file.set_len(0)?;
+            // Otherwise the checkpoint could survive a crash while the segment it points to doesn't
+            sync_directory(&self.directory)?;
+            self.segment_file = segment_file;
+            self.segment_index += 1;
+            self.segment_len = 0;
+        }
+
+        self.segment_file.seek(SeekFrom::Start(self.segment_len))?;
+        self.segment_file.write_all(record)?;
+        self.segment_file.sync_data()?;
+        self.segment_len += record.len() as u64;
+
+        let checkpoint = LogCheckpoint {
+            leaves_len: self.tree.leaves_len(),
+            root: self
+                .tree
+                .root()
+                .ok_or_else(|| invalid_data("tree has no root after a commit"))?,
+            segment_index: self.segment_index,
+            segment_end: self.segment_len,
+        };
+        let checkpoint_offset =
+            HEADER_SIZE + self.checkpoints.len() * checkpoint_record_size::<T>();
+        self.checkpoints_file
+            .seek(SeekFrom::Start(checkpoint_offset as u64))?;
+        self.checkpoints_file
+            .write_all(&encode_checkpoint::<T>(&checkpoint))?;
+        self.checkpoints_file.sync_data()?;
+        self.checkpoints.push(checkpoint);
+
+        Ok(())
+    }
+
+    /// Cuts the files back to the state of the last checkpoint after a failed commit
+    fn restore_files(&mut self, (segment_index, segment_len): (usize, u64)) -> io::Result<()> {
+        let checkpoints_len = HEADER_SIZE + self.checkpoints.len() * checkpoint_record_size::<T>();
+        self.checkpoints_file.set_len(checkpoints_len as u64)?;
+
+        if self.segment_index != segment_index {
+            fs::remove_file(segment_path(&self.directory, self.segment_index))?;
+            self.segment_file = open_file(&segment_path(&self.directory, segment_index))?;
+            self.segment_index = segment_index;
+        }
+        self.segment_file.set_len(segment_len)?;
+        self.segment_len = segment_len;
+
+        Ok(())
+    }
+
+    /// Reads the checkpoint file, writing the header if the file is new, and cuts off a torn
+    /// checkpoint at the end of the file
+    fn recover_checkpoints(file: &mut File) -> io::Result<Vec<LogCheckpoint<T::Hash>>> {
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+
+        let mut header = CHECKPOINTS_MAGIC.to_vec();
+        header.push(FORMAT_VERSION);
+        header.push(T::hash_size() as u8);
+
+        if bytes.len() < HEADER_SIZE {
+            // The log is new, or it crashed before the header was written
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&header)?;
+            file.sync_all()?;
+            return Ok(Vec::new());
+        }
+
+        if bytes[..HEADER_SIZE] != header[..] {
+            return Err(invalid_data(
+                "checkpoint file has an unknown format or another hash size",
+            ));
+        }
+
+        let checkpoints: Vec<LogCheckpoint<T::Hash>> = bytes[HEADER_SIZE..]
+            .chunks(checkpoint_record_size::<T>())
+            .map_while(decode_checkpoint::<T>)
+            .collect();
+
+        let valid_len = HEADER_SIZE + checkpoints.len() * checkpoint_record_size::<T>();
+        if valid_len < bytes.len() {
+            file.set_len(valid_len as u64)?;
+            file.sync_all()?;
+        }
+
+        Ok(checkpoints)
+    }
+
+    /// Rebuilds the tree by applying the commit records of all checkpoints in order
+    fn replay(
+        directory: &Path,
+        checkpoints: &[LogCheckpoint<T::Hash>],
+        tree_properties: TreeProperties,
+    ) -> io::Result<MerkleTree<T>> {
+        let mut store = MemoryNodeStore::<T>::new();
+        let mut segment: Option<(usize, Vec<u8>)> = None;
+        let mut record_start = 0;
+
+        for checkpoint in checkpoints {
+            let segment_bytes = match segment {
+                Some((segment_index, ref bytes)) if segment_index == checkpoint.segment_index => {
+                    bytes
+                }
+                _ => {
+                    let bytes = fs::read(segment_path(directory, checkpoint.segment_index))?;
+                    record_start = 0;
+                    &segment.insert((checkpoint.segment_index, bytes)).1
+                }
+            };
+
+            let record = usize::try_from(checkpoint.segment_end)
+                .ok()
+                .and_then(|record_end| segment_bytes.get(record_start..record_end))
+                .ok_or_else(|| invalid_data("commit record is out of the segment bounds"))?;
+            let leaves_len = apply_commit_record::<T>(&mut store, record)
+                .ok_or_else(|| invalid_data("commit record is corrupted"))?;
+
+            let root_layer_index = store
+                .layers_len()
+                .checked_sub(1)
+                .ok_or_else(|| invalid_data("commit record doesn't contain any nodes"))?;
+            if leaves_len != checkpoint.leaves_len
+                || into_ok(store.get(root_layer_index, 0)) != Some(checkpoint.root)
+            {
+                return Err(invalid_data("commit record doesn't match its checkpoint"));
+            }
+
+            record_start = checkpoint.segment_end as usize;
+        }
+
+        let mut tree = MerkleTree::with_store(store, tree_properties)
+            .map_err(|_| invalid_data("commit records don't describe a complete tree"))?;
+        // The last commit is kept in the history so it can be undone if writing it fails
+        tree.set_retention_policy(RetentionPolicy::KeepLast(2));
+
+        Ok(tree)
+    }
+}
+
+/// Inserts the nodes of a commit record into the store and returns the number of leaves after
+/// the commit, or `None` if the record is corrupted
+fn apply_commit_record<T: Hasher>(store: &mut MemoryNodeStore<T>, record: &[u8]) -> Option<usize> {
+    let body = verify_checksum::<T>(record)?;
+    let leaves_len = read_u64(body, 0)? as usize;
+    let nodes_count = read_u64(body, 8)? as usize;
+    let node_record_size = 12 + T::hash_size();
+
+    if body.len() != 16 + nodes_count.checked_mul(node_record_size)? {
+        return None;
+    }
+
+    for node_record in body[16..].chunks(node_record_size) {
+        let layer_index = u32::from_le_bytes(node_record[..4].try_into().ok()?) as usize;
+        let node_index = read_u64(node_record, 4)? as usize;
+        let node = T::Hash::try_from(node_record[12..].to_vec()).ok()?;
+        into_ok(store.insert(layer_index, node_index, node));
+    }
+
+    Some(leaves_len)
+}
+
+fn checkpoint_record_size<T: Hasher>() -> usize {
+    24 + 2 * T::hash_size()
+}
+
+fn encode_checkpoint<T: Hasher>(checkpoint: &LogCheckpoint<T::Hash>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(checkpoint_record_size::<T>());
+    let mut root_bytes: Vec<u8> = checkpoint.root.into();
+    bytes.extend_from_slice(&(checkpoint.leaves_len as u64).to_le_bytes());
+    bytes.extend_from_slice(&(checkpoint.segment_index as u64).to_le_bytes());
+    bytes.extend_from_slice(&checkpoint.segment_end.to_le_bytes());
+    bytes.append(&mut root_bytes);
+    with_checksum::<T>(bytes)
+}
+
+fn decode_checkpoint<T: Hasher>(bytes: &[u8]) -> Option<LogCheckpoint<T::Hash>> {
+    if bytes.len() != checkpoint_record_size::<T>() {
+        return None;
+    }
+
+    let body = verify_checksum::<T>(bytes)?;
+    Some(LogCheckpoint {
+        leaves_len: read_u64(body, 0)? as usize,
+        segment_index: read_u64(body, 8)? as usize,
+        segment_end: read_u64(body, 16)?,
+        root: T::Hash::try_from(body[24..].to_vec()).ok()?,
+    })
+}
+
+/// Appends the hash of the record to the record, so torn or corrupted records can be detected
+fn with_checksum<T: Hasher>(mut record: Vec<u8>) -> Vec<u8> {
+    let mut checksum: Vec<u8> = T::hash(&record).into();
+    record.append(&mut checksum);
+    record
+}
+
+/// Returns the record without its checksum if the checksum matches
+fn verify_checksum<T: Hasher>(record: &[u8]) -> Option<&[u8]> {
+    let body_len = record.len().checked_sub(T::hash_size())?;
+    let (body, checksum) = record.split_at(body_len);
+    let expected_checksum: Vec<u8> = T::hash(body).into();
+
+    if checksum == &expected_checksum[..] {
+        Some(body)
+    } else {
+        None
+    }
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    let bytes = bytes.get(offset..offset + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn segment_path(directory: &Path, segment_index: usize) -> PathBuf {
+    directory.join(format!("segment_{}", segment_index))
+}
+
+fn open_file(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+}
+
+/// Flushes the entries of the directory, so the files created in it survive a crash
+#[cfg(unix)]
+fn sync_directory(directory: &Path) -> io::Result<()> {
+    File::open(directory)?.sync_all()
+}
+
+/// Directories can't be opened as files on other platforms, where creating a file is persisted
+/// together with its data
+#[cfg(not(unix))]
+fn sync_directory(_directory: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
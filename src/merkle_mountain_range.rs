@@ -0,0 +1,447 @@
+use crate::prelude::*;
+use crate::{error::Error, utils, utils::mmr, Hasher};
+use alloc::collections::VecDeque;
+
+/// [`MerkleMountainRange`] is an append-only structure consisting of a list of perfect binary
+/// trees, called mountains, with the tree heights strictly decreasing from left to right.
+///
+/// Nodes are stored in the order they were created, so appending a leaf never changes existing
+/// nodes. The root of the range is calculated by bagging the peaks of the mountains with
+/// [`MerkleMountainRange::bag_peaks`]. Node positions, peak bagging and proof layout are
+/// compatible with Substrate's MMR pallet, so with the [`Keccak256`] hasher the root is the same
+/// as the one BEEFY commits to.
+///
+/// ## Examples
+///
+/// ```
+/// # use rs_merkle::{MerkleMountainRange, algorithms::Sha256, Hasher};
+/// let leaves: Vec<[u8; 32]> = ["a", "b", "c", "d", "e"]
+///     .iter()
+///     .map(|x| Sha256::hash(x.as_bytes()))
+///     .collect();
+///
+/// let mut mmr = MerkleMountainRange::<Sha256>::new();
+/// mmr.append(&leaves);
+///
+/// let root = mmr.root().unwrap();
+/// let proof = mmr.proof(&[1, 4]).unwrap();
+///
+/// assert!(proof.verify(root, &[1, 4], &[leaves[1], leaves[4]]));
+/// ```
+///
+/// [`Keccak256`]: crate::algorithms::Keccak256
+#[derive(Clone)]
+pub struct MerkleMountainRange<T: Hasher> {
+    nodes: Vec<T::Hash>,
+    leaves_count: usize,
+}
+
+impl<T: Hasher> Default for MerkleMountainRange<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Hasher> MerkleMountainRange<T> {
+    /// Creates an empty mountain range
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            leaves_count: 0,
+        }
+    }
+
+    /// Builds a mountain range from the leaves
+    pub fn from_leaves(leaves: &[T::Hash]) -> Self {
+        let mut mmr = Self::new();
+        mmr.append(leaves);
+        mmr
+    }
+
+    /// Appends a leaf, merging the mountains of equal height that it completes. Returns the
+    /// position of the leaf in the range.
+    pub fn push(&mut self, leaf: T::Hash) -> usize {
+        let leaf_pos = self.nodes.len();
+        // Every trailing one of the leaf index means a mountain of the same height to the left
+        // that is going to be merged with the newly completed mountain
+        let merges_count = (self.leaves_count + 1).trailing_zeros();
+
+        self.nodes.push(leaf);
+        for height in 0..merges_count {
+            let right_pos = self.nodes.len() - 1;
+            let left_pos = right_pos - mmr::sibling_offset(height);
+            let parent = T::concat_and_hash(&self.nodes[left_pos], Some(&self.nodes[right_pos]));
+            self.nodes.push(parent);
+        }
+
+        self.leaves_count += 1;
+        leaf_pos
+    }
+
+    /// Appends leaves one by one
+    pub fn append(&mut self, leaves: &[T::Hash]) -> &mut Self {
+        for leaf in leaves {
+            self.push(*leaf);
+        }
+        self
+    }
+
+    /// Returns the number of leaves in the range
+    pub fn leaves_len(&self) -> usize {
+        self.leaves_count
+    }
+
+    /// Returns the total number of nodes in the range, which is also called the MMR size
+    pub fn size(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns all nodes of the range in their positional order
+    pub fn nodes(&self) -> &[T::Hash] {
+        &self.nodes
+    }
+
+    /// Returns the leaf with the given index
+    pub fn leaf(&self, leaf_index: usize) -> Option<T::Hash> {
+        if leaf_index >= self.leaves_count {
+            return None;
+        }
+        self.nodes.get(mmr::leaf_index_to_pos(leaf_index)?).cloned()
+    }
+
+    /// Returns peaks of the mountains from left to right
+    pub fn peaks(&self) -> Vec<T::Hash> {
+        // The nodes of the range are in memory, so their positions always fit in `usize`
+        mmr::peak_positions(self.leaves_count)
+            .unwrap_or_default()
+            .iter()
+            .map(|pos| self.nodes[*pos])
+            .collect()
+    }
+
+    /// Bags the peaks into a single root, from right to left: the two rightmost peaks are merged
+    /// as `hash(right ++ left)` until one hash remains. This is the same bagging as in
+    /// the `merkle-mountain-range` crate used by Substrate's MMR pallet and BEEFY.
+    ///
+    /// Returns `None` if there are no peaks.
+    pub fn bag_peaks(peaks: &[T::Hash]) -> Option<T::Hash> {
+        let mut peaks = peaks.to_vec();
+
+        while peaks.len() > 1 {
+            let right_peak = peaks.pop()?;
+            let left_peak = peaks.pop()?;
+            peaks.push(T::concat_and_hash(&right_peak, Some(&left_peak)));
+        }
+
+        peaks.pop()
+    }
+
+    /// Returns the root of the range, or `None` if the range is empty
+    pub fn root(&self) -> Option<T::Hash> {
+        Self::bag_peaks(&self.peaks())
+    }
+
+    /// Returns the root of the range as a hex string
+    pub fn root_hex(&self) -> Option<String> {
+        let root = self.root()?;
+        Some(utils::collections::to_hex_string(&root))
+    }
+
+    /// Creates a proof for one or several leaves. Mountains that don't contain any of the
+    /// proven leaves are represented by their peaks, and all such peaks to the right of the last
+    /// proven leaf are bagged into a single hash.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if any of the indices is out of the range
+    pub fn proof(&self, leaf_indices: &[usize]) -> Result<MerkleMountainRangeProof<T>, Error> {
+        let mut positions = Vec::with_capacity(leaf_indices.len());
+        for leaf_index in leaf_indices {
+            if *leaf_index >= self.leaves_count {
+                return Err(Error::leaf_index_out_of_range(
+                    *leaf_index,
+                    self.leaves_count,
+                ));
+            }
+            positions.push(
+                mmr::leaf_index_to_pos(*leaf_index)
+                    .ok_or_else(|| Error::too_many_leaves(self.leaves_count))?,
+            );
+        }
+        positions.sort_unstable();
+        positions.dedup();
+
+        let mut proof_hashes = Vec::new();
+        let mut bagging_track = 0;
+        let mut remaining = positions.as_slice();
+        let peaks = mmr::peak_positions(self.leaves_count)
+            .ok_or_else(|| Error::too_many_leaves(self.leaves_count))?;
+
+        for peak_pos in peaks {
+            let split = remaining
+                .iter()
+                .position(|pos| *pos > peak_pos)
+                .unwrap_or(remaining.len());
+            let (peak_positions, rest) = remaining.split_at(split);
+            remaining = rest;
+
+            if peak_positions.is_empty() {
+                bagging_track += 1;
+            } else {
+                bagging_track = 0;
+            }
+            self.peak_proof(&mut proof_hashes, peak_positions, peak_pos);
+        }
+
+        if bagging_track > 1 {
+            let rhs_peaks = proof_hashes.split_off(proof_hashes.len() - bagging_track);
+            proof_hashes.extend(Self::bag_peaks(&rhs_peaks));
+        }
+
+        MerkleMountainRangeProof::new(self.leaves_count, proof_hashes)
+    }
+
+    fn peak_proof(&self, proof_hashes: &mut Vec<T::Hash>, positions: &[usize], peak_pos: usize) {
+        // The leaf is the peak itself, no hashes needed
+        if positions == [peak_pos] {
+            return;
+        }
+        // No leaves in this mountain, so the peak is all that's needed
+        if positions.is_empty() {
+            proof_hashes.push(self.nodes[peak_pos]);
+            return;
+        }
+
+        let mut queue: VecDeque<(usize, u32)> = positions.iter().map(|pos| (*pos, 0)).collect();
+        while let Some((pos, height)) = queue.pop_front() {
+            if pos == peak_pos {
+                break;
+            }
+
+            let (sibling_pos, parent_pos) = if mmr::pos_height_in_tree(pos + 1) > height {
+                // The node is the right child
+                (pos - mmr::sibling_offset(height), pos + 1)
+            } else {
+                (
+                    pos + mmr::sibling_offset(height),
+                    pos + mmr::parent_offset(height),
+                )
+            };
+
+            if queue.front().map(|(pos, _)| *pos) == Some(sibling_pos) {
+                // Sibling can be calculated from the proven leaves
+                queue.pop_front();
+            } else {
+                proof_hashes.push(self.nodes[sibling_pos]);
+            }
+
+            if parent_pos < peak_pos {
+                queue.push_back((parent_pos, height + 1));
+            }
+        }
+    }
+}
+
+/// [`MerkleMountainRangeProof`] is a proof produced by [`MerkleMountainRange::proof`]. It has
+/// the same layout as Substrate's MMR `LeafProof` items, so proofs can be passed between them.
+#[derive(Clone)]
+pub struct MerkleMountainRangeProof<T: Hasher> {
+    leaves_count: usize,
+    proof_hashes: Vec<T::Hash>,
+}
+
+impl<T: Hasher> MerkleMountainRangeProof<T> {
+    /// Creates a proof for a range with the given number of leaves.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the positions of the nodes of such a range don't fit in `usize`
+    pub fn new(leaves_count: usize, proof_hashes: Vec<T::Hash>) -> Result<Self, Error> {
+        if mmr::mmr_size(leaves_count).is_none() {
+            return Err(Error::too_many_leaves(leaves_count));
+        }
+        Ok(Self {
+            leaves_count,
+            proof_hashes,
+        })
+    }
+
+    /// Returns the number of leaves in the range the proof was made for
+    pub fn leaves_len(&self) -> usize {
+        self.leaves_count
+    }
+
+    /// Returns proof hashes in the order they are consumed during verification
+    pub fn proof_hashes(&self) -> &[T::Hash] {
+        &self.proof_hashes
+    }
+
+    /// Calculates the peaks of the range from the proof and the proven leaves. Peaks to the
+    /// right of the last proven leaf may be returned already bagged into a single hash, so the
+    /// result should only be used to calculate the root.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if an index is out of range or is repeated with a different hash, or
+    /// if the proof doesn't have the hashes the leaves need.
+    pub fn peaks(
+        &self,
+        leaf_indices: &[usize],
+        leaf_hashes: &[T::Hash],
+    ) -> Result<Vec<T::Hash>, Error> {
+        if leaf_indices.len() != leaf_hashes.len() {
+            return Err(Error::leaves_indices_count_mismatch(
+                leaf_indices.len(),
+                leaf_hashes.len(),
+            ));
+        }
+
+        let mut nodes = Vec::with_capacity(leaf_indices.len());
+        for (leaf_index, leaf_hash) in leaf_indices.iter().zip(leaf_hashes.iter()) {
+            if *leaf_index >= self.leaves_count {
+                return Err(Error::leaf_index_out_of_range(
+                    *leaf_index,
+                    self.leaves_count,
+                ));
+            }
+            let pos = mmr::leaf_index_to_pos(*leaf_index)
+                .ok_or_else(|| Error::too_many_leaves(self.leaves_count))?;
+            nodes.push((pos, *leaf_index, *leaf_hash));
+        }
+        nodes.sort_by_key(|(pos, _, _)| *pos);
+        // A repeated leaf is proven once, but only if all its hashes are the same, otherwise
+        // a forged hash would be dropped without being checked
+        if let Some(pair) = nodes
+            .windows(2)
+            .find(|pair| pair[0].0 == pair[1].0 && pair[0].2 != pair[1].2)
+        {
+            return Err(Error::conflicting_leaves(pair[0].1));
+        }
+        nodes.dedup_by_key(|(pos, _, _)| *pos);
+        let nodes: Vec<(usize, T::Hash)> = nodes
+            .into_iter()
+            .map(|(pos, _, hash)| (pos, hash))
+            .collect();
+
+        let mut proof_hashes = self.proof_hashes.iter();
+        let mut peaks = Vec::new();
+        let mut remaining = nodes.as_slice();
+        let peak_positions = mmr::peak_positions(self.leaves_count)
+            .ok_or_else(|| Error::too_many_leaves(self.leaves_count))?;
+
+        for peak_pos in peak_positions {
+            let split = remaining
+                .iter()
+                .position(|(pos, _)| *pos > peak_pos)
+                .unwrap_or(remaining.len());
+            let (peak_nodes, rest) = remaining.split_at(split);
+            remaining = rest;
+
+            let peak = match peak_nodes {
+                [(pos, hash)] if *pos == peak_pos => *hash,
+                [] => match proof_hashes.next() {
+                    Some(hash) => *hash,
+                    // The rest of the peaks were bagged into the previous hash
+                    None => break,
+                },
+                _ => Self::peak_root(peak_nodes, peak_pos, &mut proof_hashes)?,
+            };
+            peaks.push(peak);
+        }
+
+        if !remaining.is_empty() {
+            return Err(Error::not_enough_helper_nodes());
+        }
+        // Bagged peaks to the right of the last proven leaf
+        peaks.extend(proof_hashes.next());
+        if proof_hashes.next().is_some() {
+            return Err(Error::too_many_proof_hashes());
+        }
+
+        Ok(peaks)
+    }
+
+    fn peak_root<'a>(
+        nodes: &[(usize, T::Hash)],
+        peak_pos: usize,
+        proof_hashes: &mut impl Iterator<Item = &'a T::Hash>,
+    ) -> Result<T::Hash, Error>
+    where
+        T::Hash: 'a,
+    {
+        let mut queue: VecDeque<(usize, T::Hash, u32)> =
+            nodes.iter().map(|(pos, hash)| (*pos, *hash, 0)).collect();
+
+        while let Some((pos, hash, height)) = queue.pop_front() {
+            if pos == peak_pos {
+                if !queue.is_empty() {
+                    return Err(Error::not_enough_helper_nodes());
+                }
+                return Ok(hash);
+            }
+
+            let is_right_child = mmr::pos_height_in_tree(pos + 1) > height;
+            let sibling_pos = if is_right_child {
+                pos - mmr::sibling_offset(height)
+            } else {
+                pos + mmr::sibling_offset(height)
+            };
+
+            let sibling = if queue.front().map(|(pos, _, _)| *pos) == Some(sibling_pos) {
+                queue.pop_front().map(|(_, hash, _)| hash)
+            } else {
+                proof_hashes.next().cloned()
+            }
+            .ok_or_else(Error::not_enough_hashes_to_calculate_root)?;
+
+            let (parent_pos, parent) = if is_right_child {
+                (pos + 1, T::concat_and_hash(&sibling, Some(&hash)))
+            } else {
+                (
+                    pos + mmr::parent_offset(height),
+                    T::concat_and_hash(&hash, Some(&sibling)),
+                )
+            };
+
+            if parent_pos > peak_pos {
+                return Err(Error::not_enough_helper_nodes());
+            }
+            queue.push_back((parent_pos, parent, height + 1));
+        }
+
+        Err(Error::not_enough_hashes_to_calculate_root())
+    }
+
+    /// Calculates the root of the range from the proof and the proven leaves
+    pub fn root(&self, leaf_indices: &[usize], leaf_hashes: &[T::Hash]) -> Result<T::Hash, Error> {
+        let peaks = self.peaks(leaf_indices, leaf_hashes)?;
+        MerkleMountainRange::<T>::bag_peaks(&peaks)
+            .ok_or_else(Error::not_enough_hashes_to_calculate_root)
+    }
+
+    /// Verifies that the leaves are included in the range with the given root
+    pub fn verify(&self, root: T::Hash, leaf_indices: &[usize], leaf_hashes: &[T::Hash]) -> bool {
+        match self.root(leaf_indices, leaf_hashes) {
+            Ok(extracted_root) => extracted_root == root,
+            Err(_) => false,
+        }
+    }
+
+    /// Verifies that the leaves are included in the range with the given peaks. The number of
+    /// peaks must match the number of leaves the proof was made for.
+    pub fn verify_with_peaks(
+        &self,
+        peaks: &[T::Hash],
+        leaf_indices: &[usize],
+        leaf_hashes: &[T::Hash],
+    ) -> bool {
+        match mmr::peak_positions(self.leaves_count) {
+            Some(peak_positions) if peak_positions.len() == peaks.len() => {}
+            _ => return false,
+        }
+        match MerkleMountainRange::<T>::bag_peaks(peaks) {
+            Some(root) => self.verify(root, leaf_indices, leaf_hashes),
+            None => false,
+        }
+    }
+}
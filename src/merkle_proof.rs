@@ -7,7 +7,7 @@ use crate::{
     utils::properties::TreeProperties,
     Hasher,
 };
-use core::convert::TryFrom;
+use core::{cmp::Ordering, convert::TryFrom};
 
 /// [`MerkleProof`] is used to parse, verify, calculate a root for Merkle proofs.
 ///
@@ -48,6 +48,7 @@ use core::convert::TryFrom;
 ///
 /// [`Hasher`]: crate::Hasher
 /// [`algorithms::Sha256`]: crate::algorithms::Sha256
+#[derive(Clone)]
 pub struct MerkleProof<T: Hasher> {
     proof_hashes: Vec<T::Hash>,
 }
@@ -140,9 +141,7 @@ impl<T: Hasher> MerkleProof<T> {
     /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils, utils::properties::TreeProperties};
     /// # use std::convert::TryFrom;
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///    sorted_pair_enabled: false,
-    /// };
+    /// let tree_properties = TreeProperties::default();
     /// let leaves = [
     ///     Sha256::hash("a".as_bytes()),
     ///     Sha256::hash("b".as_bytes()),
@@ -180,18 +179,48 @@ impl<T: Hasher> MerkleProof<T> {
         }
     }
 
+    /// Verifies the proof of a single leaf, which is given either as a value that is hashed
+    /// with the [`Hasher`] or as a hash. Together with [`TreeProperties::SUBSTRATE`], this
+    /// matches Substrate's `binary_merkle_tree::verify_proof`, and the proof hashes are the
+    /// `proof` of Substrate's `MerkleProof`.
+    ///
+    /// For examples, please check [`MerkleTree::from_leaf_values`]
+    ///
+    /// [`MerkleTree::from_leaf_values`]: crate::MerkleTree::from_leaf_values
+    pub fn verify_leaf<'a>(
+        &self,
+        root: T::Hash,
+        leaf_index: usize,
+        leaf: impl Into<Leaf<'a, T>>,
+        total_leaves_count: usize,
+        tree_properties: TreeProperties,
+    ) -> bool {
+        if leaf_index >= total_leaves_count {
+            return false;
+        }
+
+        self.verify(
+            root,
+            &[leaf_index],
+            &[leaf.into().hash()],
+            total_leaves_count,
+            tree_properties,
+        )
+    }
+
     /// Calculates Merkle root based on provided leaves and proof hashes. Used inside the
     /// [`MerkleProof::verify`] method, but sometimes can be used on its own.
     ///
+    /// Returns an error if any of the indices is out of range or if the proof doesn't have
+    /// exactly as many hashes as the indices need.
+    ///
     /// ## Examples
     ///
     /// ```
     /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils, utils::properties::TreeProperties};
     /// # use std::convert::TryFrom;
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///     sorted_pair_enabled: false,
-    /// };
+    /// let tree_properties = TreeProperties::default();
     /// let leaves = [
     ///     Sha256::hash("a".as_bytes()),
     ///     Sha256::hash("b".as_bytes()),
@@ -232,26 +261,39 @@ impl<T: Hasher> MerkleProof<T> {
         let mut leaf_tuples: Vec<(usize, T::Hash)> = leaf_indices
             .iter()
             .cloned()
-            .zip(leaf_hashes.iter().cloned())
+            .zip(
+                leaf_hashes
+                    .iter()
+                    .map(|leaf| tree_properties.hash_leaf::<T>(leaf)),
+            )
             .collect();
         // Sorting leaves by indexes in case they weren't sorted already
-        leaf_tuples.sort_by(|(a, _), (b, _)| a.cmp(b));
+        leaf_tuples.sort_by_key(|(a, _)| *a);
         // Getting back _sorted_ indices
         let proof_indices_by_layers =
-            utils::indices::proof_indices_by_layers(leaf_indices, total_leaves_count);
+            self.proof_indices_by_layers(leaf_indices, total_leaves_count)?;
 
         // The next lines copy hashes from proof hashes and group them by layer index
         let mut proof_layers: Vec<Vec<(usize, T::Hash)>> = Vec::with_capacity(tree_depth + 1);
-        let mut proof_copy = self.proof_hashes.clone();
+        let mut proof_hashes = self.proof_hashes.as_slice();
         for proof_indices in proof_indices_by_layers {
-            let proof_hashes = proof_copy.splice(0..proof_indices.len(), []);
-            proof_layers.push(proof_indices.iter().cloned().zip(proof_hashes).collect());
+            let layer_hashes = proof_hashes
+                .get(..proof_indices.len())
+                .ok_or_else(Error::not_enough_hashes_to_calculate_root)?;
+            proof_hashes = &proof_hashes[layer_hashes.len()..];
+            proof_layers.push(
+                proof_indices
+                    .iter()
+                    .cloned()
+                    .zip(layer_hashes.iter().cloned())
+                    .collect(),
+            );
         }
 
         match proof_layers.first_mut() {
             Some(first_layer) => {
                 first_layer.append(&mut leaf_tuples);
-                first_layer.sort_by(|(a, _), (b, _)| a.cmp(b));
+                first_layer.sort_by_key(|(a, _)| *a);
             }
             None => proof_layers.push(leaf_tuples),
         }
@@ -259,7 +301,7 @@ impl<T: Hasher> MerkleProof<T> {
         let partial_tree = PartialTree::<T>::build(proof_layers, tree_depth, tree_properties)?;
 
         match partial_tree.root() {
-            Some(root) => Ok(*root),
+            Some(root) => Ok(root),
             None => Err(Error::not_enough_hashes_to_calculate_root()),
         }
     }
@@ -272,9 +314,7 @@ impl<T: Hasher> MerkleProof<T> {
     /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils, utils::properties::TreeProperties};
     /// # use std::convert::TryFrom;
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///     sorted_pair_enabled: false,
-    /// };
+    /// let tree_properties = TreeProperties::default();
     /// let leaves = [
     ///     Sha256::hash("a".as_bytes()),
     ///     Sha256::hash("b".as_bytes()),
@@ -402,9 +442,7 @@ impl<T: Hasher> MerkleProof<T> {
     /// #
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     ///
-    /// let tree_properties = TreeProperties {
-    ///    sorted_pair_enabled: false,
-    /// };
+    /// let tree_properties = TreeProperties::default();
     /// let leaf_values = ["a", "b", "c", "d", "e", "f"];
     /// let leaves: Vec<[u8; 32]> = leaf_values
     ///     .iter()
@@ -449,9 +487,7 @@ impl<T: Hasher> MerkleProof<T> {
     /// # use std::convert::TryFrom;
     /// #
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///     sorted_pair_enabled: false,
-    /// };
+    /// let tree_properties = TreeProperties::default();
     /// let leaf_values = ["a", "b", "c", "d", "e", "f"];
     /// let leaves: Vec<[u8; 32]> = leaf_values
     ///     .iter()
@@ -481,6 +517,29 @@ impl<T: Hasher> MerkleProof<T> {
     pub fn serialize<S: MerkleProofSerializer>(&self) -> Vec<u8> {
         S::serialize(self)
     }
+
+    /// Returns the indices of the proof hashes grouped by layer, checking that the leaf indices
+    /// are in range of the tree and that the proof has exactly as many hashes as the layers need
+    pub(crate) fn proof_indices_by_layers(
+        &self,
+        leaf_indices: &[usize],
+        total_leaves_count: usize,
+    ) -> Result<Vec<Vec<usize>>, Error> {
+        if let Some(index) = leaf_indices
+            .iter()
+            .find(|index| **index >= total_leaves_count)
+        {
+            return Err(Error::leaf_index_out_of_range(*index, total_leaves_count));
+        }
+        let proof_indices_by_layers =
+            utils::indices::proof_indices_by_layers(leaf_indices, total_leaves_count);
+        let proof_hashes_count: usize = proof_indices_by_layers.iter().map(Vec::len).sum();
+        match self.proof_hashes.len().cmp(&proof_hashes_count) {
+            Ordering::Less => Err(Error::not_enough_hashes_to_calculate_root()),
+            Ordering::Greater => Err(Error::too_many_proof_hashes()),
+            Ordering::Equal => Ok(proof_indices_by_layers),
+        }
+    }
 }
 
 impl<T: Hasher> TryFrom<Vec<u8>> for MerkleProof<T> {
@@ -536,3 +595,68 @@ impl<T: Hasher> TryFrom<&[u8]> for MerkleProof<T> {
         DirectHashesOrder::deserialize(bytes)
     }
 }
+
+impl<T: Hasher> PartialEq for MerkleProof<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.proof_hashes == other.proof_hashes
+    }
+}
+
+impl<T: Hasher> Eq for MerkleProof<T> where T::Hash: Eq {}
+
+impl<T: Hasher> core::fmt::Debug for MerkleProof<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MerkleProof")
+            .field("proof_hashes", &self.proof_hashes)
+            .finish()
+    }
+}
+
+/// A leaf passed to [`MerkleProof::verify_leaf`], either the value of the leaf, which is hashed
+/// with the [`Hasher`], or its hash
+pub enum Leaf<'a, T: Hasher> {
+    Value(&'a [u8]),
+    Hash(T::Hash),
+}
+
+impl<'a, T: Hasher> Leaf<'a, T> {
+    pub fn hash(&self) -> T::Hash {
+        match self {
+            Leaf::Value(value) => T::hash(value),
+            Leaf::Hash(hash) => *hash,
+        }
+    }
+}
+
+impl<'a, T: Hasher, V: AsRef<[u8]> + ?Sized> From<&'a V> for Leaf<'a, T> {
+    fn from(value: &'a V) -> Self {
+        Leaf::Value(value.as_ref())
+    }
+}
+
+impl<'a, T: Hasher> Clone for Leaf<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T: Hasher> Copy for Leaf<'a, T> {}
+
+impl<'a, T: Hasher> PartialEq for Leaf<'a, T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Leaf::Value(value), Leaf::Value(other_value)) => value == other_value,
+            (Leaf::Hash(hash), Leaf::Hash(other_hash)) => hash == other_hash,
+            _ => false,
+        }
+    }
+}
+
+impl<'a, T: Hasher> core::fmt::Debug for Leaf<'a, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Leaf::Value(value) => f.debug_tuple("Value").field(value).finish(),
+            Leaf::Hash(hash) => f.debug_tuple("Hash").field(hash).finish(),
+        }
+    }
+}
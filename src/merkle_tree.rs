@@ -1,8 +1,18 @@
 use crate::prelude::*;
 use crate::{
-    partial_tree::PartialTree, utils, utils::indices, utils::properties::TreeProperties, Hasher,
-    MerkleProof,
+    node_stores::{into_ok, MemoryNodeStore},
+    partial_tree::PartialTree,
+    snapshot::{SnapshotReader, SnapshotWriter, HISTORY_FLAG, SNAPSHOT_MAGIC, SNAPSHOT_VERSION},
+    utils,
+    utils::indices,
+    utils::properties::{OddNodeStrategy, TreeProperties},
+    CommitInfo, CommitRef, CompactMultiProof, ConsistencyProof, Error, Hasher, IndexedMerkleProof,
+    MerkleProof, NodeStore, RetentionPolicy,
 };
+use alloc::collections::{BTreeMap, VecDeque};
+use core::convert::Infallible;
+
+type HelperNodeTuples<H> = Vec<Vec<(usize, H)>>;
 
 /// [`MerkleTree`] is a Merkle Tree that is well suited for both basic and advanced usage.
 ///
@@ -12,19 +22,95 @@ use crate::{
 /// Advanced features include being able to make transactional changes to a tree with being able to
 /// roll back to any previously committed state of the tree. This scenario is similar to Git and
 /// can be found in databases and file systems.
+///
+/// Committed nodes are kept in a [`NodeStore`], which is [`MemoryNodeStore`] by default. Use
+/// [`MerkleTree::with_store`] to keep them elsewhere, for example in files. Stores that can fail
+/// are accessed with the methods that return errors, like [`MerkleTree::try_commit`]. The commit
+/// history is always kept in memory.
 #[derive(Clone)]
-pub struct MerkleTree<T: Hasher> {
-    current_working_tree: PartialTree<T>,
-    history: Vec<PartialTree<T>>,
+pub struct MerkleTree<T: Hasher, S: NodeStore<T> = MemoryNodeStore<T>> {
+    current_working_tree: PartialTree<T, S>,
+    history: Vec<HistoryEntry<T>>,
     uncommitted_leaves: Vec<T::Hash>,
+    uncommitted_updates: BTreeMap<usize, T::Hash>,
+    uncommitted_truncate_len: Option<usize>,
+    next_commit_id: u64,
+    retention_policy: RetentionPolicy,
     tree_properties: TreeProperties,
 }
 
+/// A single commit in the [`MerkleTree`] history. The reverse diff is missing for the base
+/// snapshot of a pruned history, see [`RetentionPolicy`].
+#[derive(Clone)]
+struct HistoryEntry<T: Hasher> {
+    id: u64,
+    label: Option<String>,
+    root: Option<T::Hash>,
+    leaves_len: usize,
+    reverse_diff: Option<ReverseDiff<T>>,
+}
+
+/// The nodes that were overwritten or removed by a commit and the number of leaves before it.
+/// Nodes that were added by the commit are dropped on rollback by truncating the tree to the
+/// previous length.
+#[derive(Clone)]
+struct ReverseDiff<T: Hasher> {
+    reverted_nodes: PartialTree<T>,
+    previous_leaves_len: usize,
+}
+
+impl<T: Hasher> ReverseDiff<T> {
+    /// Returns `true` if the diff has every node that a commit to a tree of `leaves_len` leaves
+    /// has to overwrite or remove, which are the nodes of the previous tree whose leaves aren't
+    /// the same in the new one. Other nodes may be overwritten by updates, so a diff without
+    /// them can't be detected.
+    fn restores_changed_nodes(&self, leaves_len: usize) -> bool {
+        let previous_leaves_len = self.previous_leaves_len;
+        if previous_leaves_len == 0 {
+            return true;
+        }
+
+        // Floors and ceilings of the leaf counts divided by the number of leaves under a node
+        // of the layer
+        let mut previous_layer_len = previous_leaves_len;
+        let mut previous_full_nodes = previous_leaves_len;
+        let mut full_nodes = leaves_len;
+        for layer_index in 0..=indices::tree_depth(previous_leaves_len) {
+            let changed_nodes = if leaves_len < previous_leaves_len {
+                // Nodes over the removed leaves are either removed or rehashed
+                full_nodes..previous_layer_len
+            } else if leaves_len > previous_leaves_len && previous_full_nodes < previous_layer_len {
+                // The last node of the layer gets the appended leaves under it
+                previous_full_nodes..previous_layer_len
+            } else {
+                0..0
+            };
+            let layer = self
+                .reverted_nodes
+                .layers()
+                .get(layer_index)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let restored_nodes = layer
+                .iter()
+                .filter(|(index, _)| changed_nodes.contains(index))
+                .count();
+            if restored_nodes != changed_nodes.len() {
+                return false;
+            }
+
+            previous_layer_len = indices::div_ceil(previous_layer_len, 2);
+            previous_full_nodes /= 2;
+            full_nodes /= 2;
+        }
+
+        true
+    }
+}
+
 impl<T: Hasher> Default for MerkleTree<T> {
     fn default() -> Self {
-        Self::new(TreeProperties {
-            sorted_pair_enabled: false,
-        })
+        Self::new(TreeProperties::default())
     }
 }
 
@@ -36,21 +122,14 @@ impl<T: Hasher> MerkleTree<T> {
     /// ```
     /// use rs_merkle::{MerkleTree, algorithms::Sha256, utils::properties::TreeProperties};
     ///
-    /// let tree_properties = TreeProperties {
-    ///    sorted_pair_enabled: false,
-    /// };
+    /// let tree_properties = TreeProperties::default();
     ///
     /// let merkle_tree: MerkleTree<Sha256> = MerkleTree::new(tree_properties);
     ///
     /// let another_merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
     /// ```
     pub fn new(tree_properties: TreeProperties) -> Self {
-        Self {
-            current_working_tree: PartialTree::new(),
-            history: Vec::new(),
-            uncommitted_leaves: Vec::new(),
-            tree_properties,
-        }
+        Self::from_store(MemoryNodeStore::new(), tree_properties)
     }
 
     /// Clones the leaves and builds the tree from them
@@ -61,9 +140,7 @@ impl<T: Hasher> MerkleTree<T> {
     /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils,utils::properties::TreeProperties};
     /// # use std::convert::TryFrom;
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///     sorted_pair_enabled: false,
-    /// };
+    /// let tree_properties = TreeProperties::default();
     /// let leaves = [
     ///     Sha256::hash("a".as_bytes()),
     ///     Sha256::hash("b".as_bytes()),
@@ -76,12 +153,1451 @@ impl<T: Hasher> MerkleTree<T> {
     pub fn from_leaves(leaves: &[T::Hash], tree_properties: TreeProperties) -> Self {
         let mut tree = Self::new(tree_properties);
 
-        tree.append(leaves.to_vec().as_mut());
-        tree.commit();
+        tree.append(leaves.to_vec().as_mut());
+        tree.commit();
+
+        tree
+    }
+
+    /// Hashes the values with the [`Hasher`] and builds the tree from the hashes, like
+    /// Substrate's `binary_merkle_tree::merkle_root` does. Combined with
+    /// [`TreeProperties::SUBSTRATE`] and [`Keccak256`], the root is the same as the one produced
+    /// by Substrate, except for an empty tree, for which Substrate returns a hash consisting of
+    /// zero bytes.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, algorithms::Keccak256, utils::properties::TreeProperties, Leaf};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let values = ["a", "b", "c"];
+    ///
+    /// let merkle_tree = MerkleTree::<Keccak256>::from_leaf_values(&values, TreeProperties::SUBSTRATE);
+    /// let root = merkle_tree.root().unwrap_or_default();
+    ///
+    /// let proof = merkle_tree.proof(&[2]);
+    /// assert!(proof.verify_leaf(root, 2, Leaf::Value(b"c"), values.len(), TreeProperties::SUBSTRATE));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Keccak256`]: crate::algorithms::Keccak256
+    pub fn from_leaf_values<V: AsRef<[u8]>>(values: &[V], tree_properties: TreeProperties) -> Self {
+        let leaves: Vec<T::Hash> = values.iter().map(|value| T::hash(value.as_ref())).collect();
+        Self::from_leaves(&leaves, tree_properties)
+    }
+
+    /// Loads a tree from a snapshot created with [`MerkleTree::to_snapshot_bytes`]. The nodes are
+    /// not rehashed, so loading is much faster than building the tree from the leaves. Instead,
+    /// the snapshot is checked against the hash at its end and the shape of the layers is
+    /// validated. The hash at the end only detects accidental corruption, and a matching
+    /// [`MerkleTree::root`] doesn't mean the leaves and the other nodes match it. Call
+    /// [`MerkleTree::verify_layers`] on the loaded tree if the snapshot isn't trusted.
+    ///
+    /// Returns an error if the snapshot is corrupted, has an unsupported version, was created
+    /// with a hash of another size, or if its history doesn't lead to the stored layers or misses
+    /// nodes needed to roll it back.
+    ///
+    /// For examples, please check [`MerkleTree::to_snapshot_bytes`]
+    pub fn from_snapshot_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = SnapshotReader::new::<T>(bytes)?;
+        if reader.read_bytes(SNAPSHOT_MAGIC.len())? != SNAPSHOT_MAGIC {
+            return Err(Error::invalid_snapshot("unknown format"));
+        }
+        if reader.read_u8()? != SNAPSHOT_VERSION {
+            return Err(Error::invalid_snapshot("unsupported version"));
+        }
+        if reader.read_u32()? as usize != T::hash_size() {
+            return Err(Error::invalid_snapshot(
+                "hash size doesn't match the hasher",
+            ));
+        }
+        let flags = reader.read_u8()?;
+        let tree_properties = reader.read_tree_properties()?;
+        let next_commit_id = reader.read_u64()?;
+
+        let layers_len = reader.read_len(8)?;
+        let mut layers = Vec::with_capacity(layers_len);
+        for _ in 0..layers_len {
+            let layer_len = reader.read_len(T::hash_size())?;
+            let mut layer = Vec::with_capacity(layer_len);
+            for _ in 0..layer_len {
+                layer.push(reader.read_hash::<T>()?);
+            }
+            layers.push(layer);
+        }
+
+        let mut tree =
+            Self::from_layers(layers, tree_properties).map_err(Error::invalid_snapshot)?;
+        tree.next_commit_id = next_commit_id;
+
+        if flags & HISTORY_FLAG != 0 {
+            tree.retention_policy = reader.read_retention_policy()?;
+            let history_len = reader.read_len(1)?;
+            for _ in 0..history_len {
+                let id = reader.read_u64()?;
+                let leaves_len = reader.read_usize()?;
+                let root = match reader.read_bool()? {
+                    true => Some(reader.read_hash::<T>()?),
+                    false => None,
+                };
+                let label = match reader.read_bool()? {
+                    true => {
+                        let label_len = reader.read_len(1)?;
+                        let label = core::str::from_utf8(reader.read_bytes(label_len)?)
+                            .map_err(|_| Error::invalid_snapshot("label is not valid UTF-8"))?;
+                        Some(label.to_string())
+                    }
+                    false => None,
+                };
+                let reverse_diff = match reader.read_bool()? {
+                    true => {
+                        let previous_leaves_len = reader.read_usize()?;
+                        let layers_len = reader.read_len(8)?;
+                        let mut layers = Vec::with_capacity(layers_len);
+                        for _ in 0..layers_len {
+                            let layer_len = reader.read_len(8 + T::hash_size())?;
+                            let mut layer = Vec::with_capacity(layer_len);
+                            for _ in 0..layer_len {
+                                layer.push((reader.read_usize()?, reader.read_hash::<T>()?));
+                            }
+                            if !layer.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+                                return Err(Error::invalid_snapshot(
+                                    "reverted node indices are not ascending",
+                                ));
+                            }
+                            layers.push(layer);
+                        }
+                        Some(ReverseDiff {
+                            reverted_nodes: PartialTree::with_store(MemoryNodeStore::from_layers(
+                                layers,
+                            )),
+                            previous_leaves_len,
+                        })
+                    }
+                    false => None,
+                };
+
+                if reverse_diff
+                    .as_ref()
+                    .is_some_and(|reverse_diff| !reverse_diff.restores_changed_nodes(leaves_len))
+                {
+                    return Err(Error::invalid_snapshot(
+                        "commit can't be rolled back with its reverted nodes",
+                    ));
+                }
+                if tree.history.last().is_some_and(|last| last.id >= id) || id >= next_commit_id {
+                    return Err(Error::invalid_snapshot("commit ids are not ascending"));
+                }
+                // Only the base snapshot of a pruned history can't be rolled back, and every
+                // other commit has to be rolled back to the state of the previous one
+                if let Some(last) = tree.history.last() {
+                    let previous_leaves_len = reverse_diff
+                        .as_ref()
+                        .map(|reverse_diff| reverse_diff.previous_leaves_len)
+                        .ok_or_else(|| Error::invalid_snapshot("commit can't be rolled back"))?;
+                    if previous_leaves_len != last.leaves_len {
+                        return Err(Error::invalid_snapshot(
+                            "commit doesn't start from the previous one",
+                        ));
+                    }
+                }
+                tree.history.push(HistoryEntry {
+                    id,
+                    label,
+                    root,
+                    leaves_len,
+                    reverse_diff,
+                });
+            }
+
+            if let Some(last) = tree.history.last() {
+                if last.leaves_len != tree.leaves_len() || last.root != tree.root() {
+                    return Err(Error::invalid_snapshot(
+                        "last commit doesn't match the tree",
+                    ));
+                }
+            }
+        }
+
+        if !reader.is_empty() {
+            return Err(Error::invalid_snapshot("unexpected bytes after the tree"));
+        }
+
+        Ok(tree)
+    }
+
+    /// Creates a tree from its committed layers without rehashing them. Returns the reason if
+    /// the layer sizes don't match a tree built from the leaves of the first layer.
+    pub(crate) fn from_layers(
+        layers: Vec<Vec<T::Hash>>,
+        tree_properties: TreeProperties,
+    ) -> Result<Self, &'static str> {
+        let layers = layers
+            .into_iter()
+            .map(|layer| layer.into_iter().enumerate().collect())
+            .collect();
+        let store = MemoryNodeStore::from_layers(layers);
+        Self::check_layer_lens(&store)?;
+
+        Ok(Self::from_store(store, tree_properties))
+    }
+}
+
+impl<T: Hasher, S: NodeStore<T>> MerkleTree<T, S> {
+    /// Creates a Merkle Tree that keeps its committed nodes in the given store. If the store
+    /// already contains a tree, for example a [`FileNodeStore`] that was written before, the
+    /// tree is opened as is, without rehashing. The history of such a tree starts empty.
+    ///
+    /// Returns an error if the layer lengths of the store don't match a tree built from the
+    /// leaves of its first layer.
+    ///
+    /// For examples, please check [`FileNodeStore`]
+    ///
+    /// [`FileNodeStore`]: crate::node_stores::FileNodeStore
+    pub fn with_store(store: S, tree_properties: TreeProperties) -> Result<Self, Error> {
+        Self::check_layer_lens(&store).map_err(Error::node_store)?;
+        Ok(Self::from_store(store, tree_properties))
+    }
+
+    fn from_store(store: S, tree_properties: TreeProperties) -> Self {
+        Self {
+            current_working_tree: PartialTree::with_store(store),
+            history: Vec::new(),
+            uncommitted_leaves: Vec::new(),
+            uncommitted_updates: BTreeMap::new(),
+            uncommitted_truncate_len: None,
+            next_commit_id: 0,
+            retention_policy: RetentionPolicy::KeepAll,
+            tree_properties,
+        }
+    }
+
+    /// Returns the reason if the layer lengths of the store don't match a tree built from the
+    /// leaves of its first layer
+    fn check_layer_lens(store: &S) -> Result<(), &'static str> {
+        let leaves_len = store.layer_len(0);
+        let expected_layers_len = if leaves_len == 0 {
+            0
+        } else {
+            indices::tree_depth(leaves_len) + 1
+        };
+        let mut expected_layer_len = leaves_len;
+        for layer_index in 0..store.layers_len() {
+            if store.layer_len(layer_index) != expected_layer_len {
+                return Err("layer sizes don't match the leaves");
+            }
+            expected_layer_len = indices::div_ceil(expected_layer_len, 2);
+        }
+        if store.layers_len() != expected_layers_len {
+            return Err("layers count doesn't match the leaves");
+        }
+
+        Ok(())
+    }
+
+    /// Returns the store that keeps the committed nodes of the tree
+    pub fn store(&self) -> &S {
+        self.current_working_tree.store()
+    }
+
+    /// Consumes the tree and returns the store that keeps its committed nodes
+    pub fn into_store(self) -> S {
+        self.current_working_tree.into_store()
+    }
+
+    /// Same as [`MerkleTree::root`], but returns an error if the store fails
+    pub fn try_root(&self) -> Result<Option<T::Hash>, S::Error> {
+        self.current_working_tree.try_root()
+    }
+
+    /// Returns helper nodes required to build a partial tree for the given indices
+    /// to be able to extract a root from it. Useful in constructing Merkle proofs
+    fn helper_nodes(&self, leaf_indices: &[usize]) -> Result<Vec<T::Hash>, S::Error> {
+        let mut helper_nodes = Vec::<T::Hash>::new();
+
+        for layer in self.helper_node_tuples(leaf_indices)? {
+            for (_index, hash) in layer {
+                helper_nodes.push(hash)
+            }
+        }
+
+        Ok(helper_nodes)
+    }
+
+    /// Gets all helper nodes required to build a partial merkle tree for the given indices,
+    /// cloning all required hashes into the resulting vector.
+    fn helper_node_tuples(
+        &self,
+        leaf_indices: &[usize],
+    ) -> Result<HelperNodeTuples<T::Hash>, S::Error> {
+        let store = self.store();
+        Self::collect_helper_node_tuples(leaf_indices, store.layers_len(), |layer_index, index| {
+            store.get(layer_index, index)
+        })
+    }
+
+    /// Gets all helper nodes required to build a partial merkle tree for the given indices from
+    /// a tree with the given number of layers, looking up the nodes with `node`
+    fn collect_helper_node_tuples<E>(
+        leaf_indices: &[usize],
+        layers_count: usize,
+        node: impl Fn(usize, usize) -> Result<Option<T::Hash>, E>,
+    ) -> Result<HelperNodeTuples<T::Hash>, E> {
+        let mut current_layer_indices = leaf_indices.to_vec();
+        let mut helper_nodes: HelperNodeTuples<T::Hash> = Vec::new();
+
+        for layer_index in 0..layers_count {
+            let mut helpers_layer = Vec::new();
+            let siblings = utils::indices::sibling_indices(&current_layer_indices);
+            // Filter all nodes that do not require an additional hash to be calculated
+            let helper_indices = utils::collections::difference(&siblings, &current_layer_indices);
+
+            for index in helper_indices {
+                if let Some(hash) = node(layer_index, index)? {
+                    helpers_layer.push((index, hash));
+                }
+            }
+
+            helper_nodes.push(helpers_layer);
+            current_layer_indices = indices::parent_indices(&current_layer_indices);
+        }
+
+        Ok(helper_nodes)
+    }
+
+    /// Same as [`MerkleTree::proof`], but returns an error if the store fails
+    pub fn try_proof(&self, leaf_indices: &[usize]) -> Result<MerkleProof<T>, S::Error> {
+        Ok(MerkleProof::<T>::new(self.helper_nodes(leaf_indices)?))
+    }
+
+    /// Same as [`MerkleTree::indexed_proof`], but returns an error if the store fails
+    pub fn try_indexed_proof(
+        &self,
+        leaf_indices: &[usize],
+    ) -> Result<IndexedMerkleProof<T>, S::Error> {
+        Ok(IndexedMerkleProof::new(
+            self.try_proof(leaf_indices)?,
+            leaf_indices.to_vec(),
+            self.leaves_len(),
+            self.tree_properties(),
+        ))
+    }
+
+    /// Returns a multiproof for the given indices in the layout of OpenZeppelin's
+    /// `MerkleProof.multiProofVerify`, see [`CompactMultiProof`]. The proof is verified with the
+    /// leaves in the ascending order of their indices, regardless of the order of `leaf_indices`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if any of the indices is out of range, if the tree doesn't use sorted
+    /// pairs or uses domain separation, or if the [`OddNodeStrategy::Promote`] moves a proved node
+    /// in a way the verifier's queue can't express.
+    pub fn compact_multiproof(
+        &self,
+        leaf_indices: &[usize],
+    ) -> Result<CompactMultiProof<T>, Error> {
+        if !self.tree_properties.sorted_pair_enabled
+            || self.tree_properties.domain_separation.is_some()
+        {
+            return Err(Error::unsupported_tree_properties(
+                "compact multiproofs require sorted pairs without domain separation",
+            ));
+        }
+
+        let store = self.store();
+        let leaves_len = self.leaves_len();
+        let mut sorted_indices = leaf_indices.to_vec();
+        sorted_indices.sort_unstable();
+        sorted_indices.dedup();
+        if let Some(index) = sorted_indices.iter().find(|index| **index >= leaves_len) {
+            return Err(Error::leaf_index_out_of_range(*index, leaves_len));
+        }
+        if sorted_indices.is_empty() {
+            let root = self.try_root().map_err(Error::node_store)?;
+            return Ok(CompactMultiProof::new(
+                root.into_iter().collect(),
+                Vec::new(),
+            ));
+        }
+
+        // Whether the subtree of the node contains any of the proved leaves
+        let is_proved = |layer_index: usize, node_index: usize| {
+            let first_leaf = node_index << layer_index;
+            let last_leaf = ((node_index + 1) << layer_index).min(leaves_len);
+            sorted_indices
+                .iter()
+                .any(|index| (first_leaf..last_leaf).contains(index))
+        };
+
+        // Simulates the verifier's queue of known nodes, as (layer index, node index) pairs
+        let root_layer_index = store.layers_len() - 1;
+        let mut queue: VecDeque<(usize, usize)> =
+            sorted_indices.iter().map(|index| (0, *index)).collect();
+        let mut proof_hashes = Vec::new();
+        let mut proof_flags = Vec::new();
+
+        while let Some((layer_index, node_index)) = queue.pop_front() {
+            if layer_index == root_layer_index {
+                break;
+            }
+
+            let sibling_index = node_index ^ 1;
+            if sibling_index >= store.layer_len(layer_index) {
+                let node = store
+                    .get(layer_index, node_index)
+                    .map_err(Error::node_store)?
+                    .ok_or_else(Error::not_enough_helper_nodes)?;
+                // The only node of a layer is promoted unchanged, like in `hash_lone_node`
+                let sibling = if node_index == 0 {
+                    None
+                } else {
+                    self.tree_properties
+                        .lone_node_sibling::<T>(&node, layer_index)
+                };
+                match sibling {
+                    Some(sibling) => {
+                        proof_hashes.push(sibling);
+                        proof_flags.push(false);
+                    }
+                    None => {
+                        // The node becomes its own parent without a verifier step
+                        queue.push_front((layer_index + 1, node_index / 2));
+                        continue;
+                    }
+                }
+            } else if queue.front() == Some(&(layer_index, sibling_index)) {
+                queue.pop_front();
+                proof_flags.push(true);
+            } else if is_proved(layer_index, sibling_index) {
+                return Err(Error::unsupported_tree_properties(
+                    "promoted nodes can't be ordered in the compact multiproof queue",
+                ));
+            } else {
+                proof_hashes.push(
+                    store
+                        .get(layer_index, sibling_index)
+                        .map_err(Error::node_store)?
+                        .ok_or_else(Error::not_enough_helper_nodes)?,
+                );
+                proof_flags.push(false);
+            }
+
+            queue.push_back((layer_index + 1, node_index / 2));
+        }
+
+        Ok(CompactMultiProof::new(proof_hashes, proof_flags))
+    }
+
+    /// Returns the Merkle proof for the given indices against the root of a commit that is
+    /// still in the history, without rolling the tree back. The commit can be referred to either
+    /// by its id or by a label set with [`MerkleTree::checkpoint`]. The proof should be verified
+    /// with the root and the number of leaves of that commit, see [`MerkleTree::root_at`] and
+    /// [`MerkleTree::history`].
+    ///
+    /// Returns an error if the commit is not in the history or if any of the indices is out
+    /// of range of the tree at that commit.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, algorithms::Sha256, Hasher, utils::properties::TreeProperties};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tree_properties = TreeProperties::default();
+    /// let leaves: Vec<[u8; 32]> = ["a", "b", "c"]
+    ///     .iter()
+    ///     .map(|x| Sha256::hash(x.as_bytes()))
+    ///     .collect();
+    /// let mut merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+    ///
+    /// merkle_tree.update(1, Sha256::hash("x".as_bytes()))?.commit();
+    /// merkle_tree.insert(Sha256::hash("d".as_bytes())).commit();
+    ///
+    /// let old_root = merkle_tree.root_at(0).ok_or("commit is not in the history")?;
+    /// let proof = merkle_tree.proof_at(0, &[1])?;
+    ///
+    /// assert!(proof.verify(old_root, &[1], &leaves[1..2], leaves.len(), tree_properties));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn proof_at<'a>(
+        &self,
+        commit: impl Into<CommitRef<'a>>,
+        leaf_indices: &[usize],
+    ) -> Result<MerkleProof<T>, Error> {
+        let commit = commit.into();
+        let position = self
+            .commit_position(commit)
+            .ok_or_else(|| Error::commit_not_found(commit))?;
+        let leaves_len = self.history[position].leaves_len;
+
+        if let Some(index) = leaf_indices.iter().find(|index| **index >= leaves_len) {
+            return Err(Error::leaf_index_out_of_range(*index, leaves_len));
+        }
+
+        let layers_count = utils::indices::tree_depth(leaves_len) + 1;
+        let helper_nodes =
+            Self::collect_helper_node_tuples(leaf_indices, layers_count, |layer_index, index| {
+                let layer_len = utils::indices::div_ceil(leaves_len, 1 << layer_index);
+                if index >= layer_len {
+                    return Ok(None);
+                }
+                self.node_at(position, layer_index, index)
+            })
+            .map_err(Error::node_store)?;
+
+        Ok(MerkleProof::<T>::new(
+            helper_nodes
+                .into_iter()
+                .flatten()
+                .map(|(_, hash)| hash)
+                .collect(),
+        ))
+    }
+
+    /// Returns the root of the tree at a commit that is still in the history, without rolling
+    /// the tree back. The commit can be referred to either by its id or by a label set with
+    /// [`MerkleTree::checkpoint`]. Returns `None` if the commit is not in the history or if the
+    /// tree was empty after it.
+    ///
+    /// For examples, please check [`MerkleTree::proof_at`]
+    pub fn root_at<'a>(&self, commit: impl Into<CommitRef<'a>>) -> Option<T::Hash> {
+        let position = self.commit_position(commit.into())?;
+        self.history[position].root
+    }
+
+    /// Returns the proof that the tree of `old_size` leaves is a prefix of the current
+    /// committed tree, as defined in RFC 6962. See [`ConsistencyProof`] for more details.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, algorithms::Sha256, Hasher, utils::properties::TreeProperties};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tree_properties = TreeProperties::default();
+    /// let leaves: Vec<[u8; 32]> = ["a", "b", "c", "d", "e", "f"]
+    ///     .iter()
+    ///     .map(|x| Sha256::hash(x.as_bytes()))
+    ///     .collect();
+    ///
+    /// let old_tree = MerkleTree::<Sha256>::from_leaves(&leaves[..4], tree_properties);
+    /// let new_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+    /// let old_root = old_tree.root().ok_or("couldn't get the merkle root")?;
+    /// let new_root = new_tree.root().ok_or("couldn't get the merkle root")?;
+    ///
+    /// let proof = new_tree.consistency_proof(4)?;
+    /// assert!(proof.verify(old_root, new_root, 4, 6, tree_properties));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if `old_size` is zero or bigger than the number of committed leaves, or
+    /// if the tree doesn't use [`OddNodeStrategy::Promote`], since with other strategies the
+    /// nodes on the right edge of the old tree are different from the nodes of the new tree
+    pub fn consistency_proof(&self, old_size: usize) -> Result<ConsistencyProof<T>, Error> {
+        if self.tree_properties.odd_node_strategy != OddNodeStrategy::Promote {
+            return Err(Error::unsupported_tree_properties(
+                "consistency proofs require the promote odd node strategy",
+            ));
+        }
+        let new_size = self.leaves_len();
+        if old_size == 0 || old_size > new_size {
+            return Err(Error::invalid_tree_size(old_size, new_size));
+        }
+
+        let mut proof_hashes = Vec::new();
+        self.consistency_subproof(old_size, 0, new_size, true, &mut proof_hashes)?;
+
+        Ok(ConsistencyProof::new(proof_hashes))
+    }
+
+    /// RFC 6962 SUBPROOF for the leaves in `start..end`. `is_complete_subtree` tells if
+    /// the old tree is a complete subtree of the current range, in which case its root is
+    /// known to the verifier and isn't included.
+    fn consistency_subproof(
+        &self,
+        old_size: usize,
+        start: usize,
+        end: usize,
+        is_complete_subtree: bool,
+        proof_hashes: &mut Vec<T::Hash>,
+    ) -> Result<(), Error> {
+        let size = end - start;
+        if old_size == size {
+            if !is_complete_subtree {
+                proof_hashes.push(self.subtree_root(start, end)?);
+            }
+            return Ok(());
+        }
+
+        // The largest power of two smaller than the size is the split point of the range
+        let split = size.next_power_of_two() / 2;
+        if old_size <= split {
+            self.consistency_subproof(
+                old_size,
+                start,
+                start + split,
+                is_complete_subtree,
+                proof_hashes,
+            )?;
+            proof_hashes.push(self.subtree_root(start + split, end)?);
+        } else {
+            self.consistency_subproof(old_size - split, start + split, end, false, proof_hashes)?;
+            proof_hashes.push(self.subtree_root(start, start + split)?);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the root of the subtree over the leaves in `start..end`. Ranges used by
+    /// consistency proofs are either complete subtrees or end at the last leaf, so they always
+    /// correspond to a node of the tree.
+    fn subtree_root(&self, start: usize, end: usize) -> Result<T::Hash, Error> {
+        let height = (end - start).next_power_of_two().trailing_zeros() as usize;
+        self.current_working_tree
+            .try_get(height, start >> height)
+            .map_err(Error::node_store)?
+            .ok_or_else(Error::not_enough_helper_nodes)
+    }
+
+    /// Inserts a new leaf. Please note it won't modify the root just yet; For the changes
+    /// to be applied to the root, [`MerkleTree::commit`] method should be called first. To get the
+    /// root of the new tree without applying the changes, you can use
+    /// [`MerkleTree::uncommitted_root`]
+    ///
+    /// # Examples
+    ///
+    /// Get the root after an insert:
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils, utils::properties::TreeProperties};
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tree_properties = TreeProperties::default();
+    /// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+    /// merkle_tree.insert(Sha256::hash("a".as_bytes()));
+    ///
+    /// assert_eq!(merkle_tree.root(), None);
+    ///
+    /// merkle_tree.commit();
+    /// assert_eq!(
+    ///     merkle_tree.root_hex(),
+    ///     Some("ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb".to_string())
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Inserts also can be chained with [`MerkleTree::commit`] for convenience:
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils,utils::properties::TreeProperties};
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tree_properties = TreeProperties::default();
+    /// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+    /// merkle_tree
+    ///     .insert(Sha256::hash("a".as_bytes()))
+    ///     .commit();
+    ///
+    /// assert_eq!(
+    ///     merkle_tree.root_hex(),
+    ///     Some("ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb".to_string())
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn insert(&mut self, leaf: T::Hash) -> &mut Self {
+        self.uncommitted_leaves
+            .push(self.tree_properties.hash_leaf::<T>(&leaf));
+        self
+    }
+
+    /// Appends leaves to the tree. Behaves similarly to [`MerkleTree::insert`], but for a list of
+    /// items. Takes ownership of the elements of the [`std::vec::Vec<T>`],
+    /// similarly to [`std::vec::Vec::append`].
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils, utils::properties::TreeProperties};
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tree_properties = TreeProperties::default();
+    /// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+    /// let mut leaves = vec![
+    ///     Sha256::hash("a".as_bytes()),
+    ///     Sha256::hash("b".as_bytes()),
+    /// ];
+    /// merkle_tree
+    ///     .append(&mut leaves)
+    ///     .commit();
+    ///
+    /// assert_eq!(
+    ///     merkle_tree.root_hex(),
+    ///     Some("e5a01fee14e0ed5c48714f22180f25ad8365b53f9779f79dc4a3d7e93963f94a".to_string())
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn append(&mut self, leaves: &mut Vec<T::Hash>) -> &mut Self {
+        let tree_properties = self.tree_properties;
+        self.uncommitted_leaves.extend(
+            leaves
+                .drain(..)
+                .map(|leaf| tree_properties.hash_leaf::<T>(&leaf)),
+        );
+        self
+    }
+
+    /// Replaces the leaf at the given index with a new one. Only the nodes on the path from the
+    /// leaf to the root are recalculated. Similarly to [`MerkleTree::insert`], the change is
+    /// applied to the tree only after [`MerkleTree::commit`], and can be undone with
+    /// [`MerkleTree::rollback`]. Leaves that were inserted, but not committed yet, can be
+    /// updated too.
+    ///
+    /// Returns an error if the index is out of range of the tree, including uncommitted leaves.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, algorithms::Sha256, Hasher, utils::properties::TreeProperties};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tree_properties = TreeProperties::default();
+    /// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+    /// let mut leaves = vec![
+    ///     Sha256::hash("a".as_bytes()),
+    ///     Sha256::hash("c".as_bytes()),
+    /// ];
+    /// merkle_tree.append(&mut leaves).commit();
+    ///
+    /// merkle_tree.update(1, Sha256::hash("b".as_bytes()))?.commit();
+    ///
+    /// assert_eq!(
+    ///     merkle_tree.root_hex(),
+    ///     Some("e5a01fee14e0ed5c48714f22180f25ad8365b53f9779f79dc4a3d7e93963f94a".to_string())
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn update(&mut self, index: usize, leaf: T::Hash) -> Result<&mut Self, Error> {
+        self.update_many(&[index], &[leaf])
+    }
+
+    /// Replaces multiple leaves at once. Behaves similarly to [`MerkleTree::update`], but for a
+    /// list of items. Nodes shared by the paths of the updated leaves are recalculated only once.
+    ///
+    /// Returns an error if the number of indices doesn't match the number of leaves, or if any
+    /// of the indices is out of range. In that case no leaves are updated.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, algorithms::Sha256, Hasher, utils::properties::TreeProperties};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tree_properties = TreeProperties::default();
+    /// let leaves: Vec<[u8; 32]> = ["a", "b", "c", "d", "e"]
+    ///     .iter()
+    ///     .map(|x| Sha256::hash(x.as_bytes()))
+    ///     .collect();
+    /// let mut merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+    ///
+    /// let new_leaves = [Sha256::hash("x".as_bytes()), Sha256::hash("y".as_bytes())];
+    /// merkle_tree.update_many(&[0, 4], &new_leaves)?.commit();
+    ///
+    /// let expected_leaves = [new_leaves[0], leaves[1], leaves[2], leaves[3], new_leaves[1]];
+    /// let expected_tree = MerkleTree::<Sha256>::from_leaves(&expected_leaves, tree_properties);
+    /// assert_eq!(merkle_tree.root(), expected_tree.root());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn update_many(
+        &mut self,
+        leaf_indices: &[usize],
+        leaves: &[T::Hash],
+    ) -> Result<&mut Self, Error> {
+        if leaf_indices.len() != leaves.len() {
+            return Err(Error::leaves_indices_count_mismatch(
+                leaf_indices.len(),
+                leaves.len(),
+            ));
+        }
+
+        let leaves_count = self.uncommitted_leaves_len();
+        if let Some(index) = leaf_indices.iter().find(|index| **index >= leaves_count) {
+            return Err(Error::leaf_index_out_of_range(*index, leaves_count));
+        }
+
+        for (index, leaf) in leaf_indices.iter().zip(leaves) {
+            self.stage_leaf(*index, self.tree_properties.hash_leaf::<T>(leaf));
+        }
+
+        Ok(self)
+    }
+
+    /// Removes the leaf at the given index and replaces it with the last leaf of the tree,
+    /// similarly to [`std::vec::Vec::swap_remove`]. This way only the paths of two leaves
+    /// have to be recalculated. The change is applied to the tree after [`MerkleTree::commit`].
+    ///
+    /// Returns the removed leaf as it's stored in the tree, see [`MerkleTree::leaves`], or an
+    /// error if the index is out of range.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, algorithms::Sha256, Hasher, utils::properties::TreeProperties};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tree_properties = TreeProperties::default();
+    /// let leaves: Vec<[u8; 32]> = ["a", "b", "c", "d"]
+    ///     .iter()
+    ///     .map(|x| Sha256::hash(x.as_bytes()))
+    ///     .collect();
+    /// let mut merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+    ///
+    /// assert_eq!(merkle_tree.remove(1)?, leaves[1]);
+    /// merkle_tree.commit();
+    ///
+    /// assert_eq!(merkle_tree.leaves(), Some(vec![leaves[0], leaves[3], leaves[2]]));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove(&mut self, index: usize) -> Result<T::Hash, Error> {
+        let leaves_count = self.uncommitted_leaves_len();
+        let leaf = self
+            .uncommitted_leaf(index)
+            .map_err(Error::node_store)?
+            .ok_or_else(|| Error::leaf_index_out_of_range(index, leaves_count))?;
+
+        if let Some(last_leaf) = self.try_pop().map_err(Error::node_store)? {
+            if index != leaves_count - 1 {
+                self.stage_leaf(index, last_leaf);
+            }
+        }
+
+        Ok(leaf)
+    }
+
+    /// Same as [`MerkleTree::pop`], but returns an error if the store fails
+    pub fn try_pop(&mut self) -> Result<Option<T::Hash>, S::Error> {
+        let leaves_count = self.uncommitted_leaves_len();
+        let leaf = match leaves_count.checked_sub(1) {
+            Some(last_index) => self.uncommitted_leaf(last_index)?,
+            None => None,
+        };
+        if leaf.is_some() {
+            self.truncate(leaves_count - 1);
+        }
+        Ok(leaf)
+    }
+
+    /// Shortens the tree, keeping the first `new_len` leaves and dropping the rest. Has no
+    /// effect if `new_len` is greater than or equal to the number of leaves, including the
+    /// uncommitted ones. The change is applied to the tree after [`MerkleTree::commit`].
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, algorithms::Sha256, Hasher, utils::properties::TreeProperties};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tree_properties = TreeProperties::default();
+    /// let leaves: Vec<[u8; 32]> = ["a", "b", "c", "d", "e"]
+    ///     .iter()
+    ///     .map(|x| Sha256::hash(x.as_bytes()))
+    ///     .collect();
+    /// let mut merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+    ///
+    /// merkle_tree.truncate(2).commit();
+    ///
+    /// assert_eq!(
+    ///     merkle_tree.root_hex(),
+    ///     Some("e5a01fee14e0ed5c48714f22180f25ad8365b53f9779f79dc4a3d7e93963f94a".to_string())
+    /// );
+    ///
+    /// // Truncation can be undone as any other commit
+    /// merkle_tree.rollback();
+    /// assert_eq!(merkle_tree.leaves_len(), 5);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn truncate(&mut self, new_len: usize) -> &mut Self {
+        let base_len = self.uncommitted_base_len();
+
+        if new_len >= base_len {
+            self.uncommitted_leaves.truncate(new_len - base_len);
+        } else {
+            self.uncommitted_leaves.clear();
+            self.uncommitted_updates.split_off(&new_len);
+            self.uncommitted_truncate_len = Some(new_len);
+        }
+
+        self
+    }
+
+    /// Same as [`MerkleTree::commit`], but returns an error if the store fails or if the nodes
+    /// needed to calculate the new root are missing from the tree. In the latter case the tree
+    /// isn't changed, while a failure of the store may leave the committed nodes partially
+    /// updated.
+    pub fn try_commit(&mut self) -> Result<(), Error> {
+        if !self.has_uncommitted_changes() {
+            return Ok(());
+        }
+
+        let leaves_len = self.uncommitted_leaves_len();
+        // The diff is empty only if all leaves were removed
+        let diff = self.uncommitted_diff()?.unwrap_or_default();
+        let previous_leaves_len = self.leaves_len();
+
+        // Only the nodes that are going to be overwritten or removed need to be remembered to
+        // be able to roll back the commit
+        let mut reverted_nodes = self
+            .current_working_tree
+            .try_intersection(&diff)
+            .map_err(Error::node_store)?;
+        self.current_working_tree
+            .try_merge_unverified(diff)
+            .map_err(Error::node_store)?;
+        let removed_nodes = self
+            .current_working_tree
+            .try_truncate(leaves_len)
+            .map_err(Error::node_store)?;
+        reverted_nodes.merge_unverified(removed_nodes);
+
+        self.history.push(HistoryEntry {
+            id: self.next_commit_id,
+            label: None,
+            root: self.try_root().map_err(Error::node_store)?,
+            leaves_len,
+            reverse_diff: Some(ReverseDiff {
+                reverted_nodes,
+                previous_leaves_len,
+            }),
+        });
+        self.next_commit_id += 1;
+        self.abort_uncommitted();
+        self.prune_history();
+
+        Ok(())
+    }
+
+    /// Same as [`MerkleTree::checkpoint`], but returns an error if the uncommitted changes
+    /// can't be committed, see [`MerkleTree::try_commit`]
+    pub fn try_checkpoint(&mut self, label: &str) -> Result<Option<u64>, Error> {
+        self.try_commit()?;
+        Ok(self.label_last_commit(label))
+    }
+
+    /// Returns the commits stored in the history, from the oldest to the most recent one.
+    /// The most recent commit describes the current state of the tree.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, algorithms::Sha256, Hasher, utils::properties::TreeProperties};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tree_properties = TreeProperties::default();
+    /// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+    ///
+    /// merkle_tree.insert(Sha256::hash("a".as_bytes())).commit();
+    /// merkle_tree.insert(Sha256::hash("b".as_bytes())).commit();
+    ///
+    /// let history = merkle_tree.history();
+    /// assert_eq!(history.len(), 2);
+    /// assert_eq!(history[1].id, 1);
+    /// assert_eq!(history[1].leaves_len, 2);
+    /// assert_eq!(history[1].root, merkle_tree.root());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn history(&self) -> Vec<CommitInfo<T::Hash>> {
+        self.history
+            .iter()
+            .map(|commit| CommitInfo {
+                id: commit.id,
+                root: commit.root,
+                leaves_len: commit.leaves_len,
+                label: commit.label.clone(),
+            })
+            .collect()
+    }
+
+    /// Sets the policy that defines which commits are kept in the history and prunes the history
+    /// according to it. The policy is applied again on every commit. By default, all commits
+    /// are kept. See [`RetentionPolicy`] for more details.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, RetentionPolicy, algorithms::Sha256, Hasher, utils::properties::TreeProperties};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tree_properties = TreeProperties::default();
+    /// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+    /// merkle_tree.set_retention_policy(RetentionPolicy::KeepLast(2));
+    ///
+    /// for leaf in ["a", "b", "c", "d"] {
+    ///     merkle_tree.insert(Sha256::hash(leaf.as_bytes())).commit();
+    /// }
+    ///
+    /// let ids: Vec<u64> = merkle_tree.history().iter().map(|commit| commit.id).collect();
+    /// assert_eq!(ids, vec![2, 3]);
+    ///
+    /// // The oldest kept commit is the base snapshot, which can't be rolled back
+    /// merkle_tree.rollback();
+    /// merkle_tree.rollback();
+    /// assert_eq!(merkle_tree.leaves_len(), 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_retention_policy(&mut self, retention_policy: RetentionPolicy) {
+        self.retention_policy = retention_policy;
+        self.prune_history();
+    }
+
+    /// Returns the policy that defines which commits are kept in the history
+    pub fn retention_policy(&self) -> RetentionPolicy {
+        self.retention_policy
+    }
+
+    /// Same as [`MerkleTree::rollback`], but returns an error if the store fails, in which case
+    /// the committed nodes may be left partially reverted
+    pub fn try_rollback(&mut self) -> Result<(), S::Error> {
+        self.uncommitted_updates.clear();
+        self.uncommitted_truncate_len = None;
+
+        // Remove the most recent commit and restore the nodes it has overwritten. Nodes added
+        // by the commit are located after the previous end of the tree, so they are dropped
+        // by the truncation. The base snapshot of a pruned history can't be rolled back
+        if let Some(reverse_diff) = self
+            .history
+            .last_mut()
+            .and_then(|commit| commit.reverse_diff.take())
+        {
+            self.history.pop();
+            self.current_working_tree
+                .try_merge_unverified(reverse_diff.reverted_nodes)?;
+            self.current_working_tree
+                .try_truncate(reverse_diff.previous_leaves_len)?;
+        }
+
+        Ok(())
+    }
+
+    /// Rolls back all commits made after the given one, so the given commit becomes the most
+    /// recent one. The commit can be referred to either by its id or by a label set with
+    /// [`MerkleTree::checkpoint`]. If any commits are rolled back, uncommitted changes are
+    /// handled the same way as in [`MerkleTree::rollback`].
+    ///
+    /// Returns an error if the commit is not in the history, in which case the tree isn't changed.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, algorithms::Sha256, Hasher, utils::properties::TreeProperties};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tree_properties = TreeProperties::default();
+    /// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+    ///
+    /// merkle_tree.insert(Sha256::hash("a".as_bytes()));
+    /// merkle_tree.checkpoint("known good");
+    /// let known_good_root = merkle_tree.root();
+    ///
+    /// merkle_tree.insert(Sha256::hash("b".as_bytes())).commit();
+    /// merkle_tree.insert(Sha256::hash("c".as_bytes())).commit();
+    ///
+    /// merkle_tree.rollback_to("known good")?;
+    /// assert_eq!(merkle_tree.root(), known_good_root);
+    ///
+    /// // Commits can be referred to by their ids as well
+    /// merkle_tree.insert(Sha256::hash("d".as_bytes())).commit();
+    /// merkle_tree.rollback_to(0)?;
+    /// assert_eq!(merkle_tree.root(), known_good_root);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rollback_to<'a>(&mut self, commit: impl Into<CommitRef<'a>>) -> Result<(), Error> {
+        let commit = commit.into();
+        let position = self
+            .commit_position(commit)
+            .ok_or_else(|| Error::commit_not_found(commit))?;
+
+        while self.history.len() > position + 1 {
+            let history_len = self.history.len();
+            self.try_rollback().map_err(Error::node_store)?;
+            // The base snapshot of a pruned history stays in the history after a rollback
+            if self.history.len() == history_len {
+                return Err(Error::commit_not_found(commit));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Same as [`MerkleTree::uncommitted_root`], but returns an error if the store fails or if
+    /// the nodes needed to calculate the root are missing from the tree
+    pub fn try_uncommitted_root(&self) -> Result<Option<T::Hash>, Error> {
+        let shadow_tree = self.uncommitted_diff()?;
+        Ok(shadow_tree.and_then(|shadow_tree| shadow_tree.root()))
+    }
+
+    /// Clears all uncommitted changes made by [`MerkleTree::insert`], [`MerkleTree::append`],
+    /// [`MerkleTree::update`], [`MerkleTree::remove`] and [`MerkleTree::truncate`] operations
+    /// without applying them to the tree.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils, utils::properties::TreeProperties};
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tree_properties = TreeProperties::default();
+    /// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+    /// let mut leaves = vec![
+    ///     Sha256::hash("a".as_bytes()),
+    ///     Sha256::hash("b".as_bytes()),
+    /// ];
+    /// assert_eq!(
+    ///     merkle_tree.root(),
+    ///     None
+    /// );
+    ///
+    /// merkle_tree.append(&mut leaves);
+    /// merkle_tree.abort_uncommitted();
+    /// merkle_tree.commit();
+    ///
+    /// assert_eq!(
+    ///     merkle_tree.root(),
+    ///     None
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn abort_uncommitted(&mut self) {
+        self.uncommitted_leaves.clear();
+        self.uncommitted_updates.clear();
+        self.uncommitted_truncate_len = None;
+    }
+
+    /// Returns the tree depth. A tree depth is how many layers there is between the
+    /// leaves and the root
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils, utils::properties::TreeProperties};
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tree_properties = TreeProperties::default();
+    /// let leaves = [
+    ///     Sha256::hash("a".as_bytes()),
+    ///     Sha256::hash("b".as_bytes()),
+    ///     Sha256::hash("c".as_bytes()),
+    /// ];
+    ///
+    /// let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves,tree_properties);
+    /// assert_eq!(merkle_tree.depth(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn depth(&self) -> usize {
+        self.current_working_tree.depth()
+    }
+
+    /// Same as [`MerkleTree::leaves`], but returns an error if the store fails
+    pub fn try_leaves(&self) -> Result<Option<Vec<T::Hash>>, S::Error> {
+        if self.store().layers_len() == 0 {
+            return Ok(None);
+        }
+
+        let leaves = self.store().layer(0)?;
+        Ok(Some(leaves.into_iter().map(|(_, leaf)| leaf).collect()))
+    }
+
+    /// Returns the number of leaves in the tree.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils,utils::properties::TreeProperties};
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tree_properties = TreeProperties::default();
+    /// let leaves = [
+    ///     Sha256::hash("a".as_bytes()),
+    ///     Sha256::hash("b".as_bytes()),
+    ///     Sha256::hash("c".as_bytes()),
+    /// ];
+    ///
+    /// let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves,tree_properties);
+    /// assert_eq!(merkle_tree.leaves_len(), 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn leaves_len(&self) -> usize {
+        self.store().layer_len(0)
+    }
+
+    /// Same as [`MerkleTree::layers`], but returns an error if the store fails
+    pub fn try_layers(&self) -> Result<Vec<Vec<T::Hash>>, S::Error> {
+        self.current_working_tree.try_layer_nodes()
+    }
+
+    /// Returns the properties the tree was created with
+    pub fn tree_properties(&self) -> TreeProperties {
+        self.tree_properties
+    }
+
+    /// Same as [`MerkleTree::to_snapshot_bytes`], but returns an error if the store fails
+    pub fn try_to_snapshot_bytes(&self, include_history: bool) -> Result<Vec<u8>, S::Error> {
+        let mut writer = SnapshotWriter::new();
+        writer.write_bytes(SNAPSHOT_MAGIC);
+        writer.write_u8(SNAPSHOT_VERSION);
+        writer.write_u32(T::hash_size() as u32);
+        writer.write_u8(if include_history { HISTORY_FLAG } else { 0 });
+        writer.write_tree_properties(&self.tree_properties);
+        writer.write_u64(self.next_commit_id);
+
+        // Committed layers are dense, so node indices are not stored
+        let store = self.store();
+        writer.write_usize(store.layers_len());
+        for layer_index in 0..store.layers_len() {
+            let layer = store.layer(layer_index)?;
+            writer.write_usize(layer.len());
+            for (_, node) in &layer {
+                writer.write_hash::<T>(node);
+            }
+        }
+
+        if include_history {
+            writer.write_retention_policy(self.retention_policy);
+            writer.write_usize(self.history.len());
+            for commit in &self.history {
+                writer.write_u64(commit.id);
+                writer.write_usize(commit.leaves_len);
+                match &commit.root {
+                    Some(root) => {
+                        writer.write_bool(true);
+                        writer.write_hash::<T>(root);
+                    }
+                    None => writer.write_bool(false),
+                }
+                match &commit.label {
+                    Some(label) => {
+                        writer.write_bool(true);
+                        writer.write_usize(label.len());
+                        writer.write_bytes(label.as_bytes());
+                    }
+                    None => writer.write_bool(false),
+                }
+                match &commit.reverse_diff {
+                    Some(reverse_diff) => {
+                        writer.write_bool(true);
+                        writer.write_usize(reverse_diff.previous_leaves_len);
+                        let layers = reverse_diff.reverted_nodes.layers();
+                        writer.write_usize(layers.len());
+                        for layer in layers {
+                            writer.write_usize(layer.len());
+                            for (node_index, node) in layer {
+                                writer.write_usize(*node_index);
+                                writer.write_hash::<T>(node);
+                            }
+                        }
+                    }
+                    None => writer.write_bool(false),
+                }
+            }
+        }
+
+        Ok(writer.finish::<T>())
+    }
+
+    /// Labels the most recent commit, removing the label from the commit that had it before
+    fn label_last_commit(&mut self, label: &str) -> Option<u64> {
+        for commit in self.history.iter_mut() {
+            if commit.label.as_deref() == Some(label) {
+                commit.label = None;
+            }
+        }
+
+        let commit = self.history.last_mut()?;
+        commit.label = Some(label.to_string());
+        Some(commit.id)
+    }
+
+    /// Returns the position of the commit in the history
+    fn commit_position(&self, commit: CommitRef) -> Option<usize> {
+        match commit {
+            // Ids are ascending, so the commit can be found with a binary search
+            CommitRef::Id(id) => self
+                .history
+                .binary_search_by_key(&id, |commit| commit.id)
+                .ok(),
+            CommitRef::Label(label) => self
+                .history
+                .iter()
+                .position(|commit| commit.label.as_deref() == Some(label)),
+        }
+    }
+
+    /// Squashes the commits that shouldn't be kept according to the retention policy into the
+    /// oldest kept commit by dropping their reverse diffs
+    fn prune_history(&mut self) {
+        if self.history.is_empty() {
+            return;
+        }
+
+        let last_position = self.history.len() - 1;
+        let base_position = match self.retention_policy {
+            RetentionPolicy::KeepAll => return,
+            RetentionPolicy::KeepLast(commits_count) => {
+                self.history.len().saturating_sub(commits_count.max(1))
+            }
+            RetentionPolicy::KeepAfter(id) => self
+                .history
+                .partition_point(|commit| commit.id < id)
+                .min(last_position),
+        };
+
+        if base_position > 0 {
+            self.history.drain(..base_position);
+            self.history[0].reverse_diff = None;
+        }
+    }
+
+    /// Returns the node as it was right after the commit at the given position in the history.
+    /// The first of the later commits that has overwritten or removed the node remembers its
+    /// value, otherwise the node hasn't changed since.
+    fn node_at(
+        &self,
+        position: usize,
+        layer_index: usize,
+        node_index: usize,
+    ) -> Result<Option<T::Hash>, S::Error> {
+        let reverted_node = self.history[position + 1..]
+            .iter()
+            .filter_map(|commit| commit.reverse_diff.as_ref())
+            .find_map(|reverse_diff| reverse_diff.reverted_nodes.get(layer_index, node_index));
+        match reverted_node {
+            Some(node) => Ok(Some(node)),
+            None => self.current_working_tree.try_get(layer_index, node_index),
+        }
+    }
+
+    fn has_uncommitted_changes(&self) -> bool {
+        !self.uncommitted_leaves.is_empty()
+            || !self.uncommitted_updates.is_empty()
+            || self.uncommitted_truncate_len.is_some()
+    }
+
+    /// Returns the number of committed leaves that are kept in the tree after the uncommitted
+    /// truncation. Uncommitted leaves are appended after them.
+    fn uncommitted_base_len(&self) -> usize {
+        self.uncommitted_truncate_len
+            .unwrap_or_else(|| self.leaves_len())
+    }
+
+    /// Returns the number of leaves the tree would have if the changes were committed
+    fn uncommitted_leaves_len(&self) -> usize {
+        self.uncommitted_base_len() + self.uncommitted_leaves.len()
+    }
+
+    /// Returns the leaf at the given index as if the changes were committed
+    fn uncommitted_leaf(&self, index: usize) -> Result<Option<T::Hash>, S::Error> {
+        let base_len = self.uncommitted_base_len();
+        if index >= base_len {
+            return Ok(self.uncommitted_leaves.get(index - base_len).cloned());
+        }
+
+        match self.uncommitted_updates.get(&index) {
+            Some(leaf) => Ok(Some(*leaf)),
+            None => self.store().get(0, index),
+        }
+    }
+
+    /// Replaces the leaf at the given index, which must be in range, with an already hashed leaf
+    fn stage_leaf(&mut self, index: usize, leaf: T::Hash) {
+        let base_len = self.uncommitted_base_len();
+        if index >= base_len {
+            self.uncommitted_leaves[index - base_len] = leaf;
+        } else {
+            self.uncommitted_updates.insert(index, leaf);
+        }
+    }
+
+    /// Creates a diff from a changes that weren't committed to the main tree yet. Can be used
+    /// to get uncommitted root or can be merged with the main tree. Returns `None` if there are
+    /// no changes or the changes remove all leaves, and an error if the committed nodes needed
+    /// to build the diff are missing.
+    fn uncommitted_diff(&self) -> Result<Option<PartialTree<T>>, Error> {
+        let leaves_in_new_tree = self.uncommitted_leaves_len();
+        if !self.has_uncommitted_changes() || leaves_in_new_tree == 0 {
+            return Ok(None);
+        }
+
+        let base_leaves_count = self.uncommitted_base_len();
+
+        // Tuples (index, hash) needed to construct a partial tree, since partial tree can't
+        // maintain indices otherwise. Updated leaves always precede the appended ones, so the
+        // tuples are sorted by index
+        let mut shadow_node_tuples: Vec<(usize, T::Hash)> = self
+            .uncommitted_updates
+            .iter()
+            .map(|(index, leaf)| (*index, *leaf))
+            .chain(
+                self.uncommitted_leaves
+                    .iter()
+                    .enumerate()
+                    .map(|(index, leaf)| (base_leaves_count + index, *leaf)),
+            )
+            .collect();
+        // If leaves were removed from the end of the tree and nothing was appended, the new
+        // last leaf has to be rehashed up to the root, since its right siblings are gone
+        if self.uncommitted_truncate_len.is_some() && self.uncommitted_leaves.is_empty() {
+            let last_index = leaves_in_new_tree - 1;
+            if !self.uncommitted_updates.contains_key(&last_index) {
+                let last_leaf = self
+                    .uncommitted_leaf(last_index)
+                    .map_err(Error::node_store)?
+                    .ok_or_else(Error::not_enough_helper_nodes)?;
+                shadow_node_tuples.push((last_index, last_leaf));
+            }
+        }
+        let shadow_indices: Vec<usize> =
+            shadow_node_tuples.iter().map(|(index, _)| *index).collect();
+        let mut partial_tree_tuples = self
+            .helper_node_tuples(&shadow_indices)
+            .map_err(Error::node_store)?;
+
+        // Helper nodes of the removed leaves must not be used
+        let mut layer_len = leaves_in_new_tree;
+        for layer in partial_tree_tuples.iter_mut() {
+            layer.retain(|(index, _)| *index < layer_len);
+            layer_len = utils::indices::div_ceil(layer_len, 2);
+        }
 
-        tree
+        // Figuring what tree height would be if we've committed the changes
+        let uncommitted_tree_depth = utils::indices::tree_depth(leaves_in_new_tree);
+
+        match partial_tree_tuples.first_mut() {
+            Some(first_layer) => {
+                first_layer.append(&mut shadow_node_tuples);
+                first_layer.sort_by_key(|(a, _)| *a);
+            }
+            None => partial_tree_tuples.push(shadow_node_tuples),
+        }
+        // Building a partial tree with the changes that would be needed to the working tree
+        PartialTree::<T>::build(
+            partial_tree_tuples,
+            uncommitted_tree_depth,
+            self.tree_properties,
+        )
+        .map(Some)
     }
+}
 
+/// Methods of the trees with stores that can't fail, like the default [`MemoryNodeStore`]. Other
+/// stores have to use the `try_` counterparts of these methods.
+impl<T: Hasher, S: NodeStore<T, Error = Infallible>> MerkleTree<T, S> {
     /// Returns the tree root - the top hash of the tree. Used in the inclusion proof verification.
     ///
     /// ## Examples
@@ -90,9 +1606,7 @@ impl<T: Hasher> MerkleTree<T> {
     /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils,utils::properties::TreeProperties};
     /// # use std::convert::TryFrom;
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///    sorted_pair_enabled: false,
-    /// };
+    /// let tree_properties = TreeProperties::default();
     /// let leaves = [
     ///     Sha256::hash("a".as_bytes()),
     ///     Sha256::hash("b".as_bytes()),
@@ -112,7 +1626,7 @@ impl<T: Hasher> MerkleTree<T> {
     /// # }
     /// ```
     pub fn root(&self) -> Option<T::Hash> {
-        Some(self.layer_tuples().last()?.first()?.1)
+        into_ok(self.try_root())
     }
 
     /// Similar to [`MerkleTree::root`], but returns a hex encoded string instead of
@@ -124,9 +1638,7 @@ impl<T: Hasher> MerkleTree<T> {
     /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils, utils::properties::TreeProperties};
     /// # use std::convert::TryFrom;
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///     sorted_pair_enabled: false,
-    /// };
+    /// let tree_properties = TreeProperties::default();
     /// let leaves = [
     ///     Sha256::hash("a".as_bytes()),
     ///     Sha256::hash("b".as_bytes()),
@@ -148,45 +1660,6 @@ impl<T: Hasher> MerkleTree<T> {
         Some(utils::collections::to_hex_string(&root))
     }
 
-    /// Returns helper nodes required to build a partial tree for the given indices
-    /// to be able to extract a root from it. Useful in constructing Merkle proofs
-    fn helper_nodes(&self, leaf_indices: &[usize]) -> Vec<T::Hash> {
-        let mut helper_nodes = Vec::<T::Hash>::new();
-
-        for layer in self.helper_node_tuples(leaf_indices) {
-            for (_index, hash) in layer {
-                helper_nodes.push(hash)
-            }
-        }
-
-        helper_nodes
-    }
-
-    /// Gets all helper nodes required to build a partial merkle tree for the given indices,
-    /// cloning all required hashes into the resulting vector.
-    fn helper_node_tuples(&self, leaf_indices: &[usize]) -> Vec<Vec<(usize, T::Hash)>> {
-        let mut current_layer_indices = leaf_indices.to_vec();
-        let mut helper_nodes: Vec<Vec<(usize, T::Hash)>> = Vec::new();
-
-        for tree_layer in self.layer_tuples() {
-            let mut helpers_layer = Vec::new();
-            let siblings = utils::indices::sibling_indices(&current_layer_indices);
-            // Filter all nodes that do not require an additional hash to be calculated
-            let helper_indices = utils::collections::difference(&siblings, &current_layer_indices);
-
-            for index in helper_indices {
-                if let Some(tuple) = tree_layer.get(index) {
-                    helpers_layer.push(*tuple);
-                }
-            }
-
-            helper_nodes.push(helpers_layer);
-            current_layer_indices = indices::parent_indices(&current_layer_indices);
-        }
-
-        helper_nodes
-    }
-
     /// Returns the Merkle proof required to prove the inclusion of items in a data set.
     ///
     /// ## Examples
@@ -195,9 +1668,7 @@ impl<T: Hasher> MerkleTree<T> {
     /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils,utils::properties::TreeProperties};
     /// # use std::convert::TryFrom;
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///     sorted_pair_enabled: false,
-    /// };
+    /// let tree_properties = TreeProperties::default();
     /// let leaves: Vec<[u8; 32]> = ["a", "b", "c", "d", "e", "f"]
     ///     .iter()
     ///     .map(|x| Sha256::hash(x.as_bytes()))
@@ -219,113 +1690,57 @@ impl<T: Hasher> MerkleTree<T> {
     /// # }
     /// ```
     pub fn proof(&self, leaf_indices: &[usize]) -> MerkleProof<T> {
-        MerkleProof::<T>::new(self.helper_nodes(leaf_indices))
+        into_ok(self.try_proof(leaf_indices))
     }
 
-    /// Inserts a new leaf. Please note it won't modify the root just yet; For the changes
-    /// to be applied to the root, [`MerkleTree::commit`] method should be called first. To get the
-    /// root of the new tree without applying the changes, you can use
-    /// [`MerkleTree::uncommitted_root`]
-    ///
-    /// # Examples
-    ///
-    /// Get the root after an insert:
-    ///
-    /// ```
-    /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils, utils::properties::TreeProperties};
-    /// # use std::convert::TryFrom;
-    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///     sorted_pair_enabled: false,
-    /// };
-    /// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
-    /// merkle_tree.insert(Sha256::hash("a".as_bytes()));
-    ///
-    /// assert_eq!(merkle_tree.root(), None);
-    ///
-    /// merkle_tree.commit();
-    /// assert_eq!(
-    ///     merkle_tree.root_hex(),
-    ///     Some("ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb".to_string())
-    /// );
-    /// # Ok(())
-    /// # }
-    /// ```
-    ///
-    /// Inserts also can be chained with [`MerkleTree::commit`] for convenience:
-    ///
-    /// ```
-    /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils,utils::properties::TreeProperties};
-    /// # use std::convert::TryFrom;
-    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///     sorted_pair_enabled: false,
-    /// };
-    /// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
-    /// merkle_tree
-    ///     .insert(Sha256::hash("a".as_bytes()))
-    ///     .commit();
-    ///
-    /// assert_eq!(
-    ///     merkle_tree.root_hex(),
-    ///     Some("ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb".to_string())
-    /// );
-    /// # Ok(())
-    /// # }
-    /// ```
-    pub fn insert(&mut self, leaf: T::Hash) -> &mut Self {
-        self.uncommitted_leaves.push(leaf);
-        self
+    /// Returns the Merkle proof for the given indices together with the indices, the number of
+    /// leaves and the tree properties needed to verify it. See [`IndexedMerkleProof`].
+    pub fn indexed_proof(&self, leaf_indices: &[usize]) -> IndexedMerkleProof<T> {
+        into_ok(self.try_indexed_proof(leaf_indices))
     }
 
-    /// Appends leaves to the tree. Behaves similarly to [`MerkleTree::insert`], but for a list of
-    /// items. Takes ownership of the elements of the [`std::vec::Vec<T>`],
-    /// similarly to [`std::vec::Vec::append`].
+    /// Removes the last leaf of the tree and returns it as it's stored in the tree, see
+    /// [`MerkleTree::leaves`]. Returns `None` if the tree is empty. The change is applied to the
+    /// tree after [`MerkleTree::commit`].
     ///
     /// ## Examples
     ///
     /// ```
-    /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils, utils::properties::TreeProperties};
-    /// # use std::convert::TryFrom;
+    /// # use rs_merkle::{MerkleTree, algorithms::Sha256, Hasher, utils::properties::TreeProperties};
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///     sorted_pair_enabled: false,
-    /// };
+    /// let tree_properties = TreeProperties::default();
     /// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
-    /// let mut leaves = vec![
-    ///     Sha256::hash("a".as_bytes()),
-    ///     Sha256::hash("b".as_bytes()),
-    /// ];
-    /// merkle_tree
-    ///     .append(&mut leaves)
-    ///     .commit();
+    /// merkle_tree.insert(Sha256::hash("a".as_bytes())).commit();
     ///
-    /// assert_eq!(
-    ///     merkle_tree.root_hex(),
-    ///     Some("e5a01fee14e0ed5c48714f22180f25ad8365b53f9779f79dc4a3d7e93963f94a".to_string())
-    /// );
+    /// assert_eq!(merkle_tree.pop(), Some(Sha256::hash("a".as_bytes())));
+    /// assert_eq!(merkle_tree.pop(), None);
+    ///
+    /// merkle_tree.commit();
+    /// assert_eq!(merkle_tree.root(), None);
     /// # Ok(())
     /// # }
     /// ```
-    pub fn append(&mut self, leaves: &mut Vec<T::Hash>) -> &mut Self {
-        self.uncommitted_leaves.append(leaves);
-        self
+    pub fn pop(&mut self) -> Option<T::Hash> {
+        into_ok(self.try_pop())
     }
 
-    /// Commits the changes made by [`MerkleTree::insert`] and [`MerkleTree::append`]
-    /// and modifies the root.
+    /// Commits the changes made by [`MerkleTree::insert`], [`MerkleTree::append`],
+    /// [`MerkleTree::update`], [`MerkleTree::remove`] and [`MerkleTree::truncate`] and modifies
+    /// the root.
     /// Commits are saved to the history, so the tree can be rolled back to any previous commit
     /// using [`MerkleTree::rollback`]
     ///
+    /// Panics if the nodes needed to calculate the new root are missing from the tree, which
+    /// can only happen if the tree was created from a store with gaps. Use
+    /// [`MerkleTree::try_commit`] to get an error instead.
+    ///
     /// ## Examples
     ///
     /// ```
     /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils,utils::properties::TreeProperties};
     /// # use std::convert::TryFrom;
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///    sorted_pair_enabled: false,
-    /// };
+    /// let tree_properties = TreeProperties::default();
     /// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
     /// let mut leaves = vec![
     ///     Sha256::hash("a".as_bytes()),
@@ -346,24 +1761,50 @@ impl<T: Hasher> MerkleTree<T> {
     /// # }
     /// ```
     pub fn commit(&mut self) {
-        if let Some(diff) = self.uncommitted_diff() {
-            self.history.push(diff.clone());
-            self.current_working_tree.merge_unverified(diff);
-            self.uncommitted_leaves.clear();
+        if let Err(error) = self.try_commit() {
+            panic!("failed to commit the changes: {}", error);
         }
     }
 
+    /// Commits the uncommitted changes, if there are any, and labels the most recent commit, so
+    /// the tree can be rolled back to it with [`MerkleTree::rollback_to`]. If the label was
+    /// already used for another commit, it's moved to the most recent one.
+    ///
+    /// Returns the id of the labeled commit, or `None` if there are no commits in the history.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use rs_merkle::{MerkleTree, algorithms::Sha256, Hasher, utils::properties::TreeProperties};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tree_properties = TreeProperties::default();
+    /// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+    ///
+    /// merkle_tree.insert(Sha256::hash("a".as_bytes()));
+    /// let commit_id = merkle_tree.checkpoint("block 1");
+    ///
+    /// assert_eq!(commit_id, Some(0));
+    /// assert_eq!(merkle_tree.history()[0].label, Some("block 1".to_string()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn checkpoint(&mut self, label: &str) -> Option<u64> {
+        self.commit();
+        self.label_last_commit(label)
+    }
+
     /// Rolls back one commit and reverts the tree to the previous state.
-    /// Removes the most recent commit from the history.
+    /// Removes the most recent commit from the history. Uncommitted updates and removals are
+    /// discarded, since they refer to the leaves of the rolled back state, while uncommitted
+    /// inserts are kept. Does nothing to the tree if the most recent commit is the base snapshot
+    /// of a pruned history, see [`MerkleTree::set_retention_policy`].
     ///
     /// ## Examples
     ///
     /// ```
     /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils,utils::properties::TreeProperties};
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///    sorted_pair_enabled: false,
-    /// };
+    /// let tree_properties = TreeProperties::default();
     /// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
     ///
     /// merkle_tree.insert(Sha256::hash("a".as_bytes())).commit();
@@ -388,25 +1829,21 @@ impl<T: Hasher> MerkleTree<T> {
     /// # }
     /// ```
     pub fn rollback(&mut self) {
-        // Remove the most recent commit
-        self.history.pop();
-        // Clear working tree
-        self.current_working_tree.clear();
-        // Applying all the commits up to the removed one. This is not an
-        // efficient way of doing things, but the diff subtraction is not implemented yet on
-        // PartialMerkleTree
-        for commit in &self.history {
-            self.current_working_tree.merge_unverified(commit.clone());
-        }
+        into_ok(self.try_rollback())
     }
 
     /// Calculates the root of the uncommitted changes as if they were committed.
     /// Will return the same hash as [`MerkleTree::root`] after [`MerkleTree::commit`]
     ///
+    /// Panics in the same cases as [`MerkleTree::commit`], use
+    /// [`MerkleTree::try_uncommitted_root`] to get an error instead.
+    ///
     /// For examples, please check [`MerkleTree::uncommitted_root_hex`]
     pub fn uncommitted_root(&self) -> Option<T::Hash> {
-        let shadow_tree = self.uncommitted_diff()?;
-        shadow_tree.root().cloned()
+        match self.try_uncommitted_root() {
+            Ok(root) => root,
+            Err(error) => panic!("failed to calculate the uncommitted root: {}", error),
+        }
     }
 
     /// Calculates the root of the uncommitted changes as if they were committed. Serializes
@@ -419,9 +1856,7 @@ impl<T: Hasher> MerkleTree<T> {
     /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils, utils::properties::TreeProperties};
     /// # use std::convert::TryFrom;
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///    sorted_pair_enabled: false,
-    /// };
+    /// let tree_properties = TreeProperties::default();
     /// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
     /// let mut leaves = vec![
     ///     Sha256::hash("a".as_bytes()),
@@ -450,71 +1885,8 @@ impl<T: Hasher> MerkleTree<T> {
         Some(utils::collections::to_hex_string(&root))
     }
 
-    /// Clears all uncommitted changes made by [`MerkleTree::insert`] and [`MerkleTree::append`]
-    /// operations without applying them to the tree.
-    ///
-    /// ## Examples
-    ///
-    /// ```
-    /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils, utils::properties::TreeProperties};
-    /// # use std::convert::TryFrom;
-    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///     sorted_pair_enabled: false,
-    /// };
-    /// let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
-    /// let mut leaves = vec![
-    ///     Sha256::hash("a".as_bytes()),
-    ///     Sha256::hash("b".as_bytes()),
-    /// ];
-    /// assert_eq!(
-    ///     merkle_tree.root(),
-    ///     None
-    /// );
-    ///
-    /// merkle_tree.append(&mut leaves);
-    /// merkle_tree.abort_uncommitted();
-    /// merkle_tree.commit();
-    ///
-    /// assert_eq!(
-    ///     merkle_tree.root(),
-    ///     None
-    /// );
-    /// # Ok(())
-    /// # }
-    /// ```
-    pub fn abort_uncommitted(&mut self) {
-        self.uncommitted_leaves.clear()
-    }
-
-    /// Returns the tree depth. A tree depth is how many layers there is between the
-    /// leaves and the root
-    ///
-    /// ## Examples
-    ///
-    /// ```
-    /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils, utils::properties::TreeProperties};
-    /// # use std::convert::TryFrom;
-    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///     sorted_pair_enabled: false,
-    /// };
-    /// let leaves = [
-    ///     Sha256::hash("a".as_bytes()),
-    ///     Sha256::hash("b".as_bytes()),
-    ///     Sha256::hash("c".as_bytes()),
-    /// ];
-    ///
-    /// let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves,tree_properties);
-    /// assert_eq!(merkle_tree.depth(), 2);
-    /// # Ok(())
-    /// # }
-    /// ```
-    pub fn depth(&self) -> usize {
-        self.layer_tuples().len() - 1
-    }
-
-    /// Returns a copy of the tree leaves - the base level of the tree.
+    /// Returns a copy of the tree leaves - the base level of the tree. If domain separation is
+    /// enabled in [`TreeProperties`], leaves are returned hashed with the leaf prefix.
     ///
     /// ### Examples
     ///
@@ -522,9 +1894,7 @@ impl<T: Hasher> MerkleTree<T> {
     /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils,utils::properties::TreeProperties};
     /// # use std::convert::TryFrom;
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///     sorted_pair_enabled: false,
-    /// };
+    /// let tree_properties = TreeProperties::default();
     /// let leaves = [
     ///     Sha256::hash("a".as_bytes()),
     ///     Sha256::hash("b".as_bytes()),
@@ -537,94 +1907,67 @@ impl<T: Hasher> MerkleTree<T> {
     /// # }
     /// ```
     pub fn leaves(&self) -> Option<Vec<T::Hash>> {
-        Some(self.layers().first()?.to_vec())
+        into_ok(self.try_leaves())
     }
 
-    /// Returns the number of leaves in the tree.
+    /// Returns the whole tree, where the first layer is leaves and
+    /// consequent layers are nodes.
+    pub fn layers(&self) -> Vec<Vec<T::Hash>> {
+        into_ok(self.try_layers())
+    }
+
+    /// Rehashes the nodes from the leaf layer and checks that they match the stored ones. Use it after
+    /// loading an untrusted snapshot with [`MerkleTree::from_snapshot_bytes`], which doesn't
+    /// rehash the nodes. Takes as long as building the tree from the leaves.
+    pub fn verify_layers(&self) -> bool {
+        let layers = self.layers();
+        let leaves = match layers.first() {
+            Some(leaves) if !leaves.is_empty() => leaves,
+            _ => return true,
+        };
+        PartialTree::<T>::build(
+            vec![leaves.iter().copied().enumerate().collect()],
+            utils::indices::tree_depth(leaves.len()),
+            self.tree_properties,
+        )
+        .is_ok_and(|tree| tree.layer_nodes() == layers)
+    }
+
+    /// Serializes the committed state of the tree into a snapshot that can be loaded with
+    /// [`MerkleTree::from_snapshot_bytes`] without rehashing the leaves. The snapshot contains
+    /// all layers of the tree and its [`TreeProperties`]. If `include_history` is true, the
+    /// commit history and the retention policy are included as well, so the loaded tree can be
+    /// rolled back. Uncommitted changes are not included.
+    ///
+    /// The snapshot starts with a header consisting of the `RSMT` magic bytes, the format version
+    /// and the hash size, and ends with the hash of all preceding bytes. Integers are encoded in
+    /// little-endian.
     ///
     /// ## Examples
     ///
     /// ```
-    /// # use rs_merkle::{MerkleTree, MerkleProof, algorithms::Sha256, Hasher, Error, utils,utils::properties::TreeProperties};
-    /// # use std::convert::TryFrom;
+    /// # use rs_merkle::{MerkleTree, algorithms::Sha256, Hasher, utils::properties::TreeProperties};
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tree_properties = TreeProperties {
-    ///     sorted_pair_enabled: false,
-    /// };
+    /// let tree_properties = TreeProperties::default();
     /// let leaves = [
     ///     Sha256::hash("a".as_bytes()),
     ///     Sha256::hash("b".as_bytes()),
     ///     Sha256::hash("c".as_bytes()),
     /// ];
     ///
-    /// let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves,tree_properties);
-    /// assert_eq!(merkle_tree.leaves_len(), 3);
+    /// let mut merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves[..2], tree_properties);
+    /// merkle_tree.insert(leaves[2]).commit();
+    /// let snapshot = merkle_tree.to_snapshot_bytes(true);
+    ///
+    /// let mut loaded_tree = MerkleTree::<Sha256>::from_snapshot_bytes(&snapshot)?;
+    /// assert_eq!(loaded_tree.root(), merkle_tree.root());
+    ///
+    /// loaded_tree.rollback();
+    /// assert_eq!(loaded_tree.leaves(), Some(leaves[..2].to_vec()));
     /// # Ok(())
     /// # }
     /// ```
-    pub fn leaves_len(&self) -> usize {
-        if let Some(leaves) = self.leaves_tuples() {
-            return leaves.len();
-        }
-
-        0
-    }
-
-    fn leaves_tuples(&self) -> Option<&[(usize, T::Hash)]> {
-        Some(self.layer_tuples().first()?.as_slice())
-    }
-
-    /// Returns the whole tree, where the first layer is leaves and
-    /// consequent layers are nodes.
-    pub fn layers(&self) -> Vec<Vec<T::Hash>> {
-        self.current_working_tree.layer_nodes()
-    }
-
-    fn layer_tuples(&self) -> &[Vec<(usize, T::Hash)>] {
-        self.current_working_tree.layers()
-    }
-
-    /// Creates a diff from a changes that weren't committed to the main tree yet. Can be used
-    /// to get uncommitted root or can be merged with the main tree
-    fn uncommitted_diff(&self) -> Option<PartialTree<T>> {
-        if self.uncommitted_leaves.is_empty() {
-            return None;
-        }
-
-        let committed_leaves_count = self.leaves_len();
-
-        let shadow_indices: Vec<usize> = self
-            .uncommitted_leaves
-            .iter()
-            .enumerate()
-            .map(|(index, _)| committed_leaves_count + index)
-            .collect();
-        // Tuples (index, hash) needed to construct a partial tree, since partial tree can't
-        // maintain indices otherwise
-        let mut shadow_node_tuples: Vec<(usize, T::Hash)> = shadow_indices
-            .iter()
-            .cloned()
-            .zip(self.uncommitted_leaves.iter().cloned())
-            .collect();
-        let mut partial_tree_tuples = self.helper_node_tuples(&shadow_indices);
-
-        // Figuring what tree height would be if we've committed the changes
-        let leaves_in_new_tree = self.leaves_len() + self.uncommitted_leaves.len();
-        let uncommitted_tree_depth = utils::indices::tree_depth(leaves_in_new_tree);
-
-        match partial_tree_tuples.first_mut() {
-            Some(first_layer) => {
-                first_layer.append(&mut shadow_node_tuples);
-                first_layer.sort_by(|(a, _), (b, _)| a.cmp(b));
-            }
-            None => partial_tree_tuples.push(shadow_node_tuples),
-        }
-        // Building a partial tree with the changes that would be needed to the working tree
-        PartialTree::<T>::build(
-            partial_tree_tuples,
-            uncommitted_tree_depth,
-            self.tree_properties,
-        )
-        .ok()
+    pub fn to_snapshot_bytes(&self, include_history: bool) -> Vec<u8> {
+        into_ok(self.try_to_snapshot_bytes(include_history))
     }
 }
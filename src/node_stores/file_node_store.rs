@@ -0,0 +1,229 @@
+use crate::{prelude::*, Hasher, NodeStore};
+use core::marker::PhantomData;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Keeps nodes in files inside of a directory, one file per layer, so trees that don't fit into
+/// memory can be built, and reopened later without rebuilding them from the leaves. A node is
+/// stored at the offset calculated from its index as a presence flag followed by the hash bytes.
+///
+/// The store must be reopened with the same [`Hasher`]. I/O errors are returned as the
+/// [`NodeStore::Error`], so the trees that use the store have to be accessed with the methods
+/// that return errors, like [`MerkleTree::try_commit`] and [`MerkleTree::try_root`].
+///
+/// [`MerkleTree::try_commit`]: crate::MerkleTree::try_commit
+/// [`MerkleTree::try_root`]: crate::MerkleTree::try_root
+///
+/// ## Examples
+///
+/// ```
+/// # use rs_merkle::{MerkleTree, node_stores::FileNodeStore, algorithms::Sha256, Hasher, utils::properties::TreeProperties};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let tree_properties = TreeProperties::default();
+/// let directory = std::env::temp_dir().join("rs_merkle_file_node_store_doc");
+/// # let _ = std::fs::remove_dir_all(&directory);
+///
+/// let store = FileNodeStore::<Sha256>::open(&directory)?;
+/// let mut merkle_tree = MerkleTree::with_store(store, tree_properties)?;
+/// merkle_tree.insert(Sha256::hash("a".as_bytes())).try_commit()?;
+/// merkle_tree.store().sync()?;
+/// let root = merkle_tree.try_root()?;
+/// drop(merkle_tree);
+///
+/// // Reopening the tree doesn't require rebuilding it
+/// let store = FileNodeStore::<Sha256>::open(&directory)?;
+/// let merkle_tree = MerkleTree::with_store(store, tree_properties)?;
+/// assert_eq!(merkle_tree.try_root()?, root);
+/// # std::fs::remove_dir_all(&directory)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FileNodeStore<T: Hasher> {
+    directory: PathBuf,
+    layer_files: Vec<File>,
+    layer_lens: Vec<usize>,
+    _hasher: PhantomData<T>,
+}
+
+impl<T: Hasher> FileNodeStore<T> {
+    /// Opens the store in the given directory, creating the directory if it doesn't exist.
+    /// Returns an error if the directory can't be created, or if the existing files can't be
+    /// read or don't match the hash size of the [`Hasher`].
+    pub fn open(directory: impl AsRef<Path>) -> io::Result<Self> {
+        let directory = directory.as_ref().to_path_buf();
+        fs::create_dir_all(&directory)?;
+
+        let mut store = Self {
+            directory,
+            layer_files: Vec::new(),
+            layer_lens: Vec::new(),
+            _hasher: PhantomData,
+        };
+
+        while store.layer_path(store.layer_files.len()).exists() {
+            let file = store.open_layer_file(store.layer_files.len())?;
+            let file_len = file.metadata()?.len() as usize;
+            if !file_len.is_multiple_of(Self::record_size()) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "layer file size doesn't match the hash size",
+                ));
+            }
+
+            store.layer_files.push(file);
+            store.layer_lens.push(file_len / Self::record_size());
+        }
+
+        Ok(store)
+    }
+
+    /// Returns the directory of the store
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Flushes all written nodes to the disk
+    pub fn sync(&self) -> io::Result<()> {
+        for file in &self.layer_files {
+            file.sync_all()?;
+        }
+
+        Ok(())
+    }
+
+    fn record_size() -> usize {
+        T::hash_size() + 1
+    }
+
+    fn layer_path(&self, layer_index: usize) -> PathBuf {
+        self.directory.join(format!("layer_{}", layer_index))
+    }
+
+    fn open_layer_file(&self, layer_index: usize) -> io::Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(self.layer_path(layer_index))
+    }
+
+    /// Reads records of the layer starting from the given index
+    fn read_records(
+        &self,
+        layer_index: usize,
+        from: usize,
+        to: usize,
+    ) -> io::Result<Vec<(usize, T::Hash)>> {
+        let record_size = Self::record_size();
+        let mut bytes = vec![0u8; (to - from) * record_size];
+        let mut file = &self.layer_files[layer_index];
+        file.seek(SeekFrom::Start((from * record_size) as u64))?;
+        file.read_exact(&mut bytes)?;
+
+        let records = bytes
+            .chunks(record_size)
+            .enumerate()
+            .filter(|(_, record)| record[0] != 0)
+            .filter_map(|(offset, record)| {
+                let hash = T::Hash::try_from(record[1..].to_vec()).ok()?;
+                Some((from + offset, hash))
+            })
+            .collect();
+        Ok(records)
+    }
+
+    fn set_layer_len(&mut self, layer_index: usize, layer_len: usize) -> io::Result<()> {
+        let file = &self.layer_files[layer_index];
+        file.set_len((layer_len * Self::record_size()) as u64)?;
+        self.layer_lens[layer_index] = layer_len;
+        Ok(())
+    }
+}
+
+impl<T: Hasher> NodeStore<T> for FileNodeStore<T> {
+    type Error = io::Error;
+
+    fn get(&self, layer_index: usize, node_index: usize) -> io::Result<Option<T::Hash>> {
+        if node_index >= self.layer_len(layer_index) {
+            return Ok(None);
+        }
+
+        let node = self
+            .read_records(layer_index, node_index, node_index + 1)?
+            .pop()
+            .map(|(_, hash)| hash);
+        Ok(node)
+    }
+
+    fn insert(&mut self, layer_index: usize, node_index: usize, node: T::Hash) -> io::Result<()> {
+        while self.layer_files.len() <= layer_index {
+            let file = self.open_layer_file(self.layer_files.len())?;
+            file.set_len(0)?;
+            self.layer_files.push(file);
+            self.layer_lens.push(0);
+        }
+
+        // Gaps are filled with zeros, which are read as missing nodes
+        if node_index >= self.layer_lens[layer_index] {
+            self.set_layer_len(layer_index, node_index + 1)?;
+        }
+
+        let mut record: Vec<u8> = vec![1];
+        let mut hash_bytes: Vec<u8> = node.into();
+        record.append(&mut hash_bytes);
+
+        let mut file = &self.layer_files[layer_index];
+        file.seek(SeekFrom::Start((node_index * Self::record_size()) as u64))?;
+        file.write_all(&record)
+    }
+
+    fn layers_len(&self) -> usize {
+        self.layer_files.len()
+    }
+
+    fn layer_len(&self, layer_index: usize) -> usize {
+        self.layer_lens.get(layer_index).cloned().unwrap_or(0)
+    }
+
+    fn layer(&self, layer_index: usize) -> io::Result<Vec<(usize, T::Hash)>> {
+        self.read_records(layer_index, 0, self.layer_len(layer_index))
+    }
+
+    fn truncate_layer(
+        &mut self,
+        layer_index: usize,
+        layer_len: usize,
+    ) -> io::Result<Vec<(usize, T::Hash)>> {
+        let current_len = self.layer_len(layer_index);
+        if layer_len >= current_len {
+            return Ok(Vec::new());
+        }
+
+        let removed_nodes = self.read_records(layer_index, layer_len, current_len)?;
+
+        // Trailing gaps are trimmed, so the layer length always points after the last node
+        let mut new_len = layer_len;
+        while new_len > 0
+            && self
+                .read_records(layer_index, new_len - 1, new_len)?
+                .is_empty()
+        {
+            new_len -= 1;
+        }
+        self.set_layer_len(layer_index, new_len)?;
+
+        Ok(removed_nodes)
+    }
+
+    fn truncate_layers(&mut self, layers_len: usize) -> io::Result<()> {
+        while self.layer_files.len() > layers_len {
+            let layer_index = self.layer_files.len() - 1;
+            fs::remove_file(self.layer_path(layer_index))?;
+            self.layer_files.pop();
+            self.layer_lens.pop();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{prelude::*, Hasher, NodeStore};
+use core::convert::Infallible;
+
+/// Keeps nodes in memory, layer by layer, as (index, hash) tuples sorted by index. The default
+/// store of [`PartialTree`] and [`MerkleTree`].
+///
+/// [`PartialTree`]: crate::PartialTree
+/// [`MerkleTree`]: crate::MerkleTree
+#[derive(Clone)]
+pub struct MemoryNodeStore<T: Hasher> {
+    layers: Vec<Vec<(usize, T::Hash)>>,
+}
+
+impl<T: Hasher> Default for MemoryNodeStore<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Hasher> MemoryNodeStore<T> {
+    pub fn new() -> Self {
+        Self { layers: Vec::new() }
+    }
+
+    /// Creates a store from layers of (index, hash) tuples. Each layer must be sorted by index.
+    pub fn from_layers(layers: Vec<Vec<(usize, T::Hash)>>) -> Self {
+        Self { layers }
+    }
+
+    /// Returns all layers of the store
+    pub fn layers(&self) -> &[Vec<(usize, T::Hash)>] {
+        &self.layers
+    }
+
+    /// Returns the position of the node in the layer vector, or the position where it should
+    /// be inserted
+    fn position(layer: &[(usize, T::Hash)], node_index: usize) -> Result<usize, usize> {
+        // Layers of a full tree have no gaps, so the node is usually found at its index
+        match layer.get(node_index) {
+            Some((index, _)) if *index == node_index => Ok(node_index),
+            _ => layer.binary_search_by_key(&node_index, |(index, _)| *index),
+        }
+    }
+}
+
+impl<T: Hasher> NodeStore<T> for MemoryNodeStore<T> {
+    type Error = Infallible;
+
+    fn get(&self, layer_index: usize, node_index: usize) -> Result<Option<T::Hash>, Infallible> {
+        let node = self.layers.get(layer_index).and_then(|layer| {
+            let position = Self::position(layer, node_index).ok()?;
+            Some(layer[position].1)
+        });
+        Ok(node)
+    }
+
+    fn insert(
+        &mut self,
+        layer_index: usize,
+        node_index: usize,
+        node: T::Hash,
+    ) -> Result<(), Infallible> {
+        if layer_index >= self.layers.len() {
+            self.layers.resize_with(layer_index + 1, Vec::new);
+        }
+
+        let layer = &mut self.layers[layer_index];
+        // Appended nodes usually go to the end of the layer, which makes inserts cheap
+        match Self::position(layer, node_index) {
+            Ok(position) => layer[position].1 = node,
+            Err(position) => layer.insert(position, (node_index, node)),
+        }
+        Ok(())
+    }
+
+    fn layers_len(&self) -> usize {
+        self.layers.len()
+    }
+
+    fn layer_len(&self, layer_index: usize) -> usize {
+        match self.layers.get(layer_index).and_then(|layer| layer.last()) {
+            Some((index, _)) => index + 1,
+            None => 0,
+        }
+    }
+
+    fn layer(&self, layer_index: usize) -> Result<Vec<(usize, T::Hash)>, Infallible> {
+        Ok(self.layers.get(layer_index).cloned().unwrap_or_default())
+    }
+
+    fn truncate_layer(
+        &mut self,
+        layer_index: usize,
+        layer_len: usize,
+    ) -> Result<Vec<(usize, T::Hash)>, Infallible> {
+        let removed_nodes = match self.layers.get_mut(layer_index) {
+            Some(layer) => {
+                // Layers are sorted by index, so the nodes to keep are found with a binary search
+                let nodes_to_keep = layer.partition_point(|(index, _)| *index < layer_len);
+                layer.split_off(nodes_to_keep)
+            }
+            None => Vec::new(),
+        };
+        Ok(removed_nodes)
+    }
+
+    fn truncate_layers(&mut self, layers_len: usize) -> Result<(), Infallible> {
+        self.layers.truncate(layers_len);
+        Ok(())
+    }
+}
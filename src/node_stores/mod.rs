@@ -0,0 +1,17 @@
+//! This module contains built-in implementations of the [`NodeStore`] trait.
+//! Node stores are used by [`PartialTree`] and [`MerkleTree`] to keep the nodes of the tree
+//!
+//! [`NodeStore`]: crate::NodeStore
+//! [`PartialTree`]: crate::PartialTree
+//! [`MerkleTree`]: crate::MerkleTree
+
+#[cfg(feature = "std")]
+mod file_node_store;
+mod memory_node_store;
+mod node_store;
+
+#[cfg(feature = "std")]
+pub use file_node_store::FileNodeStore;
+pub use memory_node_store::MemoryNodeStore;
+pub(crate) use node_store::into_ok;
+pub use node_store::NodeStore;
@@ -0,0 +1,72 @@
+use crate::{prelude::*, Hasher};
+use core::convert::Infallible;
+use core::fmt::Display;
+
+/// Trait representing a storage of tree nodes. Nodes are addressed by the index of the layer,
+/// where the layer 0 is leaves, and the index of the node in the layer. Used by [`PartialTree`]
+/// and [`MerkleTree`], so the tree can be kept outside of memory.
+///
+/// The library provides some built-in implementations of this trait - check
+/// [`node_stores`] module.
+///
+/// Methods that access the nodes return [`NodeStore::Error`] if the storage fails, for example
+/// on an I/O error. The number of layers and the layer lengths are expected to be known without
+/// accessing the storage, so they can't fail. Stores that can't fail, like [`MemoryNodeStore`],
+/// use [`Infallible`] as the error type, which allows trees that use them to provide methods
+/// that don't return errors.
+///
+/// [`PartialTree`]: crate::PartialTree
+/// [`MerkleTree`]: crate::MerkleTree
+/// [`node_stores`]: crate::node_stores
+/// [`MemoryNodeStore`]: crate::node_stores::MemoryNodeStore
+/// [`Infallible`]: core::convert::Infallible
+pub trait NodeStore<T: Hasher> {
+    /// The error returned when the storage fails
+    type Error: Display;
+
+    /// Returns the node at the given position, or `None` if there's no such node
+    fn get(&self, layer_index: usize, node_index: usize) -> Result<Option<T::Hash>, Self::Error>;
+
+    /// Inserts the node at the given position, replacing the existing one. Missing layers
+    /// below the given one are created empty.
+    fn insert(
+        &mut self,
+        layer_index: usize,
+        node_index: usize,
+        node: T::Hash,
+    ) -> Result<(), Self::Error>;
+
+    /// Returns the number of layers, including empty ones
+    fn layers_len(&self) -> usize;
+
+    /// Returns the index following the last node of the layer, which is the number of nodes
+    /// in the layer if it has no gaps. Returns 0 for a missing layer.
+    fn layer_len(&self, layer_index: usize) -> usize;
+
+    /// Returns all nodes of the layer as (index, hash) tuples sorted by index
+    fn layer(&self, layer_index: usize) -> Result<Vec<(usize, T::Hash)>, Self::Error>;
+
+    /// Removes the nodes of the layer with indices greater than or equal to `layer_len` and
+    /// returns them as (index, hash) tuples sorted by index
+    fn truncate_layer(
+        &mut self,
+        layer_index: usize,
+        layer_len: usize,
+    ) -> Result<Vec<(usize, T::Hash)>, Self::Error>;
+
+    /// Removes the layers with indices greater than or equal to `layers_len`
+    fn truncate_layers(&mut self, layers_len: usize) -> Result<(), Self::Error>;
+
+    /// Removes all nodes from the store
+    fn clear(&mut self) -> Result<(), Self::Error> {
+        self.truncate_layers(0)
+    }
+}
+
+/// Unwraps the result of a store that can't fail
+pub(crate) fn into_ok<R>(result: Result<R, Infallible>) -> R {
+    match result {
+        Ok(value) => value,
+        Err(error) => match error {},
+    }
+}
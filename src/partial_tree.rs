@@ -1,5 +1,13 @@
 use crate::prelude::*;
-use crate::{error::Error, utils, utils::properties::TreeProperties, Hasher};
+use crate::{
+    error::Error,
+    node_stores::{into_ok, MemoryNodeStore},
+    utils,
+    utils::properties::TreeProperties,
+    Hasher, NodeStore,
+};
+use core::convert::Infallible;
+use core::marker::PhantomData;
 
 type PartialTreeLayer<H> = Vec<(usize, H)>;
 
@@ -7,14 +15,17 @@ type PartialTreeLayer<H> = Vec<(usize, H)>;
 /// Used in to extract the root in a merkle proof, to apply diff to a tree or to merge
 /// multiple trees into one.
 ///
+/// Nodes are kept in a [`NodeStore`], which is [`MemoryNodeStore`] by default.
+///
 /// It is a rare case when you need to use this struct on it's own. It's mostly used inside
 /// [`MerkleTree`] and [`MerkleProof`]
 ///
 /// [`MerkleTree`]: crate::MerkleTree
 /// [`MerkleProof`]: crate::MerkleProof
 #[derive(Clone)]
-pub struct PartialTree<T: Hasher> {
-    layers: Vec<Vec<(usize, T::Hash)>>,
+pub struct PartialTree<T: Hasher, S: NodeStore<T> = MemoryNodeStore<T>> {
+    store: S,
+    _hasher: PhantomData<T>,
 }
 
 impl<T: Hasher> Default for PartialTree<T> {
@@ -27,13 +38,17 @@ impl<T: Hasher> PartialTree<T> {
     /// Takes leaves (item hashes) as an argument and build a Merkle Tree from them.
     /// Since it's a partial tree, hashes must be accompanied by their index in the original tree.
     pub fn new() -> Self {
-        Self { layers: Vec::new() }
+        Self::with_store(MemoryNodeStore::new())
     }
 
     /// This is a helper function to build a full tree from a full set of leaves without any
-    /// helper indices
+    /// helper indices. Leaves are hashed with the leaf prefix if domain separation is enabled.
     pub fn from_leaves(leaves: &[T::Hash], tree_properties: TreeProperties) -> Result<Self, Error> {
-        let leaf_tuples: Vec<(usize, T::Hash)> = leaves.iter().cloned().enumerate().collect();
+        let leaf_tuples: Vec<(usize, T::Hash)> = leaves
+            .iter()
+            .map(|leaf| tree_properties.hash_leaf::<T>(leaf))
+            .enumerate()
+            .collect();
 
         Self::build(
             vec![leaf_tuples],
@@ -49,54 +64,7 @@ impl<T: Hasher> PartialTree<T> {
     ) -> Result<Self, Error> {
         let layers = Self::build_tree(partial_layers, depth, tree_properties)?;
 
-        Ok(Self { layers })
-    }
-
-    fn sorted_concat_and_hash(
-        left_node: Option<&T::Hash>,
-        right_node: Option<&T::Hash>,
-        current_layer: &mut Vec<(usize, T::Hash)>,
-        parent_node_index: usize,
-    ) -> Result<(), Error> {
-        match left_node {
-            // Populate `current_layer` back for the next iteration
-            Some(left) => {
-                let left_hex = utils::collections::to_hex_string(left);
-
-                match right_node {
-                    Some(right) => {
-                        let right_hex = utils::collections::to_hex_string(right);
-                        if right_hex < left_hex {
-                            current_layer
-                                .push((parent_node_index, T::concat_and_hash(right, left_node)))
-                        } else {
-                            current_layer
-                                .push((parent_node_index, T::concat_and_hash(left, right_node)))
-                        }
-                    }
-                    None => current_layer
-                        .push((parent_node_index, T::concat_and_hash(left, right_node))),
-                }
-                Ok(())
-            }
-            None => return Err(Error::not_enough_helper_nodes()),
-        }
-    }
-
-    fn unsorted_concat_and_hash(
-        left_node: Option<&T::Hash>,
-        right_node: Option<&T::Hash>,
-        current_layer: &mut Vec<(usize, T::Hash)>,
-        parent_node_index: usize,
-    ) -> Result<(), Error> {
-        match left_node {
-            // Populate `current_layer` back for the next iteration
-            Some(left) => {
-                current_layer.push((parent_node_index, T::concat_and_hash(left, right_node)))
-            }
-            None => return Err(Error::not_enough_helper_nodes()),
-        }
-        Ok(())
+        Ok(Self::with_store(MemoryNodeStore::from_layers(layers)))
     }
 
     /// This is a general algorithm for building a partial tree. It can be used to extract root
@@ -118,13 +86,13 @@ impl<T: Hasher> PartialTree<T> {
         // It is iterating to full_tree_depth instead of partial_layers.len to address the case
         // of applying changes to a tree when tree requires a resize, and partial layer len
         // in that case going to be lower that the resulting tree depth
-        for _ in 0..full_tree_depth {
+        for layer_index in 0..full_tree_depth {
             // Appending helper nodes to the current known nodes
             if let Some(mut nodes) = reversed_layers.pop() {
                 current_layer.append(&mut nodes);
             }
 
-            current_layer.sort_by(|(a, _), (b, _)| a.cmp(b));
+            current_layer.sort_by_key(|(a, _)| *a);
 
             // Adding partial layer to the tree
             partial_tree.push(current_layer.clone());
@@ -134,24 +102,27 @@ impl<T: Hasher> PartialTree<T> {
             let parent_layer_indices = utils::indices::parent_indices(&indices);
 
             for (i, parent_node_index) in parent_layer_indices.iter().enumerate() {
+                // Children are paired by position, so a missing sibling would shift the nodes
+                // that follow it and pair them with the wrong parents
+                if indices.get(i * 2) != Some(&(parent_node_index * 2))
+                    || indices
+                        .get(i * 2 + 1)
+                        .is_some_and(|index| *index != parent_node_index * 2 + 1)
+                {
+                    return Err(Error::not_enough_helper_nodes());
+                }
                 let left_node = nodes.get(i * 2);
                 let right_node = nodes.get(i * 2 + 1);
 
-                if tree_properties.sorted_pair_enabled {
-                    Self::sorted_concat_and_hash(
-                        left_node,
-                        right_node,
-                        &mut current_layer,
-                        *parent_node_index,
-                    )?;
-                } else {
-                    Self::unsorted_concat_and_hash(
-                        left_node,
-                        right_node,
-                        &mut current_layer,
-                        *parent_node_index,
-                    )?;
-                }
+                // Populate `current_layer` back for the next iteration
+                let left = left_node.ok_or_else(Error::not_enough_helper_nodes)?;
+                let parent = match right_node {
+                    Some(_) => tree_properties.concat_and_hash::<T>(left, right_node),
+                    None => {
+                        tree_properties.hash_lone_node::<T>(left, layer_index, *parent_node_index)
+                    }
+                };
+                current_layer.push((*parent_node_index, parent));
             }
         }
 
@@ -160,94 +131,192 @@ impl<T: Hasher> PartialTree<T> {
         Ok(partial_tree)
     }
 
+    /// Returns partial tree layers
+    pub fn layers(&self) -> &[Vec<(usize, T::Hash)>] {
+        self.store.layers()
+    }
+}
+
+impl<T: Hasher, S: NodeStore<T>> PartialTree<T, S> {
+    /// Creates a partial tree that keeps its nodes in the given store. Nodes that are already
+    /// in the store become the nodes of the tree.
+    pub fn with_store(store: S) -> Self {
+        Self {
+            store,
+            _hasher: PhantomData,
+        }
+    }
+
+    /// Returns the store of the tree
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Consumes the tree and returns its store
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
     /// Returns how many layers there is between leaves and the root
     pub fn depth(&self) -> usize {
-        self.layers.len() - 1
+        self.store.layers_len() - 1
     }
 
-    /// Return the root of the tree
-    pub fn root(&self) -> Option<&T::Hash> {
-        Some(&self.layers.last()?.first()?.1)
+    /// Same as [`PartialTree::root`], but returns an error if the store fails
+    pub fn try_root(&self) -> Result<Option<T::Hash>, S::Error> {
+        let root_layer_index = match self.store.layers_len().checked_sub(1) {
+            Some(root_layer_index) => root_layer_index,
+            None => return Ok(None),
+        };
+        let root = self
+            .store
+            .layer(root_layer_index)?
+            .first()
+            .map(|(_, root)| *root);
+        Ok(root)
     }
 
-    pub fn contains(&self, layer_index: usize, node_index: usize) -> bool {
-        match self.layers().get(layer_index) {
-            Some(layer) => layer.iter().any(|(index, _)| *index == node_index),
-            None => false,
+    /// Same as [`PartialTree::get`], but returns an error if the store fails
+    pub fn try_get(
+        &self,
+        layer_index: usize,
+        node_index: usize,
+    ) -> Result<Option<T::Hash>, S::Error> {
+        self.store.get(layer_index, node_index)
+    }
+
+    /// Same as [`PartialTree::merge_unverified`], but returns an error if the store fails.
+    /// Nodes merged before the failure stay in the tree.
+    pub fn try_merge_unverified<O: NodeStore<T, Error = Infallible>>(
+        &mut self,
+        other: PartialTree<T, O>,
+    ) -> Result<(), S::Error> {
+        for layer_index in 0..other.store.layers_len() {
+            for (node_index, node) in into_ok(other.store.layer(layer_index)) {
+                self.store.insert(layer_index, node_index, node)?;
+            }
         }
+
+        Ok(())
     }
 
-    /// Consumes other partial tree into itself, replacing any conflicting nodes with nodes from
-    /// `other` in the process. Doesn't rehash the nodes, so the integrity of the result is
-    /// not verified. It gives an advantage in speed, but should be used only if the integrity of
-    /// the tree can't be broken, for example, it is used in the `.commit` method of the
-    /// `MerkleTree`, since both partial trees are essentially constructed in place and there's
-    /// no need to verify integrity of the result.
-    pub fn merge_unverified(&mut self, other: Self) {
-        // Figure out new tree depth after merge
-        let depth_difference = other.layers().len() - self.layers().len();
-        let combined_tree_size = if depth_difference > 0 {
-            other.layers().len()
+    /// Same as [`PartialTree::layer_nodes`], but returns an error if the store fails
+    pub fn try_layer_nodes(&self) -> Result<Vec<Vec<T::Hash>>, S::Error> {
+        (0..self.store.layers_len())
+            .map(|layer_index| {
+                let layer = self.store.layer(layer_index)?;
+                Ok(layer.into_iter().map(|(_, hash)| hash).collect())
+            })
+            .collect()
+    }
+
+    /// Same as [`PartialTree::intersection`], but returns an error if the store fails
+    pub fn try_intersection<O: NodeStore<T, Error = Infallible>>(
+        &self,
+        other: &PartialTree<T, O>,
+    ) -> Result<PartialTree<T>, S::Error> {
+        let layers_len = other.store.layers_len().min(self.store.layers_len());
+        let mut layers = Vec::with_capacity(layers_len);
+        for layer_index in 0..layers_len {
+            let mut layer = Vec::new();
+            for (node_index, _) in into_ok(other.store.layer(layer_index)) {
+                if let Some(node) = self.store.get(layer_index, node_index)? {
+                    layer.push((node_index, node));
+                }
+            }
+            layers.push(layer);
+        }
+
+        Ok(PartialTree::with_store(MemoryNodeStore::from_layers(
+            layers,
+        )))
+    }
+
+    /// Same as [`PartialTree::truncate`], but returns an error if the store fails. Layers
+    /// truncated before the failure stay truncated.
+    pub fn try_truncate(&mut self, leaves_len: usize) -> Result<PartialTree<T>, S::Error> {
+        let layers_to_keep = if leaves_len == 0 {
+            0
         } else {
-            self.layers().len()
+            utils::indices::tree_depth(leaves_len) + 1
         };
 
-        for layer_index in 0..combined_tree_size {
-            let mut combined_layer: Vec<(usize, T::Hash)> = Vec::new();
+        let mut removed_layers = Vec::with_capacity(self.store.layers_len());
+        let mut layer_len = leaves_len;
+        for layer_index in 0..self.store.layers_len() {
+            let nodes_to_keep = if layer_index < layers_to_keep {
+                layer_len
+            } else {
+                0
+            };
+            removed_layers.push(self.store.truncate_layer(layer_index, nodes_to_keep)?);
+            layer_len = utils::indices::div_ceil(layer_len, 2);
+        }
+        self.store.truncate_layers(layers_to_keep)?;
 
-            if let Some(self_layer) = self.layers().get(layer_index) {
-                let mut filtered_layer: Vec<(usize, T::Hash)> = self_layer
-                    .iter()
-                    .filter(|(node_index, _)| !other.contains(layer_index, *node_index))
-                    .cloned()
-                    .collect();
+        Ok(PartialTree::with_store(MemoryNodeStore::from_layers(
+            removed_layers,
+        )))
+    }
 
-                combined_layer.append(&mut filtered_layer);
-            }
+    /// Same as [`PartialTree::clear`], but returns an error if the store fails
+    pub fn try_clear(&mut self) -> Result<(), S::Error> {
+        self.store.clear()
+    }
+}
 
-            if let Some(other_layer) = other.layers().get(layer_index) {
-                let mut cloned_other_layer = other_layer.clone();
-                combined_layer.append(&mut cloned_other_layer);
-            }
+impl<T: Hasher, S: NodeStore<T, Error = Infallible>> PartialTree<T, S> {
+    /// Return the root of the tree
+    pub fn root(&self) -> Option<T::Hash> {
+        into_ok(self.try_root())
+    }
 
-            combined_layer.sort_by(|(a, _), (b, _)| a.cmp(b));
-            // iterate through combined layer
-            // combined_layer.iter().for_each(|(_, node)| {
-            //     std::println!("layer {} {:?}", layer_index, node);
-            // });
-            // std::println!("layer {} - {:?}", layer_index, &combined_layer);
-            self.upsert_layer(layer_index, combined_layer);
-        }
+    /// Returns the node at the given position, if it's present in the partial tree
+    pub fn get(&self, layer_index: usize, node_index: usize) -> Option<T::Hash> {
+        into_ok(self.try_get(layer_index, node_index))
     }
 
-    /// Replace layer at a given index with a new layer. Used during tree merge
-    fn upsert_layer(&mut self, layer_index: usize, mut new_layer: Vec<(usize, T::Hash)>) {
-        match self.layers.get_mut(layer_index) {
-            Some(layer) => {
-                layer.clear();
-                layer.append(new_layer.as_mut())
-            }
-            None => self.layers.push(new_layer),
-        }
+    pub fn contains(&self, layer_index: usize, node_index: usize) -> bool {
+        self.get(layer_index, node_index).is_some()
+    }
+
+    /// Consumes other partial tree into itself, replacing any conflicting nodes with nodes from
+    /// `other` in the process. Doesn't rehash the nodes, so the integrity of the result is
+    /// not verified. It gives an advantage in speed, but should be used only if the integrity of
+    /// the tree can't be broken, for example, it is used in the `.commit` method of the
+    /// `MerkleTree`, since both partial trees are essentially constructed in place and there's
+    /// no need to verify integrity of the result.
+    pub fn merge_unverified<O: NodeStore<T, Error = Infallible>>(
+        &mut self,
+        other: PartialTree<T, O>,
+    ) {
+        into_ok(self.try_merge_unverified(other))
     }
 
     pub fn layer_nodes(&self) -> Vec<Vec<T::Hash>> {
-        let hashes: Vec<Vec<T::Hash>> = self
-            .layers()
-            .iter()
-            .map(|layer| layer.iter().cloned().map(|(_, hash)| hash).collect())
-            .collect();
+        into_ok(self.try_layer_nodes())
+    }
 
-        hashes
+    /// Returns the nodes of this tree that are located at the same positions as the nodes of
+    /// `other`. Merging the result back after merging `other` with
+    /// [`PartialTree::merge_unverified`] reverts the nodes replaced by the merge.
+    pub fn intersection<O: NodeStore<T, Error = Infallible>>(
+        &self,
+        other: &PartialTree<T, O>,
+    ) -> PartialTree<T> {
+        into_ok(self.try_intersection(other))
     }
 
-    /// Returns partial tree layers
-    pub fn layers(&self) -> &[Vec<(usize, T::Hash)>] {
-        &self.layers
+    /// Removes all nodes that don't belong to a tree of `leaves_len` leaves, including the layers
+    /// above the root of such a tree, and returns them as a partial tree. Nodes on the right edge
+    /// of the tree are kept as is, so they have to be recalculated, for example by merging a
+    /// diff with [`PartialTree::merge_unverified`].
+    pub fn truncate(&mut self, leaves_len: usize) -> PartialTree<T> {
+        into_ok(self.try_truncate(leaves_len))
     }
 
     /// Clears all elements in the ree
     pub fn clear(&mut self) {
-        self.layers.clear();
+        into_ok(self.try_clear())
     }
 }
@@ -1,7 +1,5 @@
 pub use core::prelude::v1::*;
 
-pub use alloc::borrow::ToOwned;
-pub use alloc::boxed::Box;
 pub use alloc::string::{String, ToString};
 pub use alloc::vec::Vec;
 
@@ -10,4 +8,3 @@ pub use alloc::vec;
 
 // Those are exported by default in the std prelude in Rust 2021
 pub use core::convert::{TryFrom, TryInto};
-pub use core::iter::FromIterator;
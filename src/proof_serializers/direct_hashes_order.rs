@@ -19,7 +19,7 @@ impl MerkleProofSerializer for DirectHashesOrder {
     fn deserialize<T: Hasher>(bytes: &[u8]) -> Result<MerkleProof<T>, Error> {
         let hash_size = T::hash_size();
 
-        if bytes.len() % hash_size != 0 {
+        if !bytes.len().is_multiple_of(hash_size) {
             return Err(Error::wrong_proof_size(bytes.len(), hash_size));
         }
 
@@ -32,8 +32,7 @@ impl MerkleProofSerializer for DirectHashesOrder {
             let slice = bytes
                 .get(slice_start..slice_end)
                 .ok_or_else(Error::vec_to_hash_conversion_error)?;
-            let vec =
-                Vec::<u8>::try_from(slice).map_err(|_| Error::vec_to_hash_conversion_error())?;
+            let vec = Vec::<u8>::from(slice);
             match T::Hash::try_from(vec) {
                 Ok(val) => proof_hashes_slices.push(val),
                 Err(_) => return Err(Error::vec_to_hash_conversion_error()),
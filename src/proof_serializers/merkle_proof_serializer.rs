@@ -1,4 +1,4 @@
-use crate::{prelude::*, Error, Hasher, MerkleProof};
+use crate::{prelude::*, Error, Hasher, IndexedMerkleProof, MerkleProof};
 
 /// Trait representing a Merkle proof serializer. Used in [`MerkleProof::serialize`] and
 /// [`MerkleProof::deserialize`].
@@ -14,3 +14,15 @@ pub trait MerkleProofSerializer {
     /// Deserialize data produced by [`MerkleProofSerializer::serialize`] back into [`MerkleProof`]
     fn deserialize<T: Hasher>(bytes: &[u8]) -> Result<MerkleProof<T>, Error>;
 }
+
+/// Trait representing a serializer that keeps the leaf indices, the number of leaves and the
+/// tree properties of an [`IndexedMerkleProof`] along with the proof hashes. Used in
+/// [`IndexedMerkleProof::serialize`] and [`IndexedMerkleProof::deserialize`].
+pub trait IndexedProofSerializer: MerkleProofSerializer {
+    /// Serialize data from [`IndexedMerkleProof`] into a binary
+    fn serialize_indexed<T: Hasher>(proof: &IndexedMerkleProof<T>) -> Vec<u8>;
+
+    /// Deserialize data produced by [`IndexedProofSerializer::serialize_indexed`] back into
+    /// [`IndexedMerkleProof`]
+    fn deserialize_indexed<T: Hasher>(bytes: &[u8]) -> Result<IndexedMerkleProof<T>, Error>;
+}
@@ -7,8 +7,10 @@
 
 mod direct_hashes_order;
 mod merkle_proof_serializer;
+mod proof_envelope;
 mod reverse_hashes_order;
 
 pub use direct_hashes_order::DirectHashesOrder;
-pub use merkle_proof_serializer::MerkleProofSerializer;
+pub use merkle_proof_serializer::{IndexedProofSerializer, MerkleProofSerializer};
+pub use proof_envelope::ProofEnvelope;
 pub use reverse_hashes_order::ReverseHashesOrder;
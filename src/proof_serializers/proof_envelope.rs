@@ -0,0 +1,321 @@
+use crate::{
+    prelude::*,
+    utils::properties::{DomainSeparation, OddNodeStrategy, TreeProperties},
+    Error, Hasher, IndexedMerkleProof, IndexedProofSerializer, MerkleProof, MerkleProofSerializer,
+};
+use core::convert::TryFrom;
+
+const ENVELOPE_MAGIC: &[u8; 4] = b"RSMP";
+const ENVELOPE_VERSION: u8 = 1;
+
+const SORTED_PAIR_FLAG: u8 = 0b001;
+const DOMAIN_SEPARATION_FLAG: u8 = 0b010;
+/// Set if the envelope carries the leaf indices, the number of leaves and the tree properties
+const INDICES_FLAG: u8 = 0b100;
+
+/// Serializes a proof into a versioned, self-describing envelope, so the verifier doesn't need
+/// to get the leaf indices, the number of leaves and the tree properties out of band.
+///
+/// All integers are little-endian. The envelope consists of:
+///
+/// - the `RSMP` magic bytes and the format version, currently `1`, as `u8`
+/// - [`Hasher::hasher_id`] as `u32` and [`Hasher::hash_size`] as `u16`
+/// - flags as `u8`: `0b100` if the envelope carries the indices and the tree properties,
+///   `0b001` if sorted pairs are enabled, `0b010` if domain separation is enabled
+/// - if the envelope carries the indices: the leaf and node prefixes as `u8` each if domain
+///   separation is enabled, the [`OddNodeStrategy`] as `u8` in the order of the enum variants,
+///   the number of leaves as `u64`, the number of indices as `u32` and the indices as `u64` each
+/// - the number of proof hashes as `u32`, followed by the hashes in the direct order
+///
+/// Envelopes of a plain [`MerkleProof`] carry neither the indices nor the tree properties, so
+/// they can only be deserialized back into a [`MerkleProof`].
+///
+/// ## Examples
+///
+/// ```
+/// # use rs_merkle::{MerkleTree, algorithms::Sha256, Hasher, proof_serializers::ProofEnvelope, utils::properties::{OddNodeStrategy, TreeProperties}};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let tree_properties = TreeProperties {
+///     odd_node_strategy: OddNodeStrategy::Duplicate,
+///     ..Default::default()
+/// };
+/// let leaves: Vec<[u8; 32]> = ["a", "b", "c", "d", "e"]
+///     .iter()
+///     .map(|x| Sha256::hash(x.as_bytes()))
+///     .collect();
+/// let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+/// let root = merkle_tree.root().ok_or("couldn't get the merkle root")?;
+///
+/// let envelope = merkle_tree.indexed_proof(&[2, 4]).serialize::<ProofEnvelope>();
+///
+/// // Only the root and the leaves are needed on the client
+/// assert!(ProofEnvelope::verify_envelope::<Sha256>(&envelope, root, &[leaves[2], leaves[4]])?);
+/// # Ok(())
+/// # }
+/// ```
+pub struct ProofEnvelope {}
+
+impl ProofEnvelope {
+    /// Parses the envelope and verifies that the leaves, given in the same order as the indices
+    /// in the envelope, are included in the tree with the given root.
+    ///
+    /// The number of leaves and the tree properties are taken from the envelope, so they are
+    /// chosen by the prover. Use [`ProofEnvelope::verify_envelope_with`] if the verifier knows
+    /// them, or check them before trusting the result.
+    ///
+    /// Returns an error if the envelope can't be parsed, was produced with a different
+    /// [`Hasher`] or doesn't carry the indices.
+    pub fn verify_envelope<T: Hasher>(
+        bytes: &[u8],
+        root: T::Hash,
+        leaf_hashes: &[T::Hash],
+    ) -> Result<bool, Error> {
+        let proof = Self::deserialize_indexed::<T>(bytes)?;
+        Ok(proof.verify(root, leaf_hashes))
+    }
+
+    /// Same as [`ProofEnvelope::verify_envelope`], but also checks that the envelope was
+    /// produced for a tree with the expected properties and number of leaves.
+    ///
+    /// Returns an error if the envelope can't be parsed, was produced with a different
+    /// [`Hasher`], doesn't carry the indices or was produced for a different tree.
+    pub fn verify_envelope_with<T: Hasher>(
+        bytes: &[u8],
+        root: T::Hash,
+        leaf_hashes: &[T::Hash],
+        tree_properties: TreeProperties,
+        total_leaves_count: usize,
+    ) -> Result<bool, Error> {
+        let proof = Self::deserialize_indexed::<T>(bytes)?;
+        if proof.tree_properties() != tree_properties {
+            return Err(Error::invalid_proof_envelope(
+                "tree properties don't match the expected ones",
+            ));
+        }
+        if proof.total_leaves_count() != total_leaves_count {
+            return Err(Error::invalid_proof_envelope(
+                "number of leaves doesn't match the expected one",
+            ));
+        }
+        Ok(proof.verify(root, leaf_hashes))
+    }
+
+    fn write<T: Hasher>(
+        proof: &MerkleProof<T>,
+        indexed: Option<&IndexedMerkleProof<T>>,
+    ) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(ENVELOPE_MAGIC);
+        bytes.push(ENVELOPE_VERSION);
+        bytes.extend_from_slice(&T::hasher_id().to_le_bytes());
+        bytes.extend_from_slice(&(T::hash_size() as u16).to_le_bytes());
+
+        match indexed {
+            Some(indexed) => {
+                let tree_properties = indexed.tree_properties();
+                let mut flags = INDICES_FLAG;
+                if tree_properties.sorted_pair_enabled {
+                    flags |= SORTED_PAIR_FLAG;
+                }
+                if tree_properties.domain_separation.is_some() {
+                    flags |= DOMAIN_SEPARATION_FLAG;
+                }
+                bytes.push(flags);
+                if let Some(domain_separation) = tree_properties.domain_separation {
+                    bytes.push(domain_separation.leaf_prefix);
+                    bytes.push(domain_separation.node_prefix);
+                }
+                bytes.push(match tree_properties.odd_node_strategy {
+                    OddNodeStrategy::Promote => 0,
+                    OddNodeStrategy::Duplicate => 1,
+                    OddNodeStrategy::HashWithZero => 2,
+                    OddNodeStrategy::PadToPowerOfTwo => 3,
+                });
+
+                bytes.extend_from_slice(&(indexed.total_leaves_count() as u64).to_le_bytes());
+                bytes.extend_from_slice(&(indexed.leaf_indices().len() as u32).to_le_bytes());
+                for leaf_index in indexed.leaf_indices() {
+                    bytes.extend_from_slice(&(*leaf_index as u64).to_le_bytes());
+                }
+            }
+            None => bytes.push(0),
+        }
+
+        bytes.extend_from_slice(&(proof.proof_hashes().len() as u32).to_le_bytes());
+        for hash in proof.proof_hashes() {
+            let mut hash_bytes: Vec<u8> = (*hash).into();
+            bytes.append(&mut hash_bytes);
+        }
+
+        bytes
+    }
+
+    /// Parses the envelope into the proof and, if the envelope carries them, the indices, the
+    /// number of leaves and the tree properties
+    #[allow(clippy::type_complexity)]
+    fn read<T: Hasher>(
+        bytes: &[u8],
+    ) -> Result<(MerkleProof<T>, Option<(Vec<usize>, usize, TreeProperties)>), Error> {
+        let mut reader = EnvelopeReader { bytes };
+
+        if reader.read_bytes(ENVELOPE_MAGIC.len())? != ENVELOPE_MAGIC {
+            return Err(Error::invalid_proof_envelope("unknown magic bytes"));
+        }
+        if reader.read_u8()? != ENVELOPE_VERSION {
+            return Err(Error::invalid_proof_envelope("unsupported version"));
+        }
+        if reader.read_u32()? != T::hasher_id() {
+            return Err(Error::invalid_proof_envelope(
+                "envelope was produced with a different hasher",
+            ));
+        }
+        if usize::from(reader.read_u16()?) != T::hash_size() {
+            return Err(Error::invalid_proof_envelope("hash size doesn't match"));
+        }
+
+        let flags = reader.read_u8()?;
+        let known_flags = INDICES_FLAG | SORTED_PAIR_FLAG | DOMAIN_SEPARATION_FLAG;
+        let indices = if flags == 0 {
+            None
+        } else if flags & INDICES_FLAG != 0 && flags & !known_flags == 0 {
+            Some(reader.read_indices(flags)?)
+        } else {
+            return Err(Error::invalid_proof_envelope("unknown flags"));
+        };
+
+        let hashes_len = reader.read_len(T::hash_size())?;
+        let proof_hashes = (0..hashes_len)
+            .map(|_| {
+                let hash_bytes = reader.read_bytes(T::hash_size())?;
+                T::Hash::try_from(hash_bytes.to_vec())
+                    .map_err(|_| Error::vec_to_hash_conversion_error())
+            })
+            .collect::<Result<Vec<T::Hash>, Error>>()?;
+
+        if !reader.bytes.is_empty() {
+            return Err(Error::invalid_proof_envelope("unexpected trailing bytes"));
+        }
+
+        Ok((MerkleProof::new(proof_hashes), indices))
+    }
+}
+
+impl MerkleProofSerializer for ProofEnvelope {
+    fn serialize<T: Hasher>(proof: &MerkleProof<T>) -> Vec<u8> {
+        Self::write(proof, None)
+    }
+
+    fn deserialize<T: Hasher>(bytes: &[u8]) -> Result<MerkleProof<T>, Error> {
+        Ok(Self::read::<T>(bytes)?.0)
+    }
+}
+
+impl IndexedProofSerializer for ProofEnvelope {
+    fn serialize_indexed<T: Hasher>(proof: &IndexedMerkleProof<T>) -> Vec<u8> {
+        Self::write(proof.proof(), Some(proof))
+    }
+
+    fn deserialize_indexed<T: Hasher>(bytes: &[u8]) -> Result<IndexedMerkleProof<T>, Error> {
+        match Self::read::<T>(bytes)? {
+            (proof, Some((leaf_indices, total_leaves_count, tree_properties))) => {
+                let indexed_proof = IndexedMerkleProof::new(
+                    proof,
+                    leaf_indices,
+                    total_leaves_count,
+                    tree_properties,
+                );
+                indexed_proof.validate().map_err(|_| {
+                    Error::invalid_proof_envelope("proof doesn't match its leaf indices")
+                })?;
+                Ok(indexed_proof)
+            }
+            (_, None) => Err(Error::invalid_proof_envelope(
+                "envelope doesn't carry the indices",
+            )),
+        }
+    }
+}
+
+struct EnvelopeReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> EnvelopeReader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.bytes.len() < len {
+            return Err(Error::invalid_proof_envelope(
+                "unexpected end of the envelope",
+            ));
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, Error> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_le_bytes(
+            bytes.try_into().expect("4 bytes are read"),
+        ))
+    }
+
+    fn read_usize(&mut self) -> Result<usize, Error> {
+        let bytes = self.read_bytes(8)?;
+        usize::try_from(u64::from_le_bytes(
+            bytes.try_into().expect("8 bytes are read"),
+        ))
+        .map_err(|_| Error::invalid_proof_envelope("value doesn't fit into usize"))
+    }
+
+    /// Reads a `u32` length and checks that the envelope has at least `item_size` bytes for each
+    /// item, so corrupted lengths don't cause huge allocations
+    fn read_len(&mut self, item_size: usize) -> Result<usize, Error> {
+        let len = self.read_u32()? as usize;
+        match len.checked_mul(item_size) {
+            Some(size) if size <= self.bytes.len() => Ok(len),
+            _ => Err(Error::invalid_proof_envelope(
+                "length exceeds the envelope size",
+            )),
+        }
+    }
+
+    fn read_indices(&mut self, flags: u8) -> Result<(Vec<usize>, usize, TreeProperties), Error> {
+        let domain_separation = if flags & DOMAIN_SEPARATION_FLAG != 0 {
+            Some(DomainSeparation {
+                leaf_prefix: self.read_u8()?,
+                node_prefix: self.read_u8()?,
+            })
+        } else {
+            None
+        };
+        let odd_node_strategy = match self.read_u8()? {
+            0 => OddNodeStrategy::Promote,
+            1 => OddNodeStrategy::Duplicate,
+            2 => OddNodeStrategy::HashWithZero,
+            3 => OddNodeStrategy::PadToPowerOfTwo,
+            _ => return Err(Error::invalid_proof_envelope("unknown odd node strategy")),
+        };
+        let tree_properties = TreeProperties {
+            sorted_pair_enabled: flags & SORTED_PAIR_FLAG != 0,
+            domain_separation,
+            odd_node_strategy,
+        };
+
+        let total_leaves_count = self.read_usize()?;
+        let indices_len = self.read_len(8)?;
+        let leaf_indices = (0..indices_len)
+            .map(|_| self.read_usize())
+            .collect::<Result<Vec<usize>, Error>>()?;
+
+        Ok((leaf_indices, total_leaves_count, tree_properties))
+    }
+}
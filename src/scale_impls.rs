@@ -0,0 +1,147 @@
+//! SCALE `Encode`, `Decode` and `TypeInfo` implementations for the types that are generic over
+//! a [`Hasher`]. They require the [`Hasher::Hash`] to implement the same traits, which is the
+//! case for fixed-size byte arrays, so hashes are encoded without a length prefix. Indices and
+//! the number of leaves are encoded as compact integers.
+
+use crate::prelude::*;
+use crate::{utils::properties::TreeProperties, Hasher, IndexedMerkleProof, MerkleProof};
+use core::convert::TryFrom;
+use parity_scale_codec::{Compact, Decode, Encode, EncodeLike, Error, Input, Output};
+use scale_info::{build::Fields, meta_type, Path, Type, TypeInfo, TypeParameter};
+
+fn usize_to_compact(value: usize) -> Compact<u64> {
+    // usize is never wider than 64 bits on the supported targets
+    Compact(value as u64)
+}
+
+fn compact_to_usize(value: Compact<u64>) -> Result<usize, Error> {
+    usize::try_from(value.0).map_err(|_| Error::from("value doesn't fit into usize"))
+}
+
+/// Encoded as a vector of proof hashes
+impl<T: Hasher> Encode for MerkleProof<T>
+where
+    T::Hash: Encode,
+{
+    fn size_hint(&self) -> usize {
+        self.proof_hashes().size_hint()
+    }
+
+    fn encode_to<O: Output + ?Sized>(&self, dest: &mut O) {
+        self.proof_hashes().encode_to(dest)
+    }
+}
+
+impl<T: Hasher> EncodeLike for MerkleProof<T> where T::Hash: Encode {}
+
+impl<T: Hasher> Decode for MerkleProof<T>
+where
+    T::Hash: Decode,
+{
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        Ok(MerkleProof::new(Vec::<T::Hash>::decode(input)?))
+    }
+}
+
+impl<T: Hasher + 'static> TypeInfo for MerkleProof<T>
+where
+    T::Hash: TypeInfo + 'static,
+{
+    type Identity = Self;
+
+    fn type_info() -> Type {
+        Type::builder()
+            .path(Path::new("MerkleProof", module_path!()))
+            .type_params(vec![TypeParameter::new(
+                "Hash",
+                Some(meta_type::<T::Hash>()),
+            )])
+            .composite(Fields::named().field(|f| {
+                f.ty::<Vec<T::Hash>>()
+                    .name("proof_hashes")
+                    .type_name("Vec<Hash>")
+            }))
+    }
+}
+
+/// Encoded as the proof, followed by the leaf indices, the number of leaves and the tree
+/// properties
+impl<T: Hasher> Encode for IndexedMerkleProof<T>
+where
+    T::Hash: Encode,
+{
+    fn encode_to<O: Output + ?Sized>(&self, dest: &mut O) {
+        self.proof().encode_to(dest);
+        let leaf_indices: Vec<Compact<u64>> = self
+            .leaf_indices()
+            .iter()
+            .map(|leaf_index| usize_to_compact(*leaf_index))
+            .collect();
+        leaf_indices.encode_to(dest);
+        usize_to_compact(self.total_leaves_count()).encode_to(dest);
+        self.tree_properties().encode_to(dest);
+    }
+}
+
+impl<T: Hasher> EncodeLike for IndexedMerkleProof<T> where T::Hash: Encode {}
+
+impl<T: Hasher> Decode for IndexedMerkleProof<T>
+where
+    T::Hash: Decode,
+{
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let proof = MerkleProof::<T>::decode(input)?;
+        let leaf_indices = Vec::<Compact<u64>>::decode(input)?
+            .into_iter()
+            .map(compact_to_usize)
+            .collect::<Result<Vec<usize>, Error>>()?;
+        let total_leaves_count = compact_to_usize(Compact::<u64>::decode(input)?)?;
+        let tree_properties = TreeProperties::decode(input)?;
+
+        let indexed_proof =
+            IndexedMerkleProof::new(proof, leaf_indices, total_leaves_count, tree_properties);
+        indexed_proof
+            .validate()
+            .map_err(|_| Error::from("proof doesn't match its leaf indices"))?;
+        Ok(indexed_proof)
+    }
+}
+
+impl<T: Hasher + 'static> TypeInfo for IndexedMerkleProof<T>
+where
+    T::Hash: TypeInfo + 'static,
+{
+    type Identity = Self;
+
+    fn type_info() -> Type {
+        Type::builder()
+            .path(Path::new("IndexedMerkleProof", module_path!()))
+            .type_params(vec![TypeParameter::new(
+                "Hash",
+                Some(meta_type::<T::Hash>()),
+            )])
+            .composite(
+                Fields::named()
+                    .field(|f| {
+                        f.ty::<MerkleProof<T>>()
+                            .name("proof")
+                            .type_name("MerkleProof<Hash>")
+                    })
+                    .field(|f| {
+                        f.ty::<Vec<Compact<u64>>>()
+                            .name("leaf_indices")
+                            .type_name("Vec<u64>")
+                    })
+                    .field(|f| {
+                        f.compact::<u64>()
+                            .name("total_leaves_count")
+                            .type_name("u64")
+                    })
+                    .field(|f| {
+                        f.ty::<TreeProperties>()
+                            .name("tree_properties")
+                            .type_name("TreeProperties")
+                    }),
+            )
+    }
+}
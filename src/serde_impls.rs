@@ -0,0 +1,371 @@
+//! `Serialize` and `Deserialize` implementations for the types that are generic over a
+//! [`Hasher`]. Hashes are serialized as hex strings in human-readable formats, such as JSON, and
+//! as raw bytes in binary formats. [`StandardMerkleTree`] is serialized into the JSON dump
+//! format of `@openzeppelin/merkle-tree`.
+
+use crate::prelude::*;
+use crate::{
+    node_stores::MemoryNodeStore, utils, utils::properties::TreeProperties, AbiType, AbiValue,
+    Hasher, MerkleProof, MerkleTree, NodeStore, PartialTree, StandardMerkleTree,
+};
+use core::fmt::Formatter;
+use core::marker::PhantomData;
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    ser, Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// A hash of the [`Hasher`] that can be serialized
+struct SerdeHash<T: Hasher>(T::Hash);
+
+impl<T: Hasher> Serialize for SerdeHash<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&utils::collections::to_hex_string(&self.0))
+        } else {
+            let bytes: Vec<u8> = self.0.into();
+            serializer.serialize_bytes(&bytes)
+        }
+    }
+}
+
+impl<'de, T: Hasher> Deserialize<'de> for SerdeHash<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(HashVisitor(PhantomData))
+        } else {
+            deserializer.deserialize_bytes(HashVisitor(PhantomData))
+        }
+    }
+}
+
+struct HashVisitor<T: Hasher>(PhantomData<T>);
+
+impl<T: Hasher> HashVisitor<T> {
+    fn hash_from_bytes<E: de::Error>(bytes: Vec<u8>) -> Result<SerdeHash<T>, E> {
+        if bytes.len() != T::hash_size() {
+            return Err(E::invalid_length(
+                bytes.len(),
+                &format!("{} bytes", T::hash_size()).as_str(),
+            ));
+        }
+
+        T::Hash::try_from(bytes)
+            .map(SerdeHash)
+            .map_err(|_| E::custom("couldn't convert bytes into Hasher::Hash"))
+    }
+}
+
+impl<'de, T: Hasher> Visitor<'de> for HashVisitor<T> {
+    type Value = SerdeHash<T>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> core::fmt::Result {
+        write!(formatter, "a hash of {} bytes", T::hash_size())
+    }
+
+    fn visit_str<E: de::Error>(self, hex: &str) -> Result<Self::Value, E> {
+        let bytes = utils::collections::from_hex_string(hex)
+            .ok_or_else(|| E::invalid_value(de::Unexpected::Str(hex), &self))?;
+        Self::hash_from_bytes(bytes)
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+        Self::hash_from_bytes(bytes.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Self::Value, E> {
+        Self::hash_from_bytes(bytes)
+    }
+
+    // Some binary formats encode bytes as a sequence
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(T::hash_size());
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Self::hash_from_bytes(bytes)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "MerkleProof", bound = "")]
+struct MerkleProofRepr<T: Hasher> {
+    proof_hashes: Vec<SerdeHash<T>>,
+}
+
+/// Serialized as a struct with the `proof_hashes` field
+impl<T: Hasher> Serialize for MerkleProof<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        MerkleProofRepr::<T> {
+            proof_hashes: self.proof_hashes().iter().copied().map(SerdeHash).collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: Hasher> Deserialize<'de> for MerkleProof<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = MerkleProofRepr::<T>::deserialize(deserializer)?;
+        Ok(MerkleProof::new(
+            repr.proof_hashes.into_iter().map(|hash| hash.0).collect(),
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "PartialTree", bound = "")]
+struct PartialTreeRepr<T: Hasher> {
+    layers: Vec<Vec<(usize, SerdeHash<T>)>>,
+}
+
+/// Serialized as a struct with the `layers` field, where each layer is a list of
+/// `(index, hash)` pairs
+impl<T: Hasher, N: NodeStore<T>> Serialize for PartialTree<T, N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let store = self.store();
+        let layers = (0..store.layers_len())
+            .map(|layer_index| {
+                let layer = store.layer(layer_index).map_err(ser::Error::custom)?;
+                Ok(layer
+                    .into_iter()
+                    .map(|(node_index, node)| (node_index, SerdeHash(node)))
+                    .collect())
+            })
+            .collect::<Result<_, S::Error>>()?;
+
+        PartialTreeRepr::<T> { layers }.serialize(serializer)
+    }
+}
+
+impl<'de, T: Hasher> Deserialize<'de> for PartialTree<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = PartialTreeRepr::<T>::deserialize(deserializer)?;
+        let layers: Vec<Vec<(usize, T::Hash)>> = repr
+            .layers
+            .into_iter()
+            .map(|layer| {
+                layer
+                    .into_iter()
+                    .map(|(node_index, node)| (node_index, node.0))
+                    .collect()
+            })
+            .collect();
+
+        let is_sorted = layers
+            .iter()
+            .all(|layer| layer.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        if !is_sorted {
+            return Err(de::Error::custom(
+                "partial tree node indices must be unique and ascending",
+            ));
+        }
+
+        Ok(PartialTree::with_store(MemoryNodeStore::from_layers(
+            layers,
+        )))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "MerkleTree", bound = "")]
+struct MerkleTreeRepr<T: Hasher> {
+    tree_properties: TreeProperties,
+    layers: Vec<Vec<SerdeHash<T>>>,
+}
+
+/// Serialized as a struct with the `tree_properties` and `layers` fields. Only the committed
+/// state of the tree is serialized, the history and uncommitted changes are not. Use
+/// [`MerkleTree::to_snapshot_bytes`] to keep the history.
+impl<T: Hasher, N: NodeStore<T>> Serialize for MerkleTree<T, N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let store = self.store();
+        let layers = (0..store.layers_len())
+            .map(|layer_index| {
+                let layer = store.layer(layer_index).map_err(ser::Error::custom)?;
+                Ok(layer.into_iter().map(|(_, node)| SerdeHash(node)).collect())
+            })
+            .collect::<Result<_, S::Error>>()?;
+
+        MerkleTreeRepr::<T> {
+            tree_properties: self.tree_properties(),
+            layers,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// The layers are not rehashed, only their sizes are checked
+impl<'de, T: Hasher> Deserialize<'de> for MerkleTree<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = MerkleTreeRepr::<T>::deserialize(deserializer)?;
+        let layers = repr
+            .layers
+            .into_iter()
+            .map(|layer| layer.into_iter().map(|node| node.0).collect())
+            .collect();
+
+        MerkleTree::from_layers(layers, repr.tree_properties).map_err(de::Error::custom)
+    }
+}
+
+/// Format name of the dump produced by `@openzeppelin/merkle-tree`
+const STANDARD_TREE_FORMAT: &str = "standard-v1";
+
+/// An item of a value in the dump. Booleans are kept as JSON booleans, while numbers are read
+/// into strings and parsed according to the leaf encoding.
+enum DumpItem {
+    Bool(bool),
+    Text(String),
+}
+
+impl Serialize for DumpItem {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            DumpItem::Bool(value) => serializer.serialize_bool(*value),
+            DumpItem::Text(text) => serializer.serialize_str(text),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DumpItem {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(DumpItemVisitor)
+    }
+}
+
+struct DumpItemVisitor;
+
+impl<'de> Visitor<'de> for DumpItemVisitor {
+    type Value = DumpItem;
+
+    fn expecting(&self, formatter: &mut Formatter) -> core::fmt::Result {
+        write!(formatter, "a string, a number or a boolean")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(DumpItem::Bool(value))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(DumpItem::Text(value.to_string()))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(DumpItem::Text(value.to_string()))
+    }
+
+    fn visit_str<E: de::Error>(self, text: &str) -> Result<Self::Value, E> {
+        Ok(DumpItem::Text(text.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DumpValue {
+    value: Vec<DumpItem>,
+    tree_index: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "StandardMerkleTree", rename_all = "camelCase")]
+struct StandardMerkleTreeRepr {
+    format: String,
+    tree: Vec<String>,
+    values: Vec<DumpValue>,
+    leaf_encoding: Vec<String>,
+}
+
+/// Serialized as the `standard-v1` dump of `@openzeppelin/merkle-tree`, with the `format`,
+/// `tree`, `values` and `leafEncoding` fields
+impl Serialize for StandardMerkleTree {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let values = (0..self.len())
+            .filter_map(|value_index| {
+                Some((self.value(value_index)?, self.tree_index(value_index)?))
+            })
+            .map(|(value, tree_index)| DumpValue {
+                value: value
+                    .iter()
+                    .map(|item| match item {
+                        AbiValue::Bool(value) => DumpItem::Bool(*value),
+                        _ => DumpItem::Text(item.to_string()),
+                    })
+                    .collect(),
+                tree_index,
+            })
+            .collect();
+
+        StandardMerkleTreeRepr {
+            format: STANDARD_TREE_FORMAT.to_string(),
+            tree: self
+                .tree()
+                .iter()
+                .map(|node| format!("0x{}", utils::collections::to_hex_string(node)))
+                .collect(),
+            values,
+            leaf_encoding: self
+                .leaf_encoding()
+                .iter()
+                .map(|abi_type| abi_type.to_string())
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Every node is rehashed and every value is checked against its leaf
+impl<'de> Deserialize<'de> for StandardMerkleTree {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = StandardMerkleTreeRepr::deserialize(deserializer)?;
+        if repr.format != STANDARD_TREE_FORMAT {
+            return Err(de::Error::custom(format!(
+                "unknown dump format {}",
+                repr.format
+            )));
+        }
+
+        let tree = repr
+            .tree
+            .iter()
+            .map(|node| {
+                utils::collections::from_hex_string(node)
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or_else(|| de::Error::custom(format!("{} is not a valid node", node)))
+            })
+            .collect::<Result<Vec<[u8; 32]>, D::Error>>()?;
+        let leaf_encoding = repr
+            .leaf_encoding
+            .iter()
+            .map(|name| AbiType::parse(name))
+            .collect::<Result<Vec<AbiType>, _>>()
+            .map_err(de::Error::custom)?;
+
+        let values = repr
+            .values
+            .into_iter()
+            .map(|dump_value| {
+                if dump_value.value.len() != leaf_encoding.len() {
+                    return Err(de::Error::custom("value doesn't match the leaf encoding"));
+                }
+                let value = dump_value
+                    .value
+                    .iter()
+                    .zip(&leaf_encoding)
+                    .map(|(item, abi_type)| match (item, abi_type) {
+                        (DumpItem::Bool(value), AbiType::Bool) => Ok(AbiValue::Bool(*value)),
+                        (DumpItem::Text(text), _) => {
+                            AbiValue::parse(abi_type, text).map_err(de::Error::custom)
+                        }
+                        (DumpItem::Bool(_), _) => Err(de::Error::custom(format!(
+                            "boolean is not a valid {}",
+                            abi_type
+                        ))),
+                    })
+                    .collect::<Result<Vec<AbiValue>, D::Error>>()?;
+                Ok((value, dump_value.tree_index))
+            })
+            .collect::<Result<Vec<(Vec<AbiValue>, usize)>, D::Error>>()?;
+
+        StandardMerkleTree::from_dump(tree, values, leaf_encoding).map_err(de::Error::custom)
+    }
+}
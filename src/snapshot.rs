@@ -0,0 +1,213 @@
+use crate::prelude::*;
+use crate::{
+    utils::properties::{DomainSeparation, OddNodeStrategy, TreeProperties},
+    Error, Hasher, RetentionPolicy,
+};
+
+/// Identifies the snapshot format produced by [`MerkleTree::to_snapshot_bytes`]
+///
+/// [`MerkleTree::to_snapshot_bytes`]: crate::MerkleTree::to_snapshot_bytes
+pub(crate) const SNAPSHOT_MAGIC: &[u8; 4] = b"RSMT";
+pub(crate) const SNAPSHOT_VERSION: u8 = 1;
+/// Set in the snapshot flags if the snapshot carries the commit history
+pub(crate) const HISTORY_FLAG: u8 = 0b1;
+
+/// Encodes the snapshot fields. All integers are little-endian, lengths are encoded as `u64`.
+pub(crate) struct SnapshotWriter {
+    bytes: Vec<u8>,
+}
+
+impl SnapshotWriter {
+    pub fn new() -> Self {
+        Self { bytes: Vec::new() }
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn write_hash<T: Hasher>(&mut self, hash: &T::Hash) {
+        let mut hash_bytes: Vec<u8> = (*hash).into();
+        self.bytes.append(&mut hash_bytes);
+    }
+
+    pub fn write_tree_properties(&mut self, tree_properties: &TreeProperties) {
+        self.write_bool(tree_properties.sorted_pair_enabled);
+        match tree_properties.domain_separation {
+            Some(domain_separation) => {
+                self.write_bool(true);
+                self.write_u8(domain_separation.leaf_prefix);
+                self.write_u8(domain_separation.node_prefix);
+            }
+            None => self.write_bool(false),
+        }
+        self.write_u8(match tree_properties.odd_node_strategy {
+            OddNodeStrategy::Promote => 0,
+            OddNodeStrategy::Duplicate => 1,
+            OddNodeStrategy::HashWithZero => 2,
+            OddNodeStrategy::PadToPowerOfTwo => 3,
+        });
+    }
+
+    pub fn write_retention_policy(&mut self, retention_policy: RetentionPolicy) {
+        let (tag, value) = match retention_policy {
+            RetentionPolicy::KeepAll => (0, 0),
+            RetentionPolicy::KeepLast(commits_count) => (1, commits_count as u64),
+            RetentionPolicy::KeepAfter(id) => (2, id),
+        };
+        self.write_u8(tag);
+        self.write_u64(value);
+    }
+
+    /// Appends the hash of everything written so far and returns the bytes
+    pub fn finish<T: Hasher>(mut self) -> Vec<u8> {
+        let checksum = T::hash(&self.bytes);
+        self.write_hash::<T>(&checksum);
+        self.bytes
+    }
+}
+
+/// Decodes the fields written by [`SnapshotWriter`]
+pub(crate) struct SnapshotReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> SnapshotReader<'a> {
+    /// Verifies the checksum at the end of the bytes and creates a reader for the rest of them
+    pub fn new<T: Hasher>(bytes: &'a [u8]) -> Result<Self, Error> {
+        let body_len = bytes
+            .len()
+            .checked_sub(T::hash_size())
+            .ok_or_else(|| Error::invalid_snapshot("snapshot is too short"))?;
+        let (body, checksum) = bytes.split_at(body_len);
+        let expected_checksum: Vec<u8> = T::hash(body).into();
+
+        if checksum != &expected_checksum[..] {
+            return Err(Error::invalid_snapshot("checksum doesn't match"));
+        }
+
+        Ok(Self {
+            bytes: body,
+            offset: 0,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offset == self.bytes.len()
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| Error::invalid_snapshot("unexpected end of the snapshot"))?;
+        let bytes = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, Error> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_le_bytes(
+            bytes.try_into().expect("4 bytes are read"),
+        ))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, Error> {
+        let bytes = self.read_bytes(8)?;
+        Ok(u64::from_le_bytes(
+            bytes.try_into().expect("8 bytes are read"),
+        ))
+    }
+
+    pub fn read_usize(&mut self) -> Result<usize, Error> {
+        usize::try_from(self.read_u64()?)
+            .map_err(|_| Error::invalid_snapshot("length doesn't fit into usize"))
+    }
+
+    /// Reads a length and checks that the snapshot has at least `item_size` bytes for each item,
+    /// so corrupted lengths don't cause huge allocations
+    pub fn read_len(&mut self, item_size: usize) -> Result<usize, Error> {
+        let len = self.read_usize()?;
+        let remaining_len = self.bytes.len() - self.offset;
+        match len.checked_mul(item_size) {
+            Some(size) if size <= remaining_len => Ok(len),
+            _ => Err(Error::invalid_snapshot("length exceeds the snapshot size")),
+        }
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, Error> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::invalid_snapshot("boolean must be 0 or 1")),
+        }
+    }
+
+    pub fn read_hash<T: Hasher>(&mut self) -> Result<T::Hash, Error> {
+        let bytes = self.read_bytes(T::hash_size())?;
+        T::Hash::try_from(bytes.to_vec()).map_err(|_| Error::vec_to_hash_conversion_error())
+    }
+
+    pub fn read_tree_properties(&mut self) -> Result<TreeProperties, Error> {
+        let sorted_pair_enabled = self.read_bool()?;
+        let domain_separation = if self.read_bool()? {
+            Some(DomainSeparation {
+                leaf_prefix: self.read_u8()?,
+                node_prefix: self.read_u8()?,
+            })
+        } else {
+            None
+        };
+        let odd_node_strategy = match self.read_u8()? {
+            0 => OddNodeStrategy::Promote,
+            1 => OddNodeStrategy::Duplicate,
+            2 => OddNodeStrategy::HashWithZero,
+            3 => OddNodeStrategy::PadToPowerOfTwo,
+            _ => return Err(Error::invalid_snapshot("unknown odd node strategy")),
+        };
+
+        Ok(TreeProperties {
+            sorted_pair_enabled,
+            domain_separation,
+            odd_node_strategy,
+        })
+    }
+
+    pub fn read_retention_policy(&mut self) -> Result<RetentionPolicy, Error> {
+        let tag = self.read_u8()?;
+        let value = self.read_u64()?;
+        match tag {
+            0 => Ok(RetentionPolicy::KeepAll),
+            1 => Ok(RetentionPolicy::KeepLast(value as usize)),
+            2 => Ok(RetentionPolicy::KeepAfter(value)),
+            _ => Err(Error::invalid_snapshot("unknown retention policy")),
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use crate::prelude::*;
+use crate::{error::Error, utils, Hasher};
+use alloc::collections::BTreeMap;
+use core::convert::TryFrom;
+
+/// Size of the key space of the [`SparseMerkleTree`] in bits, which is also the depth of the tree
+pub const SPARSE_TREE_DEPTH: usize = 256;
+
+/// Key type of the [`SparseMerkleTree`]. Usually a hash of the actual key.
+pub type SparseKey = [u8; 32];
+
+/// Returns the bit of the key at the given depth, counting from the root. `true` means that the
+/// path goes to the right child.
+fn key_bit(key: &SparseKey, depth: usize) -> bool {
+    (key[depth / 8] >> (7 - depth % 8)) & 1 == 1
+}
+
+/// Keeps only the first `SPARSE_TREE_DEPTH - height` bits of the key, which is the path from
+/// the root to the node at the given height
+fn key_prefix(key: &SparseKey, height: usize) -> SparseKey {
+    let prefix_len = SPARSE_TREE_DEPTH - height;
+    let (full_bytes, partial_bits) = (prefix_len / 8, prefix_len % 8);
+    let mut prefix = [0; 32];
+    prefix[..full_bytes].copy_from_slice(&key[..full_bytes]);
+    if partial_bits > 0 {
+        prefix[full_bytes] = key[full_bytes] & (0xff << (8 - partial_bits));
+    }
+    prefix
+}
+
+/// [`SparseMerkleTree`] is a key-value Merkle tree with a fixed 256-bit key space.
+///
+/// Every possible key has its own leaf, and leaves that were never set contain an empty
+/// value, which is a hash consisting of zero bytes. Since the hashes of empty subtrees are
+/// precomputed, only non-empty nodes are stored. This allows the tree to produce both
+/// inclusion proofs, showing that a key maps to a value, and exclusion proofs, showing that a key
+/// is absent from the tree.
+///
+/// Unlike [`MerkleTree`], nodes are always hashed in their positional order, so
+/// [`TreeProperties`] do not apply to this tree.
+///
+/// ## Examples
+///
+/// ```
+/// # use rs_merkle::{SparseMerkleTree, algorithms::Sha256, Hasher};
+/// let mut tree = SparseMerkleTree::<Sha256>::new();
+///
+/// let key = Sha256::hash("key".as_bytes());
+/// let absent_key = Sha256::hash("absent key".as_bytes());
+/// let value = Sha256::hash("value".as_bytes());
+///
+/// tree.insert(key, value);
+/// let root = tree.root();
+///
+/// let empty_hashes = tree.empty_hashes();
+/// assert!(tree.proof(&key).verify_inclusion(root, &key, value, empty_hashes));
+/// assert!(tree.proof(&absent_key).verify_exclusion(root, &absent_key, empty_hashes));
+/// ```
+///
+/// [`MerkleTree`]: crate::MerkleTree
+/// [`TreeProperties`]: crate::utils::properties::TreeProperties
+#[derive(Clone)]
+pub struct SparseMerkleTree<T: Hasher> {
+    // Non-empty nodes, keyed by the node height and the path from the root to the node
+    nodes: BTreeMap<(usize, SparseKey), T::Hash>,
+    leaves: BTreeMap<SparseKey, T::Hash>,
+    empty_hashes: Vec<T::Hash>,
+}
+
+impl<T: Hasher> Default for SparseMerkleTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Hasher> SparseMerkleTree<T> {
+    /// Creates an empty sparse Merkle tree and precomputes empty subtree hashes
+    pub fn new() -> Self {
+        Self {
+            nodes: BTreeMap::new(),
+            leaves: BTreeMap::new(),
+            empty_hashes: Self::empty_subtree_hashes(),
+        }
+    }
+
+    /// Precomputes the hashes of empty subtrees of every height, from the empty leaf up to the
+    /// root of an empty tree. Verifiers that don't have the tree at hand can compute them once
+    /// and pass them to [`SparseMerkleProof::verify_inclusion`] for every proof.
+    pub fn empty_subtree_hashes() -> Vec<T::Hash> {
+        let mut hashes = Vec::with_capacity(SPARSE_TREE_DEPTH + 1);
+        // Empty leaf is a hash consisting of zero bytes
+        let mut current = utils::collections::zero_hash::<T>();
+        hashes.push(current);
+
+        for _ in 0..SPARSE_TREE_DEPTH {
+            current = T::concat_and_hash(&current, Some(&current));
+            hashes.push(current);
+        }
+
+        hashes
+    }
+
+    /// Sets the leaf value for the given key and recalculates the path to the root.
+    /// Inserting an empty value, i.e. a hash consisting of zero bytes, removes the key.
+    pub fn insert(&mut self, key: SparseKey, leaf: T::Hash) -> &mut Self {
+        if leaf == self.empty_hashes[0] {
+            self.leaves.remove(&key);
+        } else {
+            self.leaves.insert(key, leaf);
+        }
+
+        let mut current = leaf;
+        for height in 0..SPARSE_TREE_DEPTH {
+            self.set_node(height, &key, current);
+
+            let depth = SPARSE_TREE_DEPTH - height - 1;
+            let sibling = self.sibling(height, &key);
+            current = if key_bit(&key, depth) {
+                T::concat_and_hash(&sibling, Some(&current))
+            } else {
+                T::concat_and_hash(&current, Some(&sibling))
+            };
+        }
+        self.set_node(SPARSE_TREE_DEPTH, &key, current);
+
+        self
+    }
+
+    /// Removes the key from the tree, returning its previous leaf value if there was one
+    pub fn remove(&mut self, key: &SparseKey) -> Option<T::Hash> {
+        let previous = self.leaves.get(key).cloned()?;
+        self.insert(*key, self.empty_hashes[0]);
+        Some(previous)
+    }
+
+    /// Returns the leaf value for the given key, or `None` if the key is absent
+    pub fn get(&self, key: &SparseKey) -> Option<T::Hash> {
+        self.leaves.get(key).cloned()
+    }
+
+    /// Returns `true` if the tree contains a non-empty leaf for the given key
+    pub fn contains(&self, key: &SparseKey) -> bool {
+        self.leaves.contains_key(key)
+    }
+
+    /// Returns the number of non-empty leaves
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Returns `true` if the tree doesn't contain any non-empty leaves
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Returns the root of the tree. The root of an empty tree is the hash of the empty subtree
+    /// of the full height.
+    pub fn root(&self) -> T::Hash {
+        self.node(SPARSE_TREE_DEPTH, &[0u8; 32])
+    }
+
+    /// Returns the root of the tree as a hex string
+    pub fn root_hex(&self) -> String {
+        utils::collections::to_hex_string(&self.root())
+    }
+
+    /// Returns the precomputed hashes of empty subtrees, starting from the empty leaf and
+    /// ending with the root of the empty tree
+    pub fn empty_hashes(&self) -> &[T::Hash] {
+        &self.empty_hashes
+    }
+
+    /// Creates a proof for the given key. If the key is present in the tree, the proof is an
+    /// inclusion proof, otherwise it can be used to prove that the key is absent.
+    pub fn proof(&self, key: &SparseKey) -> SparseMerkleProof<T> {
+        let mut bitmap = [0u8; 32];
+        let mut sibling_hashes = Vec::new();
+
+        for height in 0..SPARSE_TREE_DEPTH {
+            let sibling = self.sibling(height, key);
+            if sibling != self.empty_hashes[height] {
+                bitmap[height / 8] |= 1 << (height % 8);
+                sibling_hashes.push(sibling);
+            }
+        }
+
+        SparseMerkleProof::new(bitmap, sibling_hashes)
+    }
+
+    fn node(&self, height: usize, key: &SparseKey) -> T::Hash {
+        self.nodes
+            .get(&(height, key_prefix(key, height)))
+            .cloned()
+            .unwrap_or(self.empty_hashes[height])
+    }
+
+    fn sibling(&self, height: usize, key: &SparseKey) -> T::Hash {
+        let mut sibling_key = key_prefix(key, height);
+        let depth = SPARSE_TREE_DEPTH - height - 1;
+        sibling_key[depth / 8] ^= 1 << (7 - depth % 8);
+        self.node(height, &sibling_key)
+    }
+
+    fn set_node(&mut self, height: usize, key: &SparseKey, hash: T::Hash) {
+        let node_key = (height, key_prefix(key, height));
+        if hash == self.empty_hashes[height] {
+            self.nodes.remove(&node_key);
+        } else {
+            self.nodes.insert(node_key, hash);
+        }
+    }
+}
+
+/// [`SparseMerkleProof`] is a proof produced by [`SparseMerkleTree::proof`].
+///
+/// Only non-empty sibling hashes are included in the proof. The bitmap tells which of the
+/// siblings, from the leaf level to the root, are non-empty: bit `i` is set if the sibling at
+/// height `i` is present in the list of hashes. Missing siblings are replaced by the
+/// precomputed empty subtree hashes during verification, which are passed in by the caller so
+/// that they are computed only once, see [`SparseMerkleTree::empty_hashes`].
+#[derive(Clone)]
+pub struct SparseMerkleProof<T: Hasher> {
+    bitmap: [u8; 32],
+    sibling_hashes: Vec<T::Hash>,
+}
+
+impl<T: Hasher> SparseMerkleProof<T> {
+    pub fn new(bitmap: [u8; 32], sibling_hashes: Vec<T::Hash>) -> Self {
+        Self {
+            bitmap,
+            sibling_hashes,
+        }
+    }
+
+    /// Returns the bitmap of non-empty siblings
+    pub fn bitmap(&self) -> &[u8; 32] {
+        &self.bitmap
+    }
+
+    /// Returns non-empty sibling hashes, from the leaf level to the root
+    pub fn sibling_hashes(&self) -> &[T::Hash] {
+        &self.sibling_hashes
+    }
+
+    /// Calculates the root of the tree in which the key maps to the given leaf. `None` as a leaf
+    /// means the key is absent from the tree. `empty_hashes` are the hashes of empty subtrees,
+    /// as returned by [`SparseMerkleTree::empty_hashes`].
+    pub fn root(
+        &self,
+        key: &SparseKey,
+        leaf: Option<T::Hash>,
+        empty_hashes: &[T::Hash],
+    ) -> Result<T::Hash, Error> {
+        if empty_hashes.len() <= SPARSE_TREE_DEPTH {
+            return Err(Error::not_enough_hashes_to_calculate_root());
+        }
+        let mut sibling_hashes = self.sibling_hashes.iter();
+        let mut current = leaf.unwrap_or(empty_hashes[0]);
+
+        for (height, empty_hash) in empty_hashes.iter().enumerate().take(SPARSE_TREE_DEPTH) {
+            let sibling = if self.bitmap[height / 8] & (1 << (height % 8)) != 0 {
+                *sibling_hashes
+                    .next()
+                    .ok_or_else(Error::not_enough_hashes_to_calculate_root)?
+            } else {
+                *empty_hash
+            };
+
+            let depth = SPARSE_TREE_DEPTH - height - 1;
+            current = if key_bit(key, depth) {
+                T::concat_and_hash(&sibling, Some(&current))
+            } else {
+                T::concat_and_hash(&current, Some(&sibling))
+            };
+        }
+
+        if sibling_hashes.next().is_some() {
+            return Err(Error::too_many_proof_hashes());
+        }
+
+        Ok(current)
+    }
+
+    /// Verifies that the key maps to the given leaf in the tree with the given root
+    pub fn verify_inclusion(
+        &self,
+        root: T::Hash,
+        key: &SparseKey,
+        leaf: T::Hash,
+        empty_hashes: &[T::Hash],
+    ) -> bool {
+        match self.root(key, Some(leaf), empty_hashes) {
+            Ok(extracted_root) => extracted_root == root,
+            Err(_) => false,
+        }
+    }
+
+    /// Verifies that the key is absent from the tree with the given root
+    pub fn verify_exclusion(
+        &self,
+        root: T::Hash,
+        key: &SparseKey,
+        empty_hashes: &[T::Hash],
+    ) -> bool {
+        match self.root(key, None, empty_hashes) {
+            Ok(extracted_root) => extracted_root == root,
+            Err(_) => false,
+        }
+    }
+
+    /// Serializes the proof as the 32 byte bitmap followed by sibling hashes from the leaf
+    /// level to the root
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.bitmap.to_vec();
+        for hash in &self.sibling_hashes {
+            let mut hash_bytes: Vec<u8> = (*hash).into();
+            bytes.append(&mut hash_bytes);
+        }
+        bytes
+    }
+
+    /// Parses the proof serialized with [`SparseMerkleProof::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let hash_size = T::hash_size();
+        if bytes.len() < 32 || !(bytes.len() - 32).is_multiple_of(hash_size) {
+            return Err(Error::wrong_proof_size(bytes.len(), hash_size));
+        }
+
+        let mut bitmap = [0u8; 32];
+        bitmap.copy_from_slice(&bytes[..32]);
+
+        let sibling_hashes = bytes[32..]
+            .chunks(hash_size)
+            .map(|chunk| {
+                T::Hash::try_from(chunk.to_vec()).map_err(|_| Error::vec_to_hash_conversion_error())
+            })
+            .collect::<Result<Vec<T::Hash>, Error>>()?;
+
+        Ok(Self::new(bitmap, sibling_hashes))
+    }
+}
@@ -0,0 +1,537 @@
+//! SSZ merkleization and generalized index proofs, as used by Ethereum's beacon chain.
+//!
+//! SSZ splits an object into 32-byte chunks and merkleizes them with [`Sha256`], padding the
+//! chunks with zero chunks up to the next power of two of their number, or of the limit of a
+//! list. Lists also mix their length into the root with [`mix_in_length`]. Padding is never
+//! materialized: a node without a right sibling is hashed with the root of an empty subtree of
+//! the same height, see [`zero_hash`], so lists with huge limits are cheap to merkleize.
+//!
+//! Nodes are addressed by generalized indices: the root is `1`, and the children of the node
+//! `i` are `2 * i` and `2 * i + 1`. [`SszTree`] builds the tree, [`SszProof`] is a single
+//! branch, the form the light client protocol uses for beacon state fields, and
+//! [`SszMultiProof`] proves several nodes at once with the helper nodes of
+//! [`helper_indices`].
+//!
+//! ## Examples
+//!
+//! ```
+//! # use rs_merkle::ssz::{self, SszTree};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! // A `List[uint64, 64]` with three elements takes one chunk out of the limit of 16
+//! let values: Vec<u8> = [1u64, 2, 3].iter().flat_map(|value| value.to_le_bytes()).collect();
+//! let tree = SszTree::new(&ssz::pack(&values), Some(16))?.mix_in_length(3);
+//! assert_eq!(
+//!     tree.root(),
+//!     ssz::mix_in_length(&ssz::merkleize(&ssz::pack(&values), Some(16))?, 3)
+//! );
+//!
+//! // Data chunks are under the node 2 and the length is the node 3
+//! let gindex = tree.chunk_gindex(0);
+//! assert_eq!(gindex, 32);
+//! let proof = tree.proof(gindex)?;
+//! assert!(proof.verify(&tree.root(), &ssz::pack(&values)[0]));
+//! # Ok(())
+//! # }
+//! ```
+use crate::prelude::*;
+use crate::{
+    algorithms::Sha256, error::Error, node_stores::MemoryNodeStore,
+    utils::properties::TreeProperties, PartialTree,
+};
+use alloc::collections::BTreeSet;
+use core::cmp::Ordering;
+
+/// Index of a node in a binary merkle tree: the root is `1`, and the children of the node `i`
+/// are `2 * i` and `2 * i + 1`
+pub type GeneralizedIndex = u64;
+
+/// Size of an SSZ chunk in bytes
+pub const BYTES_PER_CHUNK: usize = 32;
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    TreeProperties::SSZ.concat_and_hash::<Sha256>(left, Some(right))
+}
+
+/// Returns the root of a subtree of the given depth whose chunks are all zero
+pub fn zero_hash(depth: usize) -> [u8; 32] {
+    let mut zero_hash = [0u8; 32];
+    for _ in 0..depth {
+        zero_hash = hash_pair(&zero_hash, &zero_hash);
+    }
+    zero_hash
+}
+
+/// Packs serialized basic values into chunks, padding the last chunk with zero bytes
+pub fn pack(bytes: &[u8]) -> Vec<[u8; 32]> {
+    bytes
+        .chunks(BYTES_PER_CHUNK)
+        .map(|bytes| {
+            let mut chunk = [0u8; 32];
+            chunk[..bytes.len()].copy_from_slice(bytes);
+            chunk
+        })
+        .collect()
+}
+
+/// Calculates the root of the chunks padded with zero chunks up to the next power of two of
+/// the limit, or of the number of chunks if there is no limit, the way SSZ's `merkleize` does.
+///
+/// ## Errors
+///
+/// Returns an error if there are more chunks than the limit.
+pub fn merkleize(chunks: &[[u8; 32]], limit: Option<usize>) -> Result<[u8; 32], Error> {
+    Ok(SszTree::new(chunks, limit)?.root())
+}
+
+/// Hashes the root with the length of a list, `hash(root ++ uint256(length))`
+pub fn mix_in_length(root: &[u8; 32], length: usize) -> [u8; 32] {
+    let mut length_chunk = [0u8; 32];
+    length_chunk[..8].copy_from_slice(&(length as u64).to_le_bytes());
+    hash_pair(root, &length_chunk)
+}
+
+/// Returns the number of layers between the node and the root, `get_generalized_index_length`
+/// in the consensus specs
+pub fn generalized_index_depth(gindex: GeneralizedIndex) -> usize {
+    (63 - gindex.leading_zeros()) as usize
+}
+
+/// Combines the generalized indices of nested objects into the index in the outermost one:
+/// the first index points to a node of the root object, the second one to a node of the
+/// subtree rooted at that node, and so on.
+///
+/// ## Errors
+///
+/// Returns an error if an index is zero or the result doesn't fit into `u64`.
+pub fn concat_generalized_indices(
+    gindices: &[GeneralizedIndex],
+) -> Result<GeneralizedIndex, Error> {
+    let mut concatenated: GeneralizedIndex = 1;
+    for gindex in gindices {
+        if *gindex == 0 {
+            return Err(Error::invalid_generalized_index(*gindex));
+        }
+        let depth = generalized_index_depth(*gindex);
+        if generalized_index_depth(concatenated) + depth > 63 {
+            return Err(Error::invalid_generalized_index(*gindex));
+        }
+        concatenated = (concatenated << depth) | (gindex ^ (1 << depth));
+    }
+    Ok(concatenated)
+}
+
+/// Returns the indices of the nodes that a multiproof of the given nodes contains, in the
+/// descending order, like `get_helper_indices` in the consensus specs.
+///
+/// ## Errors
+///
+/// Returns an error if an index is zero, repeated, or is an ancestor of another index, since
+/// such a node is calculated rather than proven.
+pub fn helper_indices(gindices: &[GeneralizedIndex]) -> Result<Vec<GeneralizedIndex>, Error> {
+    let mut proven = BTreeSet::new();
+    for gindex in gindices {
+        if *gindex == 0 || !proven.insert(*gindex) {
+            return Err(Error::invalid_generalized_index(*gindex));
+        }
+    }
+
+    let mut path = BTreeSet::new();
+    let mut branch = BTreeSet::new();
+    for gindex in gindices {
+        let mut index = *gindex;
+        while index > 1 {
+            if index != *gindex && proven.contains(&index) {
+                return Err(Error::invalid_generalized_index(index));
+            }
+            path.insert(index);
+            branch.insert(index ^ 1);
+            index /= 2;
+        }
+        if *gindex != 1 && proven.contains(&1) {
+            return Err(Error::invalid_generalized_index(1));
+        }
+    }
+
+    let mut helper_indices: Vec<GeneralizedIndex> = branch.difference(&path).copied().collect();
+    helper_indices.reverse();
+    Ok(helper_indices)
+}
+
+/// Calculates the root from the node and its branch, ordered from the sibling of the node up
+/// to the child of the root. Bits of the index tell on which side the node is on each layer.
+fn branch_root(leaf: &[u8; 32], branch: &[[u8; 32]], index: u64) -> [u8; 32] {
+    branch
+        .iter()
+        .enumerate()
+        .fold(*leaf, |node, (layer_index, sibling)| {
+            if index.checked_shr(layer_index as u32).unwrap_or(0) & 1 == 1 {
+                hash_pair(sibling, &node)
+            } else {
+                hash_pair(&node, sibling)
+            }
+        })
+}
+
+/// Checks the branch of the leaf with the given index in a subtree of the given depth, like
+/// `is_valid_merkle_branch` in the consensus specs. Light client updates carry branches in
+/// this form, with the depth and the index derived from a generalized index.
+pub fn is_valid_merkle_branch(
+    leaf: &[u8; 32],
+    branch: &[[u8; 32]],
+    depth: usize,
+    index: u64,
+    root: &[u8; 32],
+) -> bool {
+    match branch.get(..depth) {
+        Some(branch) => branch_root(leaf, branch, index) == *root,
+        None => false,
+    }
+}
+
+/// [`SszTree`] is the merkle tree of SSZ chunks, padded up to a limit with empty subtrees,
+/// optionally with the length of a list mixed into the root.
+///
+/// Only the nodes calculated from the chunks are stored in the underlying [`PartialTree`];
+/// the nodes of the padding are the roots of empty subtrees and are calculated on demand.
+#[derive(Clone)]
+pub struct SszTree {
+    tree: PartialTree<Sha256>,
+    length: Option<usize>,
+    root: [u8; 32],
+}
+
+impl SszTree {
+    /// Merkleizes the chunks padded up to the next power of two of the limit, or of the number
+    /// of chunks if there is no limit.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if there are more chunks than the limit, or if the limit is so large that
+    /// generalized indices of the chunks wouldn't fit into a `u64`.
+    pub fn new(chunks: &[[u8; 32]], limit: Option<usize>) -> Result<Self, Error> {
+        let limit = limit.unwrap_or(chunks.len());
+        if chunks.len() > limit {
+            return Err(Error::invalid_tree_size(limit, chunks.len()));
+        }
+        // Generalized indices are at most 63 layers deep, one of them is kept for the length
+        let depth = limit
+            .checked_next_power_of_two()
+            .map(|size| size.trailing_zeros() as usize)
+            .filter(|depth| *depth < 63)
+            .ok_or_else(|| Error::invalid_tree_size(limit, chunks.len()))?;
+
+        let mut layers = Vec::with_capacity(depth + 1);
+        let mut layer: Vec<(usize, [u8; 32])> = chunks.iter().copied().enumerate().collect();
+        let mut empty_subtree = [0u8; 32];
+        for _ in 0..depth {
+            let parent_layer = layer
+                .chunks(2)
+                .enumerate()
+                .map(|(parent_index, pair)| {
+                    let right = pair.get(1).map_or(empty_subtree, |(_, node)| *node);
+                    (parent_index, hash_pair(&pair[0].1, &right))
+                })
+                .collect();
+            layers.push(core::mem::replace(&mut layer, parent_layer));
+            empty_subtree = hash_pair(&empty_subtree, &empty_subtree);
+        }
+        if layer.is_empty() {
+            layer.push((0, empty_subtree));
+        }
+        let root = layer[0].1;
+        layers.push(layer);
+
+        Ok(Self {
+            tree: PartialTree::with_store(MemoryNodeStore::from_layers(layers)),
+            length: None,
+            root,
+        })
+    }
+
+    /// Mixes the length of a list into the root. The merkleized chunks become the subtree at
+    /// the generalized index `2` and the length is the node `3`. Replaces the length if it
+    /// was already mixed in.
+    pub fn mix_in_length(mut self, length: usize) -> Self {
+        self.length = Some(length);
+        self.root = mix_in_length(&self.chunks_root(), length);
+        self
+    }
+
+    /// Returns the `hash_tree_root` of the object
+    pub fn root(&self) -> [u8; 32] {
+        self.root
+    }
+
+    /// Returns the root of the merkleized chunks, before the length is mixed in
+    pub fn chunks_root(&self) -> [u8; 32] {
+        self.tree.root().unwrap_or_default()
+    }
+
+    /// Returns the length mixed into the root, if any
+    pub fn length(&self) -> Option<usize> {
+        self.length
+    }
+
+    /// Returns the number of layers between the chunks and the root
+    pub fn depth(&self) -> usize {
+        self.tree.depth() + usize::from(self.length.is_some())
+    }
+
+    /// Returns the nodes calculated from the chunks, without the padding and the length
+    pub fn tree(&self) -> &PartialTree<Sha256> {
+        &self.tree
+    }
+
+    /// Returns the generalized index of the chunk with the given index
+    pub fn chunk_gindex(&self, index: usize) -> GeneralizedIndex {
+        (1 << self.depth()) + index as GeneralizedIndex
+    }
+
+    /// Returns the node with the given generalized index, or `None` if the index is zero or
+    /// below the chunks
+    pub fn get(&self, gindex: GeneralizedIndex) -> Option<[u8; 32]> {
+        if gindex == 0 {
+            return None;
+        }
+        let depth = generalized_index_depth(gindex);
+
+        let chunks_gindex = match self.length {
+            Some(length) => match (gindex, depth) {
+                (1, _) => return Some(self.root),
+                (3, _) => {
+                    let mut length_chunk = [0u8; 32];
+                    length_chunk[..8].copy_from_slice(&(length as u64).to_le_bytes());
+                    return Some(length_chunk);
+                }
+                // Below the length chunk
+                _ if (gindex >> (depth - 1)) == 3 => return None,
+                // Removes the bit of the node 2, moving the index into the chunks subtree
+                _ => (gindex ^ (1 << depth)) | (1 << (depth - 1)),
+            },
+            None => gindex,
+        };
+
+        let depth = generalized_index_depth(chunks_gindex);
+        let layer_index = self.tree.depth().checked_sub(depth)?;
+        let node_index = (chunks_gindex ^ (1 << depth)) as usize;
+        Some(
+            self.tree
+                .get(layer_index, node_index)
+                .unwrap_or_else(|| zero_hash(layer_index)),
+        )
+    }
+
+    /// Returns the branch of the node with the given generalized index.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the index doesn't point to a node of the tree.
+    pub fn proof(&self, gindex: GeneralizedIndex) -> Result<SszProof, Error> {
+        self.get(gindex)
+            .ok_or_else(|| Error::invalid_generalized_index(gindex))?;
+
+        let mut branch = Vec::with_capacity(generalized_index_depth(gindex));
+        let mut index = gindex;
+        while index > 1 {
+            branch.push(
+                self.get(index ^ 1)
+                    .ok_or_else(|| Error::invalid_generalized_index(index ^ 1))?,
+            );
+            index /= 2;
+        }
+        Ok(SszProof::new(gindex, branch))
+    }
+
+    /// Returns a multiproof of the nodes with the given generalized indices.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if an index doesn't point to a node of the tree, is repeated, or is
+    /// an ancestor of another index.
+    pub fn multiproof(&self, gindices: &[GeneralizedIndex]) -> Result<SszMultiProof, Error> {
+        for gindex in gindices {
+            self.get(*gindex)
+                .ok_or_else(|| Error::invalid_generalized_index(*gindex))?;
+        }
+        let helper_hashes = helper_indices(gindices)?
+            .into_iter()
+            .map(|gindex| {
+                self.get(gindex)
+                    .ok_or_else(|| Error::invalid_generalized_index(gindex))
+            })
+            .collect::<Result<Vec<[u8; 32]>, Error>>()?;
+        Ok(SszMultiProof::new(gindices.to_vec(), helper_hashes))
+    }
+}
+
+/// [`SszProof`] is the branch of a single node: the siblings of the node and of its ancestors,
+/// from the bottom up.
+///
+/// ## Examples
+///
+/// ```
+/// # use rs_merkle::ssz::{self, SszTree};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let chunks: Vec<[u8; 32]> = (0u8..5).map(|i| [i; 32]).collect();
+/// let tree = SszTree::new(&chunks, None)?;
+///
+/// // Five chunks are padded to eight, so the chunk 4 is the node 12
+/// let proof = tree.proof(12)?;
+/// assert_eq!(proof.branch()[0], [0u8; 32]);
+/// assert!(proof.verify(&tree.root(), &chunks[4]));
+/// assert!(ssz::is_valid_merkle_branch(&chunks[4], proof.branch(), 3, 4, &tree.root()));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SszProof {
+    gindex: GeneralizedIndex,
+    branch: Vec<[u8; 32]>,
+}
+
+impl SszProof {
+    pub fn new(gindex: GeneralizedIndex, branch: Vec<[u8; 32]>) -> Self {
+        Self { gindex, branch }
+    }
+
+    /// Returns the generalized index of the proven node
+    pub fn gindex(&self) -> GeneralizedIndex {
+        self.gindex
+    }
+
+    /// Returns the siblings from the bottom up
+    pub fn branch(&self) -> &[[u8; 32]] {
+        &self.branch
+    }
+
+    /// Calculates the root from the proven node.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the generalized index is zero or the length of the branch doesn't
+    /// match its depth.
+    pub fn root(&self, leaf: &[u8; 32]) -> Result<[u8; 32], Error> {
+        if self.gindex == 0 {
+            return Err(Error::invalid_generalized_index(self.gindex));
+        }
+        match self.branch.len().cmp(&generalized_index_depth(self.gindex)) {
+            Ordering::Less => Err(Error::not_enough_hashes_to_calculate_root()),
+            Ordering::Greater => Err(Error::too_many_proof_hashes()),
+            Ordering::Equal => Ok(branch_root(leaf, &self.branch, self.gindex)),
+        }
+    }
+
+    /// Verifies that the node is in the tree with the given root at the generalized index
+    pub fn verify(&self, root: &[u8; 32], leaf: &[u8; 32]) -> bool {
+        match self.root(leaf) {
+            Ok(extracted_root) => extracted_root == *root,
+            Err(_) => false,
+        }
+    }
+}
+
+/// [`SszMultiProof`] proves several nodes of a tree at once. It contains the nodes of
+/// [`helper_indices`], in the descending order of their generalized indices, like
+/// `calculate_multi_merkle_root` in the consensus specs expects them.
+///
+/// ## Examples
+///
+/// ```
+/// # use rs_merkle::ssz::SszTree;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let chunks: Vec<[u8; 32]> = (0u8..8).map(|i| [i; 32]).collect();
+/// let tree = SszTree::new(&chunks, None)?;
+///
+/// // The chunk 1 and the root of the subtree with the chunks 4 to 7
+/// let proof = tree.multiproof(&[9, 3])?;
+/// assert_eq!(proof.helper_hashes().len(), 2);
+///
+/// let nodes = [chunks[1], tree.get(3).ok_or("no node")?];
+/// assert!(proof.verify(&tree.root(), &nodes));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SszMultiProof {
+    gindices: Vec<GeneralizedIndex>,
+    helper_hashes: Vec<[u8; 32]>,
+}
+
+impl SszMultiProof {
+    pub fn new(gindices: Vec<GeneralizedIndex>, helper_hashes: Vec<[u8; 32]>) -> Self {
+        Self {
+            gindices,
+            helper_hashes,
+        }
+    }
+
+    /// Returns the generalized indices of the proven nodes
+    pub fn gindices(&self) -> &[GeneralizedIndex] {
+        &self.gindices
+    }
+
+    /// Returns the helper nodes in the descending order of their generalized indices
+    pub fn helper_hashes(&self) -> &[[u8; 32]] {
+        &self.helper_hashes
+    }
+
+    /// Calculates the root from the proven nodes, given in the order of their generalized
+    /// indices in the proof.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the number of nodes doesn't match the number of indices, the
+    /// indices are invalid, see [`helper_indices`], or the number of helper nodes doesn't
+    /// match them.
+    pub fn root(&self, leaves: &[[u8; 32]]) -> Result<[u8; 32], Error> {
+        if leaves.len() != self.gindices.len() {
+            return Err(Error::leaves_indices_count_mismatch(
+                self.gindices.len(),
+                leaves.len(),
+            ));
+        }
+        let helper_indices = helper_indices(&self.gindices)?;
+        match self.helper_hashes.len().cmp(&helper_indices.len()) {
+            Ordering::Less => return Err(Error::not_enough_hashes_to_calculate_root()),
+            Ordering::Greater => return Err(Error::too_many_proof_hashes()),
+            Ordering::Equal => {}
+        }
+
+        let depth = self
+            .gindices
+            .iter()
+            .map(|gindex| generalized_index_depth(*gindex))
+            .max()
+            .ok_or_else(Error::not_enough_hashes_to_calculate_root)?;
+        if depth == 0 {
+            return Ok(leaves[0]);
+        }
+
+        // Every node has its sibling among the proven and helper nodes, so the partial tree
+        // never needs to hash a node without a sibling
+        let mut partial_layers = vec![Vec::new(); depth];
+        let nodes = self
+            .gindices
+            .iter()
+            .zip(leaves)
+            .chain(helper_indices.iter().zip(&self.helper_hashes));
+        for (gindex, node) in nodes {
+            let node_depth = generalized_index_depth(*gindex);
+            let node_index = (gindex ^ (1 << node_depth)) as usize;
+            partial_layers[depth - node_depth].push((node_index, *node));
+        }
+
+        PartialTree::<Sha256>::build(partial_layers, depth, TreeProperties::SSZ)?
+            .root()
+            .ok_or_else(Error::not_enough_hashes_to_calculate_root)
+    }
+
+    /// Verifies that the nodes, given in the order of their generalized indices in the proof,
+    /// are in the tree with the given root
+    pub fn verify(&self, root: &[u8; 32], leaves: &[[u8; 32]]) -> bool {
+        match self.root(leaves) {
+            Ok(extracted_root) => extracted_root == *root,
+            Err(_) => false,
+        }
+    }
+}
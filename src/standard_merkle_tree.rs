@@ -0,0 +1,636 @@
+use crate::prelude::*;
+use crate::{
+    algorithms::Keccak256, compact_multiproof::SORTED_PAIRS, error::Error, utils, CompactMultiProof,
+};
+use alloc::collections::VecDeque;
+use core::fmt::{Display, Formatter};
+
+/// Size of an ABI word in bytes
+const WORD_SIZE: usize = 32;
+
+/// A 256-bit big-endian integer, as it is laid out in an ABI word
+type Word = [u8; WORD_SIZE];
+
+/// Returns the bit of the word, counting from the least significant one
+fn word_bit(word: &Word, bit: usize) -> bool {
+    (word[WORD_SIZE - 1 - bit / 8] >> (bit % 8)) & 1 == 1
+}
+
+/// Two's complement negation of the word
+fn negate_word(word: &Word) -> Word {
+    let mut negated = [0u8; WORD_SIZE];
+    let mut carry = 1u16;
+    for i in (0..WORD_SIZE).rev() {
+        let sum = u16::from(!word[i]) + carry;
+        negated[i] = sum as u8;
+        carry = sum >> 8;
+    }
+    negated
+}
+
+/// Multiplies the word by the radix and adds the digit, returns `None` on overflow
+fn push_digit(word: &mut Word, radix: u16, digit: u16) -> Option<()> {
+    let mut carry = digit;
+    for byte in word.iter_mut().rev() {
+        let value = u16::from(*byte) * radix + carry;
+        *byte = value as u8;
+        carry = value >> 8;
+    }
+    if carry == 0 {
+        Some(())
+    } else {
+        None
+    }
+}
+
+/// Parses a decimal or a `0x` prefixed hex number into a word
+fn parse_word(number: &str) -> Option<Word> {
+    let (digits, radix) = match number.strip_prefix("0x") {
+        Some(hex) => (hex, 16),
+        None => (number, 10),
+    };
+    if digits.is_empty() {
+        return None;
+    }
+
+    let mut word = [0u8; WORD_SIZE];
+    for digit in digits.chars() {
+        push_digit(&mut word, radix as u16, digit.to_digit(radix)? as u16)?;
+    }
+    Some(word)
+}
+
+/// Formats the word as an unsigned decimal number
+fn word_to_decimal(word: &Word) -> String {
+    let mut word = *word;
+    let mut digits = Vec::new();
+    loop {
+        let mut remainder = 0u16;
+        for byte in word.iter_mut() {
+            let value = (remainder << 8) | u16::from(*byte);
+            *byte = (value / 10) as u8;
+            remainder = value % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+        if word.iter().all(|byte| *byte == 0) {
+            break;
+        }
+    }
+    digits.iter().rev().map(|digit| *digit as char).collect()
+}
+
+/// An elementary Solidity type that can be used in the leaf encoding of a
+/// [`StandardMerkleTree`]. Arrays and tuples are not supported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AbiType {
+    Address,
+    Bool,
+    /// `uint<M>`, where `M` is the size in bits
+    Uint(usize),
+    /// `int<M>`, where `M` is the size in bits
+    Int(usize),
+    /// `bytes<M>`, where `M` is the size in bytes
+    FixedBytes(usize),
+    Bytes,
+    String,
+}
+
+impl AbiType {
+    /// Parses a Solidity type name, such as `address` or `uint256`. `uint` and `int` are
+    /// aliases of `uint256` and `int256`.
+    pub fn parse(name: &str) -> Result<Self, Error> {
+        let unsupported = || Error::invalid_standard_tree(&format!("unsupported type {}", name));
+        let size = |size: &str, default: usize| -> Result<usize, Error> {
+            if size.is_empty() {
+                Ok(default)
+            } else if size.starts_with('0') {
+                Err(unsupported())
+            } else {
+                size.parse().map_err(|_| unsupported())
+            }
+        };
+
+        let abi_type = match name {
+            "address" => AbiType::Address,
+            "bool" => AbiType::Bool,
+            "bytes" => AbiType::Bytes,
+            "string" => AbiType::String,
+            _ => {
+                if let Some(bits) = name.strip_prefix("uint") {
+                    AbiType::Uint(size(bits, 256)?)
+                } else if let Some(bits) = name.strip_prefix("int") {
+                    AbiType::Int(size(bits, 256)?)
+                } else if let Some(bytes) = name.strip_prefix("bytes") {
+                    AbiType::FixedBytes(size(bytes, 0)?)
+                } else {
+                    return Err(unsupported());
+                }
+            }
+        };
+
+        let is_valid = match abi_type {
+            AbiType::Uint(bits) | AbiType::Int(bits) => bits % 8 == 0 && (8..=256).contains(&bits),
+            AbiType::FixedBytes(bytes) => (1..=32).contains(&bytes),
+            _ => true,
+        };
+        if is_valid {
+            Ok(abi_type)
+        } else {
+            Err(unsupported())
+        }
+    }
+
+    /// Checks that the value has this type and fits into its size
+    pub fn accepts(&self, value: &AbiValue) -> bool {
+        match (self, value) {
+            (AbiType::Address, AbiValue::Address(_))
+            | (AbiType::Bool, AbiValue::Bool(_))
+            | (AbiType::Bytes, AbiValue::Bytes(_))
+            | (AbiType::String, AbiValue::String(_)) => true,
+            (AbiType::Uint(bits), AbiValue::Uint(word)) => {
+                (*bits..256).all(|bit| !word_bit(word, bit))
+            }
+            // Bits above the sign bit must be its extension
+            (AbiType::Int(bits), AbiValue::Int(word)) => {
+                let sign = word_bit(word, 255);
+                (*bits - 1..256).all(|bit| word_bit(word, bit) == sign)
+            }
+            (AbiType::FixedBytes(size), AbiValue::FixedBytes(bytes)) => bytes.len() == *size,
+            _ => false,
+        }
+    }
+
+    fn is_dynamic(&self) -> bool {
+        matches!(self, AbiType::Bytes | AbiType::String)
+    }
+}
+
+impl Display for AbiType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            AbiType::Address => write!(f, "address"),
+            AbiType::Bool => write!(f, "bool"),
+            AbiType::Uint(bits) => write!(f, "uint{}", bits),
+            AbiType::Int(bits) => write!(f, "int{}", bits),
+            AbiType::FixedBytes(bytes) => write!(f, "bytes{}", bytes),
+            AbiType::Bytes => write!(f, "bytes"),
+            AbiType::String => write!(f, "string"),
+        }
+    }
+}
+
+/// A value of an [`AbiType`]. Integers are kept as 256-bit big-endian words, with negative
+/// `int` values in two's complement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AbiValue {
+    Address([u8; 20]),
+    Bool(bool),
+    Uint([u8; 32]),
+    Int([u8; 32]),
+    FixedBytes(Vec<u8>),
+    Bytes(Vec<u8>),
+    String(String),
+}
+
+impl AbiValue {
+    /// Parses a value of the given type from the string representation that
+    /// `@openzeppelin/merkle-tree` accepts: `0x` prefixed hex for addresses and bytes, decimal or
+    /// `0x` prefixed hex for integers, `true` or `false` for booleans.
+    pub fn parse(abi_type: &AbiType, value: &str) -> Result<Self, Error> {
+        let invalid =
+            || Error::invalid_standard_tree(&format!("{} is not a valid {}", value, abi_type));
+        let hex = |value: &str| {
+            if value.starts_with("0x") {
+                utils::collections::from_hex_string(value).ok_or_else(invalid)
+            } else {
+                Err(invalid())
+            }
+        };
+
+        let parsed = match abi_type {
+            AbiType::Address => AbiValue::Address(hex(value)?.try_into().map_err(|_| invalid())?),
+            AbiType::Bool => match value {
+                "true" => AbiValue::Bool(true),
+                "false" => AbiValue::Bool(false),
+                _ => return Err(invalid()),
+            },
+            AbiType::Uint(_) => AbiValue::Uint(parse_word(value).ok_or_else(invalid)?),
+            AbiType::Int(_) => match value.strip_prefix('-') {
+                Some(magnitude) => {
+                    let magnitude = parse_word(magnitude).ok_or_else(invalid)?;
+                    if word_bit(&magnitude, 255) && magnitude != negate_word(&magnitude) {
+                        return Err(invalid());
+                    }
+                    AbiValue::Int(negate_word(&magnitude))
+                }
+                None => AbiValue::Int(parse_word(value).ok_or_else(invalid)?),
+            },
+            AbiType::FixedBytes(_) => AbiValue::FixedBytes(hex(value)?),
+            AbiType::Bytes => AbiValue::Bytes(hex(value)?),
+            AbiType::String => AbiValue::String(value.to_string()),
+        };
+
+        if abi_type.accepts(&parsed) {
+            Ok(parsed)
+        } else {
+            Err(invalid())
+        }
+    }
+
+    /// Encodes the static value, or the offset placeholder of the dynamic one, as an ABI word
+    fn head(&self) -> Word {
+        let mut word = [0u8; WORD_SIZE];
+        match self {
+            AbiValue::Address(address) => word[WORD_SIZE - 20..].copy_from_slice(address),
+            AbiValue::Bool(value) => word[WORD_SIZE - 1] = u8::from(*value),
+            AbiValue::Uint(value) | AbiValue::Int(value) => word = *value,
+            AbiValue::FixedBytes(bytes) => word[..bytes.len()].copy_from_slice(bytes),
+            AbiValue::Bytes(_) | AbiValue::String(_) => {}
+        }
+        word
+    }
+
+    /// Encodes the length and the right-padded contents of the dynamic value
+    fn tail(&self) -> Vec<u8> {
+        let bytes = match self {
+            AbiValue::Bytes(bytes) => bytes.as_slice(),
+            AbiValue::String(string) => string.as_bytes(),
+            _ => return Vec::new(),
+        };
+
+        let mut tail = word_from_usize(bytes.len()).to_vec();
+        tail.extend_from_slice(bytes);
+        tail.resize(WORD_SIZE + bytes.len().div_ceil(WORD_SIZE) * WORD_SIZE, 0);
+        tail
+    }
+}
+
+/// Formats the value the way `@openzeppelin/merkle-tree` accepts it back
+impl Display for AbiValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            AbiValue::Address(address) => {
+                write!(f, "0x{}", utils::collections::to_hex_string(address))
+            }
+            AbiValue::Bool(value) => write!(f, "{}", value),
+            AbiValue::Uint(value) => write!(f, "{}", word_to_decimal(value)),
+            AbiValue::Int(value) if word_bit(value, 255) => {
+                write!(f, "-{}", word_to_decimal(&negate_word(value)))
+            }
+            AbiValue::Int(value) => write!(f, "{}", word_to_decimal(value)),
+            AbiValue::FixedBytes(bytes) | AbiValue::Bytes(bytes) => {
+                write!(f, "0x{}", utils::collections::to_hex_string(bytes))
+            }
+            AbiValue::String(string) => write!(f, "{}", string),
+        }
+    }
+}
+
+impl From<bool> for AbiValue {
+    fn from(value: bool) -> Self {
+        AbiValue::Bool(value)
+    }
+}
+
+impl From<[u8; 20]> for AbiValue {
+    fn from(address: [u8; 20]) -> Self {
+        AbiValue::Address(address)
+    }
+}
+
+/// Creates a `uint` value
+impl From<u128> for AbiValue {
+    fn from(value: u128) -> Self {
+        let mut word = [0u8; WORD_SIZE];
+        word[WORD_SIZE - 16..].copy_from_slice(&value.to_be_bytes());
+        AbiValue::Uint(word)
+    }
+}
+
+impl From<&str> for AbiValue {
+    fn from(string: &str) -> Self {
+        AbiValue::String(string.to_string())
+    }
+}
+
+fn word_from_usize(value: usize) -> Word {
+    let mut word = [0u8; WORD_SIZE];
+    word[WORD_SIZE - 8..].copy_from_slice(&(value as u64).to_be_bytes());
+    word
+}
+
+/// Encodes the values like Solidity's `abi.encode`
+fn abi_encode(leaf_encoding: &[AbiType], value: &[AbiValue]) -> Result<Vec<u8>, Error> {
+    if leaf_encoding.len() != value.len() {
+        return Err(Error::invalid_standard_tree(&format!(
+            "expected {} values, got {}",
+            leaf_encoding.len(),
+            value.len()
+        )));
+    }
+
+    let mut head = Vec::with_capacity(value.len() * WORD_SIZE);
+    let mut tail = Vec::new();
+    for (abi_type, item) in leaf_encoding.iter().zip(value) {
+        if !abi_type.accepts(item) {
+            return Err(Error::invalid_standard_tree(&format!(
+                "{} is not a valid {}",
+                item, abi_type
+            )));
+        }
+
+        if abi_type.is_dynamic() {
+            head.extend_from_slice(&word_from_usize(value.len() * WORD_SIZE + tail.len()));
+            tail.append(&mut item.tail());
+        } else {
+            head.extend_from_slice(&item.head());
+        }
+    }
+
+    head.append(&mut tail);
+    Ok(head)
+}
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    SORTED_PAIRS.concat_and_hash::<Keccak256>(left, Some(right))
+}
+
+fn sibling_index(tree_index: usize) -> usize {
+    if tree_index % 2 == 1 {
+        tree_index + 1
+    } else {
+        tree_index - 1
+    }
+}
+
+fn parent_index(tree_index: usize) -> usize {
+    (tree_index - 1) / 2
+}
+
+/// [`StandardMerkleTree`] builds the same tree as `StandardMerkleTree` of the
+/// `@openzeppelin/merkle-tree` JavaScript library, so its roots and proofs can be verified with
+/// the library and OpenZeppelin's `MerkleProof` Solidity contract.
+///
+/// Every value is a list of [`AbiValue`]s, described by the leaf encoding. The leaf of a value is
+/// `keccak256(keccak256(abi.encode(value)))`, leaves are sorted by their hashes, and pairs of
+/// nodes are hashed in the sorted order. Nodes are kept in the array layout of the library: the
+/// root is at index `0`, the children of the node `i` are at `2i + 1` and `2i + 2`, and leaves
+/// take the end of the array in the reverse order.
+///
+/// With the `serde` feature, the tree is serialized into the `standard-v1` JSON dump of the
+/// library and can be loaded from it.
+///
+/// ## Examples
+///
+/// ```
+/// # use rs_merkle::{StandardMerkleTree, AbiType, AbiValue};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let leaf_encoding = [AbiType::Address, AbiType::Uint(256)];
+/// let values = [
+///     ["0x1111111111111111111111111111111111111111", "5000000000000000000"],
+///     ["0x2222222222222222222222222222222222222222", "2500000000000000000"],
+/// ]
+/// .iter()
+/// .map(|value| {
+///     value
+///         .iter()
+///         .zip(&leaf_encoding)
+///         .map(|(item, abi_type)| AbiValue::parse(abi_type, item))
+///         .collect()
+/// })
+/// .collect::<Result<Vec<Vec<AbiValue>>, _>>()?;
+///
+/// let tree = StandardMerkleTree::of(values, &leaf_encoding)?;
+/// let proof = tree.proof(1)?;
+///
+/// assert!(StandardMerkleTree::verify(
+///     tree.root(),
+///     &leaf_encoding,
+///     tree.value(1).ok_or("no value")?,
+///     &proof
+/// )?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StandardMerkleTree {
+    tree: Vec<[u8; 32]>,
+    // Values in the order they were given, with the indices of their leaves in the tree
+    values: Vec<(Vec<AbiValue>, usize)>,
+    leaf_encoding: Vec<AbiType>,
+}
+
+impl StandardMerkleTree {
+    /// Builds the tree from the values, like `StandardMerkleTree.of` does.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if there are no values or if any of them doesn't match the leaf
+    /// encoding.
+    pub fn of(values: Vec<Vec<AbiValue>>, leaf_encoding: &[AbiType]) -> Result<Self, Error> {
+        if values.is_empty() {
+            return Err(Error::invalid_standard_tree("expected at least one value"));
+        }
+
+        let mut hashed_values = values
+            .iter()
+            .enumerate()
+            .map(|(value_index, value)| Ok((Self::leaf_hash(leaf_encoding, value)?, value_index)))
+            .collect::<Result<Vec<([u8; 32], usize)>, Error>>()?;
+        // The sort is stable, so equal values keep their order, like in the library
+        hashed_values.sort_by_key(|(leaf, _)| *leaf);
+
+        let tree_len = 2 * values.len() - 1;
+        let mut tree = vec![[0u8; 32]; tree_len];
+        let mut tree_indices = vec![0; values.len()];
+        for (leaf_index, (leaf, value_index)) in hashed_values.into_iter().enumerate() {
+            tree[tree_len - 1 - leaf_index] = leaf;
+            tree_indices[value_index] = tree_len - 1 - leaf_index;
+        }
+        for tree_index in (0..tree_len - values.len()).rev() {
+            tree[tree_index] = hash_pair(&tree[2 * tree_index + 1], &tree[2 * tree_index + 2]);
+        }
+
+        Ok(Self {
+            tree,
+            values: values.into_iter().zip(tree_indices).collect(),
+            leaf_encoding: leaf_encoding.to_vec(),
+        })
+    }
+
+    /// Restores the tree from the parts of its dump, checking that every node is the hash of its
+    /// children and every value is a leaf of the tree
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_dump(
+        tree: Vec<[u8; 32]>,
+        values: Vec<(Vec<AbiValue>, usize)>,
+        leaf_encoding: Vec<AbiType>,
+    ) -> Result<Self, Error> {
+        if tree.is_empty() {
+            return Err(Error::invalid_standard_tree("tree is empty"));
+        }
+        for (tree_index, node) in tree.iter().enumerate() {
+            let left = 2 * tree_index + 1;
+            if left + 1 < tree.len() {
+                if *node != hash_pair(&tree[left], &tree[left + 1]) {
+                    return Err(Error::invalid_standard_tree(
+                        "node is not the hash of its children",
+                    ));
+                }
+            } else if left < tree.len() {
+                return Err(Error::invalid_standard_tree("node has a single child"));
+            }
+        }
+
+        for (value, tree_index) in &values {
+            let is_leaf = *tree_index < tree.len() && 2 * tree_index + 1 >= tree.len();
+            if !is_leaf || tree[*tree_index] != Self::leaf_hash(&leaf_encoding, value)? {
+                return Err(Error::invalid_standard_tree(
+                    "value doesn't match the leaf of the tree",
+                ));
+            }
+        }
+
+        Ok(Self {
+            tree,
+            values,
+            leaf_encoding,
+        })
+    }
+
+    /// Returns the leaf of the value, `keccak256(keccak256(abi.encode(value)))`
+    pub fn leaf_hash(leaf_encoding: &[AbiType], value: &[AbiValue]) -> Result<[u8; 32], Error> {
+        let encoded = abi_encode(leaf_encoding, value)?;
+        Ok(Keccak256::hash(&Keccak256::hash(&encoded)))
+    }
+
+    /// Verifies a proof returned by [`StandardMerkleTree::proof`] without building the tree
+    pub fn verify(
+        root: [u8; 32],
+        leaf_encoding: &[AbiType],
+        value: &[AbiValue],
+        proof: &[[u8; 32]],
+    ) -> Result<bool, Error> {
+        let leaf = Self::leaf_hash(leaf_encoding, value)?;
+        let extracted_root = proof
+            .iter()
+            .fold(leaf, |node, sibling| hash_pair(&node, sibling));
+        Ok(extracted_root == root)
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.tree[0]
+    }
+
+    /// Returns the root as a `0x` prefixed hex string, the way the library prints it
+    pub fn root_hex(&self) -> String {
+        format!("0x{}", utils::collections::to_hex_string(&self.root()))
+    }
+
+    /// Returns the nodes in the array layout of the library
+    pub fn tree(&self) -> &[[u8; 32]] {
+        &self.tree
+    }
+
+    pub fn leaf_encoding(&self) -> &[AbiType] {
+        &self.leaf_encoding
+    }
+
+    /// Returns the number of values in the tree
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the value by its index in the list the tree was built from
+    pub fn value(&self, value_index: usize) -> Option<&[AbiValue]> {
+        self.values
+            .get(value_index)
+            .map(|(value, _)| value.as_slice())
+    }
+
+    /// Returns the index of the leaf of the value in the array returned by
+    /// [`StandardMerkleTree::tree`]
+    pub fn tree_index(&self, value_index: usize) -> Option<usize> {
+        self.values
+            .get(value_index)
+            .map(|(_, tree_index)| *tree_index)
+    }
+
+    /// Returns the index of the first value equal to the given one
+    pub fn find(&self, value: &[AbiValue]) -> Option<usize> {
+        self.values
+            .iter()
+            .position(|(tree_value, _)| tree_value.as_slice() == value)
+    }
+
+    /// Returns the proof for the value, like `getProof` does. The proof is accepted by
+    /// `MerkleProof.verify` of the Solidity contract.
+    pub fn proof(&self, value_index: usize) -> Result<Vec<[u8; 32]>, Error> {
+        let mut tree_index = self.tree_index_of(value_index)?;
+        let mut proof = Vec::new();
+        while tree_index > 0 {
+            proof.push(self.tree[sibling_index(tree_index)]);
+            tree_index = parent_index(tree_index);
+        }
+        Ok(proof)
+    }
+
+    /// Returns the multiproof for the values, like `getMultiProof` does, together with the
+    /// leaves in the order the verifier expects them. The leaves don't follow the order of
+    /// `value_indices`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if any of the indices is out of range or repeated.
+    pub fn multi_proof(
+        &self,
+        value_indices: &[usize],
+    ) -> Result<(Vec<[u8; 32]>, CompactMultiProof<Keccak256>), Error> {
+        let mut tree_indices = value_indices
+            .iter()
+            .map(|value_index| self.tree_index_of(*value_index))
+            .collect::<Result<Vec<usize>, Error>>()?;
+        // Deeper leaves come first, so the nodes of the queue are processed layer by layer
+        tree_indices.sort_unstable_by(|a, b| b.cmp(a));
+        if tree_indices.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(Error::invalid_standard_tree("can't prove a value twice"));
+        }
+
+        let mut queue: VecDeque<usize> = tree_indices.iter().copied().collect();
+        let mut proof_hashes = Vec::new();
+        let mut proof_flags = Vec::new();
+        while let Some(tree_index) = queue.pop_front() {
+            if tree_index == 0 {
+                break;
+            }
+
+            let sibling = sibling_index(tree_index);
+            if queue.front() == Some(&sibling) {
+                queue.pop_front();
+                proof_flags.push(true);
+            } else {
+                proof_hashes.push(self.tree[sibling]);
+                proof_flags.push(false);
+            }
+            queue.push_back(parent_index(tree_index));
+        }
+        if tree_indices.is_empty() {
+            proof_hashes.push(self.root());
+        }
+
+        let leaves = tree_indices
+            .into_iter()
+            .map(|tree_index| self.tree[tree_index])
+            .collect();
+        Ok((leaves, CompactMultiProof::new(proof_hashes, proof_flags)))
+    }
+
+    fn tree_index_of(&self, value_index: usize) -> Result<usize, Error> {
+        self.tree_index(value_index)
+            .ok_or_else(|| Error::leaf_index_out_of_range(value_index, self.values.len()))
+    }
+}
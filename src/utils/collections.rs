@@ -1,4 +1,5 @@
-use crate::prelude::*;
+use crate::{prelude::*, Hasher};
+use core::convert::TryFrom;
 
 fn byte_to_hex(byte: &u8) -> String {
     format!("{:02x}", byte)
@@ -11,9 +12,29 @@ pub fn to_hex_string<T: Clone + Into<Vec<u8>>>(bytes: &T) -> String {
     hex_vec.join("")
 }
 
+/// Parses a hex string, optionally prefixed with `0x`, into bytes. Returns `None` if the string
+/// isn't valid hex.
+pub fn from_hex_string(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
 /// Find a difference between two vectors and return a third vector
 /// containing the difference. This function preserves the first
 /// vector order.
 pub fn difference<T: Clone + PartialEq>(a: &[T], b: &[T]) -> Vec<T> {
-    a.iter().cloned().filter(|x| !b.contains(x)).collect()
+    a.iter().filter(|x| !b.contains(x)).cloned().collect()
+}
+
+/// Returns the hash that consists only of zero bytes
+pub fn zero_hash<T: Hasher>() -> T::Hash {
+    T::Hash::try_from(vec![0u8; T::hash_size()])
+        .unwrap_or_else(|_| panic!("Hasher::Hash can not be constructed from zero bytes"))
 }
@@ -0,0 +1,68 @@
+//! Position arithmetic for the [`MerkleMountainRange`]. Nodes are numbered in the order they
+//! are appended, i.e. in post-order, starting from 0.
+//!
+//! [`MerkleMountainRange`]: crate::MerkleMountainRange
+use crate::prelude::*;
+
+/// Returns the height of the node at the given position, leaves having height 0
+pub fn pos_height_in_tree(pos: usize) -> u32 {
+    fn all_ones(num: usize) -> bool {
+        num != 0 && num.count_zeros() == num.leading_zeros()
+    }
+
+    fn jump_left(pos: usize) -> usize {
+        let bit_length = usize::BITS - pos.leading_zeros();
+        let most_significant_bits = 1 << (bit_length - 1);
+        pos - (most_significant_bits - 1)
+    }
+
+    let mut pos = pos + 1;
+    while !all_ones(pos) {
+        pos = jump_left(pos)
+    }
+
+    usize::BITS - pos.leading_zeros() - 1
+}
+
+/// Offset from a node of the given height to its parent, when the node is a left child
+pub fn parent_offset(height: u32) -> usize {
+    2 << height
+}
+
+/// Offset between two siblings of the given height
+pub fn sibling_offset(height: u32) -> usize {
+    (2 << height) - 1
+}
+
+/// Returns the total number of nodes in a mountain range with the given number of leaves, or
+/// `None` if the positions of the nodes don't fit in `usize`
+pub fn mmr_size(leaves_count: usize) -> Option<usize> {
+    let double_leaves_count = leaves_count.checked_mul(2)?;
+    Some(double_leaves_count - leaves_count.count_ones() as usize)
+}
+
+/// Returns the position of the leaf with the given index, or `None` if the position doesn't fit
+/// in `usize`
+pub fn leaf_index_to_pos(leaf_index: usize) -> Option<usize> {
+    let leaves_count = leaf_index.checked_add(1)?;
+    Some(mmr_size(leaves_count)? - leaves_count.trailing_zeros() as usize - 1)
+}
+
+/// Returns positions of the peaks of a mountain range with the given number of leaves, from
+/// left to right. Every set bit of the leaves count corresponds to one peak. Returns `None` if
+/// the positions of the nodes don't fit in `usize`.
+pub fn peak_positions(leaves_count: usize) -> Option<Vec<usize>> {
+    mmr_size(leaves_count)?;
+    let mut peaks = Vec::new();
+    let mut offset = 0;
+
+    for bit in (0..usize::BITS).rev() {
+        if leaves_count & (1 << bit) != 0 {
+            let mountain_size = (2 << bit) - 1;
+            peaks.push(offset + mountain_size - 1);
+            offset += mountain_size;
+        }
+    }
+
+    Some(peaks)
+}
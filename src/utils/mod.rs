@@ -1,4 +1,5 @@
 //! Utilities used internally to manipulate tree indices
 pub mod collections;
 pub mod indices;
+pub mod mmr;
 pub mod properties;
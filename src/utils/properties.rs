@@ -1,4 +1,252 @@
-#[derive(Clone, Copy)]
+use crate::{prelude::*, utils, Hasher};
+
+/// Tags prepended to leaves and interior nodes before hashing them, so that a leaf can never
+/// be interpreted as an interior node. Without them, a leaf that is a concatenation of two
+/// hashes is indistinguishable from an interior node, which allows forging proofs for
+/// interior nodes (a second-preimage attack).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "parity-scale-codec",
+    derive(
+        parity_scale_codec::Encode,
+        parity_scale_codec::Decode,
+        scale_info::TypeInfo
+    )
+)]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub struct DomainSeparation {
+    pub leaf_prefix: u8,
+    pub node_prefix: u8,
+}
+
+impl DomainSeparation {
+    /// Tags used by RFC 6962: `0x00` for leaves and `0x01` for interior nodes
+    pub const RFC_6962: DomainSeparation = DomainSeparation {
+        leaf_prefix: 0x00,
+        node_prefix: 0x01,
+    };
+}
+
+/// Defines how a node without a right sibling is turned into its parent. Proofs never include
+/// the missing sibling, so the verifier applies the same strategy to calculate the root.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "parity-scale-codec",
+    derive(
+        parity_scale_codec::Encode,
+        parity_scale_codec::Decode,
+        scale_info::TypeInfo
+    )
+)]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub enum OddNodeStrategy {
+    /// The node is promoted to the next layer unchanged, or hashed the way the [`Hasher`]
+    /// implements [`Hasher::concat_and_hash`] for a missing right node
+    Promote,
+    /// The node is concatenated with itself and hashed, like in Bitcoin
+    Duplicate,
+    /// The node is concatenated with a hash consisting of zero bytes and hashed
+    HashWithZero,
+    /// The tree is padded with zero leaves up to the next power of two, so the node is
+    /// concatenated with the root of an empty subtree of the same height and hashed
+    PadToPowerOfTwo,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "parity-scale-codec",
+    derive(
+        parity_scale_codec::Encode,
+        parity_scale_codec::Decode,
+        scale_info::TypeInfo
+    )
+)]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
 pub struct TreeProperties {
+    /// If set, the node with the lower byte value is placed first when hashing a pair, so
+    /// proofs don't need to carry the position of every sibling
     pub sorted_pair_enabled: bool,
+    /// If set, leaves are hashed as `hash(leaf_prefix ++ leaf)` when they are added to the tree
+    /// or verified with a proof, and interior nodes are hashed as
+    /// `hash(node_prefix ++ left ++ right)`. Recommended when leaves come from untrusted data.
+    pub domain_separation: Option<DomainSeparation>,
+    pub odd_node_strategy: OddNodeStrategy,
+}
+
+impl Default for TreeProperties {
+    /// Returns the properties of the trees built by rs_merkle 1.x: pairs are hashed in their
+    /// original order without prefixes, and a node without a right sibling is promoted. New
+    /// fields are added with the value that keeps this behaviour, so building properties as
+    /// `TreeProperties { sorted_pair_enabled: true, ..Default::default() }` keeps working and
+    /// keeps producing the same roots after an upgrade.
+    ///
+    /// Domain separation isn't enabled by default for the same reason: turning it on changes
+    /// every root, so it would silently invalidate stored roots and proofs. Trees with leaves
+    /// from untrusted data should enable it explicitly with [`DomainSeparation::RFC_6962`].
+    fn default() -> Self {
+        TreeProperties {
+            sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
+        }
+    }
+}
+
+impl TreeProperties {
+    /// Properties of the trees built by Substrate's `binary_merkle_tree`, which BEEFY uses for
+    /// authority sets and parachain heads. Pairs are hashed in their original order without
+    /// prefixes, and a node without a right sibling is promoted unchanged, so the [`Hasher`]
+    /// must promote a missing right node, like [`Keccak256`] does. Substrate hashes the leaf
+    /// values before building the tree, use [`MerkleTree::from_leaf_values`] to do the same.
+    ///
+    /// [`Keccak256`]: crate::algorithms::Keccak256
+    /// [`MerkleTree::from_leaf_values`]: crate::MerkleTree::from_leaf_values
+    pub const SUBSTRATE: TreeProperties = TreeProperties {
+        sorted_pair_enabled: false,
+        domain_separation: None,
+        odd_node_strategy: OddNodeStrategy::Promote,
+    };
+
+    /// Properties of the Bitcoin transaction merkle tree: pairs are hashed in their original
+    /// order without prefixes, and a node without a right sibling is concatenated with itself.
+    /// Use them with [`DoubleSha256`] and txids in the internal byte order, see the
+    /// [`bitcoin`] module.
+    ///
+    /// [`DoubleSha256`]: crate::algorithms::DoubleSha256
+    /// [`bitcoin`]: crate::bitcoin
+    pub const BITCOIN: TreeProperties = TreeProperties {
+        sorted_pair_enabled: false,
+        domain_separation: None,
+        odd_node_strategy: OddNodeStrategy::Duplicate,
+    };
+
+    /// Properties of CometBFT's simple merkle trees: RFC 6962 prefixes, pairs hashed in their
+    /// original order, and a node without a right sibling promoted unchanged, which gives the
+    /// same tree as splitting the leaves at the largest power of two smaller than their
+    /// number. Use them with [`Sha256`] and leaves that are hashes, like transaction hashes,
+    /// see the [`cometbft`] module for leaves of any size.
+    ///
+    /// [`Sha256`]: crate::algorithms::Sha256
+    /// [`cometbft`]: crate::cometbft
+    pub const COMETBFT: TreeProperties = TreeProperties {
+        sorted_pair_enabled: false,
+        domain_separation: Some(DomainSeparation::RFC_6962),
+        odd_node_strategy: OddNodeStrategy::Promote,
+    };
+
+    /// Properties of SSZ merkleization, which Ethereum's beacon chain uses for
+    /// `hash_tree_root`: pairs are hashed in their original order without prefixes, and a node
+    /// without a right sibling is hashed with the root of an empty subtree, as if the chunks
+    /// were padded with zero chunks to the next power of two. Use them with [`Sha256`]. Lists
+    /// are padded up to their limit instead, see the [`ssz`] module.
+    ///
+    /// [`Sha256`]: crate::algorithms::Sha256
+    /// [`ssz`]: crate::ssz
+    pub const SSZ: TreeProperties = TreeProperties {
+        sorted_pair_enabled: false,
+        domain_separation: None,
+        odd_node_strategy: OddNodeStrategy::PadToPowerOfTwo,
+    };
+
+    /// Returns the hash stored in the leaf layer of the tree for the given leaf. If domain
+    /// separation is enabled, the leaf is hashed with the leaf prefix, otherwise it is
+    /// returned as is.
+    pub fn hash_leaf<T: Hasher>(&self, leaf: &T::Hash) -> T::Hash {
+        match self.domain_separation {
+            Some(domain_separation) => {
+                let mut prefixed: Vec<u8> = vec![domain_separation.leaf_prefix];
+                let mut leaf_bytes: Vec<u8> = (*leaf).into();
+                prefixed.append(&mut leaf_bytes);
+                T::hash(&prefixed)
+            }
+            None => *leaf,
+        }
+    }
+
+    /// Calculates the parent of a node that doesn't have a right sibling on the given layer,
+    /// according to the [`OddNodeStrategy`]. A lone node with the parent index 0 is the only
+    /// node of its layer, so it is the root and it is always promoted unless the strategy is
+    /// [`OddNodeStrategy::Promote`], in which case the [`Hasher`] decides.
+    pub fn hash_lone_node<T: Hasher>(
+        &self,
+        node: &T::Hash,
+        layer_index: usize,
+        parent_node_index: usize,
+    ) -> T::Hash {
+        if self.odd_node_strategy != OddNodeStrategy::Promote && parent_node_index == 0 {
+            return *node;
+        }
+
+        let sibling = self.lone_node_sibling::<T>(node, layer_index);
+        self.concat_and_hash::<T>(node, sibling.as_ref())
+    }
+
+    /// Returns the node that the [`OddNodeStrategy`] pairs with a node without a right sibling
+    /// on the given layer, or `None` if the node is promoted by the [`Hasher`].
+    pub fn lone_node_sibling<T: Hasher>(
+        &self,
+        node: &T::Hash,
+        layer_index: usize,
+    ) -> Option<T::Hash> {
+        match self.odd_node_strategy {
+            OddNodeStrategy::Promote => None,
+            OddNodeStrategy::Duplicate => Some(*node),
+            OddNodeStrategy::HashWithZero => Some(utils::collections::zero_hash::<T>()),
+            OddNodeStrategy::PadToPowerOfTwo => {
+                let mut empty_subtree = utils::collections::zero_hash::<T>();
+                for _ in 0..layer_index {
+                    empty_subtree = self.concat_and_hash::<T>(&empty_subtree, Some(&empty_subtree));
+                }
+                Some(empty_subtree)
+            }
+        }
+    }
+
+    /// Hashes two nodes into their parent according to the tree properties. If sorted pairs are
+    /// enabled, the node with the lower byte value is placed first, otherwise nodes are
+    /// hashed in their original order. If domain separation is enabled, the node prefix is
+    /// prepended to the concatenated nodes. The right node is optional, see
+    /// [`Hasher::concat_and_hash`].
+    pub fn concat_and_hash<T: Hasher>(&self, left: &T::Hash, right: Option<&T::Hash>) -> T::Hash {
+        let right_node = match right {
+            Some(right_node) => right_node,
+            None => return T::concat_and_hash(left, None),
+        };
+
+        let (left, right_node) = if self.sorted_pair_enabled {
+            let left_bytes: Vec<u8> = (*left).into();
+            let right_bytes: Vec<u8> = (*right_node).into();
+            if right_bytes < left_bytes {
+                (right_node, left)
+            } else {
+                (left, right_node)
+            }
+        } else {
+            (left, right_node)
+        };
+
+        match self.domain_separation {
+            Some(domain_separation) => {
+                let mut concatenated: Vec<u8> = vec![domain_separation.node_prefix];
+                let mut left_bytes: Vec<u8> = (*left).into();
+                let mut right_bytes: Vec<u8> = (*right_node).into();
+                concatenated.append(&mut left_bytes);
+                concatenated.append(&mut right_bytes);
+                T::hash(&concatenated)
+            }
+            None => T::concat_and_hash(left, Some(right_node)),
+        }
+    }
 }
@@ -0,0 +1,189 @@
+mod common;
+
+pub mod bitcoin {
+    use crate::common;
+    use rs_merkle::{
+        bitcoin::{self, MerkleBlock, PartialMerkleTree},
+        ErrorKind,
+    };
+
+    // `gettxoutproof '["220ebc64e21abece964927322cba69180ed853bb187fbc6923bac7d010b9d87a"]'`
+    // for the block 0000000000013b8ab2cd513b0261a14096412195a72a0c4827d229dcc7e0f7af
+    const MERKLE_BLOCK_HEX: &str = "0100000090f0a9f110702f808219ebea1173056042a714bad51b916cb6800000000000005275289558f51c9966699404ae2294730c3c9f9bda53523ce50e9b95e558da2fdb261b4d4c86041b1ab1bf930900000005fac7708a6e81b2a986dea60db2663840ed141130848162eb1bd1dee54f309a1b2ee1e12587e497ada70d9bd10d31e83f0a924825b96cb8d04e8936d793fb60db7ad8b910d0c7ba2369bc7f18bb53d80e1869ba2c32274996cebe1ae264bc0e2289189ff0316cdc10511da71da757e553cada9f3b5b1434f3923673adb57d83caac392c38af156d6fc30b55fad4112df2b95531e68114e9ad10011e72f7b7cfdb025700";
+
+    fn merkle_block_bytes() -> Vec<u8> {
+        rs_merkle::utils::collections::from_hex_string(MERKLE_BLOCK_HEX).unwrap()
+    }
+
+    fn txids(len: usize) -> Vec<[u8; 32]> {
+        (0..len)
+            .map(|i| bitcoin::DoubleSha256::hash(&(i as u64).to_le_bytes()))
+            .collect()
+    }
+
+    #[test]
+    pub fn should_verify_a_merkle_block_from_a_node() {
+        let bytes = merkle_block_bytes();
+        let merkle_block = MerkleBlock::from_bytes(&bytes).unwrap();
+
+        assert_eq!(
+            bitcoin::hash_to_hex(&merkle_block.block_hash()),
+            "0000000000013b8ab2cd513b0261a14096412195a72a0c4827d229dcc7e0f7af"
+        );
+        assert_eq!(merkle_block.partial_merkle_tree().transactions_count(), 9);
+
+        let matches = merkle_block.verify().unwrap();
+        let txid = bitcoin::hash_from_hex(
+            "220ebc64e21abece964927322cba69180ed853bb187fbc6923bac7d010b9d87a",
+        )
+        .unwrap();
+        assert_eq!(matches, vec![(3, txid)]);
+
+        // Serializing it again gives the same bytes
+        assert_eq!(merkle_block.to_bytes(), bytes);
+    }
+
+    #[test]
+    pub fn should_calculate_the_merkle_root_of_a_block() {
+        // Block 100000
+        let txids: Vec<[u8; 32]> = [
+            "8c14f0db3df150123e6f3dbbf30f8b955a8249b62ac1d1ff16284aefa3d06d87",
+            "fff2525b8931402dd09222c50775608f75787bd2b87e56995a7bdd30f79702c4",
+            "6359f0868171b1d194cbee1af2f16ea598ae8fad666d9b012c8ed2b79a236ec4",
+            "e9a66845e05d5abc0ad04ec80f774a7e585c6e8db975962d069a522137b80c1d",
+        ]
+        .iter()
+        .map(|txid| bitcoin::hash_from_hex(txid).unwrap())
+        .collect();
+
+        assert_eq!(
+            bitcoin::hash_to_hex(&bitcoin::merkle_root(&txids).unwrap()),
+            "f3e94742aca4b5ef85488dc37c06c3282295ffec960994b2c0d5ac2a25a95766"
+        );
+        assert!(!bitcoin::is_mutated(&txids));
+        assert_eq!(bitcoin::merkle_root(&[]), None);
+    }
+
+    #[test]
+    pub fn should_round_trip_partial_merkle_trees() {
+        for len in [1, 2, 3, 4, 5, 7, 8, 9, 17, 56, 100] {
+            let txids = txids(len);
+            let root = bitcoin::merkle_root(&txids).unwrap();
+
+            let mut all_indices = vec![vec![], vec![0], vec![len - 1], (0..len).collect()];
+            if len <= 7 {
+                all_indices.extend(common::combinations((0..len).collect()));
+            } else {
+                all_indices.push((0..len).step_by(3).collect());
+            }
+
+            for indices in all_indices {
+                let partial_merkle_tree = PartialMerkleTree::from_txids(&txids, &indices).unwrap();
+                let bytes = partial_merkle_tree.to_bytes();
+                let parsed = PartialMerkleTree::from_bytes(&bytes).unwrap();
+                assert_eq!(parsed.to_bytes(), bytes);
+
+                let (partial_tree, matches) = parsed.to_partial_tree().unwrap();
+                assert_eq!(partial_tree.root(), Some(root), "{} {:?}", len, indices);
+                let expected: Vec<(usize, [u8; 32])> = indices
+                    .iter()
+                    .map(|index| (*index, txids[*index]))
+                    .collect();
+                assert_eq!(matches, expected);
+
+                // The extracted tree contains every node needed to build the proof again
+                let rebuilt =
+                    PartialMerkleTree::from_partial_tree(&partial_tree, len as u32, &indices)
+                        .unwrap();
+                assert_eq!(rebuilt, partial_merkle_tree);
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_reject_the_duplicate_transactions_mutation() {
+        // CVE-2012-2459: duplicating the last two transactions of ten keeps the root
+        let mut txids: Vec<[u8; 32]> = (1..=10u8)
+            .map(|i| {
+                let mut txid = [0u8; 32];
+                txid[0] = i;
+                txid
+            })
+            .collect();
+        let root = bitcoin::merkle_root(&txids).unwrap();
+        assert!(!bitcoin::is_mutated(&txids));
+
+        txids.extend_from_within(8..);
+        assert_eq!(bitcoin::merkle_root(&txids), Some(root));
+        assert!(bitcoin::is_mutated(&txids));
+
+        let partial_merkle_tree = PartialMerkleTree::from_txids(&txids, &[9, 10]).unwrap();
+        let error = partial_merkle_tree.to_partial_tree().err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidMerkleBlock);
+    }
+
+    #[test]
+    pub fn should_reject_malformed_partial_merkle_trees() {
+        let txids = txids(5);
+        let partial_merkle_tree = PartialMerkleTree::from_txids(&txids, &[1]).unwrap();
+        let hashes = partial_merkle_tree.hashes().to_vec();
+        let flags = partial_merkle_tree.flags().to_vec();
+
+        let mut malformed = vec![
+            PartialMerkleTree::new(0, vec![], vec![]),
+            PartialMerkleTree::new(
+                bitcoin::MAX_BLOCK_TRANSACTIONS + 1,
+                hashes.clone(),
+                flags.clone(),
+            ),
+            PartialMerkleTree::new(5, hashes[1..].to_vec(), flags.clone()),
+            PartialMerkleTree::new(5, [hashes.clone(), hashes.clone()].concat(), flags.clone()),
+            PartialMerkleTree::new(5, hashes.clone(), flags[..flags.len() - 1].to_vec()),
+        ];
+        let mut extra_flags = flags.clone();
+        extra_flags.resize(flags.len() + 8, false);
+        malformed.push(PartialMerkleTree::new(5, hashes, extra_flags));
+
+        for partial_merkle_tree in malformed {
+            let error = partial_merkle_tree.to_partial_tree().err().unwrap();
+            assert_eq!(error.kind(), ErrorKind::InvalidMerkleBlock);
+        }
+
+        let error = PartialMerkleTree::from_txids(&txids, &[5]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::LeafIndexOutOfRange);
+        let error = PartialMerkleTree::from_txids(&[], &[]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidMerkleBlock);
+
+        let (partial_tree, _) = partial_merkle_tree.to_partial_tree().unwrap();
+        for transactions_count in [0, bitcoin::MAX_BLOCK_TRANSACTIONS + 1, u32::MAX] {
+            let error =
+                PartialMerkleTree::from_partial_tree(&partial_tree, transactions_count, &[1])
+                    .unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidMerkleBlock);
+        }
+    }
+
+    #[test]
+    pub fn should_reject_malformed_merkle_blocks() {
+        let bytes = merkle_block_bytes();
+
+        let error = MerkleBlock::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidMerkleBlock);
+        let error = MerkleBlock::from_bytes(&[bytes.as_slice(), &[0]].concat()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidMerkleBlock);
+
+        // The number of hashes encoded with a non-canonical length prefix
+        let mut non_canonical = bytes[..84].to_vec();
+        non_canonical.extend_from_slice(&[0xfd, 0x05, 0x00]);
+        non_canonical.extend_from_slice(&bytes[85..]);
+        let error = MerkleBlock::from_bytes(&non_canonical).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidMerkleBlock);
+
+        // A different merkle root in the header
+        let mut wrong_root = bytes;
+        wrong_root[36] ^= 1;
+        let merkle_block = MerkleBlock::from_bytes(&wrong_root).unwrap();
+        let error = merkle_block.verify().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidMerkleBlock);
+    }
+}
@@ -0,0 +1,99 @@
+#![cfg(feature = "borsh")]
+
+mod common;
+
+pub mod borsh {
+    use crate::common;
+    use borsh::BorshDeserialize;
+    use rs_merkle::{
+        algorithms::Sha256,
+        utils::properties::{OddNodeStrategy, TreeProperties},
+        IndexedMerkleProof, MerkleProof, MerkleTree,
+    };
+
+    const TREE_PROPERTIES: TreeProperties =
+        common::tree_properties(false, OddNodeStrategy::PadToPowerOfTwo);
+
+    #[test]
+    pub fn should_encode_proof_like_a_vector_of_arrays() {
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&common::leaves(9), TREE_PROPERTIES);
+        let proof = merkle_tree.proof(&[4, 8]);
+
+        let bytes = borsh::to_vec(&proof).unwrap();
+        assert_eq!(
+            bytes,
+            borsh::to_vec(&proof.proof_hashes().to_vec()).unwrap()
+        );
+
+        let decoded: MerkleProof<Sha256> = borsh::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, proof);
+    }
+
+    #[test]
+    pub fn should_round_trip_tree_properties() {
+        let bytes = borsh::to_vec(&TREE_PROPERTIES).unwrap();
+        // One byte for the bool, the option tag with two prefixes and the strategy variant
+        assert_eq!(bytes.len(), 5);
+
+        let decoded: TreeProperties = borsh::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, TREE_PROPERTIES);
+    }
+
+    #[test]
+    pub fn should_round_trip_and_verify_indexed_proof() {
+        let leaves = common::leaves(13);
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, TREE_PROPERTIES);
+        let root = merkle_tree.root().unwrap();
+        let proof = merkle_tree.indexed_proof(&[0, 12]);
+
+        let bytes = borsh::to_vec(&proof).unwrap();
+        let decoded: IndexedMerkleProof<Sha256> = borsh::from_slice(&bytes).unwrap();
+
+        assert_eq!(decoded, proof);
+        assert_eq!(decoded.leaf_indices(), &[0, 12]);
+        assert_eq!(decoded.total_leaves_count(), 13);
+        assert!(decoded.verify(root, &[leaves[0], leaves[12]]));
+    }
+
+    #[test]
+    pub fn should_reject_malformed_input() {
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&common::leaves(5), TREE_PROPERTIES);
+        let bytes = borsh::to_vec(&merkle_tree.indexed_proof(&[1])).unwrap();
+
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(IndexedMerkleProof::<Sha256>::try_from_slice(truncated).is_err());
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(IndexedMerkleProof::<Sha256>::try_from_slice(&trailing).is_err());
+
+        // The length claims more hashes than there are bytes
+        let huge_len = u32::MAX.to_le_bytes();
+        assert!(MerkleProof::<Sha256>::try_from_slice(&huge_len).is_err());
+    }
+
+    #[test]
+    pub fn should_reject_proofs_that_dont_match_their_indices() {
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&common::leaves(5), TREE_PROPERTIES);
+        let proof = merkle_tree.indexed_proof(&[1]);
+        let proof_hashes = proof.proof().proof_hashes().to_vec();
+        let deserialize = |proof_hashes: Vec<[u8; 32]>, leaf_indices: Vec<usize>, total: usize| {
+            let bytes = borsh::to_vec(&IndexedMerkleProof::<Sha256>::new(
+                MerkleProof::new(proof_hashes),
+                leaf_indices,
+                total,
+                TREE_PROPERTIES,
+            ))
+            .unwrap();
+            IndexedMerkleProof::<Sha256>::try_from_slice(&bytes)
+        };
+
+        assert!(deserialize(proof_hashes.clone(), vec![1], 5).is_ok());
+        assert!(deserialize(proof_hashes.clone(), vec![5], 5).is_err());
+        assert!(deserialize(proof_hashes.clone(), vec![1], u64::MAX as usize).is_err());
+        assert!(deserialize(proof_hashes[1..].to_vec(), vec![1], 5).is_err());
+        let mut extra_hashes = proof_hashes;
+        extra_hashes.push([0; 32]);
+        assert!(deserialize(extra_hashes, vec![1], 5).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+pub mod cometbft {
+    use rs_merkle::{
+        algorithms::Sha256,
+        cometbft::{self, Proof},
+        utils::{collections::to_hex_string, properties::TreeProperties},
+        MerkleTree,
+    };
+
+    fn items(len: usize) -> Vec<Vec<u8>> {
+        (0..len).map(|i| vec![i as u8; i % 5]).collect()
+    }
+
+    /// `HashFromByteSlices` as CometBFT implements it, recursing at the split point
+    fn naive_root(items: &[Vec<u8>]) -> [u8; 32] {
+        match items.len() {
+            0 => Sha256::hash(&[]),
+            1 => Sha256::hash(&[&[0u8], items[0].as_slice()].concat()),
+            len => {
+                let split = len.next_power_of_two() / 2;
+                let left = naive_root(&items[..split]);
+                let right = naive_root(&items[split..]);
+                Sha256::hash(&[&[1u8], left.as_slice(), right.as_slice()].concat())
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_match_the_cometbft_test_vectors() {
+        let cases: Vec<(Vec<&[u8]>, &str)> = vec![
+            (
+                vec![],
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            ),
+            (
+                vec![&[1, 2, 3]],
+                "054edec1d0211f624fed0cbca9d4f9400b0e491c43742af2c5b0abebf0c990d8",
+            ),
+            (
+                vec![&[]],
+                "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+            ),
+            (
+                vec![&[1, 2, 3], &[4, 5, 6]],
+                "82e6cfce00453804379b53962939eaa7906b39904be0813fcadd31b100773c4b",
+            ),
+            (
+                vec![&[1, 2], &[3, 4], &[5, 6], &[7, 8], &[9, 10]],
+                "f326493eceab4f2d9ffbc78c59432a0a005d6ea98392045c74df5d14a113be18",
+            ),
+        ];
+        for (items, expected_root) in cases {
+            assert_eq!(
+                to_hex_string(&cometbft::hash_from_byte_slices(&items)),
+                expected_root
+            );
+        }
+    }
+
+    #[test]
+    pub fn should_split_at_the_largest_power_of_two() {
+        assert_eq!(cometbft::split_point(1), 0);
+        assert_eq!(cometbft::split_point(2), 1);
+        assert_eq!(cometbft::split_point(5), 4);
+        assert_eq!(cometbft::split_point(8), 4);
+        assert_eq!(cometbft::split_point(9), 8);
+        assert_eq!(cometbft::split_point(usize::MAX), 1 << (usize::BITS - 1));
+        assert_eq!(
+            cometbft::split_point(1 << (usize::BITS - 1)),
+            1 << (usize::BITS - 2)
+        );
+
+        for len in 0..=33 {
+            let items = items(len);
+            let (root, proofs) = cometbft::proofs_from_byte_slices(&items);
+            assert_eq!(root, naive_root(&items), "{}", len);
+            assert_eq!(cometbft::hash_from_byte_slices(&items), root);
+            assert_eq!(proofs.len(), len);
+
+            for (index, proof) in proofs.iter().enumerate() {
+                assert_eq!(proof.total(), len);
+                assert_eq!(proof.index(), index);
+                assert_eq!(proof.compute_root_hash(), Some(root), "{} {}", len, index);
+                assert!(proof.verify(&root, &items[index]));
+                assert!(!proof.verify(&root, b"wrong"));
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_match_merkle_tree_with_cometbft_properties() {
+        for len in 1..=17 {
+            let tx_hashes: Vec<[u8; 32]> = items(len).iter().map(|tx| Sha256::hash(tx)).collect();
+            let (root, proofs) = cometbft::proofs_from_byte_slices(&tx_hashes);
+            let merkle_tree =
+                MerkleTree::<Sha256>::from_leaves(&tx_hashes, TreeProperties::COMETBFT);
+            assert_eq!(merkle_tree.root(), Some(root));
+
+            for (index, proof) in proofs.iter().enumerate() {
+                assert_eq!(merkle_tree.proof(&[index]).proof_hashes(), proof.aunts());
+                let indexed_proof = proof.to_indexed_proof();
+                assert_eq!(indexed_proof, merkle_tree.indexed_proof(&[index]));
+                assert!(indexed_proof.verify(root, &[tx_hashes[index]]));
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_reject_malformed_proofs() {
+        let items = items(6);
+        let (root, proofs) = cometbft::proofs_from_byte_slices(&items);
+        let proof = &proofs[4];
+
+        let out_of_range = Proof::new(6, 6, proof.leaf_hash(), proof.aunts().to_vec());
+        assert_eq!(out_of_range.compute_root_hash(), None);
+
+        let short = Proof::new(6, 4, proof.leaf_hash(), proof.aunts()[1..].to_vec());
+        assert_eq!(short.compute_root_hash(), None);
+
+        let mut aunts = proof.aunts().to_vec();
+        aunts.insert(0, [0u8; 32]);
+        let long = Proof::new(6, 4, proof.leaf_hash(), aunts);
+        assert_eq!(long.compute_root_hash(), None);
+
+        let wrong_total = Proof::new(5, 4, proof.leaf_hash(), proof.aunts().to_vec());
+        assert!(!wrong_total.verify(&root, &items[4]));
+
+        let mut aunts = vec![[0u8; 32]; cometbft::MAX_AUNTS + 1];
+        aunts.extend_from_slice(proof.aunts());
+        let too_many_aunts = Proof::new(6, 4, proof.leaf_hash(), aunts);
+        assert!(!too_many_aunts.verify(&root, &items[4]));
+
+        for total in [(1 << (usize::BITS - 1)) + 1, usize::MAX] {
+            let huge_total = Proof::new(total, 0, proof.leaf_hash(), proof.aunts().to_vec());
+            assert_eq!(huge_total.compute_root_hash(), None);
+            assert!(!huge_total.verify(&root, &items[4]));
+        }
+    }
+}
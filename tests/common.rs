@@ -1,13 +1,21 @@
-use rs_merkle::{utils::properties::TreeProperties, Hasher, MerkleTree};
-
+use rs_merkle::{
+    algorithms::Sha256,
+    utils::properties::{DomainSeparation, OddNodeStrategy, TreeProperties},
+    Hasher, MerkleTree,
+};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+#[allow(dead_code)]
 pub struct TestData<T: Hasher> {
     pub leaf_values: Vec<String>,
     pub expected_root_hex: String,
     pub leaf_hashes: Vec<T::Hash>,
 }
 
+#[allow(dead_code)]
 fn combine<T: Clone>(active: Vec<T>, rest: Vec<T>, mut combinations: Vec<Vec<T>>) -> Vec<Vec<T>> {
-    return if rest.is_empty() {
+    if rest.is_empty() {
         if active.is_empty() {
             combinations
         } else {
@@ -17,21 +25,23 @@ fn combine<T: Clone>(active: Vec<T>, rest: Vec<T>, mut combinations: Vec<Vec<T>>
     } else {
         let mut next = active.clone();
 
-        if let Some(first) = rest.get(0) {
+        if let Some(first) = rest.first() {
             next.push(first.clone());
         }
 
         combinations = combine(next, rest.clone().drain(1..).collect(), combinations);
         combinations = combine(active, rest.clone().drain(1..).collect(), combinations);
         combinations
-    };
+    }
 }
 
 /// Create all possible combinations of elements inside a vector without duplicates
+#[allow(dead_code)]
 pub fn combinations<T: Clone>(vec: Vec<T>) -> Vec<Vec<T>> {
     combine(Vec::new(), vec, Vec::new())
 }
 
+#[allow(dead_code)]
 pub fn setup<T: Hasher>(leaf_values: &[&str], expected_root_hex: &str) -> TestData<T> {
     let leaf_hashes: Vec<T::Hash> = leaf_values.iter().map(|x| T::hash(x.as_bytes())).collect();
 
@@ -43,17 +53,20 @@ pub fn setup<T: Hasher>(leaf_values: &[&str], expected_root_hex: &str) -> TestDa
 }
 
 #[derive(Clone)]
+#[allow(dead_code)]
 pub struct ProofTestCases<T: Hasher> {
     pub merkle_tree: MerkleTree<T>,
     pub cases: Vec<MerkleProofTestCase<T>>,
 }
 
 #[derive(Clone)]
+#[allow(dead_code)]
 pub struct MerkleProofTestCase<T: Hasher> {
     pub leaf_indices_to_prove: Vec<usize>,
     pub leaf_hashes_to_prove: Vec<T::Hash>,
 }
 
+#[allow(dead_code)]
 impl<T: Hasher> MerkleProofTestCase<T> {
     fn new(leaf_hashes_to_prove: Vec<T::Hash>, leaf_indices_to_prove: Vec<usize>) -> Self {
         Self {
@@ -64,6 +77,7 @@ impl<T: Hasher> MerkleProofTestCase<T> {
     }
 }
 
+#[allow(dead_code)]
 pub fn setup_proof_test_cases<T: Hasher>(
     tree_properties: TreeProperties,
 ) -> Vec<ProofTestCases<T>> {
@@ -88,7 +102,6 @@ pub fn setup_proof_test_cases<T: Hasher>(
 
             let cases: Vec<MerkleProofTestCase<T>> = possible_proof_elements_combinations
                 .iter()
-                .cloned()
                 .map(|proof_elements| {
                     let (indices, leaves2): (Vec<usize>, Vec<T::Hash>) =
                         proof_elements.iter().cloned().unzip();
@@ -97,8 +110,60 @@ pub fn setup_proof_test_cases<T: Hasher>(
                 .collect();
             let merkle_tree = MerkleTree::<T>::from_leaves(&leaves, tree_properties);
 
-            let case = ProofTestCases { merkle_tree, cases };
-            case
+            ProofTestCases { merkle_tree, cases }
         })
         .collect()
 }
+
+/// Tree properties with the RFC 6962 domain separation
+#[allow(dead_code)]
+pub const fn tree_properties(
+    sorted_pair_enabled: bool,
+    odd_node_strategy: OddNodeStrategy,
+) -> TreeProperties {
+    TreeProperties {
+        sorted_pair_enabled,
+        domain_separation: Some(DomainSeparation::RFC_6962),
+        odd_node_strategy,
+    }
+}
+
+/// Sha256 hashes of the first `len` bytes
+#[allow(dead_code)]
+pub fn leaves(len: u8) -> Vec<[u8; 32]> {
+    (0..len).map(|i| Sha256::hash(&[i])).collect()
+}
+
+/// Directory in the system temp directory that is removed when dropped, so it's cleaned up even
+/// if the test fails
+#[allow(dead_code)]
+pub struct TempDirectory(PathBuf);
+
+impl Deref for TempDirectory {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDirectory {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDirectory {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Path of a temporary directory unique to the test and the process. Anything left there by a
+/// previous run is removed, and the directory itself isn't created.
+#[allow(dead_code)]
+pub fn temp_directory(name: &str) -> TempDirectory {
+    let directory = std::env::temp_dir().join(format!("rs_merkle_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    TempDirectory(directory)
+}
@@ -0,0 +1,159 @@
+mod common;
+
+pub mod compact_multiproof {
+    use crate::common;
+    use rs_merkle::{
+        algorithms::Keccak256,
+        utils::properties::{OddNodeStrategy, TreeProperties},
+        CompactMultiProof, ErrorKind, MerkleTree,
+    };
+
+    fn leaves(len: usize) -> Vec<[u8; 32]> {
+        (0..len)
+            .map(|i| Keccak256::hash(&(i as u64).to_le_bytes()))
+            .collect()
+    }
+
+    #[test]
+    pub fn should_match_openzeppelin_layout_for_a_balanced_tree() {
+        let leaves = leaves(4);
+        let merkle_tree = MerkleTree::<Keccak256>::from_leaves(
+            &leaves,
+            TreeProperties {
+                sorted_pair_enabled: true,
+                ..Default::default()
+            },
+        );
+        let root = merkle_tree.root().unwrap();
+
+        // Leaves 0 and 1 are siblings, leaf 3 needs leaf 2 from the proof, and the two parents
+        // are hashed together from the queue
+        let proof = merkle_tree.compact_multiproof(&[3, 0, 1]).unwrap();
+        assert_eq!(proof.proof_flags(), &[true, false, true]);
+        assert_eq!(proof.proof_hashes(), &[leaves[2]]);
+        assert!(proof.verify(root, &[leaves[0], leaves[1], leaves[3]]));
+    }
+
+    #[test]
+    pub fn should_verify_every_combination_of_leaves() {
+        let strategies = [
+            OddNodeStrategy::Duplicate,
+            OddNodeStrategy::HashWithZero,
+            OddNodeStrategy::PadToPowerOfTwo,
+            OddNodeStrategy::Promote,
+        ];
+
+        for odd_node_strategy in strategies {
+            for leaves_len in 1..=9 {
+                let leaves = leaves(leaves_len);
+                let merkle_tree = MerkleTree::<Keccak256>::from_leaves(
+                    &leaves,
+                    TreeProperties {
+                        sorted_pair_enabled: true,
+                        odd_node_strategy,
+                        ..Default::default()
+                    },
+                );
+                let root = merkle_tree.root().unwrap();
+
+                for indices in common::combinations((0..leaves_len).collect()) {
+                    let proved_leaves: Vec<[u8; 32]> =
+                        indices.iter().map(|index| leaves[*index]).collect();
+
+                    match merkle_tree.compact_multiproof(&indices) {
+                        Ok(proof) => {
+                            assert!(
+                                proof.verify(root, &proved_leaves),
+                                "{:?} {} {:?}",
+                                odd_node_strategy,
+                                leaves_len,
+                                indices
+                            );
+                            assert_eq!(
+                                proof.proof_hashes().len() + proved_leaves.len(),
+                                proof.proof_flags().len() + 1
+                            );
+                        }
+                        // Only promoted nodes can break the queue order
+                        Err(error) => {
+                            assert_eq!(odd_node_strategy, OddNodeStrategy::Promote);
+                            assert_eq!(error.kind(), ErrorKind::UnsupportedTreeProperties);
+                            assert!(!leaves_len.is_power_of_two());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_reject_wrong_leaves() {
+        let leaves = leaves(7);
+        let merkle_tree = MerkleTree::<Keccak256>::from_leaves(
+            &leaves,
+            TreeProperties {
+                sorted_pair_enabled: true,
+                odd_node_strategy: OddNodeStrategy::Duplicate,
+                ..Default::default()
+            },
+        );
+        let root = merkle_tree.root().unwrap();
+        let proof = merkle_tree.compact_multiproof(&[2, 5]).unwrap();
+
+        assert!(!proof.verify(root, &[leaves[2], leaves[6]]));
+        assert!(!proof.verify(root, &[leaves[2]]));
+        assert!(!proof.verify(root, &[leaves[2], leaves[5], leaves[6]]));
+    }
+
+    #[test]
+    pub fn should_handle_empty_and_single_leaf_proofs() {
+        let leaves = leaves(5);
+        let merkle_tree = MerkleTree::<Keccak256>::from_leaves(
+            &leaves,
+            TreeProperties {
+                sorted_pair_enabled: true,
+                odd_node_strategy: OddNodeStrategy::Duplicate,
+                ..Default::default()
+            },
+        );
+        let root = merkle_tree.root().unwrap();
+
+        let proof = merkle_tree.compact_multiproof(&[]).unwrap();
+        assert_eq!(proof.proof_hashes(), &[root]);
+        assert!(proof.verify(root, &[]));
+
+        let single_leaf_tree = MerkleTree::<Keccak256>::from_leaves(
+            &leaves[..1],
+            TreeProperties {
+                sorted_pair_enabled: true,
+                odd_node_strategy: OddNodeStrategy::Duplicate,
+                ..Default::default()
+            },
+        );
+        let proof = single_leaf_tree.compact_multiproof(&[0]).unwrap();
+        assert!(proof.proof_flags().is_empty());
+        assert!(proof.verify(leaves[0], &[leaves[0]]));
+
+        let proof = CompactMultiProof::<Keccak256>::new(vec![], vec![]);
+        assert!(proof.root(&[]).is_err());
+    }
+
+    #[test]
+    pub fn should_reject_unsupported_trees() {
+        let leaves = leaves(4);
+        let unsorted_tree =
+            MerkleTree::<Keccak256>::from_leaves(&leaves, TreeProperties::default());
+        let error = unsorted_tree.compact_multiproof(&[0]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnsupportedTreeProperties);
+
+        let merkle_tree = MerkleTree::<Keccak256>::from_leaves(
+            &leaves,
+            TreeProperties {
+                sorted_pair_enabled: true,
+                ..Default::default()
+            },
+        );
+        let error = merkle_tree.compact_multiproof(&[4]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::LeafIndexOutOfRange);
+    }
+}
@@ -0,0 +1,98 @@
+pub mod consistency_proof {
+    use rs_merkle::{
+        algorithms::{Keccak256, Sha256},
+        utils::properties::TreeProperties,
+        ConsistencyProof, Hasher, MerkleTree,
+    };
+
+    fn should_verify_all_prefixes<T: Hasher>(tree_properties: TreeProperties) {
+        let leaves: Vec<T::Hash> = (0..17u8).map(|i| T::hash(&[i])).collect();
+
+        for new_size in 1..=leaves.len() {
+            let new_tree = MerkleTree::<T>::from_leaves(&leaves[..new_size], tree_properties);
+            let new_root = new_tree.root().unwrap();
+
+            for old_size in 1..=new_size {
+                let old_tree = MerkleTree::<T>::from_leaves(&leaves[..old_size], tree_properties);
+                let old_root = old_tree.root().unwrap();
+                let proof = new_tree.consistency_proof(old_size).unwrap();
+
+                assert!(proof.verify(old_root, new_root, old_size, new_size, tree_properties));
+
+                if old_size < new_size {
+                    let wrong_root = T::hash(b"wrong");
+                    assert!(!proof.verify(
+                        wrong_root,
+                        new_root,
+                        old_size,
+                        new_size,
+                        tree_properties
+                    ));
+                    assert!(!proof.verify(
+                        old_root,
+                        wrong_root,
+                        old_size,
+                        new_size,
+                        tree_properties
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_verify_consistency_of_all_prefixes_sha256() {
+        should_verify_all_prefixes::<Sha256>(TreeProperties::default());
+    }
+
+    #[test]
+    pub fn should_verify_consistency_of_all_prefixes_keccak256_sorted() {
+        should_verify_all_prefixes::<Keccak256>(TreeProperties {
+            sorted_pair_enabled: true,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    pub fn should_match_rfc_6962_proof_sizes() {
+        let tree_properties = TreeProperties::default();
+        let leaves: Vec<[u8; 32]> = (0..7u8).map(|i| Sha256::hash(&[i])).collect();
+        let tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+
+        // Examples from RFC 6962, section 2.1.3
+        assert_eq!(tree.consistency_proof(3).unwrap().proof_hashes().len(), 4);
+        assert_eq!(tree.consistency_proof(4).unwrap().proof_hashes().len(), 1);
+        assert_eq!(tree.consistency_proof(6).unwrap().proof_hashes().len(), 3);
+        assert!(tree.consistency_proof(7).unwrap().proof_hashes().is_empty());
+    }
+
+    #[test]
+    pub fn should_prove_consistency_between_commits() {
+        let tree_properties = TreeProperties::default();
+        let mut tree = MerkleTree::<Sha256>::new(tree_properties);
+
+        tree.append(&mut vec![Sha256::hash(b"a"), Sha256::hash(b"b")])
+            .commit();
+        let old_root = tree.root().unwrap();
+
+        tree.insert(Sha256::hash(b"c")).commit();
+        tree.insert(Sha256::hash(b"d")).commit();
+        let new_root = tree.root().unwrap();
+
+        let proof_bytes = tree.consistency_proof(2).unwrap().to_bytes();
+        let proof = ConsistencyProof::<Sha256>::from_bytes(&proof_bytes).unwrap();
+
+        assert!(proof.verify(old_root, new_root, 2, 4, tree_properties));
+        assert!(!proof.verify(old_root, new_root, 3, 4, tree_properties));
+    }
+
+    #[test]
+    pub fn should_return_error_for_invalid_sizes() {
+        let tree_properties = TreeProperties::default();
+        let leaves: Vec<[u8; 32]> = (0..3u8).map(|i| Sha256::hash(&[i])).collect();
+        let tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+
+        assert!(tree.consistency_proof(0).is_err());
+        assert!(tree.consistency_proof(4).is_err());
+    }
+}
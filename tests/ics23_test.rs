@@ -0,0 +1,275 @@
+mod common;
+
+pub mod ics23 {
+    use crate::common;
+    use ::ics23::HostFunctionsManager;
+    use prost::Message;
+    use rs_merkle::{
+        algorithms::{DoubleSha256, Keccak256, Sha256},
+        ics23::{
+            self, CommitmentProof, ExistenceProof, HashOp, InnerOp, InnerSpec, LeafOp, LengthOp,
+            ProofSpec,
+        },
+        utils::properties::{DomainSeparation, OddNodeStrategy, TreeProperties},
+        ErrorKind, Hasher, MerkleTree,
+    };
+
+    fn entries(len: usize) -> Vec<(Vec<u8>, Vec<u8>)> {
+        (0..len)
+            .map(|i| {
+                let key = format!("key{}", i).into_bytes();
+                let value = format!("value{}", i).into_bytes();
+                (key, value)
+            })
+            .collect()
+    }
+
+    fn round_trip<T: Hasher>(tree_properties: TreeProperties, len: usize) {
+        let entries = entries(len);
+        let leaves: Vec<T::Hash> = entries
+            .iter()
+            .map(|(key, value)| ics23::leaf_hash::<T>(key, value).unwrap())
+            .collect();
+        let merkle_tree = MerkleTree::<T>::from_leaves(&leaves, tree_properties);
+        let root: Vec<u8> = merkle_tree.root().unwrap().into();
+        let spec = ics23::proof_spec::<T>(tree_properties).unwrap();
+
+        for (index, (key, value)) in entries.iter().enumerate() {
+            let proof = merkle_tree.indexed_proof(&[index]);
+            let existence_proof = ExistenceProof::from_indexed_proof(&proof, key, value).unwrap();
+            let message = format!("{} {} {:?}", len, index, tree_properties);
+            assert_eq!(
+                existence_proof.calculate_root().unwrap(),
+                root,
+                "{}",
+                message
+            );
+
+            let commitment_proof = CommitmentProof::from(existence_proof.clone());
+            let bytes = commitment_proof.to_bytes();
+            let parsed = CommitmentProof::from_bytes(&bytes).unwrap();
+            assert_eq!(parsed, commitment_proof);
+            assert!(
+                ics23::verify_membership(&spec, &root, &parsed, key, value),
+                "{}",
+                message
+            );
+            assert!(!ics23::verify_membership(
+                &spec, &root, &parsed, key, b"wrong"
+            ));
+
+            // The reference implementation parses our encodings and accepts the proof
+            let reference_proof = ::ics23::CommitmentProof::decode(bytes.as_slice()).unwrap();
+            let reference_spec = ::ics23::ProofSpec::decode(spec.to_bytes().as_slice()).unwrap();
+            assert!(
+                ::ics23::verify_membership::<HostFunctionsManager>(
+                    &reference_proof,
+                    &reference_spec,
+                    &root,
+                    key,
+                    value
+                ),
+                "{}",
+                message
+            );
+            assert!(!::ics23::verify_membership::<HostFunctionsManager>(
+                &reference_proof,
+                &reference_spec,
+                &root,
+                key,
+                b"wrong"
+            ));
+
+            let converted = existence_proof
+                .to_indexed_proof::<T>(index, len, tree_properties)
+                .unwrap();
+            assert_eq!(converted, proof, "{}", message);
+            assert!(converted.verify(merkle_tree.root().unwrap(), &[leaves[index]]));
+        }
+    }
+
+    /// Tendermint's spec for its simple merkle trees, `TendermintSpec` of ICS23
+    fn tendermint_spec() -> ProofSpec {
+        ProofSpec {
+            leaf_spec: Some(LeafOp {
+                hash: HashOp::Sha256,
+                prehash_key: HashOp::NoHash,
+                prehash_value: HashOp::Sha256,
+                length: LengthOp::VarProto,
+                prefix: vec![0],
+            }),
+            inner_spec: Some(InnerSpec {
+                child_order: vec![0, 1],
+                child_size: 32,
+                min_prefix_length: 1,
+                max_prefix_length: 1,
+                empty_child: vec![],
+                hash: HashOp::Sha256,
+            }),
+            max_depth: 0,
+            min_depth: 0,
+            prehash_key_before_comparison: false,
+        }
+    }
+
+    #[test]
+    pub fn should_round_trip_proofs_of_every_leaf() {
+        let strategies = [
+            OddNodeStrategy::Promote,
+            OddNodeStrategy::Duplicate,
+            OddNodeStrategy::HashWithZero,
+            OddNodeStrategy::PadToPowerOfTwo,
+        ];
+        for len in 1..=9 {
+            for odd_node_strategy in strategies {
+                for sorted_pair_enabled in [false, true] {
+                    round_trip::<Sha256>(
+                        common::tree_properties(sorted_pair_enabled, odd_node_strategy),
+                        len,
+                    );
+                }
+            }
+        }
+        round_trip::<Keccak256>(common::tree_properties(false, OddNodeStrategy::Promote), 7);
+    }
+
+    #[test]
+    pub fn should_verify_proofs_against_the_tendermint_spec() {
+        let leaf_op = tendermint_spec().leaf_spec.unwrap();
+        let left = leaf_op.apply(b"a", b"1").unwrap();
+        let right = leaf_op.apply(b"b", b"2").unwrap();
+        let root = Sha256::hash(&[&[1u8], left.as_slice(), right.as_slice()].concat());
+
+        let mut existence_proof = ExistenceProof {
+            key: b"b".to_vec(),
+            value: b"2".to_vec(),
+            leaf: Some(leaf_op),
+            path: vec![InnerOp {
+                hash: HashOp::Sha256,
+                prefix: [&[1u8], left.as_slice()].concat(),
+                suffix: vec![],
+            }],
+        };
+        assert!(existence_proof.verify(&tendermint_spec(), &root, b"b", b"2"));
+        assert!(!existence_proof.verify(&tendermint_spec(), &root, b"a", b"2"));
+
+        // An inner operation starting with the leaf prefix could be confused with a leaf
+        existence_proof.path[0].prefix[0] = 0;
+        let error = existence_proof
+            .check_against_spec(&tendermint_spec())
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidIcs23Proof);
+
+        // Our spec describes a different leaf operation
+        let spec =
+            ics23::proof_spec::<Sha256>(common::tree_properties(false, OddNodeStrategy::Promote))
+                .unwrap();
+        existence_proof.path[0].prefix[0] = 1;
+        assert!(existence_proof
+            .check_against_spec(&tendermint_spec())
+            .is_ok());
+        assert!(existence_proof.check_against_spec(&spec).is_err());
+    }
+
+    #[test]
+    pub fn should_decode_the_protobuf_encoding() {
+        let bytes = rs_merkle::utils::collections::from_hex_string(
+            "0a180a016b1201761a090801180120012a010022050801120101",
+        )
+        .unwrap();
+        let expected = CommitmentProof::from(ExistenceProof {
+            key: b"k".to_vec(),
+            value: b"v".to_vec(),
+            leaf: Some(LeafOp {
+                hash: HashOp::Sha256,
+                prehash_key: HashOp::NoHash,
+                prehash_value: HashOp::Sha256,
+                length: LengthOp::VarProto,
+                prefix: vec![0],
+            }),
+            path: vec![InnerOp {
+                hash: HashOp::Sha256,
+                prefix: vec![1],
+                suffix: vec![],
+            }],
+        });
+        assert_eq!(CommitmentProof::from_bytes(&bytes).unwrap(), expected);
+        assert_eq!(expected.to_bytes(), bytes);
+
+        let spec = tendermint_spec();
+        assert_eq!(ProofSpec::from_bytes(&spec.to_bytes()).unwrap(), spec);
+        assert_eq!(
+            ::ics23::ProofSpec::decode(spec.to_bytes().as_slice()).unwrap(),
+            ::ics23::tendermint_spec()
+        );
+    }
+
+    #[test]
+    pub fn should_reject_unsupported_trees_and_malformed_proofs() {
+        let error = ics23::proof_spec::<Sha256>(TreeProperties {
+            sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
+        })
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnsupportedTreeProperties);
+        let error = ics23::proof_spec::<Sha256>(TreeProperties {
+            sorted_pair_enabled: false,
+            domain_separation: Some(DomainSeparation {
+                leaf_prefix: 0x00,
+                node_prefix: ics23::KEY_VALUE_PREFIX,
+            }),
+            odd_node_strategy: OddNodeStrategy::Promote,
+        })
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnsupportedTreeProperties);
+        let error = ics23::leaf_hash::<DoubleSha256>(b"k", b"v").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnsupportedTreeProperties);
+        let error = ics23::leaf_hash::<Sha256>(b"", b"v").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidIcs23Proof);
+
+        let tree_properties = common::tree_properties(false, OddNodeStrategy::Promote);
+        let entries = entries(5);
+        let leaves: Vec<[u8; 32]> = entries
+            .iter()
+            .map(|(key, value)| ics23::leaf_hash::<Sha256>(key, value).unwrap())
+            .collect();
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+        let (key, value) = &entries[2];
+
+        let error =
+            ExistenceProof::from_indexed_proof(&merkle_tree.indexed_proof(&[1, 2]), key, value)
+                .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidIcs23Proof);
+
+        let existence_proof =
+            ExistenceProof::from_indexed_proof(&merkle_tree.indexed_proof(&[2]), key, value)
+                .unwrap();
+        let error = existence_proof
+            .to_indexed_proof::<Sha256>(3, 5, tree_properties)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidIcs23Proof);
+        let error = existence_proof
+            .to_indexed_proof::<Sha256>(2, 9, tree_properties)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidIcs23Proof);
+        let error = existence_proof
+            .to_indexed_proof::<Keccak256>(2, 5, tree_properties)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidIcs23Proof);
+
+        let mut tampered = existence_proof.clone();
+        tampered.path[1].prefix[0] = 0x03;
+        let error = tampered
+            .to_indexed_proof::<Sha256>(2, 5, tree_properties)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidIcs23Proof);
+
+        let bytes = CommitmentProof::from(existence_proof).to_bytes();
+        let error = CommitmentProof::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidIcs23Proof);
+        // A non-existence proof, with an empty message
+        let error = CommitmentProof::from_bytes(&[0x12, 0x00]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidIcs23Proof);
+    }
+}
@@ -0,0 +1,193 @@
+mod common;
+
+pub mod merkle_log {
+    use crate::common;
+    use rs_merkle::{algorithms::Sha256, utils::properties::TreeProperties, MerkleLog, MerkleTree};
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+
+    fn leaves(range: std::ops::Range<u8>) -> Vec<[u8; 32]> {
+        range.map(|i| Sha256::hash(&[i])).collect()
+    }
+
+    fn segment_path(directory: &Path, segment_index: usize) -> PathBuf {
+        directory.join(format!("segment_{}", segment_index))
+    }
+
+    /// Writes three commits of 3, 2 and 4 leaves to a new log
+    fn write_log(directory: &Path) -> MerkleTree<Sha256> {
+        let mut merkle_log =
+            MerkleLog::<Sha256>::open(directory, TreeProperties::default()).unwrap();
+        let mut expected_tree = MerkleTree::<Sha256>::new(TreeProperties::default());
+
+        for range in [0..3, 3..5, 5..9] {
+            merkle_log
+                .append(&mut leaves(range.clone()))
+                .commit()
+                .unwrap();
+            expected_tree.append(&mut leaves(range)).commit();
+        }
+
+        expected_tree
+    }
+
+    #[test]
+    pub fn should_persist_commits_and_reopen() {
+        let directory = common::temp_directory("merkle_log_reopen");
+        let mut merkle_log =
+            MerkleLog::<Sha256>::open(&directory, TreeProperties::default()).unwrap();
+        merkle_log.set_segment_size(200);
+        let mut expected_tree = MerkleTree::<Sha256>::new(TreeProperties::default());
+        let mut expected_roots = Vec::new();
+
+        for i in 0..19u8 {
+            merkle_log.insert(Sha256::hash(&[i]));
+            expected_tree.insert(Sha256::hash(&[i]));
+            if i % 3 == 0 {
+                merkle_log.commit().unwrap();
+                expected_tree.commit();
+                expected_roots.push(expected_tree.root());
+            }
+        }
+        drop(merkle_log);
+
+        // Small segment size forces segment rotation
+        assert!(segment_path(&directory, 1).exists());
+
+        let mut merkle_log =
+            MerkleLog::<Sha256>::open(&directory, TreeProperties::default()).unwrap();
+        assert_eq!(merkle_log.leaves_len(), 19);
+        assert_eq!(merkle_log.root(), expected_tree.root());
+        assert_eq!(merkle_log.tree().layers(), expected_tree.layers());
+
+        let checkpoint_roots: Vec<Option<[u8; 32]>> = merkle_log
+            .checkpoints()
+            .iter()
+            .map(|checkpoint| checkpoint.root)
+            .collect();
+        assert_eq!(checkpoint_roots, expected_roots);
+
+        // The reopened log can be appended to
+        merkle_log.insert(Sha256::hash(&[19])).commit().unwrap();
+        expected_tree.insert(Sha256::hash(&[19])).commit();
+        assert_eq!(merkle_log.root(), expected_tree.root());
+
+        let consistency_proof = merkle_log.tree().consistency_proof(7).unwrap();
+        assert!(consistency_proof.verify(
+            expected_roots[2].unwrap(),
+            merkle_log.root().unwrap(),
+            7,
+            20,
+            TreeProperties::default()
+        ));
+    }
+
+    #[test]
+    pub fn should_recover_after_a_torn_commit_record() {
+        let directory = common::temp_directory("merkle_log_torn_record");
+        let expected_tree = write_log(&directory);
+
+        // A crash in the middle of writing the next commit record
+        let mut segment = OpenOptions::new()
+            .append(true)
+            .open(segment_path(&directory, 0))
+            .unwrap();
+        segment.write_all(&[7u8; 45]).unwrap();
+        drop(segment);
+
+        let mut merkle_log =
+            MerkleLog::<Sha256>::open(&directory, TreeProperties::default()).unwrap();
+        assert_eq!(merkle_log.root(), expected_tree.root());
+        assert_eq!(merkle_log.checkpoints().len(), 3);
+
+        merkle_log.append(&mut leaves(9..12)).commit().unwrap();
+        drop(merkle_log);
+
+        let merkle_log = MerkleLog::<Sha256>::open(&directory, TreeProperties::default()).unwrap();
+        let expected_tree =
+            MerkleTree::<Sha256>::from_leaves(&leaves(0..12), TreeProperties::default());
+        assert_eq!(merkle_log.root(), expected_tree.root());
+    }
+
+    #[test]
+    pub fn should_recover_after_a_torn_checkpoint() {
+        let directory = common::temp_directory("merkle_log_torn_checkpoint");
+        write_log(&directory);
+
+        // The last checkpoint is only partially written, so its commit is discarded
+        let checkpoints = OpenOptions::new()
+            .write(true)
+            .open(directory.join("checkpoints"))
+            .unwrap();
+        let checkpoints_len = checkpoints.metadata().unwrap().len();
+        checkpoints.set_len(checkpoints_len - 10).unwrap();
+        drop(checkpoints);
+
+        let merkle_log = MerkleLog::<Sha256>::open(&directory, TreeProperties::default()).unwrap();
+        let expected_tree =
+            MerkleTree::<Sha256>::from_leaves(&leaves(0..5), TreeProperties::default());
+        assert_eq!(merkle_log.root(), expected_tree.root());
+        assert_eq!(merkle_log.leaves_len(), 5);
+        assert_eq!(merkle_log.checkpoints().len(), 2);
+    }
+
+    #[test]
+    pub fn should_remove_segments_without_checkpoints() {
+        let directory = common::temp_directory("merkle_log_stale_segment");
+        let expected_tree = write_log(&directory);
+        std::fs::write(segment_path(&directory, 1), [1u8; 100]).unwrap();
+
+        let merkle_log = MerkleLog::<Sha256>::open(&directory, TreeProperties::default()).unwrap();
+        assert_eq!(merkle_log.root(), expected_tree.root());
+        assert!(!segment_path(&directory, 1).exists());
+    }
+
+    #[test]
+    pub fn should_reject_corrupted_logs() {
+        let directory = common::temp_directory("merkle_log_corrupted");
+        write_log(&directory);
+
+        let mut segment = std::fs::read(segment_path(&directory, 0)).unwrap();
+        segment[20] ^= 1;
+        std::fs::write(segment_path(&directory, 0), &segment).unwrap();
+
+        let err = MerkleLog::<Sha256>::open(&directory, TreeProperties::default())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        std::fs::write(directory.join("checkpoints"), b"not a log").unwrap();
+        let err = MerkleLog::<Sha256>::open(&directory, TreeProperties::default())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    pub fn should_reject_records_without_nodes() {
+        let directory = common::temp_directory("merkle_log_empty_record");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        // A record of one leaf without any nodes, and a valid checkpoint pointing to it
+        let mut record = [1u64.to_le_bytes(), 0u64.to_le_bytes()].concat();
+        record.extend_from_slice(&Sha256::hash(&record));
+        let mut checkpoint = [1u64, 0, record.len() as u64]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect::<Vec<u8>>();
+        checkpoint.extend_from_slice(&leaves(0..1)[0]);
+        checkpoint.extend_from_slice(&Sha256::hash(&checkpoint));
+        std::fs::write(segment_path(&directory, 0), &record).unwrap();
+        std::fs::write(
+            directory.join("checkpoints"),
+            [&b"RSMLOG\x01\x20"[..], &checkpoint].concat(),
+        )
+        .unwrap();
+
+        let err = MerkleLog::<Sha256>::open(&directory, TreeProperties::default())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
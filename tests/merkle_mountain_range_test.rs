@@ -0,0 +1,228 @@
+mod common;
+
+pub mod root {
+    use rs_merkle::{algorithms::Keccak256, Hasher, MerkleMountainRange};
+
+    #[test]
+    pub fn should_bag_peaks_from_right_to_left() {
+        let leaves: Vec<[u8; 32]> = ["a", "b", "c"]
+            .iter()
+            .map(|x| Keccak256::hash(x.as_bytes()))
+            .collect();
+
+        let mmr = MerkleMountainRange::<Keccak256>::from_leaves(&leaves);
+        let left_peak = Keccak256::concat_and_hash(&leaves[0], Some(&leaves[1]));
+
+        assert_eq!(mmr.size(), 4);
+        assert_eq!(mmr.peaks(), vec![left_peak, leaves[2]]);
+        assert_eq!(
+            mmr.root(),
+            Some(Keccak256::concat_and_hash(&leaves[2], Some(&left_peak)))
+        );
+    }
+
+    #[test]
+    pub fn should_not_change_existing_nodes_when_appending() {
+        let mut mmr = MerkleMountainRange::<Keccak256>::new();
+        assert_eq!(mmr.root(), None);
+
+        let mut previous_nodes = Vec::new();
+        for i in 0..20u8 {
+            let position = mmr.push(Keccak256::hash(&[i]));
+
+            assert_eq!(mmr.nodes()[position], Keccak256::hash(&[i]));
+            assert_eq!(mmr.leaf(i as usize), Some(Keccak256::hash(&[i])));
+            assert_eq!(&mmr.nodes()[..previous_nodes.len()], &previous_nodes[..]);
+            assert_eq!(mmr.peaks().len(), (i as usize + 1).count_ones() as usize);
+            previous_nodes = mmr.nodes().to_vec();
+        }
+
+        assert_eq!(mmr.leaves_len(), 20);
+        assert_eq!(mmr.size(), 2 * 20 - 2);
+    }
+}
+
+pub mod proof {
+    use crate::common;
+    use rs_merkle::{
+        algorithms::Keccak256, ErrorKind, Hasher, MerkleMountainRange, MerkleMountainRangeProof,
+    };
+
+    fn setup(leaves_count: u8) -> (MerkleMountainRange<Keccak256>, Vec<[u8; 32]>) {
+        let leaves: Vec<[u8; 32]> = (0..leaves_count).map(|i| Keccak256::hash(&[i])).collect();
+        (MerkleMountainRange::from_leaves(&leaves), leaves)
+    }
+
+    #[test]
+    pub fn should_verify_single_leaf_proofs() {
+        for leaves_count in 1..=17 {
+            let (mmr, leaves) = setup(leaves_count);
+            let root = mmr.root().unwrap();
+
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = mmr.proof(&[index]).unwrap();
+
+                assert!(proof.verify(root, &[index], &[*leaf]));
+                assert!(proof.verify_with_peaks(&mmr.peaks(), &[index], &[*leaf]));
+                assert!(!proof.verify(root, &[index], &[Keccak256::hash(b"wrong")]));
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_verify_multi_leaf_proofs() {
+        for leaves_count in 1..=11 {
+            let (mmr, leaves) = setup(leaves_count);
+            let root = mmr.root().unwrap();
+            let indices: Vec<usize> = (0..leaves.len()).collect();
+
+            for indices_to_prove in common::combinations(indices) {
+                let leaves_to_prove: Vec<[u8; 32]> =
+                    indices_to_prove.iter().map(|i| leaves[*i]).collect();
+                let proof = mmr.proof(&indices_to_prove).unwrap();
+
+                assert!(proof.verify(root, &indices_to_prove, &leaves_to_prove));
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_bag_peaks_to_the_right_of_proven_leaves() {
+        // 7 leaves form mountains of 4, 2 and 1 leaves
+        let (mmr, leaves) = setup(7);
+        let peaks = mmr.peaks();
+
+        let proof = mmr.proof(&[0]).unwrap();
+        let bagged_rhs_peaks = Keccak256::concat_and_hash(&peaks[2], Some(&peaks[1]));
+
+        assert_eq!(proof.proof_hashes().len(), 3);
+        assert_eq!(proof.proof_hashes()[2], bagged_rhs_peaks);
+        assert!(proof.verify(mmr.root().unwrap(), &[0], &[leaves[0]]));
+    }
+
+    #[test]
+    pub fn should_return_error_for_out_of_range_leaves() {
+        let (mmr, leaves) = setup(5);
+        let proof = mmr.proof(&[1]).unwrap();
+
+        assert!(mmr.proof(&[5]).is_err());
+        assert!(proof.root(&[5], &[leaves[1]]).is_err());
+        assert!(proof.root(&[1, 2], &[leaves[1]]).is_err());
+    }
+
+    #[test]
+    pub fn should_reject_repeated_leaves_with_conflicting_hashes() {
+        let (mmr, leaves) = setup(5);
+        let root = mmr.root().unwrap();
+        let proof = mmr.proof(&[1, 1]).unwrap();
+        let forged = Keccak256::hash(b"forged");
+
+        assert!(proof.verify(root, &[1, 1], &[leaves[1], leaves[1]]));
+        assert!(!proof.verify(root, &[1, 1], &[leaves[1], forged]));
+        assert!(!proof.verify(root, &[1, 1], &[forged, leaves[1]]));
+        let error = proof.root(&[1, 1], &[leaves[1], forged]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ConflictingLeaves);
+    }
+
+    #[test]
+    pub fn should_return_error_for_too_many_leaves() {
+        let (_, leaves) = setup(1);
+        for leaves_count in [1 << (usize::BITS - 1), usize::MAX] {
+            let error = MerkleMountainRangeProof::<Keccak256>::new(leaves_count, vec![])
+                .err()
+                .unwrap();
+            assert_eq!(error.kind(), ErrorKind::TooManyLeaves);
+        }
+
+        // The largest range has a peak for every bit of the leaves count, the last leaf being
+        // a peak itself
+        let leaves_count = (1 << (usize::BITS - 1)) - 1;
+        let proof = MerkleMountainRangeProof::<Keccak256>::new(
+            leaves_count,
+            vec![leaves[0]; usize::BITS as usize - 2],
+        )
+        .unwrap();
+        let leaf_index = leaves_count - 1;
+        assert!(proof.root(&[leaf_index], &[leaves[0]]).is_ok());
+        assert!(!proof.verify_with_peaks(&[leaves[0]], &[leaf_index], &[leaves[0]]));
+    }
+}
+
+pub mod substrate {
+    use crate::common;
+    use polkadot_ckb_merkle_mountain_range::{
+        leaf_index_to_pos, util::MemMMR, util::MemStore, Merge, MerkleProof,
+    };
+    use rs_merkle::{algorithms::Keccak256, MerkleMountainRange};
+
+    /// Keccak256 merge of Substrate's MMR pallet, which uses the `merkle-mountain-range` crate
+    /// as the reference implementation
+    struct Keccak256Merge;
+
+    impl Merge for Keccak256Merge {
+        type Item = [u8; 32];
+
+        fn merge(
+            left: &Self::Item,
+            right: &Self::Item,
+        ) -> polkadot_ckb_merkle_mountain_range::Result<Self::Item> {
+            Ok(Keccak256::hash(
+                &[left.as_slice(), right.as_slice()].concat(),
+            ))
+        }
+    }
+
+    fn leaves(leaves_count: u8) -> Vec<[u8; 32]> {
+        (0..leaves_count).map(|i| Keccak256::hash(&[i])).collect()
+    }
+
+    #[test]
+    pub fn should_match_reference_roots_with_peak_bagging() {
+        for leaves_count in 1..=40 {
+            let leaves = leaves(leaves_count);
+            let store = MemStore::default();
+            let mut reference = MemMMR::<_, Keccak256Merge>::new(0, &store);
+            for leaf in &leaves {
+                reference.push(*leaf).unwrap();
+            }
+            let mmr = MerkleMountainRange::<Keccak256>::from_leaves(&leaves);
+
+            assert_eq!(mmr.size() as u64, reference.mmr_size());
+            assert_eq!(mmr.root(), Some(reference.get_root().unwrap()));
+        }
+    }
+
+    #[test]
+    pub fn should_match_reference_multi_leaf_proofs() {
+        for leaves_count in 1..=11 {
+            let leaves = leaves(leaves_count);
+            let store = MemStore::default();
+            let mut reference = MemMMR::<_, Keccak256Merge>::new(0, &store);
+            for leaf in &leaves {
+                reference.push(*leaf).unwrap();
+            }
+            let reference_root = reference.get_root().unwrap();
+            let mmr = MerkleMountainRange::<Keccak256>::from_leaves(&leaves);
+
+            for indices in common::combinations((0..leaves.len()).collect()) {
+                let positions: Vec<u64> = indices
+                    .iter()
+                    .map(|i| leaf_index_to_pos(*i as u64))
+                    .collect();
+                let reference_proof = reference.gen_proof(positions.clone()).unwrap();
+                let proof = mmr.proof(&indices).unwrap();
+                assert_eq!(proof.proof_hashes(), reference_proof.proof_items());
+
+                // Proofs of each implementation are accepted by the other one
+                let proven_leaves: Vec<[u8; 32]> = indices.iter().map(|i| leaves[*i]).collect();
+                assert!(proof.verify(reference_root, &indices, &proven_leaves));
+                let converted = MerkleProof::<[u8; 32], Keccak256Merge>::new(
+                    reference.mmr_size(),
+                    proof.proof_hashes().to_vec(),
+                );
+                let reference_leaves = positions.into_iter().zip(proven_leaves).collect();
+                assert!(converted.verify(reference_root, reference_leaves).unwrap());
+            }
+        }
+    }
+}
@@ -4,8 +4,8 @@ pub mod root {
     use crate::common;
     use rs_merkle::{
         algorithms::{Keccak256, Sha256},
-        utils::properties::TreeProperties,
-        Error, Hasher, MerkleTree,
+        utils::properties::{OddNodeStrategy, TreeProperties},
+        Error, ErrorKind, Hasher, MerkleProof, MerkleTree,
     };
     use std::time::Instant;
 
@@ -31,6 +31,8 @@ pub mod root {
         let expected_root_hex = "1f7379539707bcaea00564168d1d4d626b09b73f8a2a365234c62d763f854da2";
         let tree_properties = TreeProperties {
             sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
         };
         should_return_a_correct_root::<Sha256>(&leaf_values, expected_root_hex, tree_properties)
     }
@@ -40,6 +42,8 @@ pub mod root {
         let expected_root_hex = "9012f1e18a87790d2e01faace75aaaca38e53df437cdce2c0552464dda4af49c";
         let tree_properties = TreeProperties {
             sorted_pair_enabled: true,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
         };
         should_return_a_correct_root::<Keccak256>(&leaf_values, expected_root_hex, tree_properties)
     }
@@ -49,14 +53,14 @@ pub mod root {
         expected_root_hex: &str,
         tree_properties: TreeProperties,
     ) -> Result<(), Error> {
-        let test_data = common::setup::<T>(&leaf_values, expected_root_hex);
+        let test_data = common::setup::<T>(leaf_values, expected_root_hex);
         let expected_root = test_data.expected_root_hex.clone();
         let leaf_hashes = &test_data.leaf_hashes;
         let indices_to_prove = vec![3, 4];
 
         let leaves_to_prove: Vec<T::Hash> = indices_to_prove
             .iter()
-            .map(|i| leaf_hashes.get(*i).unwrap().clone())
+            .map(|i| *leaf_hashes.get(*i).unwrap())
             .collect();
 
         let merkle_tree = MerkleTree::<T>::from_leaves(&test_data.leaf_hashes, tree_properties);
@@ -108,13 +112,39 @@ pub mod root {
 
         Ok(())
     }
+
+    #[test]
+    pub fn should_return_error_when_proof_hashes_dont_match_indices() {
+        let tree_properties = TreeProperties::default();
+        let test_data = common::setup::<Sha256>(&["a", "b", "c", "d", "e"], "");
+        let leaf_hashes = &test_data.leaf_hashes;
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(leaf_hashes, tree_properties);
+        let proof_hashes = merkle_tree.proof(&[2]).proof_hashes().to_vec();
+
+        let mut extra_hashes = proof_hashes.clone();
+        extra_hashes.push([0; 32]);
+        let error = MerkleProof::<Sha256>::new(extra_hashes)
+            .root(&[2], &[leaf_hashes[2]], 5, tree_properties)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TooManyProofHashes);
+
+        let error = MerkleProof::<Sha256>::new(proof_hashes[1..].to_vec())
+            .root(&[2], &[leaf_hashes[2]], 5, tree_properties)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotEnoughHashesToCalculateRoot);
+
+        let error = MerkleProof::<Sha256>::new(proof_hashes)
+            .root(&[5], &[leaf_hashes[2]], 5, tree_properties)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::LeafIndexOutOfRange);
+    }
 }
 
 pub mod to_bytes {
     use crate::common;
     use rs_merkle::{
         algorithms::{Keccak256, Sha256},
-        utils::properties::TreeProperties,
+        utils::properties::{OddNodeStrategy, TreeProperties},
         Hasher, MerkleTree,
     };
 
@@ -133,6 +163,8 @@ pub mod to_bytes {
         let expected_root_hex = "1f7379539707bcaea00564168d1d4d626b09b73f8a2a365234c62d763f854da2";
         let tree_properties = TreeProperties {
             sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
         };
         should_correctly_serialize_to_bytes::<Sha256>(
             &leaf_values,
@@ -155,6 +187,8 @@ pub mod to_bytes {
         let expected_root_hex = "9012f1e18a87790d2e01faace75aaaca38e53df437cdce2c0552464dda4af49c";
         let tree_properties = TreeProperties {
             sorted_pair_enabled: true,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
         };
         should_correctly_serialize_to_bytes::<Keccak256>(
             &leaf_values,
@@ -170,7 +204,7 @@ pub mod to_bytes {
         expected_bytes: Vec<u8>,
         tree_properties: TreeProperties,
     ) {
-        let test_data = common::setup::<T>(&leaf_values, expected_root_hex);
+        let test_data = common::setup::<T>(leaf_values, expected_root_hex);
         let indices_to_prove = vec![3, 4];
         let merkle_tree = MerkleTree::<T>::from_leaves(&test_data.leaf_hashes, tree_properties);
         let proof = merkle_tree.proof(&indices_to_prove);
@@ -260,3 +294,179 @@ pub mod from_bytes {
         );
     }
 }
+
+pub mod domain_separation {
+    use crate::common;
+    use rs_merkle::{
+        algorithms::Sha256,
+        utils::properties::{DomainSeparation, OddNodeStrategy, TreeProperties},
+        MerkleProof, MerkleTree,
+    };
+
+    #[test]
+    pub fn should_verify_proofs_with_domain_separation() {
+        let tree_properties = TreeProperties {
+            sorted_pair_enabled: false,
+            domain_separation: Some(DomainSeparation::RFC_6962),
+            odd_node_strategy: OddNodeStrategy::Promote,
+        };
+
+        for leaves_count in 1..=9u8 {
+            let leaves: Vec<[u8; 32]> = (0..leaves_count).map(|i| Sha256::hash(&[i])).collect();
+            let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+            let root = merkle_tree.root().unwrap();
+            let indices: Vec<usize> = (0..leaves.len()).collect();
+
+            for indices_to_prove in common::combinations(indices) {
+                let leaves_to_prove: Vec<[u8; 32]> =
+                    indices_to_prove.iter().map(|i| leaves[*i]).collect();
+                let proof = merkle_tree.proof(&indices_to_prove);
+
+                assert!(proof.verify(
+                    root,
+                    &indices_to_prove,
+                    &leaves_to_prove,
+                    leaves.len(),
+                    tree_properties
+                ));
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_reject_interior_nodes_passed_as_leaves() {
+        let leaves: Vec<[u8; 32]> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|x| Sha256::hash(x.as_bytes()))
+            .collect();
+
+        for domain_separation in [None, Some(DomainSeparation::RFC_6962)] {
+            let tree_properties = TreeProperties {
+                sorted_pair_enabled: false,
+                domain_separation,
+                odd_node_strategy: OddNodeStrategy::Promote,
+            };
+            let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+            let root = merkle_tree.root().unwrap();
+            let layers = merkle_tree.layers();
+
+            // Pretending that the first interior node is a leaf of a 2-leaf tree
+            let forged_leaf = layers[1][0];
+            let forged_proof = MerkleProof::<Sha256>::new(vec![layers[1][1]]);
+            let is_forgery_accepted =
+                forged_proof.verify(root, &[0], &[forged_leaf], 2, tree_properties);
+
+            assert_eq!(is_forgery_accepted, domain_separation.is_none());
+        }
+    }
+}
+
+pub mod odd_node_strategy {
+    use crate::common;
+    use rs_merkle::{
+        algorithms::Sha256,
+        utils::properties::{OddNodeStrategy, TreeProperties},
+        Hasher, MerkleTree,
+    };
+
+    fn tree_properties(odd_node_strategy: OddNodeStrategy) -> TreeProperties {
+        TreeProperties {
+            sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy,
+        }
+    }
+
+    #[test]
+    pub fn should_verify_proofs_with_every_strategy() {
+        let strategies = [
+            OddNodeStrategy::Promote,
+            OddNodeStrategy::Duplicate,
+            OddNodeStrategy::HashWithZero,
+            OddNodeStrategy::PadToPowerOfTwo,
+        ];
+
+        for strategy in strategies {
+            let tree_properties = tree_properties(strategy);
+
+            for leaves_count in 1..=9u8 {
+                let leaves: Vec<[u8; 32]> = (0..leaves_count).map(|i| Sha256::hash(&[i])).collect();
+                let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+                let root = merkle_tree.root().unwrap();
+                let indices: Vec<usize> = (0..leaves.len()).collect();
+
+                for indices_to_prove in common::combinations(indices) {
+                    let leaves_to_prove: Vec<[u8; 32]> =
+                        indices_to_prove.iter().map(|i| leaves[*i]).collect();
+                    let proof = merkle_tree.proof(&indices_to_prove);
+
+                    assert!(proof.verify(
+                        root,
+                        &indices_to_prove,
+                        &leaves_to_prove,
+                        leaves.len(),
+                        tree_properties
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_calculate_roots_according_to_strategy() {
+        let leaves: Vec<[u8; 32]> = ["a", "b", "c"]
+            .iter()
+            .map(|x| Sha256::hash(x.as_bytes()))
+            .collect();
+        let zero = [0u8; 32];
+        let hash = |left: &[u8; 32], right: &[u8; 32]| Sha256::concat_and_hash(left, Some(right));
+        let left_subtree = hash(&leaves[0], &leaves[1]);
+
+        let expected_roots = [
+            (OddNodeStrategy::Promote, hash(&left_subtree, &leaves[2])),
+            (
+                OddNodeStrategy::Duplicate,
+                hash(&left_subtree, &hash(&leaves[2], &leaves[2])),
+            ),
+            (
+                OddNodeStrategy::HashWithZero,
+                hash(&left_subtree, &hash(&leaves[2], &zero)),
+            ),
+            (
+                OddNodeStrategy::PadToPowerOfTwo,
+                hash(&left_subtree, &hash(&leaves[2], &zero)),
+            ),
+        ];
+
+        for (strategy, expected_root) in expected_roots {
+            let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties(strategy));
+            assert_eq!(merkle_tree.root(), Some(expected_root));
+
+            // Single leaf tree root is the leaf itself
+            let single_leaf_tree =
+                MerkleTree::<Sha256>::from_leaves(&leaves[..1], tree_properties(strategy));
+            assert_eq!(single_leaf_tree.root(), Some(leaves[0]));
+        }
+    }
+
+    #[test]
+    pub fn should_match_a_padded_tree() {
+        let leaves: Vec<[u8; 32]> = (0..5u8).map(|i| Sha256::hash(&[i])).collect();
+        let mut padded_leaves = leaves.clone();
+        padded_leaves.resize(8, [0u8; 32]);
+
+        let padded_tree = MerkleTree::<Sha256>::from_leaves(
+            &padded_leaves,
+            tree_properties(OddNodeStrategy::Promote),
+        );
+
+        // Appending leaves one by one must give the same result as building the tree at once
+        let mut merkle_tree =
+            MerkleTree::<Sha256>::new(tree_properties(OddNodeStrategy::PadToPowerOfTwo));
+        for leaf in &leaves {
+            merkle_tree.insert(*leaf).commit();
+        }
+
+        assert_eq!(merkle_tree.root(), padded_tree.root());
+    }
+}
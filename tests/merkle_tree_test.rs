@@ -4,7 +4,7 @@ pub mod root {
     use crate::common;
     use rs_merkle::{
         algorithms::{Keccak256, Sha256},
-        utils::properties::TreeProperties,
+        utils::properties::{OddNodeStrategy, TreeProperties},
         MerkleTree,
     };
 
@@ -14,6 +14,8 @@ pub mod root {
         let expected_root_hex = "1f7379539707bcaea00564168d1d4d626b09b73f8a2a365234c62d763f854da2";
         let tree_properties = TreeProperties {
             sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
         };
         let test_data = common::setup::<Sha256>(&leaf_values, expected_root_hex);
 
@@ -32,6 +34,8 @@ pub mod root {
         let expected_root_hex = "9012f1e18a87790d2e01faace75aaaca38e53df437cdce2c0552464dda4af49c";
         let tree_properties = TreeProperties {
             sorted_pair_enabled: true,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
         };
         let test_data = common::setup::<Keccak256>(&leaf_values, expected_root_hex);
 
@@ -49,7 +53,7 @@ pub mod tree_depth {
     use crate::common;
     use rs_merkle::{
         algorithms::{Keccak256, Sha256},
-        utils::properties::TreeProperties,
+        utils::properties::{OddNodeStrategy, TreeProperties},
         MerkleTree,
     };
 
@@ -59,6 +63,8 @@ pub mod tree_depth {
         let expected_root_hex = "1f7379539707bcaea00564168d1d4d626b09b73f8a2a365234c62d763f854da2";
         let tree_properties = TreeProperties {
             sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
         };
         let test_data = common::setup::<Sha256>(&leaf_values, expected_root_hex);
 
@@ -75,6 +81,8 @@ pub mod tree_depth {
         let expected_root_hex = "9012f1e18a87790d2e01faace75aaaca38e53df437cdce2c0552464dda4af49c";
         let tree_properties = TreeProperties {
             sorted_pair_enabled: true,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
         };
         let test_data = common::setup::<Keccak256>(&leaf_values, expected_root_hex);
 
@@ -90,7 +98,7 @@ pub mod proof {
     use crate::common;
     use rs_merkle::{
         algorithms::{Keccak256, Sha256},
-        utils::properties::TreeProperties,
+        utils::properties::{OddNodeStrategy, TreeProperties},
         MerkleTree,
     };
 
@@ -100,6 +108,8 @@ pub mod proof {
         let expected_root_hex = "1f7379539707bcaea00564168d1d4d626b09b73f8a2a365234c62d763f854da2";
         let tree_properties = TreeProperties {
             sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
         };
         let test_data = common::setup::<Sha256>(&leaf_values, expected_root_hex);
         let indices_to_prove = vec![3, 4];
@@ -123,6 +133,8 @@ pub mod proof {
         let expected_root_hex = "9012f1e18a87790d2e01faace75aaaca38e53df437cdce2c0552464dda4af49c";
         let tree_properties = TreeProperties {
             sorted_pair_enabled: true,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
         };
         let test_data = common::setup::<Keccak256>(&leaf_values, expected_root_hex);
         let indices_to_prove = vec![3, 4];
@@ -145,7 +157,8 @@ pub mod commit {
     use crate::common;
     use rs_merkle::{
         algorithms::{Keccak256, Sha256},
-        utils::properties::TreeProperties,
+        node_stores::MemoryNodeStore,
+        utils::properties::{OddNodeStrategy, TreeProperties},
         MerkleTree,
     };
 
@@ -155,6 +168,8 @@ pub mod commit {
         let expected_root_hex = "1f7379539707bcaea00564168d1d4d626b09b73f8a2a365234c62d763f854da2";
         let tree_properties = TreeProperties {
             sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
         };
         let test_data = common::setup::<Sha256>(&leaf_values, expected_root_hex);
         let expected_root = test_data.expected_root_hex.clone();
@@ -163,7 +178,7 @@ pub mod commit {
 
         // Passing empty vec to create an empty tree
         let mut merkle_tree = MerkleTree::<Sha256>::from_leaves(&vec, tree_properties);
-        let merkle_tree2 = MerkleTree::<Sha256>::from_leaves(&leaf_hashes, tree_properties);
+        let merkle_tree2 = MerkleTree::<Sha256>::from_leaves(leaf_hashes, tree_properties);
         // Adding leaves
         merkle_tree.append(leaf_hashes.clone().as_mut());
         let root = merkle_tree.uncommitted_root_hex();
@@ -216,6 +231,8 @@ pub mod commit {
         let expected_root_hex = "9012f1e18a87790d2e01faace75aaaca38e53df437cdce2c0552464dda4af49c";
         let tree_properties = TreeProperties {
             sorted_pair_enabled: true,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
         };
         let test_data = common::setup::<Keccak256>(&leaf_values, expected_root_hex);
         let expected_root = test_data.expected_root_hex.clone();
@@ -224,7 +241,7 @@ pub mod commit {
 
         // Passing empty vec to create an empty tree
         let mut merkle_tree = MerkleTree::<Keccak256>::from_leaves(&vec, tree_properties);
-        let merkle_tree2 = MerkleTree::<Keccak256>::from_leaves(&leaf_hashes, tree_properties);
+        let merkle_tree2 = MerkleTree::<Keccak256>::from_leaves(leaf_hashes, tree_properties);
         // Adding leaves
         merkle_tree.append(leaf_hashes.clone().as_mut());
         let root = merkle_tree.uncommitted_root_hex();
@@ -279,6 +296,8 @@ pub mod commit {
         let elements = ["a", "b", "c", "d", "e", "f"];
         let tree_properties = TreeProperties {
             sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
         };
         let mut leaves: Vec<[u8; 32]> = elements
             .iter()
@@ -352,6 +371,8 @@ pub mod commit {
         let elements = ["a", "b", "c", "d", "e", "f"];
         let tree_properties = TreeProperties {
             sorted_pair_enabled: true,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
         };
         let mut leaves: Vec<[u8; 32]> = elements
             .iter()
@@ -424,12 +445,46 @@ pub mod commit {
             Some("9012f1e18a87790d2e01faace75aaaca38e53df437cdce2c0552464dda4af49c".to_string())
         );
     }
+
+    /// A tree whose store is missing the left node of the first layer, which is needed to
+    /// recalculate the root after an update of the last leaf
+    fn tree_with_missing_node() -> MerkleTree<Sha256> {
+        let merkle_tree =
+            MerkleTree::<Sha256>::from_leaves(&common::leaves(4), TreeProperties::default());
+        let mut layers: Vec<Vec<(usize, [u8; 32])>> = merkle_tree
+            .layers()
+            .into_iter()
+            .map(|layer| layer.into_iter().enumerate().collect())
+            .collect();
+        layers[1].remove(0);
+        let store = MemoryNodeStore::from_layers(layers);
+        let mut merkle_tree = MerkleTree::with_store(store, TreeProperties::default()).unwrap();
+        merkle_tree.update(3, Sha256::hash("e".as_bytes())).unwrap();
+        merkle_tree
+    }
+
+    #[test]
+    pub fn should_return_error_when_nodes_are_missing() {
+        let mut merkle_tree = tree_with_missing_node();
+        let root = merkle_tree.root();
+
+        assert!(merkle_tree.try_uncommitted_root().is_err());
+        assert!(merkle_tree.try_commit().is_err());
+        assert_eq!(merkle_tree.root(), root);
+        assert_eq!(merkle_tree.history().len(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "failed to commit the changes")]
+    pub fn should_panic_when_nodes_are_missing() {
+        tree_with_missing_node().commit();
+    }
 }
 
 pub mod rollback {
     use rs_merkle::{
         algorithms::{Keccak256, Sha256},
-        utils::properties::TreeProperties,
+        utils::properties::{OddNodeStrategy, TreeProperties},
         MerkleTree,
     };
 
@@ -438,6 +493,8 @@ pub mod rollback {
         let leaf_values = ["a", "b", "c", "d", "e", "f"];
         let tree_properties = TreeProperties {
             sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
         };
         let leaves: Vec<[u8; 32]> = leaf_values
             .iter()
@@ -517,6 +574,8 @@ pub mod rollback {
         let leaf_values = ["a", "b", "c", "d", "e", "f"];
         let tree_properties = TreeProperties {
             sorted_pair_enabled: true,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
         };
         let leaves: Vec<[u8; 32]> = leaf_values
             .iter()
@@ -596,3 +655,871 @@ pub mod rollback {
         );
     }
 }
+
+pub mod domain_separation {
+    use rs_merkle::{
+        algorithms::Sha256,
+        utils::properties::{DomainSeparation, OddNodeStrategy, TreeProperties},
+        MerkleTree,
+    };
+
+    #[test]
+    pub fn should_prefix_leaves_and_interior_nodes() {
+        let tree_properties = TreeProperties {
+            sorted_pair_enabled: false,
+            domain_separation: Some(DomainSeparation::RFC_6962),
+            odd_node_strategy: OddNodeStrategy::Promote,
+        };
+        let leaves: Vec<[u8; 32]> = ["a", "b", "c"]
+            .iter()
+            .map(|x| Sha256::hash(x.as_bytes()))
+            .collect();
+
+        let leaf_hash = |leaf: &[u8; 32]| Sha256::hash(&[&[0x00], &leaf[..]].concat());
+        let node_hash = |left: [u8; 32], right: [u8; 32]| {
+            Sha256::hash(&[&[0x01], &left[..], &right[..]].concat())
+        };
+        let expected_root = node_hash(
+            node_hash(leaf_hash(&leaves[0]), leaf_hash(&leaves[1])),
+            leaf_hash(&leaves[2]),
+        );
+
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+        assert_eq!(merkle_tree.root(), Some(expected_root));
+
+        // Incremental commits must give the same root
+        let mut incremental_tree = MerkleTree::<Sha256>::new(tree_properties);
+        for leaf in &leaves {
+            incremental_tree.insert(*leaf).commit();
+        }
+        assert_eq!(incremental_tree.root(), Some(expected_root));
+    }
+
+    #[test]
+    pub fn should_keep_the_roots_of_previous_versions_by_default() {
+        let leaves: Vec<[u8; 32]> = ["a", "b", "c", "d", "e", "f"]
+            .iter()
+            .map(|x| Sha256::hash(x.as_bytes()))
+            .collect();
+        let tree_properties = TreeProperties {
+            sorted_pair_enabled: false,
+            ..Default::default()
+        };
+        assert_eq!(tree_properties.domain_separation, None);
+
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+        assert_eq!(
+            merkle_tree.root_hex().unwrap(),
+            "1f7379539707bcaea00564168d1d4d626b09b73f8a2a365234c62d763f854da2"
+        );
+    }
+}
+
+pub mod update {
+    use rs_merkle::{
+        algorithms::{Keccak256, Sha256},
+        utils::properties::{DomainSeparation, OddNodeStrategy, TreeProperties},
+        Hasher, MerkleTree,
+    };
+
+    fn should_match_rebuilt_tree<T: Hasher>(tree_properties: TreeProperties) {
+        for leaves_count in 1..=17usize {
+            let leaves: Vec<T::Hash> = (0..leaves_count).map(|i| T::hash(&[i as u8])).collect();
+
+            for index in 0..leaves_count {
+                let mut merkle_tree = MerkleTree::<T>::from_leaves(&leaves, tree_properties);
+                let new_leaf = T::hash(b"updated");
+                merkle_tree.update(index, new_leaf).unwrap().commit();
+
+                let mut expected_leaves = leaves.clone();
+                expected_leaves[index] = new_leaf;
+                let expected_tree = MerkleTree::<T>::from_leaves(&expected_leaves, tree_properties);
+
+                assert_eq!(merkle_tree.layers(), expected_tree.layers());
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_update_leaves_with_all_tree_properties() {
+        let strategies = [
+            OddNodeStrategy::Promote,
+            OddNodeStrategy::Duplicate,
+            OddNodeStrategy::HashWithZero,
+            OddNodeStrategy::PadToPowerOfTwo,
+        ];
+
+        for odd_node_strategy in strategies {
+            for domain_separation in [None, Some(DomainSeparation::RFC_6962)] {
+                should_match_rebuilt_tree::<Sha256>(TreeProperties {
+                    sorted_pair_enabled: false,
+                    domain_separation,
+                    odd_node_strategy,
+                });
+                should_match_rebuilt_tree::<Keccak256>(TreeProperties {
+                    sorted_pair_enabled: true,
+                    domain_separation,
+                    odd_node_strategy,
+                });
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_update_many_leaves_together_with_appended_leaves() {
+        let tree_properties = TreeProperties {
+            sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
+        };
+        let leaves: Vec<[u8; 32]> = (0..11u8).map(|i| Sha256::hash(&[i])).collect();
+        let mut merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves[..7], tree_properties);
+
+        merkle_tree.append(leaves[7..].to_vec().as_mut());
+        let new_leaves = [Sha256::hash(b"x"), Sha256::hash(b"y"), Sha256::hash(b"z")];
+        // Index 9 is an uncommitted leaf, so it's replaced before being committed
+        merkle_tree.update_many(&[6, 1, 9], &new_leaves).unwrap();
+
+        let mut expected_leaves = leaves.clone();
+        expected_leaves[6] = new_leaves[0];
+        expected_leaves[1] = new_leaves[1];
+        expected_leaves[9] = new_leaves[2];
+        let expected_tree = MerkleTree::<Sha256>::from_leaves(&expected_leaves, tree_properties);
+
+        // Changes are staged until committed
+        assert_eq!(
+            merkle_tree.root(),
+            MerkleTree::<Sha256>::from_leaves(&leaves[..7], tree_properties).root()
+        );
+        assert_eq!(merkle_tree.uncommitted_root(), expected_tree.root());
+
+        merkle_tree.commit();
+        assert_eq!(merkle_tree.layers(), expected_tree.layers());
+    }
+
+    #[test]
+    pub fn should_rollback_and_abort_updates() {
+        let tree_properties = TreeProperties {
+            sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
+        };
+        let leaves: Vec<[u8; 32]> = (0..5u8).map(|i| Sha256::hash(&[i])).collect();
+        let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+        merkle_tree.append(leaves.clone().as_mut()).commit();
+        let original_root = merkle_tree.root();
+
+        merkle_tree.update(2, Sha256::hash(b"x")).unwrap();
+        merkle_tree.abort_uncommitted();
+        assert_eq!(merkle_tree.uncommitted_root(), None);
+
+        merkle_tree.update(2, Sha256::hash(b"x")).unwrap().commit();
+        merkle_tree.update(4, Sha256::hash(b"y")).unwrap().commit();
+        assert_ne!(merkle_tree.root(), original_root);
+
+        merkle_tree.rollback();
+        merkle_tree.rollback();
+        assert_eq!(merkle_tree.root(), original_root);
+        assert_eq!(merkle_tree.leaves(), Some(leaves));
+    }
+
+    #[test]
+    pub fn should_return_error_for_invalid_updates() {
+        let tree_properties = TreeProperties {
+            sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
+        };
+        let leaves: Vec<[u8; 32]> = (0..3u8).map(|i| Sha256::hash(&[i])).collect();
+        let mut merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+        let new_leaf = Sha256::hash(b"x");
+
+        assert!(merkle_tree.update(3, new_leaf).is_err());
+        assert!(merkle_tree.update_many(&[0, 1], &[new_leaf]).is_err());
+        // Nothing is staged if any of the indices is invalid
+        assert!(merkle_tree
+            .update_many(&[0, 5], &[new_leaf, new_leaf])
+            .is_err());
+        assert_eq!(merkle_tree.uncommitted_root(), None);
+    }
+}
+
+pub mod remove {
+    use crate::common;
+    use rs_merkle::{algorithms::Sha256, utils::properties::OddNodeStrategy, MerkleTree};
+
+    #[test]
+    pub fn should_truncate_to_any_length() {
+        let strategies = [
+            OddNodeStrategy::Promote,
+            OddNodeStrategy::Duplicate,
+            OddNodeStrategy::HashWithZero,
+            OddNodeStrategy::PadToPowerOfTwo,
+        ];
+
+        for odd_node_strategy in strategies {
+            let tree_properties = common::tree_properties(false, odd_node_strategy);
+            let leaves = common::leaves(17);
+
+            for new_len in 1..leaves.len() {
+                let mut merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+                let expected_tree =
+                    MerkleTree::<Sha256>::from_leaves(&leaves[..new_len], tree_properties);
+
+                merkle_tree.truncate(new_len);
+                assert_eq!(merkle_tree.uncommitted_root(), expected_tree.root());
+
+                merkle_tree.commit();
+                assert_eq!(merkle_tree.layers(), expected_tree.layers());
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_swap_remove_leaves() {
+        let tree_properties = common::tree_properties(false, OddNodeStrategy::Promote);
+        let leaves = common::leaves(13);
+
+        for index in 0..leaves.len() {
+            let mut merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+            let mut expected_leaves = leaves.clone();
+            expected_leaves.swap_remove(index);
+            let expected_tree =
+                MerkleTree::<Sha256>::from_leaves(&expected_leaves, tree_properties);
+
+            // Removed leaves are returned the way they are stored in the tree
+            let removed_leaf = merkle_tree.remove(index).unwrap();
+            assert_eq!(Some(removed_leaf), merkle_tree.leaves().map(|l| l[index]));
+
+            merkle_tree.commit();
+            assert_eq!(merkle_tree.layers(), expected_tree.layers());
+        }
+    }
+
+    #[test]
+    pub fn should_combine_removals_with_inserts_and_updates() {
+        let tree_properties = common::tree_properties(false, OddNodeStrategy::Duplicate);
+        let leaves = common::leaves(20);
+        let mut merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves[..12], tree_properties);
+        let mut expected_leaves = leaves[..12].to_vec();
+
+        merkle_tree.truncate(6);
+        merkle_tree.update(2, leaves[19]).unwrap();
+        merkle_tree.append(leaves[12..16].to_vec().as_mut());
+        merkle_tree.remove(1).unwrap();
+        merkle_tree.pop();
+        merkle_tree.update(7, leaves[18]).unwrap();
+
+        expected_leaves.truncate(6);
+        expected_leaves[2] = leaves[19];
+        expected_leaves.extend_from_slice(&leaves[12..16]);
+        expected_leaves.swap_remove(1);
+        expected_leaves.pop();
+        expected_leaves[7] = leaves[18];
+
+        let expected_tree = MerkleTree::<Sha256>::from_leaves(&expected_leaves, tree_properties);
+        assert_eq!(merkle_tree.uncommitted_root(), expected_tree.root());
+
+        merkle_tree.commit();
+        assert_eq!(merkle_tree.layers(), expected_tree.layers());
+    }
+
+    #[test]
+    pub fn should_rollback_removals() {
+        let tree_properties = common::tree_properties(false, OddNodeStrategy::Promote);
+        let leaves = common::leaves(9);
+        let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+        merkle_tree.append(leaves.clone().as_mut()).commit();
+        let original_layers = merkle_tree.layers();
+
+        merkle_tree.remove(0).unwrap();
+        merkle_tree.commit();
+        merkle_tree.truncate(3).commit();
+        assert_eq!(merkle_tree.leaves_len(), 3);
+
+        merkle_tree.rollback();
+        assert_eq!(merkle_tree.leaves_len(), 8);
+
+        merkle_tree.rollback();
+        assert_eq!(merkle_tree.layers(), original_layers);
+    }
+
+    #[test]
+    pub fn should_remove_all_leaves() {
+        let tree_properties = common::tree_properties(false, OddNodeStrategy::Promote);
+        let leaves = common::leaves(3);
+        let mut merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+
+        merkle_tree.truncate(0);
+        assert_eq!(merkle_tree.uncommitted_root(), None);
+        assert_eq!(merkle_tree.pop(), None);
+        assert!(merkle_tree.remove(0).is_err());
+
+        merkle_tree.commit();
+        assert_eq!(merkle_tree.root(), None);
+        assert_eq!(merkle_tree.leaves_len(), 0);
+
+        merkle_tree.insert(leaves[0]).commit();
+        assert_eq!(
+            merkle_tree.root(),
+            MerkleTree::<Sha256>::from_leaves(&leaves[..1], tree_properties).root()
+        );
+    }
+}
+
+pub mod reverse_diff {
+    use rs_merkle::{
+        algorithms::Sha256,
+        utils::properties::{OddNodeStrategy, TreeProperties},
+        MerkleTree,
+    };
+
+    #[test]
+    pub fn should_restore_every_previous_state_on_rollback() {
+        let strategies = [
+            OddNodeStrategy::Promote,
+            OddNodeStrategy::Duplicate,
+            OddNodeStrategy::HashWithZero,
+            OddNodeStrategy::PadToPowerOfTwo,
+        ];
+
+        for odd_node_strategy in strategies {
+            let tree_properties = TreeProperties {
+                sorted_pair_enabled: false,
+                domain_separation: None,
+                odd_node_strategy,
+            };
+            let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+            let mut states = vec![merkle_tree.layers()];
+
+            for i in 1..30u8 {
+                let leaves_len = merkle_tree.leaves_len();
+                match i % 5 {
+                    _ if leaves_len < 2 => {
+                        merkle_tree.insert(Sha256::hash(&[i]));
+                    }
+                    0 | 1 => {
+                        merkle_tree.append(
+                            (0..i)
+                                .map(|j| Sha256::hash(&[i, j]))
+                                .collect::<Vec<_>>()
+                                .as_mut(),
+                        );
+                    }
+                    2 => {
+                        let index = (i as usize * 7) % leaves_len;
+                        merkle_tree.update(index, Sha256::hash(&[i])).unwrap();
+                    }
+                    3 => {
+                        merkle_tree.remove(i as usize % leaves_len).unwrap();
+                    }
+                    _ => {
+                        merkle_tree.truncate(leaves_len / 2);
+                    }
+                }
+                merkle_tree.commit();
+
+                // Every state must be the same as a tree built from scratch
+                let leaves = merkle_tree.leaves().unwrap();
+                let expected_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+                assert_eq!(merkle_tree.root(), expected_tree.root());
+
+                states.push(merkle_tree.layers());
+            }
+
+            states.pop();
+            while let Some(state) = states.pop() {
+                merkle_tree.rollback();
+                assert_eq!(merkle_tree.layers(), state);
+            }
+        }
+    }
+}
+
+pub mod checkpoint {
+    use rs_merkle::{
+        algorithms::Sha256, utils::properties::TreeProperties, CommitInfo, ErrorKind, MerkleTree,
+    };
+
+    #[test]
+    pub fn should_list_history_entries() {
+        let mut merkle_tree = MerkleTree::<Sha256>::new(TreeProperties::default());
+        assert!(merkle_tree.history().is_empty());
+        assert_eq!(merkle_tree.checkpoint("empty"), None);
+
+        merkle_tree.insert(Sha256::hash(b"a")).commit();
+        let first_root = merkle_tree.root();
+        merkle_tree.insert(Sha256::hash(b"b"));
+        assert_eq!(merkle_tree.checkpoint("two leaves"), Some(1));
+        let second_root = merkle_tree.root();
+        merkle_tree.truncate(0).commit();
+
+        assert_eq!(
+            merkle_tree.history(),
+            vec![
+                CommitInfo {
+                    id: 0,
+                    root: first_root,
+                    leaves_len: 1,
+                    label: None,
+                },
+                CommitInfo {
+                    id: 1,
+                    root: second_root,
+                    leaves_len: 2,
+                    label: Some("two leaves".to_string()),
+                },
+                CommitInfo {
+                    id: 2,
+                    root: None,
+                    leaves_len: 0,
+                    label: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    pub fn should_rollback_to_commit_id_and_label() {
+        let mut merkle_tree = MerkleTree::<Sha256>::new(TreeProperties::default());
+        let mut roots = Vec::new();
+
+        for i in 0..10u8 {
+            merkle_tree.insert(Sha256::hash(&[i])).commit();
+            if i == 3 {
+                merkle_tree.update(0, Sha256::hash(b"updated")).unwrap();
+                merkle_tree.checkpoint("reorg point");
+            }
+            roots.push(merkle_tree.root());
+        }
+        assert_eq!(merkle_tree.history().len(), 11);
+
+        merkle_tree.rollback_to(7).unwrap();
+        assert_eq!(merkle_tree.history().last().unwrap().id, 7);
+        assert_eq!(merkle_tree.root(), roots[6]);
+
+        merkle_tree.rollback_to("reorg point").unwrap();
+        assert_eq!(merkle_tree.history().last().unwrap().id, 4);
+        assert_eq!(merkle_tree.root(), roots[3]);
+        assert_eq!(merkle_tree.leaves_len(), 4);
+    }
+
+    #[test]
+    pub fn should_not_reuse_ids_and_move_labels() {
+        let mut merkle_tree = MerkleTree::<Sha256>::new(TreeProperties::default());
+
+        merkle_tree.insert(Sha256::hash(b"a"));
+        merkle_tree.checkpoint("head");
+        merkle_tree.insert(Sha256::hash(b"b")).commit();
+        merkle_tree.rollback();
+        merkle_tree.insert(Sha256::hash(b"c"));
+
+        // Id 1 belonged to the rolled back commit
+        assert_eq!(merkle_tree.checkpoint("head"), Some(2));
+        let labels: Vec<Option<String>> = merkle_tree
+            .history()
+            .into_iter()
+            .map(|commit| commit.label)
+            .collect();
+        assert_eq!(labels, vec![None, Some("head".to_string())]);
+    }
+
+    #[test]
+    pub fn should_return_error_for_unknown_commits() {
+        let mut merkle_tree = MerkleTree::<Sha256>::new(TreeProperties::default());
+        merkle_tree.insert(Sha256::hash(b"a")).commit();
+        merkle_tree.insert(Sha256::hash(b"b")).commit();
+        merkle_tree.rollback();
+        let root = merkle_tree.root();
+
+        let error = merkle_tree.rollback_to(1).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::CommitNotFound);
+        assert!(merkle_tree.rollback_to("missing").is_err());
+        assert_eq!(merkle_tree.root(), root);
+    }
+}
+
+pub mod historical_proof {
+    use rs_merkle::{
+        algorithms::Sha256,
+        utils::properties::{DomainSeparation, OddNodeStrategy, TreeProperties},
+        MerkleTree,
+    };
+
+    #[test]
+    pub fn should_prove_leaves_against_every_commit() {
+        let tree_properties = TreeProperties {
+            sorted_pair_enabled: false,
+            domain_separation: Some(DomainSeparation::RFC_6962),
+            odd_node_strategy: OddNodeStrategy::Duplicate,
+        };
+        let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+        // Leaves as they were passed to the tree, since the tree stores them prefixed
+        let mut leaves: Vec<[u8; 32]> = Vec::new();
+        let mut versions = Vec::new();
+
+        for i in 1..20u8 {
+            let leaf = Sha256::hash(&[i]);
+            match i % 4 {
+                0 => {
+                    let index = i as usize % leaves.len();
+                    merkle_tree.update(index, leaf).unwrap();
+                    leaves[index] = leaf;
+                }
+                1 if leaves.len() > 4 => {
+                    merkle_tree.truncate(leaves.len() - 3);
+                    leaves.truncate(leaves.len() - 3);
+                }
+                _ => {
+                    let mut new_leaves: Vec<[u8; 32]> =
+                        (0..i).map(|j| Sha256::hash(&[i, j])).collect();
+                    leaves.extend_from_slice(&new_leaves);
+                    merkle_tree.append(&mut new_leaves);
+                }
+            }
+            merkle_tree.commit();
+            versions.push(leaves.clone());
+        }
+
+        for (commit, version_leaves) in merkle_tree.history().iter().zip(&versions) {
+            let expected_tree = MerkleTree::<Sha256>::from_leaves(version_leaves, tree_properties);
+            let root = merkle_tree.root_at(commit.id).unwrap();
+            assert_eq!(Some(root), expected_tree.root());
+
+            for indices in [vec![0], vec![version_leaves.len() - 1], vec![1, 2, 5]] {
+                if indices.iter().any(|index| *index >= version_leaves.len()) {
+                    continue;
+                }
+                let proof = merkle_tree.proof_at(commit.id, &indices).unwrap();
+                let proof_leaves: Vec<[u8; 32]> =
+                    indices.iter().map(|index| version_leaves[*index]).collect();
+
+                assert_eq!(
+                    proof.proof_hashes(),
+                    expected_tree.proof(&indices).proof_hashes()
+                );
+                assert!(proof.verify(
+                    root,
+                    &indices,
+                    &proof_leaves,
+                    version_leaves.len(),
+                    tree_properties
+                ));
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_return_error_for_unknown_commits_and_indices() {
+        let tree_properties = TreeProperties {
+            sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
+        };
+        let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+        merkle_tree.insert(Sha256::hash(b"a"));
+        merkle_tree.checkpoint("first");
+        merkle_tree.insert(Sha256::hash(b"b")).commit();
+
+        assert!(merkle_tree.proof_at("first", &[0]).is_ok());
+        assert!(merkle_tree.proof_at("first", &[1]).is_err());
+        assert!(merkle_tree.proof_at(2, &[0]).is_err());
+        assert_eq!(merkle_tree.root_at(2), None);
+        assert_eq!(merkle_tree.root_at("first"), Some(Sha256::hash(b"a")));
+    }
+}
+
+pub mod retention {
+    use rs_merkle::{
+        algorithms::Sha256,
+        utils::properties::{OddNodeStrategy, TreeProperties},
+        MerkleTree, RetentionPolicy,
+    };
+
+    fn tree_with_commits(commits_count: u8) -> (MerkleTree<Sha256>, Vec<Option<[u8; 32]>>) {
+        let tree_properties = TreeProperties {
+            sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
+        };
+        let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+        let mut roots = Vec::new();
+
+        for i in 0..commits_count {
+            merkle_tree.insert(Sha256::hash(&[i]));
+            if i > 2 {
+                merkle_tree.update(1, Sha256::hash(&[i, i])).unwrap();
+            }
+            merkle_tree.commit();
+            roots.push(merkle_tree.root());
+        }
+
+        (merkle_tree, roots)
+    }
+
+    fn commit_ids(merkle_tree: &MerkleTree<Sha256>) -> Vec<u64> {
+        merkle_tree
+            .history()
+            .iter()
+            .map(|commit| commit.id)
+            .collect()
+    }
+
+    #[test]
+    pub fn should_keep_last_commits() {
+        let (mut merkle_tree, roots) = tree_with_commits(10);
+        assert_eq!(merkle_tree.retention_policy(), RetentionPolicy::KeepAll);
+
+        merkle_tree.set_retention_policy(RetentionPolicy::KeepLast(3));
+        assert_eq!(commit_ids(&merkle_tree), vec![7, 8, 9]);
+
+        // The policy is applied on every commit
+        merkle_tree.insert(Sha256::hash(b"a")).commit();
+        assert_eq!(commit_ids(&merkle_tree), vec![8, 9, 10]);
+
+        // Proofs can still be generated for the base snapshot
+        let proof = merkle_tree.proof_at(8, &[1]).unwrap();
+        assert_eq!(merkle_tree.root_at(8), roots[8]);
+        assert!(proof.verify(
+            roots[8].unwrap(),
+            &[1],
+            &[Sha256::hash(&[8, 8])],
+            9,
+            TreeProperties {
+                sorted_pair_enabled: false,
+                domain_separation: None,
+                odd_node_strategy: OddNodeStrategy::Promote,
+            }
+        ));
+
+        merkle_tree.rollback_to(8).unwrap();
+        assert_eq!(merkle_tree.root(), roots[8]);
+
+        // The base snapshot can't be rolled back
+        merkle_tree.rollback();
+        assert_eq!(commit_ids(&merkle_tree), vec![8]);
+        assert_eq!(merkle_tree.root(), roots[8]);
+    }
+
+    #[test]
+    pub fn should_keep_commits_after_id() {
+        let (mut merkle_tree, roots) = tree_with_commits(10);
+
+        merkle_tree.set_retention_policy(RetentionPolicy::KeepAfter(4));
+        assert_eq!(commit_ids(&merkle_tree), vec![4, 5, 6, 7, 8, 9]);
+
+        merkle_tree.rollback_to(5).unwrap();
+        merkle_tree.rollback();
+        merkle_tree.rollback();
+        assert_eq!(merkle_tree.root(), roots[4]);
+        assert_eq!(merkle_tree.leaves_len(), 5);
+
+        // The commit is gone, so the oldest commit after it becomes the base snapshot
+        merkle_tree.set_retention_policy(RetentionPolicy::KeepAfter(3));
+        assert_eq!(commit_ids(&merkle_tree), vec![4]);
+        merkle_tree.set_retention_policy(RetentionPolicy::KeepAfter(100));
+        merkle_tree.insert(Sha256::hash(b"a")).commit();
+        assert_eq!(commit_ids(&merkle_tree), vec![10]);
+    }
+
+    #[test]
+    pub fn should_keep_the_most_recent_commit() {
+        let (mut merkle_tree, roots) = tree_with_commits(3);
+
+        merkle_tree.set_retention_policy(RetentionPolicy::KeepLast(0));
+        assert_eq!(commit_ids(&merkle_tree), vec![2]);
+        assert_eq!(merkle_tree.root_at(2), roots[2]);
+    }
+}
+
+pub mod snapshot {
+    use rs_merkle::{
+        algorithms::Sha256,
+        utils::properties::{DomainSeparation, OddNodeStrategy, TreeProperties},
+        ErrorKind, MerkleTree, RetentionPolicy,
+    };
+
+    #[test]
+    pub fn should_load_the_same_tree() {
+        let strategies = [
+            OddNodeStrategy::Promote,
+            OddNodeStrategy::Duplicate,
+            OddNodeStrategy::HashWithZero,
+            OddNodeStrategy::PadToPowerOfTwo,
+        ];
+
+        for (i, odd_node_strategy) in strategies.iter().copied().enumerate() {
+            let tree_properties = TreeProperties {
+                sorted_pair_enabled: i % 2 == 0,
+                domain_separation: Some(DomainSeparation::RFC_6962).filter(|_| i > 1),
+                odd_node_strategy,
+            };
+
+            for leaves_count in 0..=9u8 {
+                let leaves: Vec<[u8; 32]> = (0..leaves_count).map(|i| Sha256::hash(&[i])).collect();
+                let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, tree_properties);
+
+                let snapshot = merkle_tree.to_snapshot_bytes(false);
+                let loaded_tree = MerkleTree::<Sha256>::from_snapshot_bytes(&snapshot).unwrap();
+
+                assert_eq!(loaded_tree.root(), merkle_tree.root());
+                assert_eq!(loaded_tree.layers(), merkle_tree.layers());
+                assert_eq!(loaded_tree.tree_properties(), tree_properties);
+                assert!(loaded_tree.history().is_empty());
+                assert!(loaded_tree.verify_layers());
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_load_the_history() {
+        let tree_properties = TreeProperties {
+            sorted_pair_enabled: false,
+            domain_separation: None,
+            odd_node_strategy: OddNodeStrategy::Promote,
+        };
+        let leaves: Vec<[u8; 32]> = (0..6u8).map(|i| Sha256::hash(&[i])).collect();
+
+        let mut merkle_tree = MerkleTree::<Sha256>::new(tree_properties);
+        merkle_tree.set_retention_policy(RetentionPolicy::KeepLast(3));
+        merkle_tree.append(&mut leaves[..3].to_vec()).commit();
+        merkle_tree.checkpoint("three leaves");
+        merkle_tree.append(&mut leaves[3..].to_vec()).commit();
+        merkle_tree.update(1, leaves[5]).unwrap().commit();
+        merkle_tree.truncate(4).commit();
+        // Uncommitted changes are not a part of the snapshot
+        merkle_tree.insert(leaves[0]);
+
+        let snapshot = merkle_tree.to_snapshot_bytes(true);
+        let mut loaded_tree = MerkleTree::<Sha256>::from_snapshot_bytes(&snapshot).unwrap();
+
+        assert_eq!(loaded_tree.history(), merkle_tree.history());
+        assert_eq!(loaded_tree.retention_policy(), RetentionPolicy::KeepLast(3));
+        assert_eq!(loaded_tree.leaves_len(), 4);
+        assert_eq!(loaded_tree.uncommitted_root(), None);
+        assert_eq!(
+            loaded_tree.proof_at(2, &[1, 4]).unwrap().proof_hashes(),
+            merkle_tree.proof_at(2, &[1, 4]).unwrap().proof_hashes()
+        );
+
+        // Commit ids continue after the loaded ones
+        loaded_tree.insert(leaves[0]).commit();
+        merkle_tree.commit();
+        assert_eq!(loaded_tree.history(), merkle_tree.history());
+
+        loaded_tree.rollback_to(2).unwrap();
+        merkle_tree.rollback_to(2).unwrap();
+        assert_eq!(loaded_tree.layers(), merkle_tree.layers());
+    }
+
+    #[test]
+    pub fn should_return_error_for_invalid_snapshots() {
+        let leaves: Vec<[u8; 32]> = (0..5u8).map(|i| Sha256::hash(&[i])).collect();
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(
+            &leaves,
+            TreeProperties {
+                sorted_pair_enabled: false,
+                domain_separation: None,
+                odd_node_strategy: OddNodeStrategy::Promote,
+            },
+        );
+        let snapshot = merkle_tree.to_snapshot_bytes(true);
+
+        let mut corrupted_snapshot = snapshot.clone();
+        corrupted_snapshot[40] ^= 1;
+        let truncated_snapshot = &snapshot[..snapshot.len() - 1];
+
+        for invalid_snapshot in [&corrupted_snapshot[..], truncated_snapshot, &[]] {
+            let err = MerkleTree::<Sha256>::from_snapshot_bytes(invalid_snapshot)
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), ErrorKind::InvalidSnapshot);
+        }
+    }
+
+    /// Replaces the hash at the end of the snapshot after it was modified
+    fn with_checksum(mut snapshot: Vec<u8>) -> Vec<u8> {
+        snapshot.truncate(snapshot.len() - 32);
+        let checksum = Sha256::hash(&snapshot);
+        snapshot.extend_from_slice(&checksum);
+        snapshot
+    }
+
+    #[test]
+    pub fn should_detect_nodes_that_dont_match_the_leaves() {
+        let leaves: Vec<[u8; 32]> = (0..5u8).map(|i| Sha256::hash(&[i])).collect();
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, TreeProperties::default());
+        let snapshot = merkle_tree.to_snapshot_bytes(false);
+
+        // Replacing a leaf keeps the root, since the nodes are not rehashed on load
+        let leaf_start = snapshot
+            .windows(32)
+            .position(|window| window == leaves[1])
+            .unwrap();
+        let mut forged_snapshot = snapshot.clone();
+        forged_snapshot[leaf_start..leaf_start + 32].copy_from_slice(&Sha256::hash(b"forged"));
+        let loaded_tree =
+            MerkleTree::<Sha256>::from_snapshot_bytes(&with_checksum(forged_snapshot)).unwrap();
+
+        assert_eq!(loaded_tree.root(), merkle_tree.root());
+        assert!(!loaded_tree.verify_layers());
+    }
+
+    #[test]
+    pub fn should_return_error_for_inconsistent_history() {
+        let leaves: Vec<[u8; 32]> = (0..2u8).map(|i| Sha256::hash(&[i])).collect();
+        let mut merkle_tree = MerkleTree::<Sha256>::new(TreeProperties::default());
+        merkle_tree.insert(leaves[0]).commit();
+        merkle_tree.insert(leaves[1]).commit();
+        let snapshot = merkle_tree.to_snapshot_bytes(true);
+
+        // The last commit is at the end of the snapshot, its reverse diff follows the id, the
+        // number of leaves, the root and an empty label
+        let mut last_commit = [1u64.to_le_bytes(), 2u64.to_le_bytes()].concat();
+        last_commit.push(1);
+        last_commit.extend_from_slice(&merkle_tree.root().unwrap());
+        last_commit.push(0);
+        let commit_start = snapshot
+            .windows(last_commit.len())
+            .position(|window| window == &last_commit[..])
+            .unwrap();
+        let reverse_diff_start = commit_start + last_commit.len();
+        assert!(MerkleTree::<Sha256>::from_snapshot_bytes(&snapshot).is_ok());
+
+        let mut without_reverse_diff = snapshot[..reverse_diff_start].to_vec();
+        without_reverse_diff.push(0);
+        without_reverse_diff.extend_from_slice(&[0; 32]);
+
+        let mut wrong_previous_len = snapshot.clone();
+        wrong_previous_len[reverse_diff_start + 1] = 2;
+
+        let mut wrong_last_len = snapshot.clone();
+        wrong_last_len[commit_start + 8] = 1;
+
+        let mut wrong_last_root = snapshot.clone();
+        wrong_last_root[commit_start + 17] ^= 1;
+
+        // The reverse diff has the leaf and the root of the previous tree on two layers, without
+        // the root layer the rollback would leave the tree without a root
+        let layers_len_start = reverse_diff_start + 9;
+        assert_eq!(snapshot[layers_len_start], 2);
+        let first_layer_len = snapshot[layers_len_start + 8] as usize;
+        let mut truncated_reverse_diff =
+            snapshot[..layers_len_start + 16 + first_layer_len * 40].to_vec();
+        truncated_reverse_diff[layers_len_start] = 1;
+        truncated_reverse_diff.extend_from_slice(&[0; 32]);
+
+        for invalid_snapshot in [
+            without_reverse_diff,
+            wrong_previous_len,
+            wrong_last_len,
+            wrong_last_root,
+            truncated_reverse_diff,
+        ] {
+            let err = MerkleTree::<Sha256>::from_snapshot_bytes(&with_checksum(invalid_snapshot))
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), ErrorKind::InvalidSnapshot);
+        }
+    }
+}
@@ -0,0 +1,199 @@
+mod common;
+
+pub mod memory_node_store {
+    use rs_merkle::{algorithms::Sha256, node_stores::MemoryNodeStore, NodeStore};
+
+    #[test]
+    pub fn should_insert_get_and_truncate_nodes() {
+        let a = Sha256::hash("a".as_bytes());
+        let b = Sha256::hash("b".as_bytes());
+        let c = Sha256::hash("c".as_bytes());
+        let mut store = MemoryNodeStore::<Sha256>::new();
+
+        store.insert(1, 3, c).unwrap();
+        store.insert(0, 0, a).unwrap();
+        store.insert(0, 5, b).unwrap();
+        store.insert(0, 0, c).unwrap();
+
+        assert_eq!(store.layers_len(), 2);
+        assert_eq!(store.layer_len(0), 6);
+        assert_eq!(store.layer_len(1), 4);
+        assert_eq!(store.get(0, 0).unwrap(), Some(c));
+        assert_eq!(store.get(0, 3).unwrap(), None);
+        assert_eq!(store.layer(0).unwrap(), vec![(0, c), (5, b)]);
+
+        let removed = store.truncate_layer(0, 5).unwrap();
+        assert_eq!(removed, vec![(5, b)]);
+        assert_eq!(store.layer_len(0), 1);
+
+        store.truncate_layers(1).unwrap();
+        assert_eq!(store.layers_len(), 1);
+        assert_eq!(store.get(1, 3).unwrap(), None);
+
+        store.clear().unwrap();
+        assert_eq!(store.layers_len(), 0);
+    }
+}
+
+pub mod file_node_store {
+    use crate::common;
+    use rs_merkle::{
+        algorithms::Sha256, node_stores::FileNodeStore, utils::properties::TreeProperties,
+        ErrorKind, MerkleTree, NodeStore, PartialTree,
+    };
+
+    fn assert_same_tree(
+        merkle_tree: &MerkleTree<Sha256, FileNodeStore<Sha256>>,
+        expected_tree: &MerkleTree<Sha256>,
+    ) {
+        assert_eq!(merkle_tree.try_root().unwrap(), expected_tree.root());
+        assert_eq!(merkle_tree.try_layers().unwrap(), expected_tree.layers());
+        assert_eq!(merkle_tree.try_leaves().unwrap(), expected_tree.leaves());
+
+        let indices: Vec<usize> = (0..expected_tree.leaves_len()).step_by(2).collect();
+        assert_eq!(
+            merkle_tree.try_proof(&indices).unwrap().proof_hashes(),
+            expected_tree.proof(&indices).proof_hashes()
+        );
+    }
+
+    #[test]
+    pub fn should_keep_nodes_in_sync_with_a_memory_tree() {
+        let directory = common::temp_directory("file_node_store_sync");
+        let leaves: Vec<[u8; 32]> = (0..11u8).map(|i| Sha256::hash(&[i])).collect();
+        let store = FileNodeStore::<Sha256>::open(&directory).unwrap();
+        let mut merkle_tree = MerkleTree::with_store(store, TreeProperties::default()).unwrap();
+        let mut expected_tree = MerkleTree::<Sha256>::new(TreeProperties::default());
+
+        merkle_tree
+            .append(&mut leaves.clone())
+            .try_commit()
+            .unwrap();
+        expected_tree.append(&mut leaves.clone()).commit();
+        assert_same_tree(&merkle_tree, &expected_tree);
+
+        merkle_tree
+            .update(4, leaves[0])
+            .unwrap()
+            .try_commit()
+            .unwrap();
+        expected_tree.update(4, leaves[0]).unwrap().commit();
+        assert_same_tree(&merkle_tree, &expected_tree);
+
+        merkle_tree.remove(2).unwrap();
+        merkle_tree.try_commit().unwrap();
+        expected_tree.remove(2).unwrap();
+        expected_tree.commit();
+        assert_same_tree(&merkle_tree, &expected_tree);
+
+        merkle_tree.truncate(5).try_commit().unwrap();
+        expected_tree.truncate(5).commit();
+        assert_same_tree(&merkle_tree, &expected_tree);
+
+        merkle_tree.try_rollback().unwrap();
+        expected_tree.rollback();
+        assert_same_tree(&merkle_tree, &expected_tree);
+
+        merkle_tree.rollback_to(0).unwrap();
+        expected_tree.rollback();
+        expected_tree.rollback();
+        assert_same_tree(&merkle_tree, &expected_tree);
+    }
+
+    #[test]
+    pub fn should_reopen_a_tree_without_rebuilding() {
+        let directory = common::temp_directory("file_node_store_reopen");
+        let leaves: Vec<[u8; 32]> = (0..7u8).map(|i| Sha256::hash(&[i])).collect();
+        let expected_tree = MerkleTree::<Sha256>::from_leaves(&leaves, TreeProperties::default());
+
+        let store = FileNodeStore::<Sha256>::open(&directory).unwrap();
+        let mut merkle_tree = MerkleTree::with_store(store, TreeProperties::default()).unwrap();
+        merkle_tree
+            .append(&mut leaves.clone())
+            .try_commit()
+            .unwrap();
+        merkle_tree.store().sync().unwrap();
+        drop(merkle_tree);
+
+        let store = FileNodeStore::<Sha256>::open(&directory).unwrap();
+        assert_eq!(store.layers_len(), 4);
+        assert_eq!(store.layer_len(0), 7);
+
+        let merkle_tree = MerkleTree::with_store(store, TreeProperties::default()).unwrap();
+        assert_same_tree(&merkle_tree, &expected_tree);
+    }
+
+    #[test]
+    pub fn should_reject_files_of_another_hash_size() {
+        let directory = common::temp_directory("file_node_store_corrupted");
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("layer_0"), [1u8; 20]).unwrap();
+
+        let err = FileNodeStore::<Sha256>::open(&directory).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    pub fn should_keep_partial_tree_nodes_in_a_store() {
+        let directory = common::temp_directory("file_node_store_partial_tree");
+        let leaves: Vec<[u8; 32]> = (0..5u8).map(|i| Sha256::hash(&[i])).collect();
+        let expected_tree =
+            PartialTree::<Sha256>::from_leaves(&leaves, TreeProperties::default()).unwrap();
+
+        let store = FileNodeStore::<Sha256>::open(&directory).unwrap();
+        let mut partial_tree = PartialTree::with_store(store);
+        partial_tree
+            .try_merge_unverified(expected_tree.clone())
+            .unwrap();
+
+        assert_eq!(partial_tree.try_root().unwrap(), expected_tree.root());
+        assert_eq!(partial_tree.depth(), expected_tree.depth());
+        assert_eq!(
+            partial_tree.try_layer_nodes().unwrap(),
+            expected_tree.layer_nodes()
+        );
+        assert!(partial_tree.try_get(2, 1).unwrap().is_some());
+        assert!(partial_tree.try_get(2, 2).unwrap().is_none());
+
+        let removed = partial_tree.try_truncate(2).unwrap();
+        let truncated_tree =
+            PartialTree::<Sha256>::from_leaves(&leaves[..2], TreeProperties::default()).unwrap();
+        // Nodes on the right edge are stale until a diff is merged, so only the leaves and
+        // the complete subtree are compared
+        assert_eq!(partial_tree.depth(), truncated_tree.depth());
+        assert_eq!(
+            partial_tree.try_layer_nodes().unwrap()[..2],
+            truncated_tree.layer_nodes()[..2]
+        );
+        assert_eq!(removed.get(0, 4), Some(leaves[4]));
+    }
+
+    #[test]
+    pub fn should_return_store_errors() {
+        let directory = common::temp_directory("file_node_store_errors");
+        let leaves: Vec<[u8; 32]> = (0..5u8).map(|i| Sha256::hash(&[i])).collect();
+        let store = FileNodeStore::<Sha256>::open(&directory).unwrap();
+        let mut merkle_tree = MerkleTree::with_store(store, TreeProperties::default()).unwrap();
+        merkle_tree
+            .append(&mut leaves.clone())
+            .try_commit()
+            .unwrap();
+
+        // The root layer can't be removed by the truncation if it's already gone
+        std::fs::remove_file(directory.join("layer_3")).unwrap();
+        let err = merkle_tree.truncate(2).try_commit().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NodeStore);
+    }
+
+    #[test]
+    pub fn should_reject_stores_that_dont_contain_a_tree() {
+        let directory = common::temp_directory("file_node_store_incomplete");
+        let mut store = FileNodeStore::<Sha256>::open(&directory).unwrap();
+        store.insert(0, 2, Sha256::hash(&[0])).unwrap();
+
+        let err = MerkleTree::with_store(store, TreeProperties::default())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::NodeStore);
+    }
+}
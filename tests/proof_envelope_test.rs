@@ -0,0 +1,207 @@
+mod common;
+
+pub mod proof_envelope {
+    use crate::common;
+    use rs_merkle::{
+        algorithms::{Keccak256, Sha256},
+        proof_serializers::{DirectHashesOrder, ProofEnvelope},
+        utils::properties::{OddNodeStrategy, TreeProperties},
+        ErrorKind, IndexedMerkleProof, MerkleProof, MerkleTree,
+    };
+
+    const TREE_PROPERTIES: TreeProperties =
+        common::tree_properties(true, OddNodeStrategy::HashWithZero);
+
+    #[test]
+    pub fn should_round_trip_indexed_proof() {
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&common::leaves(10), TREE_PROPERTIES);
+        let proof = merkle_tree.indexed_proof(&[7, 2, 9]);
+
+        let bytes = proof.serialize::<ProofEnvelope>();
+        assert_eq!(&bytes[..5], b"RSMP\x01");
+
+        let parsed = IndexedMerkleProof::<Sha256>::deserialize::<ProofEnvelope>(&bytes).unwrap();
+        assert_eq!(parsed, proof);
+        assert_eq!(parsed.leaf_indices(), &[7, 2, 9]);
+        assert_eq!(parsed.total_leaves_count(), 10);
+        assert_eq!(parsed.tree_properties(), TREE_PROPERTIES);
+    }
+
+    #[test]
+    pub fn should_verify_envelope_with_only_root_and_leaves() {
+        let leaves = common::leaves(10);
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, TREE_PROPERTIES);
+        let root = merkle_tree.root().unwrap();
+        let bytes = merkle_tree
+            .indexed_proof(&[3, 5])
+            .serialize::<ProofEnvelope>();
+
+        assert!(
+            ProofEnvelope::verify_envelope::<Sha256>(&bytes, root, &[leaves[3], leaves[5]])
+                .unwrap()
+        );
+        assert!(
+            !ProofEnvelope::verify_envelope::<Sha256>(&bytes, root, &[leaves[3], leaves[6]])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    pub fn should_reject_envelope_of_an_unexpected_tree() {
+        let leaves = common::leaves(10);
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, TREE_PROPERTIES);
+        let root = merkle_tree.root().unwrap();
+        let bytes = merkle_tree
+            .indexed_proof(&[3, 5])
+            .serialize::<ProofEnvelope>();
+        let leaf_hashes = [leaves[3], leaves[5]];
+
+        assert!(ProofEnvelope::verify_envelope_with::<Sha256>(
+            &bytes,
+            root,
+            &leaf_hashes,
+            TREE_PROPERTIES,
+            10
+        )
+        .unwrap());
+
+        let error = ProofEnvelope::verify_envelope_with::<Sha256>(
+            &bytes,
+            root,
+            &leaf_hashes,
+            TREE_PROPERTIES,
+            11,
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidProofEnvelope);
+
+        let other_properties = common::tree_properties(true, OddNodeStrategy::Duplicate);
+        let error = ProofEnvelope::verify_envelope_with::<Sha256>(
+            &bytes,
+            root,
+            &leaf_hashes,
+            other_properties,
+            10,
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidProofEnvelope);
+
+        // The prover picks the properties of the envelope, so a proof for a different tree
+        // verifies against its own root unless the verifier checks them
+        let other_tree = MerkleTree::<Sha256>::from_leaves(&leaves, other_properties);
+        let other_root = other_tree.root().unwrap();
+        let other_bytes = other_tree
+            .indexed_proof(&[3, 5])
+            .serialize::<ProofEnvelope>();
+        assert!(
+            ProofEnvelope::verify_envelope::<Sha256>(&other_bytes, other_root, &leaf_hashes)
+                .unwrap()
+        );
+        let error = ProofEnvelope::verify_envelope_with::<Sha256>(
+            &other_bytes,
+            other_root,
+            &leaf_hashes,
+            TREE_PROPERTIES,
+            10,
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidProofEnvelope);
+    }
+
+    #[test]
+    pub fn should_serialize_plain_proof_without_indices() {
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&common::leaves(6), TREE_PROPERTIES);
+        let proof = merkle_tree.proof(&[1]);
+
+        let bytes = proof.serialize::<ProofEnvelope>();
+        let parsed = MerkleProof::<Sha256>::deserialize::<ProofEnvelope>(&bytes).unwrap();
+        assert_eq!(parsed, proof);
+
+        let error = IndexedMerkleProof::<Sha256>::deserialize::<ProofEnvelope>(&bytes).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidProofEnvelope);
+    }
+
+    #[test]
+    pub fn should_read_hashes_from_indexed_envelope_as_plain_proof() {
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&common::leaves(6), TREE_PROPERTIES);
+        let proof = merkle_tree.indexed_proof(&[4]);
+
+        let bytes = proof.serialize::<ProofEnvelope>();
+        let parsed = MerkleProof::<Sha256>::deserialize::<ProofEnvelope>(&bytes).unwrap();
+        assert_eq!(
+            parsed.serialize::<DirectHashesOrder>(),
+            proof.proof().to_bytes()
+        );
+    }
+
+    #[test]
+    pub fn should_reject_envelope_of_a_different_hasher() {
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&common::leaves(4), TREE_PROPERTIES);
+        let bytes = merkle_tree.indexed_proof(&[0]).serialize::<ProofEnvelope>();
+
+        let error =
+            IndexedMerkleProof::<Keccak256>::deserialize::<ProofEnvelope>(&bytes).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidProofEnvelope);
+    }
+
+    #[test]
+    pub fn should_reject_malformed_envelopes() {
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&common::leaves(5), TREE_PROPERTIES);
+        let bytes = merkle_tree
+            .indexed_proof(&[1, 2])
+            .serialize::<ProofEnvelope>();
+
+        for len in 0..bytes.len() {
+            assert!(
+                IndexedMerkleProof::<Sha256>::deserialize::<ProofEnvelope>(&bytes[..len]).is_err()
+            );
+        }
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(IndexedMerkleProof::<Sha256>::deserialize::<ProofEnvelope>(&trailing).is_err());
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] = 2;
+        assert!(
+            IndexedMerkleProof::<Sha256>::deserialize::<ProofEnvelope>(&wrong_version).is_err()
+        );
+
+        // Flags follow the magic, the version, the hasher id and the hash size
+        let mut unknown_flags = bytes;
+        unknown_flags[11] |= 0b1000;
+        assert!(
+            IndexedMerkleProof::<Sha256>::deserialize::<ProofEnvelope>(&unknown_flags).is_err()
+        );
+    }
+
+    #[test]
+    pub fn should_reject_or_verify_envelopes_with_flipped_bits() {
+        let leaves = common::leaves(5);
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, TREE_PROPERTIES);
+        let root = merkle_tree.root().unwrap();
+        let bytes = merkle_tree
+            .indexed_proof(&[1, 2])
+            .serialize::<ProofEnvelope>();
+
+        for bit in 0..bytes.len() * 8 {
+            let mut flipped = bytes.clone();
+            flipped[bit / 8] ^= 1 << (bit % 8);
+
+            // Flipped bits either break the envelope or give a proof that can be verified, some
+            // odd node strategies even calculate the same root for this tree
+            match IndexedMerkleProof::<Sha256>::deserialize::<ProofEnvelope>(&flipped) {
+                Ok(proof) => {
+                    let calculated_root = proof.root(&[leaves[1], leaves[2]]).unwrap();
+                    let verified = ProofEnvelope::verify_envelope::<Sha256>(
+                        &flipped,
+                        root,
+                        &[leaves[1], leaves[2]],
+                    );
+                    assert_eq!(verified.unwrap(), calculated_root == root, "{}", bit);
+                }
+                Err(error) => assert_eq!(error.kind(), ErrorKind::InvalidProofEnvelope, "{}", bit),
+            }
+        }
+    }
+}
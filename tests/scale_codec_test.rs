@@ -0,0 +1,129 @@
+#![cfg(feature = "parity-scale-codec")]
+
+mod common;
+
+pub mod scale_codec {
+    use crate::common;
+    use parity_scale_codec::{Compact, Decode, Encode};
+    use rs_merkle::{
+        algorithms::Sha256,
+        utils::properties::{OddNodeStrategy, TreeProperties},
+        IndexedMerkleProof, MerkleProof, MerkleTree,
+    };
+    use scale_info::{meta_type, Registry, TypeDef};
+
+    const TREE_PROPERTIES: TreeProperties =
+        common::tree_properties(true, OddNodeStrategy::HashWithZero);
+
+    #[test]
+    pub fn should_encode_proof_as_vector_of_hashes() {
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&common::leaves(6), TREE_PROPERTIES);
+        let proof = merkle_tree.proof(&[2]);
+
+        let encoded = proof.encode();
+        assert_eq!(encoded, proof.proof_hashes().to_vec().encode());
+
+        // Compact length prefix followed by the hashes without length prefixes
+        let mut expected = Compact(proof.proof_hashes().len() as u32).encode();
+        expected.extend(proof.to_bytes());
+        assert_eq!(encoded, expected);
+
+        let decoded = MerkleProof::<Sha256>::decode(&mut encoded.as_slice()).unwrap();
+        assert_eq!(decoded, proof);
+    }
+
+    #[test]
+    pub fn should_round_trip_tree_properties() {
+        let encoded = TREE_PROPERTIES.encode();
+        assert_eq!(
+            TreeProperties::decode(&mut encoded.as_slice()).unwrap(),
+            TREE_PROPERTIES
+        );
+    }
+
+    #[test]
+    pub fn should_round_trip_and_verify_indexed_proof() {
+        let leaves = common::leaves(11);
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&leaves, TREE_PROPERTIES);
+        let root = merkle_tree.root().unwrap();
+        let proof = merkle_tree.indexed_proof(&[3, 9]);
+
+        let encoded = proof.encode();
+        let decoded = IndexedMerkleProof::<Sha256>::decode(&mut encoded.as_slice()).unwrap();
+
+        assert_eq!(decoded, proof);
+        assert_eq!(decoded.leaf_indices(), &[3, 9]);
+        assert_eq!(decoded.total_leaves_count(), 11);
+        assert_eq!(decoded.tree_properties(), TREE_PROPERTIES);
+        assert!(decoded.verify(root, &[leaves[3], leaves[9]]));
+        assert!(!decoded.verify(root, &[leaves[9], leaves[3]]));
+    }
+
+    #[test]
+    pub fn should_reject_truncated_input() {
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&common::leaves(5), TREE_PROPERTIES);
+        let encoded = merkle_tree.indexed_proof(&[0]).encode();
+
+        let truncated = &encoded[..encoded.len() - 1];
+        assert!(IndexedMerkleProof::<Sha256>::decode(&mut &truncated[..]).is_err());
+    }
+
+    #[test]
+    pub fn should_reject_proofs_that_dont_match_their_indices() {
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&common::leaves(5), TREE_PROPERTIES);
+        let proof = merkle_tree.indexed_proof(&[2]);
+        let proof_hashes = proof.proof().proof_hashes().to_vec();
+        let decode = |proof_hashes: Vec<[u8; 32]>, leaf_indices: Vec<usize>, total: usize| {
+            let encoded = IndexedMerkleProof::<Sha256>::new(
+                MerkleProof::new(proof_hashes),
+                leaf_indices,
+                total,
+                TREE_PROPERTIES,
+            )
+            .encode();
+            IndexedMerkleProof::<Sha256>::decode(&mut encoded.as_slice())
+        };
+
+        assert!(decode(proof_hashes.clone(), vec![2], 5).is_ok());
+        assert!(decode(proof_hashes.clone(), vec![5], 5).is_err());
+        assert!(decode(proof_hashes.clone(), vec![2], u64::MAX as usize).is_err());
+        assert!(decode(proof_hashes[1..].to_vec(), vec![2], 5).is_err());
+        let mut extra_hashes = proof_hashes;
+        extra_hashes.push([0; 32]);
+        assert!(decode(extra_hashes, vec![2], 5).is_err());
+
+        // The largest tree is 64 layers deep, a proof of its first leaf has a hash per layer
+        let decoded = decode(vec![[0; 32]; 64], vec![0], u64::MAX as usize).unwrap();
+        assert!(!decoded.verify(merkle_tree.root().unwrap(), &[common::leaves(1)[0]]));
+    }
+
+    #[test]
+    pub fn should_describe_proof_types() {
+        let mut registry = Registry::new();
+        registry.register_type(&meta_type::<IndexedMerkleProof<Sha256>>());
+        let registry: scale_info::PortableRegistry = registry.into();
+
+        let proof_type = registry
+            .types
+            .iter()
+            .find(|ty| ty.ty.path.ident() == Some("IndexedMerkleProof".into()))
+            .unwrap();
+        let field_names: Vec<&str> = match &proof_type.ty.type_def {
+            TypeDef::Composite(composite) => composite
+                .fields
+                .iter()
+                .filter_map(|field| field.name.as_deref())
+                .collect(),
+            _ => panic!("IndexedMerkleProof should be described as a composite"),
+        };
+        assert_eq!(
+            field_names,
+            vec![
+                "proof",
+                "leaf_indices",
+                "total_leaves_count",
+                "tree_properties"
+            ]
+        );
+    }
+}
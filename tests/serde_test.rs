@@ -0,0 +1,129 @@
+#![cfg(feature = "serde")]
+
+mod common;
+
+pub mod serde {
+    use crate::common;
+    use rs_merkle::{
+        algorithms::Sha256,
+        utils::properties::{OddNodeStrategy, TreeProperties},
+        MerkleProof, MerkleTree, PartialTree,
+    };
+
+    const TREE_PROPERTIES: TreeProperties =
+        common::tree_properties(false, OddNodeStrategy::Duplicate);
+
+    #[test]
+    pub fn should_serialize_proof_hashes_as_hex_in_json() {
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&common::leaves(5), TREE_PROPERTIES);
+        let proof = merkle_tree.proof(&[1, 3]);
+
+        let json = serde_json::to_value(&proof).unwrap();
+        let expected_hashes: Vec<String> = proof.proof_hashes_hex();
+        assert_eq!(json, serde_json::json!({ "proof_hashes": expected_hashes }));
+
+        let parsed: MerkleProof<Sha256> = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.proof_hashes(), proof.proof_hashes());
+    }
+
+    #[test]
+    pub fn should_round_trip_proof_through_binary_format() {
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&common::leaves(7), TREE_PROPERTIES);
+        let proof = merkle_tree.proof(&[0, 6]);
+
+        let bytes = bincode::serialize(&proof).unwrap();
+        // Length prefix of the list, then a length prefix and raw bytes for every hash
+        assert_eq!(bytes.len(), 8 + proof.proof_hashes().len() * (8 + 32));
+
+        let parsed: MerkleProof<Sha256> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(parsed.proof_hashes(), proof.proof_hashes());
+    }
+
+    #[test]
+    pub fn should_accept_0x_prefixed_hashes() {
+        let hash = Sha256::hash(b"a");
+        let json = format!(
+            r#"{{"proof_hashes":["0x{}"]}}"#,
+            rs_merkle::utils::collections::to_hex_string(&hash)
+        );
+
+        let parsed: MerkleProof<Sha256> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.proof_hashes(), &[hash]);
+    }
+
+    #[test]
+    pub fn should_reject_malformed_hashes() {
+        let wrong_length = r#"{"proof_hashes":["abcd"]}"#;
+        assert!(serde_json::from_str::<MerkleProof<Sha256>>(wrong_length).is_err());
+
+        let not_hex = format!(r#"{{"proof_hashes":["{}"]}}"#, "zz".repeat(32));
+        assert!(serde_json::from_str::<MerkleProof<Sha256>>(&not_hex).is_err());
+    }
+
+    #[test]
+    pub fn should_round_trip_tree_properties() {
+        let json = serde_json::to_string(&TREE_PROPERTIES).unwrap();
+        let parsed: TreeProperties = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, TREE_PROPERTIES);
+
+        let bytes = bincode::serialize(&TREE_PROPERTIES).unwrap();
+        let parsed: TreeProperties = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(parsed, TREE_PROPERTIES);
+    }
+
+    #[test]
+    pub fn should_round_trip_merkle_tree() {
+        let mut merkle_tree =
+            MerkleTree::<Sha256>::from_leaves(&common::leaves(6), TREE_PROPERTIES);
+        merkle_tree.insert(Sha256::hash(b"uncommitted"));
+
+        let json = serde_json::to_string(&merkle_tree).unwrap();
+        let from_json: MerkleTree<Sha256> = serde_json::from_str(&json).unwrap();
+        let bytes = bincode::serialize(&merkle_tree).unwrap();
+        let from_bytes: MerkleTree<Sha256> = bincode::deserialize(&bytes).unwrap();
+
+        for parsed in [from_json, from_bytes] {
+            // Uncommitted changes are not serialized
+            assert_eq!(parsed.uncommitted_root(), None);
+            assert_eq!(parsed.root(), merkle_tree.root());
+            assert_eq!(parsed.layers(), merkle_tree.layers());
+            assert_eq!(parsed.tree_properties(), TREE_PROPERTIES);
+            assert_eq!(
+                parsed.proof(&[2]).proof_hashes(),
+                merkle_tree.proof(&[2]).proof_hashes()
+            );
+        }
+    }
+
+    #[test]
+    pub fn should_reject_tree_with_inconsistent_layers() {
+        let merkle_tree = MerkleTree::<Sha256>::from_leaves(&common::leaves(4), TREE_PROPERTIES);
+        let mut json = serde_json::to_value(&merkle_tree).unwrap();
+        json["layers"][1].as_array_mut().unwrap().pop();
+
+        assert!(serde_json::from_value::<MerkleTree<Sha256>>(json).is_err());
+    }
+
+    #[test]
+    pub fn should_round_trip_partial_tree() {
+        let partial_tree =
+            PartialTree::<Sha256>::from_leaves(&common::leaves(5), TREE_PROPERTIES).unwrap();
+
+        let json = serde_json::to_string(&partial_tree).unwrap();
+        let parsed: PartialTree<Sha256> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.layers(), partial_tree.layers());
+        assert_eq!(parsed.root(), partial_tree.root());
+
+        let bytes = bincode::serialize(&partial_tree).unwrap();
+        let parsed: PartialTree<Sha256> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(parsed.layers(), partial_tree.layers());
+    }
+
+    #[test]
+    pub fn should_reject_partial_tree_with_unordered_indices() {
+        let hash = rs_merkle::utils::collections::to_hex_string(&Sha256::hash(b"a"));
+        let json = format!(r#"{{"layers":[[[1,"{0}"],[0,"{0}"]]]}}"#, hash);
+
+        assert!(serde_json::from_str::<PartialTree<Sha256>>(&json).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+pub mod root {
+    use rs_merkle::{algorithms::Sha256, SparseMerkleTree};
+
+    #[test]
+    pub fn should_return_empty_subtree_root_for_empty_tree() {
+        let tree = SparseMerkleTree::<Sha256>::new();
+
+        assert_eq!(tree.root(), tree.empty_hashes()[256]);
+        assert_eq!(tree.empty_hashes()[0], [0u8; 32]);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    pub fn should_not_depend_on_insertion_order() {
+        let keys: Vec<[u8; 32]> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|x| Sha256::hash(x.as_bytes()))
+            .collect();
+
+        let mut tree = SparseMerkleTree::<Sha256>::new();
+        let mut reversed_tree = SparseMerkleTree::<Sha256>::new();
+        for key in &keys {
+            tree.insert(*key, Sha256::hash(key));
+        }
+        for key in keys.iter().rev() {
+            reversed_tree.insert(*key, Sha256::hash(key));
+        }
+
+        assert_eq!(tree.root(), reversed_tree.root());
+        assert_eq!(tree.len(), 4);
+    }
+
+    #[test]
+    pub fn should_restore_the_root_after_removal() {
+        let mut tree = SparseMerkleTree::<Sha256>::new();
+        let key = Sha256::hash("a".as_bytes());
+        let other_key = Sha256::hash("b".as_bytes());
+
+        tree.insert(key, Sha256::hash("value a".as_bytes()));
+        let root = tree.root();
+
+        tree.insert(other_key, Sha256::hash("value b".as_bytes()));
+        assert_ne!(tree.root(), root);
+
+        assert_eq!(
+            tree.remove(&other_key),
+            Some(Sha256::hash("value b".as_bytes()))
+        );
+        assert_eq!(tree.root(), root);
+        assert_eq!(tree.get(&other_key), None);
+        assert_eq!(tree.remove(&other_key), None);
+
+        tree.remove(&key);
+        assert_eq!(tree.root(), tree.empty_hashes()[256]);
+    }
+}
+
+pub mod proof {
+    use rs_merkle::{algorithms::Sha256, SparseMerkleProof, SparseMerkleTree};
+
+    fn setup() -> SparseMerkleTree<Sha256> {
+        let mut tree = SparseMerkleTree::<Sha256>::new();
+        for value in ["a", "b", "c", "d", "e"] {
+            let key = Sha256::hash(value.as_bytes());
+            tree.insert(key, Sha256::hash(&key));
+        }
+        // Keys sharing a long common prefix
+        let mut key = [0u8; 32];
+        tree.insert(key, Sha256::hash("first".as_bytes()));
+        key[31] = 1;
+        tree.insert(key, Sha256::hash("second".as_bytes()));
+        tree
+    }
+
+    #[test]
+    pub fn should_verify_inclusion_proofs() {
+        let tree = setup();
+        let root = tree.root();
+        let empty_hashes = tree.empty_hashes();
+
+        for value in ["a", "b", "c", "d", "e"] {
+            let key = Sha256::hash(value.as_bytes());
+            let proof = tree.proof(&key);
+
+            assert!(proof.verify_inclusion(root, &key, Sha256::hash(&key), empty_hashes));
+            assert!(!proof.verify_inclusion(
+                root,
+                &key,
+                Sha256::hash("wrong".as_bytes()),
+                empty_hashes
+            ));
+            assert!(!proof.verify_exclusion(root, &key, empty_hashes));
+        }
+
+        let mut key = [0u8; 32];
+        key[31] = 1;
+        assert!(tree.proof(&key).verify_inclusion(
+            root,
+            &key,
+            Sha256::hash("second".as_bytes()),
+            empty_hashes
+        ));
+    }
+
+    #[test]
+    pub fn should_verify_exclusion_proofs() {
+        let tree = setup();
+        let root = tree.root();
+        let empty_hashes = tree.empty_hashes();
+
+        for value in ["f", "g", "h"] {
+            let key = Sha256::hash(value.as_bytes());
+            let proof = tree.proof(&key);
+
+            assert!(proof.verify_exclusion(root, &key, empty_hashes));
+            assert!(!proof.verify_inclusion(root, &key, Sha256::hash(&key), empty_hashes));
+        }
+
+        let mut key = [0u8; 32];
+        key[31] = 2;
+        assert!(tree.proof(&key).verify_exclusion(root, &key, empty_hashes));
+    }
+
+    #[test]
+    pub fn should_round_trip_through_bytes() {
+        let tree = setup();
+        let key = Sha256::hash("c".as_bytes());
+        let proof = tree.proof(&key);
+
+        let bytes = proof.to_bytes();
+        assert_eq!(bytes.len(), 32 + proof.sibling_hashes().len() * 32);
+
+        let parsed = SparseMerkleProof::<Sha256>::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.bitmap(), proof.bitmap());
+        assert_eq!(parsed.sibling_hashes(), proof.sibling_hashes());
+        // A verifier without the tree precomputes the empty hashes once
+        let empty_hashes = SparseMerkleTree::<Sha256>::empty_subtree_hashes();
+        assert_eq!(empty_hashes, tree.empty_hashes());
+        assert!(parsed.verify_inclusion(tree.root(), &key, Sha256::hash(&key), &empty_hashes));
+        assert!(!parsed.verify_inclusion(
+            tree.root(),
+            &key,
+            Sha256::hash(&key),
+            &empty_hashes[..256]
+        ));
+
+        assert!(SparseMerkleProof::<Sha256>::from_bytes(&bytes[..40]).is_err());
+    }
+}
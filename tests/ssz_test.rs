@@ -0,0 +1,249 @@
+mod common;
+
+pub mod ssz {
+    use crate::common;
+    use rs_merkle::{
+        algorithms::Sha256,
+        ssz::{self, SszMultiProof, SszProof, SszTree},
+        utils::properties::TreeProperties,
+        ErrorKind, MerkleTree,
+    };
+
+    fn chunks(len: usize) -> Vec<[u8; 32]> {
+        (0..len)
+            .map(|i| Sha256::hash(&(i as u64).to_le_bytes()))
+            .collect()
+    }
+
+    fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        Sha256::hash(&[left.as_slice(), right.as_slice()].concat())
+    }
+
+    /// Merkleizes the chunks with the padding materialized, nodes are laid out by their
+    /// generalized indices
+    fn naive_tree(chunks: &[[u8; 32]], limit: usize) -> Vec<[u8; 32]> {
+        let width = limit.next_power_of_two();
+        let mut nodes = vec![[0u8; 32]; 2 * width];
+        nodes[width..width + chunks.len()].copy_from_slice(chunks);
+        for gindex in (1..width).rev() {
+            nodes[gindex] = hash_pair(&nodes[2 * gindex], &nodes[2 * gindex + 1]);
+        }
+        nodes
+    }
+
+    #[test]
+    pub fn should_match_known_zero_hashes() {
+        assert_eq!(ssz::zero_hash(0), [0u8; 32]);
+        assert_eq!(
+            rs_merkle::utils::collections::to_hex_string(&ssz::zero_hash(1)),
+            "f5a5fd42d16a20302798ef6ed309979b43003d2320d9f0e8ea9831a92759fb4b"
+        );
+
+        // The root of the empty deposit contract, a list with the limit of 2^32 deposits
+        let deposit_root = ssz::merkleize(&[], Some(1 << 32)).unwrap();
+        assert_eq!(deposit_root, ssz::zero_hash(32));
+        assert_eq!(
+            rs_merkle::utils::collections::to_hex_string(&ssz::mix_in_length(&deposit_root, 0)),
+            "d70a234731285c6804c2a4f56711ddb8c82c99740f207854891028af34e27e5e"
+        );
+    }
+
+    #[test]
+    pub fn should_match_the_padded_tree() {
+        for len in 0..=9 {
+            let chunks = chunks(len);
+            for limit in [None, Some(len), Some(9), Some(16)] {
+                if matches!(limit, Some(limit) if limit < len) {
+                    continue;
+                }
+                let tree = SszTree::new(&chunks, limit).unwrap();
+                let nodes = naive_tree(&chunks, limit.unwrap_or(len));
+                assert_eq!(tree.root(), nodes[1], "{} {:?}", len, limit);
+
+                for (gindex, node) in nodes.iter().enumerate().skip(1) {
+                    let gindex = gindex as u64;
+                    assert_eq!(tree.get(gindex), Some(*node));
+
+                    let proof = tree.proof(gindex).unwrap();
+                    assert!(proof.verify(&tree.root(), node));
+                    assert!(!proof.verify(&tree.root(), &[1u8; 32]));
+                }
+                assert_eq!(tree.get(nodes.len() as u64), None);
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_match_merkle_tree_with_ssz_properties() {
+        for len in 1..=17 {
+            let chunks = chunks(len);
+            let merkle_tree = MerkleTree::<Sha256>::from_leaves(&chunks, TreeProperties::SSZ);
+            assert_eq!(
+                merkle_tree.root(),
+                Some(ssz::merkleize(&chunks, None).unwrap())
+            );
+        }
+    }
+
+    #[test]
+    pub fn should_mix_in_the_length_of_lists() {
+        let chunks = chunks(3);
+        let tree = SszTree::new(&chunks, Some(4)).unwrap().mix_in_length(5);
+        let nodes = naive_tree(&chunks, 4);
+
+        let mut length_chunk = [0u8; 32];
+        length_chunk[0] = 5;
+        assert_eq!(tree.root(), hash_pair(&nodes[1], &length_chunk));
+        assert_eq!(tree.chunks_root(), nodes[1]);
+        assert_eq!(tree.length(), Some(5));
+        assert_eq!(tree.depth(), 3);
+
+        assert_eq!(tree.get(1), Some(tree.root()));
+        assert_eq!(tree.get(2), Some(nodes[1]));
+        assert_eq!(tree.get(3), Some(length_chunk));
+        assert_eq!(tree.get(6), None);
+        for index in 0..4 {
+            let gindex = tree.chunk_gindex(index);
+            assert_eq!(gindex, 8 + index as u64);
+            assert_eq!(tree.get(gindex), Some(nodes[4 + index]));
+
+            let proof = tree.proof(gindex).unwrap();
+            assert_eq!(proof.branch().len(), 3);
+            assert_eq!(proof.branch()[2], length_chunk);
+            assert!(proof.verify(&tree.root(), &nodes[4 + index]));
+        }
+    }
+
+    #[test]
+    pub fn should_verify_branches_like_the_light_client() {
+        let chunks = chunks(6);
+        let tree = SszTree::new(&chunks, Some(8)).unwrap();
+        let proof = tree.proof(13).unwrap();
+
+        assert!(ssz::is_valid_merkle_branch(
+            &chunks[5],
+            proof.branch(),
+            3,
+            5,
+            &tree.root()
+        ));
+        assert!(!ssz::is_valid_merkle_branch(
+            &chunks[5],
+            proof.branch(),
+            3,
+            4,
+            &tree.root()
+        ));
+        assert!(!ssz::is_valid_merkle_branch(
+            &chunks[5],
+            &proof.branch()[..2],
+            3,
+            5,
+            &tree.root()
+        ));
+
+        let short_proof = SszProof::new(13, proof.branch()[..2].to_vec());
+        let error = short_proof.root(&chunks[5]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotEnoughHashesToCalculateRoot);
+        let error = SszProof::new(0, vec![]).root(&chunks[5]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidGeneralizedIndex);
+    }
+
+    #[test]
+    pub fn should_verify_multiproofs_of_every_combination() {
+        let chunks = chunks(6);
+        let tree = SszTree::new(&chunks, Some(8)).unwrap().mix_in_length(6);
+
+        let chunk_gindices: Vec<u64> = (0..8).map(|index| tree.chunk_gindex(index)).collect();
+        for gindices in common::combinations(chunk_gindices) {
+            let proof = tree.multiproof(&gindices).unwrap();
+            let leaves: Vec<[u8; 32]> = gindices
+                .iter()
+                .map(|gindex| tree.get(*gindex).unwrap())
+                .collect();
+            assert!(proof.verify(&tree.root(), &leaves), "{:?}", gindices);
+        }
+
+        // Nodes of different depths, including the length
+        for gindices in [vec![3], vec![17, 5, 3], vec![2, 3], vec![1], vec![22, 4]] {
+            let proof = tree.multiproof(&gindices).unwrap();
+            let mut leaves: Vec<[u8; 32]> = gindices
+                .iter()
+                .map(|gindex| tree.get(*gindex).unwrap())
+                .collect();
+            assert!(proof.verify(&tree.root(), &leaves), "{:?}", gindices);
+
+            leaves[0] = [1u8; 32];
+            assert!(!proof.verify(&tree.root(), &leaves), "{:?}", gindices);
+        }
+    }
+
+    #[test]
+    pub fn should_list_helper_indices_like_the_specs() {
+        assert_eq!(ssz::helper_indices(&[9, 3]).unwrap(), vec![8, 5]);
+        assert_eq!(ssz::helper_indices(&[8, 9, 14]).unwrap(), vec![15, 6, 5]);
+        assert_eq!(ssz::helper_indices(&[1]).unwrap(), Vec::<u64>::new());
+
+        for gindices in [vec![0], vec![5, 5], vec![2, 5], vec![1, 3]] {
+            let error = ssz::helper_indices(&gindices).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidGeneralizedIndex);
+        }
+    }
+
+    #[test]
+    pub fn should_concat_generalized_indices() {
+        assert_eq!(ssz::concat_generalized_indices(&[]).unwrap(), 1);
+        assert_eq!(ssz::concat_generalized_indices(&[6, 5]).unwrap(), 25);
+        assert_eq!(ssz::concat_generalized_indices(&[1, 3, 1]).unwrap(), 3);
+        assert_eq!(ssz::generalized_index_depth(25), 4);
+
+        let error = ssz::concat_generalized_indices(&[1 << 40, 1 << 30]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidGeneralizedIndex);
+        let error = ssz::concat_generalized_indices(&[2, 0]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidGeneralizedIndex);
+    }
+
+    #[test]
+    pub fn should_reject_invalid_trees_and_proofs() {
+        let chunks = chunks(5);
+        let error = SszTree::new(&chunks, Some(4)).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidTreeSize);
+        let error = SszTree::new(&chunks, Some(usize::MAX)).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidTreeSize);
+        let error = SszTree::new(&chunks, Some(1 << 63)).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidTreeSize);
+
+        // The deepest tree still has generalized indices for its chunks and the length
+        let deepest = SszTree::new(&chunks, Some(1 << 62))
+            .unwrap()
+            .mix_in_length(5);
+        assert_eq!(deepest.depth(), 63);
+        assert_eq!(deepest.chunk_gindex(4), (1 << 63) + 4);
+        let proof = deepest.proof(deepest.chunk_gindex(4)).unwrap();
+        assert!(proof.verify(&deepest.root(), &chunks[4]));
+
+        let tree = SszTree::new(&chunks, None).unwrap();
+        let error = tree.proof(16).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidGeneralizedIndex);
+        let error = tree.multiproof(&[8, 4]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidGeneralizedIndex);
+
+        let proof = tree.multiproof(&[8, 12]).unwrap();
+        let leaves = [chunks[0], chunks[4]];
+        let mut helper_hashes = proof.helper_hashes().to_vec();
+        helper_hashes.push([0u8; 32]);
+        let error = SszMultiProof::new(vec![8, 12], helper_hashes)
+            .root(&leaves)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TooManyProofHashes);
+
+        let error = SszMultiProof::new(vec![8, 12], proof.helper_hashes()[1..].to_vec())
+            .root(&leaves)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotEnoughHashesToCalculateRoot);
+
+        let error = proof.root(&leaves[..1]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::LeavesIndicesCountMismatch);
+        assert!(!SszMultiProof::new(vec![], vec![]).verify(&tree.root(), &[]));
+    }
+}
@@ -0,0 +1,269 @@
+mod common;
+
+pub mod standard_merkle_tree {
+    use crate::common;
+    use rs_merkle::{algorithms::Keccak256, AbiType, AbiValue, ErrorKind, StandardMerkleTree};
+
+    fn parse_values(leaf_encoding: &[AbiType], values: &[&[&str]]) -> Vec<Vec<AbiValue>> {
+        values
+            .iter()
+            .map(|value| {
+                value
+                    .iter()
+                    .zip(leaf_encoding)
+                    .map(|(item, abi_type)| AbiValue::parse(abi_type, item).unwrap())
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn airdrop_tree() -> StandardMerkleTree {
+        let leaf_encoding = [AbiType::Address, AbiType::Uint(256)];
+        let values = parse_values(
+            &leaf_encoding,
+            &[
+                &[
+                    "0x1111111111111111111111111111111111111111",
+                    "5000000000000000000",
+                ],
+                &[
+                    "0x2222222222222222222222222222222222222222",
+                    "2500000000000000000",
+                ],
+            ],
+        );
+        StandardMerkleTree::of(values, &leaf_encoding).unwrap()
+    }
+
+    fn numbered_tree(len: u128) -> StandardMerkleTree {
+        let values = (0..len).map(|i| vec![AbiValue::from(i)]).collect();
+        StandardMerkleTree::of(values, &[AbiType::Uint(256)]).unwrap()
+    }
+
+    #[test]
+    pub fn should_match_the_root_of_the_library() {
+        // The example from the README of @openzeppelin/merkle-tree
+        assert_eq!(
+            airdrop_tree().root_hex(),
+            "0xd4dee0beab2d53f2cc83e567171bd2820e49898130a22622b10ead383e90bd77"
+        );
+    }
+
+    #[test]
+    pub fn should_hash_abi_encoded_values_twice() {
+        let leaf_encoding = [AbiType::Address, AbiType::String, AbiType::Int(8)];
+        let value = vec![
+            AbiValue::Address([0x11; 20]),
+            AbiValue::from("airdrop"),
+            AbiValue::parse(&AbiType::Int(8), "-1").unwrap(),
+        ];
+
+        // Static heads, the offset of the string, then its length and padded contents
+        let mut encoded = vec![0u8; 12];
+        encoded.extend_from_slice(&[0x11; 20]);
+        encoded.extend_from_slice(&[0u8; 31]);
+        encoded.push(96);
+        encoded.extend_from_slice(&[0xff; 32]);
+        encoded.extend_from_slice(&[0u8; 31]);
+        encoded.push(7);
+        encoded.extend_from_slice(b"airdrop");
+        encoded.extend_from_slice(&[0u8; 25]);
+
+        assert_eq!(
+            StandardMerkleTree::leaf_hash(&leaf_encoding, &value).unwrap(),
+            Keccak256::hash(&Keccak256::hash(&encoded))
+        );
+    }
+
+    #[test]
+    pub fn should_keep_leaves_sorted_in_the_array_layout() {
+        let tree = numbered_tree(5);
+        let nodes = tree.tree();
+        assert_eq!(nodes.len(), 9);
+
+        // Leaves take the end of the array in the reverse order of their hashes
+        assert!(nodes[4..].windows(2).all(|pair| pair[0] > pair[1]));
+        for value_index in 0..tree.len() {
+            let tree_index = tree.tree_index(value_index).unwrap();
+            assert_eq!(
+                nodes[tree_index],
+                StandardMerkleTree::leaf_hash(
+                    tree.leaf_encoding(),
+                    tree.value(value_index).unwrap()
+                )
+                .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    pub fn should_verify_proofs_of_every_value() {
+        for len in 1..=9 {
+            let tree = numbered_tree(len);
+            for value_index in 0..tree.len() {
+                let proof = tree.proof(value_index).unwrap();
+                let value = tree.value(value_index).unwrap();
+                assert!(StandardMerkleTree::verify(
+                    tree.root(),
+                    tree.leaf_encoding(),
+                    value,
+                    &proof
+                )
+                .unwrap());
+
+                let other_value = [AbiValue::from(len)];
+                assert!(!StandardMerkleTree::verify(
+                    tree.root(),
+                    tree.leaf_encoding(),
+                    &other_value,
+                    &proof
+                )
+                .unwrap());
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_verify_multiproofs_of_every_combination() {
+        for len in 1..=7 {
+            let tree = numbered_tree(len as u128);
+            for value_indices in common::combinations((0..len).collect()) {
+                let (leaves, proof) = tree.multi_proof(&value_indices).unwrap();
+                assert_eq!(leaves.len(), value_indices.len());
+                assert!(proof.verify(tree.root(), &leaves));
+            }
+        }
+
+        let tree = numbered_tree(4);
+        let error = tree.multi_proof(&[1, 1]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidStandardTree);
+        let error = tree.multi_proof(&[4]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::LeafIndexOutOfRange);
+    }
+
+    #[test]
+    pub fn should_reject_values_that_do_not_match_the_encoding() {
+        assert!(AbiValue::parse(&AbiType::Uint(8), "255").is_ok());
+        assert!(AbiValue::parse(&AbiType::Uint(8), "256").is_err());
+        assert!(AbiValue::parse(&AbiType::Int(8), "-128").is_ok());
+        assert!(AbiValue::parse(&AbiType::Int(8), "-129").is_err());
+        assert!(AbiValue::parse(&AbiType::Int(8), "128").is_err());
+        assert!(AbiValue::parse(&AbiType::Address, "0x1111").is_err());
+        assert!(AbiValue::parse(&AbiType::FixedBytes(2), "0x1234").is_ok());
+        assert!(AbiValue::parse(&AbiType::FixedBytes(2), "1234").is_err());
+
+        assert_eq!(AbiType::parse("uint").unwrap(), AbiType::Uint(256));
+        assert!(AbiType::parse("uint7").is_err());
+        assert!(AbiType::parse("bytes33").is_err());
+        assert!(AbiType::parse("address[]").is_err());
+
+        let error = StandardMerkleTree::of(vec![], &[AbiType::Bool]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidStandardTree);
+        let error = StandardMerkleTree::of(vec![vec![AbiValue::Bool(true)]], &[AbiType::Uint(256)])
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidStandardTree);
+    }
+
+    #[test]
+    pub fn should_format_values_back_to_strings() {
+        let int = AbiValue::parse(&AbiType::Int(256), "-1234567890123456789").unwrap();
+        assert_eq!(int.to_string(), "-1234567890123456789");
+        let uint = AbiValue::parse(&AbiType::Uint(256), "0xff").unwrap();
+        assert_eq!(uint.to_string(), "255");
+        assert_eq!(AbiValue::from(0u128).to_string(), "0");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    pub fn should_round_trip_the_dump() {
+        let tree = airdrop_tree();
+        let dump = serde_json::to_value(&tree).unwrap();
+
+        assert_eq!(dump["format"], "standard-v1");
+        assert_eq!(
+            dump["leafEncoding"],
+            serde_json::json!(["address", "uint256"])
+        );
+        assert_eq!(dump["tree"][0], tree.root_hex());
+        assert_eq!(
+            dump["values"][0]["value"],
+            serde_json::json!([
+                "0x1111111111111111111111111111111111111111",
+                "5000000000000000000"
+            ])
+        );
+
+        let loaded: StandardMerkleTree = serde_json::from_value(dump).unwrap();
+        assert_eq!(loaded, tree);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    pub fn should_load_the_dump_of_the_library() {
+        let tree = airdrop_tree();
+        let json = format!(
+            r#"{{
+                "format": "standard-v1",
+                "tree": [{}],
+                "values": [
+                    {{ "value": ["0x1111111111111111111111111111111111111111", "5000000000000000000"], "treeIndex": {} }},
+                    {{ "value": ["0x2222222222222222222222222222222222222222", "2500000000000000000"], "treeIndex": {} }}
+                ],
+                "leafEncoding": ["address", "uint"]
+            }}"#,
+            tree.tree()
+                .iter()
+                .map(|node| format!(
+                    "\"0x{}\"",
+                    rs_merkle::utils::collections::to_hex_string(node)
+                ))
+                .collect::<Vec<String>>()
+                .join(", "),
+            tree.tree_index(0).unwrap(),
+            tree.tree_index(1).unwrap(),
+        );
+
+        let loaded: StandardMerkleTree = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.root(), tree.root());
+        assert_eq!(loaded.proof(1).unwrap(), tree.proof(1).unwrap());
+
+        // Numbers and booleans are accepted as JSON values
+        let numbers = r#"{
+            "format": "standard-v1",
+            "tree": ["0x0000000000000000000000000000000000000000000000000000000000000000"],
+            "values": [{ "value": [7, true], "treeIndex": 0 }],
+            "leafEncoding": ["uint8", "bool"]
+        }"#;
+        let value = vec![AbiValue::from(7u128), AbiValue::Bool(true)];
+        let leaf =
+            StandardMerkleTree::leaf_hash(&[AbiType::Uint(8), AbiType::Bool], &value).unwrap();
+        let numbers = numbers.replace(
+            "0x0000000000000000000000000000000000000000000000000000000000000000",
+            &format!("0x{}", rs_merkle::utils::collections::to_hex_string(&leaf)),
+        );
+        let loaded: StandardMerkleTree = serde_json::from_str(&numbers).unwrap();
+        assert_eq!(loaded.value(0).unwrap(), value.as_slice());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    pub fn should_reject_tampered_dumps() {
+        let dump = serde_json::to_value(airdrop_tree()).unwrap();
+
+        let mut wrong_format = dump.clone();
+        wrong_format["format"] = serde_json::json!("simple-v1");
+        assert!(serde_json::from_value::<StandardMerkleTree>(wrong_format).is_err());
+
+        let mut wrong_root = dump.clone();
+        wrong_root["tree"][0] = serde_json::json!(format!("0x{}", "00".repeat(32)));
+        assert!(serde_json::from_value::<StandardMerkleTree>(wrong_root).is_err());
+
+        let mut wrong_value = dump.clone();
+        wrong_value["values"][0]["value"][1] = serde_json::json!("1");
+        assert!(serde_json::from_value::<StandardMerkleTree>(wrong_value).is_err());
+
+        let mut wrong_index = dump;
+        wrong_index["values"][0]["treeIndex"] = serde_json::json!(0);
+        assert!(serde_json::from_value::<StandardMerkleTree>(wrong_index).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+pub mod substrate {
+    use rs_merkle::{algorithms::Keccak256, utils::properties::TreeProperties, Leaf, MerkleTree};
+
+    /// Keccak256 for Substrate's `binary_merkle_tree`, which is used as the reference
+    /// implementation
+    struct SubstrateKeccak256;
+
+    impl hash_db::Hasher for SubstrateKeccak256 {
+        type Out = [u8; 32];
+        type StdHasher = std::collections::hash_map::DefaultHasher;
+        const LENGTH: usize = 32;
+
+        fn hash(data: &[u8]) -> [u8; 32] {
+            Keccak256::hash(data)
+        }
+    }
+
+    fn values(len: usize) -> Vec<Vec<u8>> {
+        (0..len)
+            .map(|i| (0..=i as u8).map(|byte| byte.wrapping_mul(31)).collect())
+            .collect()
+    }
+
+    #[test]
+    pub fn should_match_substrate_test_vectors() {
+        let tree =
+            MerkleTree::<Keccak256>::from_leaf_values(&["a", "b", "c"], TreeProperties::SUBSTRATE);
+        assert_eq!(
+            tree.root_hex().unwrap(),
+            "aff1208e69c9e8be9b584b07ebac4e48a1ee9d15ce3afe20b77a4d29e4175aa3"
+        );
+
+        let tree =
+            MerkleTree::<Keccak256>::from_leaf_values(&["a", "b", "a"], TreeProperties::SUBSTRATE);
+        assert_eq!(
+            tree.root_hex().unwrap(),
+            "b8912f7269068901f231a965adfefbc10f0eedcfa61852b103efd54dac7db3d7"
+        );
+
+        let empty =
+            MerkleTree::<Keccak256>::from_leaf_values::<&str>(&[], TreeProperties::SUBSTRATE);
+        assert_eq!(
+            empty.root().unwrap_or_default(),
+            binary_merkle_tree::merkle_root::<SubstrateKeccak256, Vec<&str>>(vec![])
+        );
+    }
+
+    #[test]
+    pub fn should_produce_identical_roots_and_proofs() {
+        for len in 1..=33 {
+            let values = values(len);
+            let tree =
+                MerkleTree::<Keccak256>::from_leaf_values(&values, TreeProperties::SUBSTRATE);
+            let root = tree.root().unwrap();
+            assert_eq!(
+                root,
+                binary_merkle_tree::merkle_root::<SubstrateKeccak256, _>(&values)
+            );
+
+            for leaf_index in 0..len {
+                let proof = tree.proof(&[leaf_index]);
+                let substrate_proof = binary_merkle_tree::merkle_proof::<SubstrateKeccak256, _, _>(
+                    &values,
+                    leaf_index as u32,
+                );
+                assert_eq!(substrate_proof.root, root);
+                assert_eq!(proof.proof_hashes(), substrate_proof.proof.as_slice());
+
+                assert!(
+                    binary_merkle_tree::verify_proof::<SubstrateKeccak256, _, _>(
+                        &root,
+                        proof.proof_hashes().to_vec(),
+                        len as u32,
+                        leaf_index as u32,
+                        &values[leaf_index],
+                    )
+                );
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_verify_leaf_values_and_hashes() {
+        let values = values(11);
+        let tree = MerkleTree::<Keccak256>::from_leaf_values(&values, TreeProperties::SUBSTRATE);
+        let root = tree.root().unwrap();
+
+        for leaf_index in 0..values.len() {
+            let proof = tree.proof(&[leaf_index]);
+            let value = &values[leaf_index];
+
+            assert!(proof.verify_leaf(
+                root,
+                leaf_index,
+                value,
+                values.len(),
+                TreeProperties::SUBSTRATE
+            ));
+            assert!(proof.verify_leaf(
+                root,
+                leaf_index,
+                Leaf::Hash(Keccak256::hash(value)),
+                values.len(),
+                TreeProperties::SUBSTRATE
+            ));
+            // The hash of the value is not the value itself
+            assert!(!proof.verify_leaf(
+                root,
+                leaf_index,
+                Leaf::Value(&Keccak256::hash(value)),
+                values.len(),
+                TreeProperties::SUBSTRATE
+            ));
+            assert!(!proof.verify_leaf(
+                root,
+                values.len(),
+                value,
+                values.len(),
+                TreeProperties::SUBSTRATE
+            ));
+        }
+    }
+}